@@ -5,7 +5,9 @@ use chrono::Duration;
 use chrono_english::*;
 use clap::{ArgEnum, Parser, ValueHint};
 
+use pueue_lib::log::{LogLimitPolicy, LogRange};
 use pueue_lib::network::message::Signal;
+use pueue_lib::task::{OutputPattern, StallAction};
 
 #[derive(Parser, Debug)]
 pub enum SubCommand {
@@ -59,6 +61,113 @@ pub enum SubCommand {
         /// This is useful when scripting and working with dependencies.
         #[clap(short, long)]
         print_task_id: bool,
+
+        /// Add one task for each combination of the given parameters.
+        /// Parameters are specified as `<name>=<values>`, where values are either a comma
+        /// separated list (`lr=0.1,0.01`) or an inclusive integer range (`seed=1..5`).
+        /// Any `{name}` in the command is replaced by the respective value.
+        /// Each task also gets its parameters as environment variables and in its label.
+        /// All tasks are added as a single batch.
+        #[clap(short, long, conflicts_with = "print-task-id", parse(try_from_str=parse_matrix_parameter))]
+        matrix: Vec<(String, Vec<String>)>,
+
+        /// Capture stdout and stderr of the task in separate log files.
+        /// The interleaved output is still shown by default, but the order of lines from
+        /// different streams might differ from the order in which they've been printed.
+        /// Ignored, if the daemon detaches tasks (`detach_tasks`).
+        #[clap(long)]
+        separate_output: bool,
+
+        /// Capture the output of the task with a timestamp for each line.
+        /// This allows to use `pueue log --timestamps` and `pueue log --since`.
+        /// Ignored, if the daemon detaches tasks (`detach_tasks`).
+        #[clap(long)]
+        timestamps: bool,
+
+        /// Limit the size of each of the task's log files.
+        /// Either in bytes or with a unit, such as "512K", "10M" or "1G".
+        /// Ignored, if the daemon detaches tasks (`detach_tasks`).
+        #[clap(long, parse(try_from_str=parse_size))]
+        log_limit: Option<u64>,
+
+        /// What happens, once the log exceeds its size limit.
+        /// `head_tail` keeps the start and the end of the output, `rotate` only keeps the newest
+        /// output and `kill` kills the task.
+        #[clap(long, requires = "log-limit", parse(try_from_str=parse_log_limit_policy))]
+        log_limit_policy: Option<LogLimitPolicy>,
+
+        /// The exit codes, that count as success, e.g. "0,1" for `grep` or `diff`.
+        /// Defaults to the policy of the task's group, which is only "0" by default.
+        #[clap(long, use_value_delimiter = true, require_value_delimiter = true)]
+        success_codes: Vec<i32>,
+
+        /// The exit codes, that mark a temporary failure, e.g. "75".
+        /// Temporarily failed tasks are restarted in place, until they ran out of retries.
+        /// Only then, they fail their dependants and might pause their group.
+        #[clap(long, use_value_delimiter = true, require_value_delimiter = true)]
+        temporary_codes: Vec<i32>,
+
+        /// How often the task is restarted after a temporary failure or a requested retry.
+        /// Defaults to 3.
+        #[clap(long, requires = "temporary-codes")]
+        max_retries: Option<usize>,
+
+        /// The task fails, if any line of its output matches this regular expression.
+        /// This is checked once the task exited successfully.
+        /// Only the first 64KiB of each line are matched.
+        #[clap(long, parse(try_from_str=parse_output_pattern))]
+        fail_if_output: Option<OutputPattern>,
+
+        /// The task fails, unless any line of its output matches this regular expression.
+        /// This is checked once the task exited successfully.
+        /// Only the first 64KiB of each line are matched.
+        #[clap(long, parse(try_from_str=parse_output_pattern))]
+        succeed_if_output: Option<OutputPattern>,
+
+        /// Consider the task stalled, if it doesn't write any output for the given duration.
+        /// Either in seconds or with a unit, such as "90s", "30m", "2h" or "1d".
+        #[clap(long, parse(try_from_str=parse_duration))]
+        stall_timeout: Option<u64>,
+
+        /// What happens, once the task stalled.
+        /// `notify` calls the callback with the `stalled` event, `pause` pauses the task and
+        /// `kill` kills it.
+        #[clap(long, requires = "stall-timeout", parse(try_from_str=parse_stall_action))]
+        stall_action: Option<StallAction>,
+    },
+    /// Add multiple tasks at once, as described by a workflow file.
+    ///
+    /// The workflow file is a YAML file with a `tasks` map.
+    /// Each task has a unique symbolic name and may depend on other tasks of the same file:
+    ///
+    /// tasks:
+    ///   build:
+    ///     command: make
+    ///     path: /home/user/project
+    ///     group: compile
+    ///     label: Build the project
+    ///     envs:
+    ///       CC: clang
+    ///   test:
+    ///     command: make test
+    ///     needs: [build]
+    ///
+    /// Only `command` is required. Tasks without a path are executed in the current directory.
+    /// Tasks without a label use their name as label.
+    #[clap(verbatim_doc_comment)]
+    Submit {
+        /// The path to the workflow file.
+        #[clap(value_hint = ValueHint::FilePath)]
+        workflow: PathBuf,
+
+        /// Stash all tasks of the workflow instead of queuing them.
+        #[clap(short, long)]
+        stashed: bool,
+
+        /// Track all tasks of the workflow as a single batch.
+        /// The batch can then be managed via `pueue batch`.
+        #[clap(short, long)]
+        batch: bool,
     },
     /// Remove tasks from the list.
     /// Running or paused tasks need to be killed first.
@@ -129,6 +238,10 @@ pub enum SubCommand {
         #[clap(short, long, conflicts_with = "all")]
         group: Option<String>,
 
+        /// Resume a specific batch and all paused tasks in it.
+        #[clap(short, long, conflicts_with_all = &["group", "all"])]
+        batch: Option<usize>,
+
         /// Resume all groups!
         /// All groups will be set to running and paused tasks will be resumed.
         #[clap(short, long)]
@@ -158,6 +271,11 @@ pub enum SubCommand {
         #[clap(short = 'g', long, conflicts_with = "all-failed")]
         failed_in_group: Option<String>,
 
+        /// Like `--all-failed`, but only restart failed tasks of a specific batch.
+        /// The restarted tasks stay part of the batch.
+        #[clap(short = 'b', long, conflicts_with_all = &["all-failed", "failed-in-group"])]
+        failed_in_batch: Option<usize>,
+
         /// Immediately start the tasks, no matter how many open slots there are.
         /// This will ignore any dependencies tasks may have.
         #[clap(short = 'k', long, conflicts_with = "stashed")]
@@ -204,6 +322,10 @@ pub enum SubCommand {
         #[clap(short, long, conflicts_with = "all")]
         group: Option<String>,
 
+        /// Pause a specific batch and all running tasks in it.
+        #[clap(short, long, conflicts_with_all = &["group", "all"])]
+        batch: Option<usize>,
+
         /// Pause all groups!
         #[clap(short, long)]
         all: bool,
@@ -230,6 +352,10 @@ pub enum SubCommand {
         #[clap(short, long, conflicts_with = "all")]
         group: Option<String>,
 
+        /// Kill all running tasks of a batch. This also pauses the batch.
+        #[clap(short, long, conflicts_with_all = &["group", "all"])]
+        batch: Option<usize>,
+
         /// Kill all running tasks across ALL groups. This also pauses all groups.
         #[clap(short, long)]
         all: bool,
@@ -255,13 +381,23 @@ pub enum SubCommand {
         input: String,
     },
 
-    /// Edit the command, path or label of a stashed or queued task.
+    /// Edit the command, path, label, envs, group or dependencies of a stashed or queued task.
     /// By default only the command is edited.
     /// Multiple properties can be added in one go.
+    ///
+    /// When editing multiple tasks, all of their properties are edited in a single YAML file.
+    /// The changes are then applied to all tasks at once.
     #[clap(verbatim_doc_comment)]
     Edit {
-        /// The task's id.
-        task_id: usize,
+        /// The ids of the tasks that should be edited.
+        /// Ranges of task ids such as `10-40` are accepted as well.
+        #[clap(required_unless_present = "query", parse(try_from_str=parse_task_range))]
+        task_ids: Vec<(usize, usize)>,
+
+        /// Edit all queued/stashed tasks that match this `pueue status` query.
+        /// For instance: `--query "label%=sweep"`.
+        #[clap(short, long, conflicts_with = "task-ids")]
+        query: Option<String>,
 
         /// Edit the task's command.
         #[clap(short, long)]
@@ -274,6 +410,20 @@ pub enum SubCommand {
         /// Edit the task's label.
         #[clap(short, long)]
         label: bool,
+
+        /// Edit the task's environment variables.
+        /// They're opened in the editor as `KEY=VALUE` lines.
+        #[clap(short, long)]
+        envs: bool,
+
+        /// Edit the group of the task.
+        #[clap(short, long)]
+        group: bool,
+
+        /// Edit the task's dependencies.
+        /// They're opened in the editor as a whitespace separated list of task ids.
+        #[clap(short, long)]
+        dependencies: bool,
     },
 
     /// Use this to add or remove groups.
@@ -283,6 +433,64 @@ pub enum SubCommand {
         cmd: Option<GroupCommand>,
     },
 
+    /// Manage backups of the daemon's state.
+    /// By default, this will simply list all existing backups.
+    Backup {
+        #[clap(subcommand)]
+        cmd: Option<BackupCommand>,
+    },
+
+    /// Inspect the aliases of the daemon's `pueue_aliases.yml` file.
+    /// By default, this will list all aliases.
+    Alias {
+        #[clap(subcommand)]
+        cmd: Option<AliasCommand>,
+    },
+
+    /// Manage reusable task templates, which are stored in the daemon.
+    /// By default, this will list all templates.
+    Template {
+        #[clap(subcommand)]
+        cmd: Option<TemplateCommand>,
+    },
+
+    /// Add a new task from a template.
+    /// Parameters of the template are passed as `key=value`.
+    Run {
+        /// The name of the template.
+        template: String,
+
+        /// The values for the template's parameters, e.g. `target=db`.
+        #[clap(parse(try_from_str=parse_key_value))]
+        parameters: Vec<(String, String)>,
+
+        /// Use another group than the one of the template.
+        #[clap(short, long)]
+        group: Option<String>,
+
+        /// Immediately start the task.
+        #[clap(name = "immediate", short, long, conflicts_with = "stashed")]
+        start_immediately: bool,
+
+        /// Create the task in Stashed state.
+        #[clap(name = "stashed", short, long, conflicts_with = "immediate")]
+        stashed: bool,
+    },
+
+    /// Manage batches of tasks, such as the tasks of a `pueue add --matrix` call.
+    /// By default, this will display the aggregated status of all batches.
+    Batch {
+        #[clap(subcommand)]
+        cmd: Option<BatchCommand>,
+    },
+
+    /// Act on the current task from within its process.
+    /// This authenticates with the task's token, so the shared secret isn't needed.
+    Task {
+        #[clap(subcommand)]
+        cmd: TaskCommand,
+    },
+
     /// Display the current status of all tasks.
     Status {
         /// Users can specify a custom query to filter for specific values, order by a column
@@ -321,8 +529,8 @@ pub enum SubCommand {
 
         /// Print the resulting tasks and output as json.
         /// By default only the last lines will be returned unless --full is provided.
-        /// Take care, as the json cannot be streamed!
-        /// If your logs are really huge, using --full can use all of your machine's RAM.
+        /// The output is written while it's being received from the daemon, so even huge
+        /// logs never have to be kept in memory as a whole.
         #[clap(short, long)]
         json: bool,
 
@@ -334,6 +542,83 @@ pub enum SubCommand {
         /// Show the whole output.
         #[clap(short, long)]
         full: bool,
+
+        /// Only print the first X lines of each task's output.
+        #[clap(long, conflicts_with_all = &["lines", "full", "line-range", "byte-range"])]
+        head: Option<usize>,
+
+        /// Only print the lines from START to END of each task's output, such as `100-200`.
+        /// Lines are counted from 1. Omit END to print everything from START onwards.
+        #[clap(
+            long,
+            value_name = "START-END",
+            parse(try_from_str=parse_line_range),
+            conflicts_with_all = &["lines", "full", "byte-range"]
+        )]
+        line_range: Option<LogRange>,
+
+        /// Only print the bytes from START up to, but excluding, END of each task's output.
+        /// Omit END to print everything from START onwards.
+        #[clap(
+            long,
+            value_name = "START-END",
+            parse(try_from_str=parse_byte_range),
+            conflicts_with_all = &["lines", "full"]
+        )]
+        byte_range: Option<LogRange>,
+
+        /// Only show the stdout of tasks, whose output has been captured separately.
+        #[clap(long, conflicts_with = "stderr")]
+        stdout: bool,
+
+        /// Only show the stderr of tasks, whose output has been captured separately.
+        #[clap(long)]
+        stderr: bool,
+
+        /// Prefix each line with the time at which it has been printed.
+        /// Only works for tasks, whose output has been captured with timestamps.
+        #[clap(short, long)]
+        timestamps: bool,
+
+        /// Only show lines that have been printed since the given point in time.
+        /// Either a duration such as "90s", "10m" or "2h" or a date/time such as "10:30".
+        /// Only works for tasks, whose output has been captured with timestamps.
+        #[clap(long, parse(try_from_str=parse_since))]
+        since: Option<DateTime<Local>>,
+    },
+
+    /// Search the logs of multiple tasks for lines that contain a pattern.
+    /// Matching lines are printed as `<task_id>:<line>:<text>`, context lines as
+    /// `<task_id>-<line>-<text>`.
+    /// Exits with 1, if no line matches.
+    Grep {
+        /// The regular expression that should be searched for.
+        pattern: String,
+
+        /// Search for the pattern as a fixed string instead of a regular expression.
+        #[clap(short = 'F', long)]
+        fixed_strings: bool,
+
+        /// Only search the logs of tasks in this group.
+        #[clap(short, long)]
+        group: Option<String>,
+
+        /// Only search the logs of tasks that match this `pueue status` query.
+        /// For instance: `--query "label%=sweep"`.
+        #[clap(short, long)]
+        query: Option<String>,
+
+        /// Print this many lines of context before and after each matching line.
+        #[clap(short = 'C', long, default_value = "0")]
+        context: usize,
+
+        /// Ignore the case of the pattern and the log.
+        #[clap(short, long)]
+        ignore_case: bool,
+
+        /// Stop after this many matching lines.
+        #[clap(short, long, default_value = "1000")]
+        max_count: usize,
     },
 
     /// Follow the output of a currently running task.
@@ -380,6 +665,10 @@ pub enum SubCommand {
         /// Only clean tasks of a specific group
         #[clap(short, long)]
         group: Option<String>,
+
+        /// Only clean tasks of a specific batch
+        #[clap(short, long)]
+        batch: Option<usize>,
     },
 
     /// Kill all tasks, clean up afterwards and reset EVERYTHING!
@@ -395,7 +684,17 @@ pub enum SubCommand {
     },
 
     /// Remotely shut down the daemon. Should only be used if the daemon isn't started by a service manager.
-    Shutdown,
+    Shutdown {
+        /// Don't start any new tasks and shut down once all running tasks finished.
+        /// The same can be achieved by sending SIGUSR1 to the daemon.
+        #[clap(long)]
+        drain: bool,
+
+        /// Kill all tasks that are still running after <deadline>, when draining.
+        /// Accepts the same formats as `pueue enqueue --delay`.
+        #[clap(long, requires = "drain", parse(try_from_str=parse_delay_until))]
+        deadline: Option<DateTime<Local>>,
+    },
 
     /// Set the amount of allowed parallel tasks.
     /// By default, adjusts the amount of the default group.
@@ -437,6 +736,158 @@ pub enum GroupCommand {
     Remove { name: String },
 }
 
+#[derive(Parser, Debug)]
+pub enum BackupCommand {
+    /// List all existing state backups.
+    List,
+
+    /// Create a new backup of the current state.
+    Create,
+
+    /// Replace the current state with a previous backup.
+    /// This only works, if no tasks are currently running.
+    /// The current state will be backed up beforehand.
+    Restore {
+        /// The name of the backup, as shown by `pueue backup list`.
+        name: String,
+    },
+}
+
+#[derive(Parser, Debug)]
+pub enum AliasCommand {
+    /// List all aliases and their commands.
+    List,
+
+    /// Show the parameters and defaults of a single alias.
+    Show {
+        /// The name of the alias.
+        name: String,
+    },
+}
+
+#[derive(Parser, Debug)]
+pub enum TemplateCommand {
+    /// Add a template by name.
+    /// The command and the label may contain parameters such as `{name}` or `{name=default}`.
+    #[clap(trailing_var_arg = true)]
+    Add {
+        name: String,
+
+        /// The command template.
+        #[clap(required = true, multiple_values = true, value_hint = ValueHint::CommandWithArguments)]
+        command: Vec<String>,
+
+        /// The working directory of the tasks.
+        /// Relative paths are resolved against the directory `pueue run` is called in.
+        #[clap(name = "working-directory", short = 'w', long, value_hint = ValueHint::DirPath)]
+        working_directory: Option<PathBuf>,
+
+        /// The default group of the tasks.
+        #[clap(short, long)]
+        group: Option<String>,
+
+        /// The label template of the tasks.
+        #[clap(short, long)]
+        label: Option<String>,
+
+        /// Environment variables for the tasks, e.g. `-e KEY=VALUE`.
+        #[clap(short, long, parse(try_from_str=parse_key_value))]
+        env: Vec<(String, String)>,
+
+        /// Kill the tasks, if they run longer than the given duration.
+        /// Either in seconds or with a unit, such as "90s", "30m", "2h" or "1d".
+        #[clap(long, parse(try_from_str=parse_duration))]
+        timeout: Option<u64>,
+
+        /// Each task depends on the previous task of this template, if that one hasn't finished yet.
+        /// This way, the tasks of this template run one after another.
+        #[clap(short, long)]
+        after_previous: bool,
+    },
+
+    /// List all templates.
+    List,
+
+    /// Remove a template by name.
+    Remove { name: String },
+}
+
+#[derive(Parser, Debug)]
+pub enum BatchCommand {
+    /// Display the aggregated status and the timings of a batch.
+    Status { batch: usize },
+
+    /// Kill all running tasks of a batch.
+    Kill { batch: usize },
+
+    /// Restart all failed tasks of a batch.
+    /// The restarted tasks are part of the same batch.
+    RestartFailed {
+        batch: usize,
+
+        /// Immediately start the tasks, no matter how many open slots there are.
+        /// This will ignore any dependencies tasks may have.
+        #[clap(short = 'k', long, conflicts_with = "stashed")]
+        start_immediately: bool,
+
+        /// Set the restarted tasks to a "Stashed" state.
+        /// Useful to avoid immediate execution.
+        #[clap(short, long)]
+        stashed: bool,
+
+        /// Restart the tasks by reusing the already existing tasks.
+        /// This will overwrite any previous logs of the restarted tasks.
+        #[clap(short, long)]
+        in_place: bool,
+    },
+
+    /// Wait until all tasks of a batch are finished.
+    Wait {
+        batch: usize,
+
+        /// Don't show any log output while waiting
+        #[clap(short, long)]
+        quiet: bool,
+    },
+
+    /// Remove all finished tasks of a batch.
+    Clean {
+        batch: usize,
+
+        /// Only clean tasks that finished successfully.
+        #[clap(short, long)]
+        successful_only: bool,
+    },
+}
+
+#[derive(Parser, Debug)]
+pub enum TaskCommand {
+    /// Set the label of the current task.
+    Label { label: String },
+
+    /// Report the progress of the current task, e.g. `50%` or `step 3/5`.
+    /// The progress is reset, once the task is started again.
+    Progress { progress: String },
+
+    /// Add a task, that depends on the current task.
+    #[clap(trailing_var_arg = true)]
+    FollowUp {
+        /// The command of the follow-up task.
+        #[clap(required = true, multiple_values = true, value_hint = ValueHint::CommandWithArguments)]
+        command: Vec<String>,
+
+        /// Add a label to the follow-up task.
+        /// The group, the path and the environment variables are taken from the current task.
+        #[clap(short, long)]
+        label: Option<String>,
+    },
+
+    /// Restart the current task in place, once it finished.
+    /// This doesn't happen, if the task is killed.
+    /// Just like temporary failures, this is limited by `--max-retries` (3 by default).
+    Retry,
+}
+
 #[derive(Parser, ArgEnum, Debug, Clone, PartialEq, Eq)]
 pub enum ColorChoice {
     Auto,
@@ -478,10 +929,198 @@ pub struct CliArguments {
     #[clap(short, long)]
     pub profile: Option<String>,
 
+    /// Only execute the command, if the daemon's state still has this revision.
+    /// The current revision is part of `pueue status --json`.
+    /// Applies to restarts, edits, submissions and all commands that send a single request to
+    /// the daemon. Edits check the revision, when the tasks are locked for editing.
+    #[clap(long, global = true)]
+    pub if_revision: Option<u64>,
+
     #[clap(subcommand)]
     pub cmd: Option<SubCommand>,
 }
 
+/// Parse a matrix parameter of the form `<name>=<values>`.
+/// Values are either a comma separated list or an inclusive integer range such as `1..5`.
+fn parse_matrix_parameter(src: &str) -> Result<(String, Vec<String>), String> {
+    let (name, values) = src
+        .split_once('=')
+        .ok_or_else(|| String::from("expected a parameter in the form of <name>=<values>"))?;
+
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|char| char.is_ascii_alphanumeric() || char == '_')
+    {
+        return Err(format!(
+            "\"{name}\" isn't a valid parameter name. Only use letters, digits and underscores"
+        ));
+    }
+
+    // Check for an integer range first.
+    if let Some((start, end)) = values.split_once("..") {
+        if let (Ok(start), Ok(end)) = (start.parse::<i64>(), end.parse::<i64>()) {
+            if start > end {
+                return Err(format!("the range {values} is empty"));
+            }
+            let values = (start..=end).map(|value| value.to_string()).collect();
+            return Ok((name.to_string(), values));
+        }
+    }
+
+    let values: Vec<String> = values.split(',').map(String::from).collect();
+    if values.iter().any(|value| value.is_empty()) {
+        return Err(format!("parameter \"{name}\" has an empty value"));
+    }
+
+    Ok((name.to_string(), values))
+}
+
+/// Parse a `key=value` pair.
+fn parse_key_value(src: &str) -> Result<(String, String), String> {
+    match src.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(String::from(
+            "expected a value in the form of <key>=<value>",
+        )),
+    }
+}
+
+/// Parse a task id or an inclusive range of task ids, such as `10-40`.
+/// A single id is returned as a range that only contains this id.
+fn parse_task_range(src: &str) -> Result<(usize, usize), String> {
+    let parse_id = |id: &str| {
+        id.trim()
+            .parse::<usize>()
+            .map_err(|_| format!("{id} isn't a valid task id"))
+    };
+
+    match src.split_once('-') {
+        Some((start, end)) => {
+            let (start, end) = (parse_id(start)?, parse_id(end)?);
+            if start > end {
+                return Err(format!("the range {src} is empty"));
+            }
+            Ok((start, end))
+        }
+        None => {
+            let id = parse_id(src)?;
+            Ok((id, id))
+        }
+    }
+}
+
+/// Parse a range of lines such as `100-200` or `100-`.
+fn parse_line_range(src: &str) -> Result<LogRange, String> {
+    let (start, end) = parse_open_range(src)?;
+    Ok(LogRange::Lines {
+        start: start as usize,
+        end: end.map(|end| end as usize),
+    })
+}
+
+/// Parse a range of bytes such as `0-4096` or `4096-`.
+fn parse_byte_range(src: &str) -> Result<LogRange, String> {
+    let (start, end) = parse_open_range(src)?;
+    Ok(LogRange::Bytes { start, end })
+}
+
+/// Parse a range of the form `START-END`, whose end may be omitted.
+fn parse_open_range(src: &str) -> Result<(u64, Option<u64>), String> {
+    let parse_number = |number: &str| {
+        number
+            .trim()
+            .parse::<u64>()
+            .map_err(|_| format!("{number} isn't a valid number"))
+    };
+
+    let (start, end) = src
+        .split_once('-')
+        .ok_or_else(|| format!("{src} isn't a range of the form START-END"))?;
+    let start = parse_number(start)?;
+    let end = if end.trim().is_empty() {
+        None
+    } else {
+        Some(parse_number(end)?)
+    };
+    if end.map_or(false, |end| end < start) {
+        return Err(format!("the range {src} is empty"));
+    }
+
+    Ok((start, end))
+}
+
+/// Parse a duration into seconds.
+/// The duration is either given in seconds or with one of the units `s`, `m`, `h` or `d`.
+fn parse_duration(src: &str) -> Result<u64, String> {
+    let (number, multiplier) = match src.char_indices().last() {
+        Some((index, 's')) => (&src[..index], 1),
+        Some((index, 'm')) => (&src[..index], 60),
+        Some((index, 'h')) => (&src[..index], 60 * 60),
+        Some((index, 'd')) => (&src[..index], 60 * 60 * 24),
+        _ => (src, 1),
+    };
+
+    match number.parse::<u64>() {
+        Ok(number) if number > 0 => number
+            .checked_mul(multiplier)
+            .ok_or_else(|| String::from("the duration is too large")),
+        _ => Err(String::from(
+            "expected a positive duration such as \"90\", \"90s\", \"30m\", \"2h\" or \"1d\"",
+        )),
+    }
+}
+
+/// Parse a size into bytes.
+/// The size is either given in bytes or with one of the units `K`, `M` or `G`.
+fn parse_size(src: &str) -> Result<u64, String> {
+    let (number, multiplier) = match src.char_indices().last() {
+        Some((index, 'K' | 'k')) => (&src[..index], 1024),
+        Some((index, 'M' | 'm')) => (&src[..index], 1024 * 1024),
+        Some((index, 'G' | 'g')) => (&src[..index], 1024 * 1024 * 1024),
+        _ => (src, 1),
+    };
+
+    match number.parse::<u64>() {
+        Ok(number) if number > 0 => number
+            .checked_mul(multiplier)
+            .ok_or_else(|| String::from("the size is too large")),
+        _ => Err(String::from(
+            "expected a positive size such as \"4096\", \"512K\", \"10M\" or \"1G\"",
+        )),
+    }
+}
+
+fn parse_log_limit_policy(src: &str) -> Result<LogLimitPolicy, String> {
+    src.parse()
+        .map_err(|_| String::from("expected one of \"head_tail\", \"rotate\" or \"kill\""))
+}
+
+fn parse_stall_action(src: &str) -> Result<StallAction, String> {
+    src.parse()
+        .map_err(|_| String::from("expected one of \"notify\", \"pause\" or \"kill\""))
+}
+
+fn parse_output_pattern(src: &str) -> Result<OutputPattern, String> {
+    OutputPattern::new(src).map_err(|err| err.to_string())
+}
+
+/// Parse the point in time, from which on log output should be shown.
+/// This is either a duration, which is subtracted from the current time, or a date/time.
+fn parse_since(src: &str) -> Result<DateTime<Local>, String> {
+    if let Ok(seconds) = parse_duration(src) {
+        return Ok(Local::now() - Duration::seconds(seconds as i64));
+    }
+
+    if let Ok(date_time) = parse_date_string(src, Local::now(), Dialect::Us) {
+        return Ok(date_time);
+    }
+
+    Err(String::from(
+        "could not parse as a duration or date/time. Try something like \"10m\" or \"10:30\"",
+    ))
+}
+
 fn parse_delay_until(src: &str) -> Result<DateTime<Local>, String> {
     if let Ok(seconds) = src.parse::<i64>() {
         let delay_until = Local::now() + Duration::seconds(seconds);
@@ -1,21 +1,32 @@
-use std::env::{current_dir, vars};
+use std::env::{current_dir, var, vars};
 use std::io::{self, stdout, Write};
-use std::{borrow::Cow, collections::HashMap};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+};
 
 use anyhow::{bail, Context, Result};
 use clap::crate_version;
 use crossterm::tty::IsTty;
 use log::error;
 
+use pueue_lib::environment;
+use pueue_lib::log::LogLimit;
 use pueue_lib::network::message::*;
 use pueue_lib::network::protocol::*;
 use pueue_lib::network::secret::read_shared_secret;
-use pueue_lib::settings::Settings;
+use pueue_lib::settings::{Settings, Shared};
 use pueue_lib::state::PUEUE_DEFAULT_GROUP;
+use pueue_lib::task::{ExitCodePolicy, OutputPattern, OutputRules, StallTimeout};
+use pueue_lib::template::{DependencyPolicy, TaskTemplate};
 
-use crate::cli::{CliArguments, ColorChoice, GroupCommand, SubCommand};
+use crate::cli::{
+    AliasCommand, BackupCommand, BatchCommand, CliArguments, ColorChoice, GroupCommand, SubCommand,
+    TaskCommand, TemplateCommand,
+};
 use crate::commands::*;
 use crate::display::*;
+use crate::query::apply_query;
 
 /// This struct contains the base logic for the client.
 /// The client is responsible for connecting to the daemon, sending instructions
@@ -26,6 +37,8 @@ use crate::display::*;
 /// or the `edit` command, which needs to open an editor.
 pub struct Client {
     subcommand: SubCommand,
+    /// Only apply the command, if the state still has this revision.
+    if_revision: Option<u64>,
     settings: Settings,
     style: OutputStyle,
     stream: GenericStream,
@@ -40,19 +53,22 @@ pub fn group_or_default(group: &Option<String>) -> String {
 
 /// This is a small helper which determines a task selection depending on
 /// given commandline parameters.
-/// I.e. whether the default group, a set of tasks or a specific group should be selected.
-/// `start`, `pause` and `kill` can target either of these three selections.
+/// I.e. whether the default group, a set of tasks, a specific group or a batch should be selected.
+/// `start`, `pause` and `kill` can target either of these four selections.
 ///
 /// If no parameters are given, it returns to the default group.
 pub fn selection_from_params(
     all: bool,
     group: &Option<String>,
+    batch: &Option<usize>,
     task_ids: &[usize],
 ) -> TaskSelection {
     if all {
         TaskSelection::All
     } else if let Some(group) = group {
         TaskSelection::Group(group.clone())
+    } else if let Some(batch) = batch {
+        TaskSelection::Batch(*batch)
     } else if !task_ids.is_empty() {
         TaskSelection::TaskIds(task_ids.to_owned())
     } else {
@@ -60,13 +76,78 @@ pub fn selection_from_params(
     }
 }
 
+/// Build the exit code policy of a task from the given commandline parameters.
+/// If none are given, the task uses the policy of its group.
+fn exit_code_policy(
+    success_codes: &[i32],
+    temporary_codes: &[i32],
+    max_retries: Option<usize>,
+) -> Option<ExitCodePolicy> {
+    if success_codes.is_empty() && temporary_codes.is_empty() {
+        return None;
+    }
+
+    let mut policy = ExitCodePolicy::default();
+    if !success_codes.is_empty() {
+        policy.success = success_codes.to_vec();
+    }
+    policy.temporary = temporary_codes.to_vec();
+    if let Some(max_retries) = max_retries {
+        policy.max_retries = max_retries;
+    }
+    Some(policy)
+}
+
+/// Build the output rules of a task from the given commandline parameters.
+fn output_rules(
+    fail_if: &Option<OutputPattern>,
+    succeed_if: &Option<OutputPattern>,
+) -> Option<OutputRules> {
+    if fail_if.is_none() && succeed_if.is_none() {
+        return None;
+    }
+
+    Some(OutputRules {
+        fail_if: fail_if.clone(),
+        succeed_if: succeed_if.clone(),
+    })
+}
+
+/// Read the token of the task, from within whose process `pueue task` has been called.
+/// The daemon's address is taken from the task's environment as well.
+fn task_token(shared: &mut Shared) -> Result<String> {
+    let token = var(environment::TASK_TOKEN)
+        .context("This command can only be used from within a running task")?;
+
+    if let Ok(address) = var(environment::SOCKET) {
+        #[cfg(not(target_os = "windows"))]
+        if shared.use_unix_socket {
+            shared.unix_socket_path = Some(address.into());
+            return Ok(token);
+        }
+
+        let (host, port) = address
+            .rsplit_once(':')
+            .context(format!("Invalid daemon address: {address}"))?;
+        shared.host = host.to_string();
+        shared.port = port.to_string();
+    }
+
+    Ok(token)
+}
+
 impl Client {
     /// Initialize a new client.
     /// This includes establishing a connection to the daemon:
     ///     - Connect to the daemon.
-    ///     - Authorize via secret.
+    ///     - Authorize via secret or, from within a task, via the task's token.
     ///     - Check versions incompatibilities.
-    pub async fn new(settings: Settings, opt: CliArguments) -> Result<Self> {
+    pub async fn new(mut settings: Settings, opt: CliArguments) -> Result<Self> {
+        let token = match opt.cmd {
+            Some(SubCommand::Task { .. }) => Some(task_token(&mut settings.shared)?),
+            _ => None,
+        };
+
         // Connect to daemon and get stream used for communication.
         let mut stream = get_client_stream(&settings.shared)
             .await
@@ -75,7 +156,10 @@ impl Client {
         // Next we do a handshake with the daemon
         // 1. Client sends the secret to the daemon.
         // 2. If successful, the daemon responds with their version.
-        let secret = read_shared_secret(&settings.shared.shared_secret_path())?;
+        let secret = match token {
+            Some(token) => token.into_bytes(),
+            None => read_shared_secret(&settings.shared.shared_secret_path())?,
+        };
         send_bytes(&secret, &mut stream)
             .await
             .context("Failed to send secret.")?;
@@ -133,6 +217,7 @@ impl Client {
         });
 
         Ok(Client {
+            if_revision: opt.if_revision,
             settings,
             style,
             stream,
@@ -189,15 +274,198 @@ impl Client {
             }
 
             SubCommand::Edit {
-                task_id,
+                task_ids,
+                query,
                 command,
                 path,
                 label,
+                envs,
+                group,
+                dependencies,
+            } => {
+                let mut selected: Vec<usize> = match query {
+                    Some(query) => {
+                        let state = get_state(&mut self.stream).await?;
+                        let tasks = state
+                            .tasks
+                            .into_values()
+                            .filter(|task| task.is_queued())
+                            .collect();
+                        let tasks = apply_query(query.clone())?.apply_filters(tasks);
+                        if tasks.is_empty() {
+                            bail!("No queued or stashed task matches the query");
+                        }
+                        tasks.iter().map(|task| task.id).collect()
+                    }
+                    None => {
+                        // Ranges are intersected with the existing tasks, instead of expanding
+                        // them, as they may span an arbitrary amount of ids.
+                        // Single ids are always kept, so the daemon reports missing tasks.
+                        let state = get_state(&mut self.stream).await?;
+                        let mut selected: Vec<usize> = state
+                            .tasks
+                            .into_keys()
+                            .filter(|id| {
+                                task_ids
+                                    .iter()
+                                    .any(|(start, end)| (start..=end).contains(&id))
+                            })
+                            .collect();
+                        selected.extend(
+                            task_ids
+                                .iter()
+                                .filter(|(start, end)| start == end)
+                                .map(|(id, _)| *id),
+                        );
+                        if selected.is_empty() {
+                            bail!("None of the given tasks exist");
+                        }
+                        selected
+                    }
+                };
+                selected.sort_unstable();
+                selected.dedup();
+
+                // Multiple tasks are edited in a single file.
+                let message = if selected.len() == 1 && query.is_none() {
+                    edit(
+                        &mut self.stream,
+                        selected[0],
+                        *command,
+                        *path,
+                        *label,
+                        *envs,
+                        *group,
+                        *dependencies,
+                        self.if_revision,
+                    )
+                    .await?
+                } else {
+                    // All properties are always shown, when editing multiple tasks.
+                    if *command || *path || *label || *envs || *group || *dependencies {
+                        bail!("Multiple tasks are edited as a whole. Flags that select the properties to edit are only supported for a single task.");
+                    }
+                    bulk_edit(&mut self.stream, selected, self.if_revision).await?
+                };
+                self.handle_response(message)?;
+                Ok(true)
+            }
+            SubCommand::Log { json: false, .. } if !self.settings.client.read_local_logs => {
+                // Plain logs are received in chunks, so they never have to be kept in memory
+                // as a whole.
+                let message = match self.get_message_from_opt()? {
+                    Message::Log(message) => message,
+                    _ => unreachable!(),
+                };
+                print_chunked_logs(
+                    &mut self.stream,
+                    message,
+                    &self.subcommand,
+                    &self.style,
+                    &self.settings,
+                )
+                .await?;
+                Ok(true)
+            }
+            SubCommand::Log { json: true, .. } if !self.settings.client.read_local_logs => {
+                // The json is written while the output is received, for the same reason.
+                let message = match self.get_message_from_opt()? {
+                    Message::Log(message) => message,
+                    _ => unreachable!(),
+                };
+                print_chunked_json_logs(&mut self.stream, message).await?;
+                Ok(true)
+            }
+            SubCommand::Grep {
+                pattern,
+                fixed_strings,
+                group,
+                query,
+                context,
+                ignore_case,
+                max_count,
+            } => {
+                grep(
+                    &mut self.stream,
+                    &self.settings,
+                    &self.style,
+                    pattern,
+                    *fixed_strings,
+                    group,
+                    query,
+                    *context,
+                    *ignore_case,
+                    *max_count,
+                )
+                .await?;
+                Ok(true)
+            }
+            SubCommand::Submit {
+                workflow,
+                stashed,
+                batch,
             } => {
-                let message = edit(&mut self.stream, *task_id, *command, *path, *label).await?;
+                let message = submit(
+                    &mut self.stream,
+                    workflow,
+                    *stashed,
+                    *batch,
+                    self.if_revision,
+                )
+                .await?;
                 self.handle_response(message)?;
                 Ok(true)
             }
+            SubCommand::Batch { cmd } => match cmd {
+                None => {
+                    let state = get_state(&mut self.stream).await?;
+                    print_batches(&state, None, &self.settings, &self.style);
+                    Ok(true)
+                }
+                Some(BatchCommand::Status { batch }) => {
+                    let state = get_state(&mut self.stream).await?;
+                    print_batches(&state, Some(*batch), &self.settings, &self.style);
+                    Ok(true)
+                }
+                Some(BatchCommand::RestartFailed {
+                    batch,
+                    start_immediately,
+                    stashed,
+                    in_place,
+                }) => {
+                    restart(
+                        &mut self.stream,
+                        Vec::new(),
+                        false,
+                        None,
+                        Some(*batch),
+                        *start_immediately,
+                        *stashed,
+                        self.settings.client.restart_in_place || *in_place,
+                        false,
+                        false,
+                        false,
+                        self.if_revision,
+                    )
+                    .await?;
+                    Ok(true)
+                }
+                Some(BatchCommand::Wait { batch, quiet }) => {
+                    wait(
+                        &mut self.stream,
+                        &[],
+                        PUEUE_DEFAULT_GROUP,
+                        Some(*batch),
+                        false,
+                        *quiet,
+                        &self.style,
+                    )
+                    .await?;
+                    Ok(true)
+                }
+                // Killing and cleaning are simple messages.
+                Some(BatchCommand::Kill { .. }) | Some(BatchCommand::Clean { .. }) => Ok(false),
+            },
             SubCommand::Wait {
                 task_ids,
                 group,
@@ -209,6 +477,7 @@ impl Client {
                     &mut self.stream,
                     task_ids,
                     &group,
+                    None,
                     *all,
                     *quiet,
                     &self.style,
@@ -220,6 +489,7 @@ impl Client {
                 task_ids,
                 all_failed,
                 failed_in_group,
+                failed_in_batch,
                 start_immediately,
                 stashed,
                 in_place,
@@ -236,12 +506,14 @@ impl Client {
                     task_ids.clone(),
                     *all_failed,
                     failed_in_group.clone(),
+                    *failed_in_batch,
                     *start_immediately,
                     *stashed,
                     in_place,
                     *edit,
                     *edit_path,
                     *edit_label,
+                    self.if_revision,
                 )
                 .await?;
                 Ok(true)
@@ -284,7 +556,15 @@ impl Client {
     async fn handle_simple_command(&mut self) -> Result<()> {
         // Create the message that should be sent to the daemon
         // depending on the given commandline options.
-        let message = self.get_message_from_opt()?;
+        let mut message = self.get_message_from_opt()?;
+        if let Some(revision) = self.if_revision {
+            message = ConditionalMessage {
+                if_revision: Some(revision),
+                if_status: BTreeMap::new(),
+                message: Box::new(message),
+            }
+            .into();
+        }
 
         // Create the message payload and send it to the daemon.
         send_message(message, &mut self.stream).await?;
@@ -320,6 +600,17 @@ impl Client {
                 print_logs(task_logs, &self.subcommand, &self.style, &self.settings)
             }
             Message::GroupResponse(groups) => print_groups(groups, &self.style),
+            Message::BackupResponse(backups) => print_backups(backups, &self.style),
+            Message::TemplateResponse(message) => print_templates(message, &self.style),
+            Message::AliasResponse(message) => {
+                let detailed = matches!(
+                    self.subcommand,
+                    SubCommand::Alias {
+                        cmd: Some(AliasCommand::Show { .. })
+                    }
+                );
+                print_aliases(message, detailed, &self.style)
+            }
             Message::Stream(text) => {
                 print!("{}", text);
                 io::stdout().flush().unwrap();
@@ -386,6 +677,18 @@ impl Client {
                 dependencies,
                 label,
                 print_task_id,
+                matrix,
+                separate_output,
+                timestamps,
+                log_limit,
+                log_limit_policy,
+                success_codes,
+                temporary_codes,
+                max_retries,
+                fail_if_output,
+                succeed_if_output,
+                stall_timeout,
+                stall_action,
             } => {
                 // Either take the user-specified path or default to the current working directory.
                 let path = working_directory
@@ -403,7 +706,7 @@ impl Client {
                         .collect();
                 }
 
-                AddMessage {
+                let message = AddMessage {
                     command: command.join(" "),
                     path,
                     // Catch the current environment for later injection into the task's process.
@@ -415,9 +718,81 @@ impl Client {
                     dependencies: dependencies.to_vec(),
                     label: label.clone(),
                     print_task_id: *print_task_id,
+                    batch: None,
+                    timeout: None,
+                    separate_output: *separate_output,
+                    timestamps: *timestamps,
+                    log_limit: log_limit.map(|size| LogLimit {
+                        size,
+                        policy: log_limit_policy.unwrap_or_default(),
+                    }),
+                    exit_code_policy: exit_code_policy(
+                        success_codes,
+                        temporary_codes,
+                        *max_retries,
+                    ),
+                    output_rules: output_rules(fail_if_output, succeed_if_output),
+                    stall_timeout: stall_timeout.map(|seconds| StallTimeout {
+                        seconds,
+                        action: stall_action.unwrap_or_default(),
+                    }),
+                };
+
+                // Parameter sweeps are expanded into a batch of tasks.
+                if !matrix.is_empty() {
+                    return Ok(expand_matrix(message, matrix)?.into());
                 }
-                .into()
+
+                message.into()
+            }
+            SubCommand::Template { cmd } => match cmd {
+                Some(TemplateCommand::Add {
+                    name,
+                    command,
+                    working_directory,
+                    group,
+                    label,
+                    env,
+                    timeout,
+                    after_previous,
+                }) => TemplateMessage::Add {
+                    name: name.clone(),
+                    template: TaskTemplate {
+                        command: command.join(" "),
+                        group: group.clone(),
+                        path: working_directory.clone(),
+                        envs: env.iter().cloned().collect(),
+                        label: label.clone(),
+                        timeout: *timeout,
+                        dependencies: if *after_previous {
+                            DependencyPolicy::AfterPrevious
+                        } else {
+                            DependencyPolicy::Independent
+                        },
+                        last_task: None,
+                    },
+                },
+                Some(TemplateCommand::Remove { name }) => TemplateMessage::Remove(name.clone()),
+                Some(TemplateCommand::List) | None => TemplateMessage::List,
             }
+            .into(),
+            SubCommand::Run {
+                template,
+                parameters,
+                group,
+                start_immediately,
+                stashed,
+            } => RunMessage {
+                template: template.clone(),
+                parameters: parameters.iter().cloned().collect(),
+                path: current_dir()?,
+                // Catch the current environment for later injection into the task's process.
+                envs: HashMap::from_iter(vars()),
+                group: group.clone(),
+                start_immediately: *start_immediately,
+                stashed: *stashed,
+            }
+            .into(),
             SubCommand::Remove { task_ids } => {
                 if self.settings.client.show_confirmation_questions {
                     self.handle_user_confirmation("remove", task_ids)?;
@@ -444,21 +819,23 @@ impl Client {
             SubCommand::Start {
                 task_ids,
                 group,
+                batch,
                 all,
                 children,
             } => StartMessage {
-                tasks: selection_from_params(*all, group, task_ids),
+                tasks: selection_from_params(*all, group, batch, task_ids),
                 children: *children,
             }
             .into(),
             SubCommand::Pause {
                 task_ids,
                 group,
+                batch,
                 wait,
                 all,
                 children,
             } => PauseMessage {
-                tasks: selection_from_params(*all, group, task_ids),
+                tasks: selection_from_params(*all, group, batch, task_ids),
                 wait: *wait,
                 children: *children,
             }
@@ -466,6 +843,7 @@ impl Client {
             SubCommand::Kill {
                 task_ids,
                 group,
+                batch,
                 all,
                 children,
                 signal,
@@ -474,7 +852,7 @@ impl Client {
                     self.handle_user_confirmation("kill", task_ids)?;
                 }
                 KillMessage {
-                    tasks: selection_from_params(*all, group, task_ids),
+                    tasks: selection_from_params(*all, group, batch, task_ids),
                     children: *children,
                     signal: signal.clone(),
                 }
@@ -494,19 +872,70 @@ impl Client {
                 None => GroupMessage::List,
             }
             .into(),
+            SubCommand::Alias { cmd } => match cmd {
+                Some(AliasCommand::Show { name }) => AliasMessage::Show(name.to_owned()),
+                Some(AliasCommand::List) | None => AliasMessage::List,
+            }
+            .into(),
+            SubCommand::Batch { cmd } => match cmd {
+                Some(BatchCommand::Kill { batch }) => {
+                    if self.settings.client.show_confirmation_questions {
+                        self.handle_user_confirmation(
+                            &format!("kill all tasks of batch {batch}"),
+                            &[],
+                        )?;
+                    }
+                    KillMessage {
+                        tasks: TaskSelection::Batch(*batch),
+                        children: false,
+                        signal: None,
+                    }
+                    .into()
+                }
+                Some(BatchCommand::Clean {
+                    batch,
+                    successful_only,
+                }) => CleanMessage {
+                    successful_only: *successful_only,
+                    group: None,
+                    batch: Some(*batch),
+                }
+                .into(),
+                _ => bail!("Batch commands have to be handled earlier"),
+            },
+            SubCommand::Backup { cmd } => match cmd {
+                Some(BackupCommand::Create) => BackupMessage::Create,
+                Some(BackupCommand::Restore { name }) => BackupMessage::Restore(name.to_owned()),
+                Some(BackupCommand::List) | None => BackupMessage::List,
+            }
+            .into(),
+            SubCommand::Task { cmd } => match cmd {
+                TaskCommand::Label { label } => TaskControlMessage::SetLabel(label.clone()),
+                TaskCommand::Progress { progress } => {
+                    TaskControlMessage::ReportProgress(progress.clone())
+                }
+                TaskCommand::FollowUp { command, label } => {
+                    TaskControlMessage::AddFollowUp(FollowUpMessage {
+                        command: command.join(" "),
+                        label: label.clone(),
+                    })
+                }
+                TaskCommand::Retry => TaskControlMessage::Retry,
+            }
+            .into(),
             SubCommand::Status { .. } => Message::Status,
-            SubCommand::Log {
-                task_ids,
-                lines,
-                full,
-                ..
-            } => {
-                let lines = determine_log_line_amount(*full, lines);
+            SubCommand::Log { task_ids, .. } => {
+                let filter = OutputFilter::from_cli(&self.subcommand);
 
                 let message = LogRequestMessage {
                     task_ids: task_ids.clone(),
                     send_logs: !self.settings.client.read_local_logs,
-                    lines,
+                    lines: None,
+                    range: filter.range,
+                    chunk_size: None,
+                    stream: filter.stream,
+                    timestamps: filter.timestamps,
+                    since: filter.since,
                 };
                 Message::Log(message)
             }
@@ -518,9 +947,11 @@ impl Client {
             SubCommand::Clean {
                 successful_only,
                 group,
+                batch,
             } => CleanMessage {
                 successful_only: *successful_only,
                 group: group.clone(),
+                batch: *batch,
             }
             .into(),
             SubCommand::Reset { children, force } => {
@@ -533,7 +964,16 @@ impl Client {
                 }
                 .into()
             }
-            SubCommand::Shutdown => Shutdown::Graceful.into(),
+            SubCommand::Shutdown { drain, deadline } => {
+                if *drain {
+                    Shutdown::Drain {
+                        deadline: *deadline,
+                    }
+                    .into()
+                } else {
+                    Shutdown::Graceful.into()
+                }
+            }
             SubCommand::Parallel {
                 parallel_tasks,
                 group,
@@ -553,6 +993,8 @@ impl Client {
             SubCommand::Restart { .. } => bail!("Restarts have to be handled earlier"),
             SubCommand::Edit { .. } => bail!("Edits have to be handled earlier"),
             SubCommand::Wait { .. } => bail!("Wait has to be handled earlier"),
+            SubCommand::Grep { .. } => bail!("Grep has to be handled earlier"),
+            SubCommand::Submit { .. } => bail!("Submissions have to be handled earlier"),
         })
     }
 }
@@ -1,8 +1,10 @@
+use std::collections::{BTreeMap, HashMap};
 use std::env;
-use std::io::{Read, Seek, SeekFrom, Write};
+use std::io::Write;
 use std::path::{Path, PathBuf};
 
 use anyhow::{bail, Context, Result};
+use serde_derive::{Deserialize, Serialize};
 use tempfile::NamedTempFile;
 
 use pueue_lib::network::message::*;
@@ -16,15 +18,23 @@ use pueue_lib::process_helper::compile_shell_command;
 ///
 /// After receiving the task information, the user can then edit it in their editor.
 /// Upon exiting the text editor, the line will then be read and sent to the server
+///
+/// If `if_revision` is set, the task is only locked, if the daemon's state still has this
+/// revision. The lock then ensures that nobody else changes the task while editing.
+#[allow(clippy::too_many_arguments)]
 pub async fn edit(
     stream: &mut GenericStream,
     task_id: usize,
     edit_command: bool,
     edit_path: bool,
     edit_label: bool,
+    edit_envs: bool,
+    edit_group: bool,
+    edit_dependencies: bool,
+    if_revision: Option<u64>,
 ) -> Result<Message> {
     // Request the data to edit from the server and issue a task-lock while doing so.
-    let init_message = Message::EditRequest(task_id);
+    let init_message = edit_request(Message::EditRequest(task_id), if_revision);
     send_message(init_message, stream).await?;
 
     let init_response = receive_message(stream).await?;
@@ -39,7 +49,8 @@ pub async fn edit(
     };
 
     // Edit the command if explicitly specified or if no flags are provided (the default)
-    let edit_command = edit_command || !edit_path && !edit_label;
+    let edit_command = edit_command
+        || !edit_path && !edit_label && !edit_envs && !edit_group && !edit_dependencies;
 
     // Edit all requested properties.
     let edit_result = edit_task_properties(
@@ -49,7 +60,17 @@ pub async fn edit(
         edit_command,
         edit_path,
         edit_label,
-    );
+    )
+    .and_then(|mut props| {
+        edit_queue_properties(
+            &mut props,
+            &init_response,
+            edit_envs,
+            edit_group,
+            edit_dependencies,
+        )?;
+        Ok(props)
+    });
 
     // Any error while editing will result in the client aborting the editing process.
     // However, as the daemon moves tasks that're edited into the `Locked` state, we cannot simply
@@ -58,18 +79,7 @@ pub async fn edit(
     let edited_props = match edit_result {
         Ok(inner) => inner,
         Err(error) => {
-            eprintln!("Encountered an error while editing. Trying to restore the task's status.");
-            // Notify the daemon that something went wrong.
-            let edit_message = Message::EditRestore(task_id);
-            send_message(edit_message, stream).await?;
-            let response = receive_message(stream).await?;
-            match response {
-                Message::Failure(message) | Message::Success(message) => {
-                    eprintln!("{message}");
-                }
-                _ => eprintln!("Received unknown resonse: {response:?}"),
-            };
-
+            restore_tasks(stream, &[task_id]).await?;
             return Err(error);
         }
     };
@@ -81,18 +91,168 @@ pub async fn edit(
         path: edited_props.path,
         label: edited_props.label,
         delete_label: edited_props.delete_label,
+        envs: edited_props.envs,
+        group: edited_props.group,
+        dependencies: edited_props.dependencies,
     };
     send_message(edit_message, stream).await?;
 
     Ok(receive_message(stream).await?)
 }
 
+/// Edit several tasks in a single editor session.
+///
+/// All tasks are locked at once and presented as a YAML list of their editable properties.
+/// Only the changed properties are sent back to the daemon, which applies all changes at once.
+/// Just like in `edit`, `if_revision` is checked when the tasks are locked.
+pub async fn bulk_edit(
+    stream: &mut GenericStream,
+    task_ids: Vec<usize>,
+    if_revision: Option<u64>,
+) -> Result<Message> {
+    // Request the data to edit from the server and lock all tasks while doing so.
+    let init_message = edit_request(Message::BulkEditRequest(task_ids), if_revision);
+    send_message(init_message, stream).await?;
+    let init_response = match receive_message(stream).await? {
+        Message::BulkEditResponse(message) => message,
+        response => return Ok(response),
+    };
+    if !init_response.skipped.is_empty() {
+        eprintln!(
+            "Skipping tasks that don't exist or aren't queued/stashed: {:?}",
+            init_response.skipped
+        );
+    }
+
+    // Just like in `edit`, restore all locked tasks if anything goes wrong while editing.
+    let tasks = match edit_tasks_file(&init_response.tasks) {
+        Ok(tasks) => tasks,
+        Err(error) => {
+            let task_ids: Vec<usize> = init_response
+                .tasks
+                .iter()
+                .map(|task| task.task_id)
+                .collect();
+            restore_tasks(stream, &task_ids).await?;
+            return Err(error);
+        }
+    };
+
+    send_message(BulkEditMessage { tasks }, stream).await?;
+
+    Ok(receive_message(stream).await?)
+}
+
+/// Only request the tasks to edit, if the daemon's state still has the expected revision.
+fn edit_request(message: Message, if_revision: Option<u64>) -> Message {
+    match if_revision {
+        Some(revision) => ConditionalMessage {
+            if_revision: Some(revision),
+            if_status: BTreeMap::new(),
+            message: Box::new(message),
+        }
+        .into(),
+        None => message,
+    }
+}
+
+/// The editable properties of a single task, as they're presented when editing multiple tasks.
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct EditableTask {
+    id: usize,
+    command: String,
+    path: PathBuf,
+    label: Option<String>,
+    group: String,
+    dependencies: Vec<usize>,
+    envs: BTreeMap<String, String>,
+}
+
+/// Open all given tasks as a single YAML file in the editor.
+/// Return an edit message for each task, which only contains the changed properties.
+fn edit_tasks_file(originals: &[EditResponseMessage]) -> Result<Vec<EditMessage>> {
+    let editable: Vec<EditableTask> = originals
+        .iter()
+        .map(|task| EditableTask {
+            id: task.task_id,
+            command: task.command.clone(),
+            path: task.path.clone(),
+            label: task.label.clone(),
+            group: task.group.clone(),
+            dependencies: task.dependencies.clone(),
+            envs: task.envs.clone().into_iter().collect(),
+        })
+        .collect();
+    let content = serde_yaml::to_string(&editable).context("Failed to serialize tasks")?;
+    let content = format!(
+        "# Edit the properties of the tasks below. Tasks cannot be added or removed.\n{content}"
+    );
+
+    let edited = edit_line(&content)?;
+    let edited: Vec<EditableTask> =
+        serde_yaml::from_str(&edited).context("Failed to parse the edited tasks")?;
+
+    // Make sure that exactly the same tasks are still there.
+    let mut original_ids: Vec<usize> = originals.iter().map(|task| task.task_id).collect();
+    let mut edited_ids: Vec<usize> = edited.iter().map(|task| task.id).collect();
+    original_ids.sort_unstable();
+    edited_ids.sort_unstable();
+    if original_ids != edited_ids {
+        bail!("Tasks cannot be added or removed while editing. Expected tasks {original_ids:?}");
+    }
+
+    let mut messages = Vec::new();
+    for task in edited {
+        let original = originals
+            .iter()
+            .find(|original| original.task_id == task.id)
+            .expect("We ensured that the task exists.");
+
+        let envs: HashMap<String, String> = task.envs.into_iter().collect();
+        let label = task.label.filter(|label| !label.trim().is_empty());
+        messages.push(EditMessage {
+            task_id: task.id,
+            command: Some(task.command).filter(|command| command != &original.command),
+            path: Some(task.path).filter(|path| path != &original.path),
+            delete_label: label.is_none() && original.label.is_some(),
+            label: label.filter(|label| Some(label) != original.label.as_ref()),
+            envs: Some(envs).filter(|envs| envs != &original.envs),
+            group: Some(task.group).filter(|group| group != &original.group),
+            dependencies: Some(task.dependencies)
+                .filter(|dependencies| dependencies != &original.dependencies),
+        });
+    }
+
+    Ok(messages)
+}
+
+/// Notify the daemon that editing failed, so it can restore the tasks' previous status.
+async fn restore_tasks(stream: &mut GenericStream, task_ids: &[usize]) -> Result<()> {
+    eprintln!("Encountered an error while editing. Trying to restore the task's status.");
+    for task_id in task_ids {
+        send_message(Message::EditRestore(*task_id), stream).await?;
+        let response = receive_message(stream).await?;
+        match response {
+            Message::Failure(message) | Message::Success(message) => {
+                eprintln!("{message}");
+            }
+            _ => eprintln!("Received unknown resonse: {response:?}"),
+        };
+    }
+
+    Ok(())
+}
+
 #[derive(Default)]
 pub struct EditedProperties {
     pub command: Option<String>,
     pub path: Option<PathBuf>,
     pub label: Option<String>,
     pub delete_label: bool,
+    pub envs: Option<HashMap<String, String>>,
+    pub group: Option<String>,
+    pub dependencies: Option<Vec<usize>>,
 }
 
 /// Takes several task properties and edit them if requested.
@@ -142,6 +302,74 @@ pub fn edit_task_properties(
     Ok(props)
 }
 
+/// Edit the properties that only matter for queued tasks, if requested.
+/// These are the environment variables, the group and the dependencies of the task.
+///
+/// Environment variables are edited as `KEY=VALUE` lines, dependencies as a whitespace or comma
+/// separated list of task ids.
+fn edit_queue_properties(
+    props: &mut EditedProperties,
+    original: &EditResponseMessage,
+    edit_envs: bool,
+    edit_group: bool,
+    edit_dependencies: bool,
+) -> Result<()> {
+    // Update the environment variables if requested.
+    if edit_envs {
+        // Sort the variables, so they're easier to find in the editor.
+        let mut lines: Vec<String> = original
+            .envs
+            .iter()
+            .map(|(key, value)| format!("{key}={value}"))
+            .collect();
+        lines.sort();
+
+        let edited = edit_line(&lines.join("\n"))?;
+        let mut envs = HashMap::new();
+        for line in edited.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match line.split_once('=') {
+                Some((key, value)) if !key.trim().is_empty() => {
+                    envs.insert(key.trim().to_string(), value.to_string());
+                }
+                _ => bail!("Invalid environment variable '{line}'. Expected KEY=VALUE"),
+            }
+        }
+        props.envs = Some(envs);
+    }
+
+    // Update the group if requested.
+    if edit_group {
+        let group = edit_line(&original.group)?;
+        if group.is_empty() {
+            bail!("The group of a task cannot be empty");
+        }
+        props.group = Some(group);
+    }
+
+    // Update the dependencies if requested.
+    if edit_dependencies {
+        let ids: Vec<String> = original.dependencies.iter().map(usize::to_string).collect();
+        let edited = edit_line(&ids.join(" "))?;
+
+        let mut dependencies = Vec::new();
+        for id in edited.split(|char: char| char.is_whitespace() || char == ',') {
+            if id.is_empty() {
+                continue;
+            }
+            let id = id
+                .parse::<usize>()
+                .with_context(|| format!("Invalid task id '{id}'"))?;
+            dependencies.push(id);
+        }
+        props.dependencies = Some(dependencies);
+    }
+
+    Ok(())
+}
+
 /// This function enables the user to edit a task's details.
 /// Save any string to a temporary file, which is opened in the specified `$EDITOR`.
 /// As soon as the editor is closed, read the file content and return the line.
@@ -169,13 +397,9 @@ fn edit_line(line: &str) -> Result<String> {
     }
 
     // Read the file.
-    let mut file = file.into_file();
-    file.seek(SeekFrom::Start(0))
-        .context("Couldn't seek to start of file. Aborting.")?;
-
-    let mut line = String::new();
-    file.read_to_string(&mut line)
-        .context("Failed to read Command after editing")?;
+    // The file is opened again, as some editors replace the file instead of writing to it.
+    let mut line =
+        std::fs::read_to_string(file.path()).context("Failed to read Command after editing")?;
 
     // Remove any trailing newlines from the command.
     while line.ends_with('\n') || line.ends_with('\r') {
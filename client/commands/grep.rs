@@ -0,0 +1,98 @@
+use std::collections::BTreeMap;
+
+use anyhow::{bail, Context, Result};
+
+use pueue_lib::log::{build_search_pattern, search_log};
+use pueue_lib::network::message::*;
+use pueue_lib::network::protocol::*;
+use pueue_lib::settings::Settings;
+
+use super::get_state;
+use crate::display::{print_log_matches, OutputStyle};
+use crate::query::apply_query;
+
+/// Search the logs of all tasks in the given group, which match the given `pueue status` query.
+/// If no group is given, tasks of all groups are searched.
+///
+/// The logs are searched directly, if `read_local_logs` is set.
+/// Otherwise, the daemon searches its logs and only sends back the matching lines.
+/// In both cases, the search stops after `max_matches` matching lines.
+///
+/// Just like `grep`, the client exits with 1, if no line matched.
+#[allow(clippy::too_many_arguments)]
+pub async fn grep(
+    stream: &mut GenericStream,
+    settings: &Settings,
+    style: &OutputStyle,
+    pattern: &str,
+    fixed_strings: bool,
+    group: &Option<String>,
+    query: &Option<String>,
+    context: usize,
+    ignore_case: bool,
+    max_matches: usize,
+) -> Result<()> {
+    // Check the pattern, before anything is sent to the daemon.
+    let regex = build_search_pattern(pattern, fixed_strings, ignore_case)
+        .context("Invalid search pattern")?;
+
+    let state = get_state(stream).await?;
+    let mut tasks = state
+        .tasks
+        .into_values()
+        .filter(|task| group.as_ref().map_or(true, |group| &task.group == group))
+        .collect();
+    if let Some(query) = query {
+        let query_result = apply_query(query.clone())?;
+        tasks = query_result.apply_filters(tasks);
+        tasks = query_result.order_tasks(tasks);
+        tasks = query_result.limit_tasks(tasks);
+    }
+    let task_ids: Vec<usize> = tasks.iter().map(|task| task.id).collect();
+
+    let response = if settings.client.read_local_logs {
+        let pueue_directory = settings.shared.pueue_directory();
+        let mut matches = BTreeMap::new();
+        let mut remaining = max_matches;
+        for task_id in task_ids {
+            if remaining == 0 {
+                break;
+            }
+            let lines = search_log(task_id, &pueue_directory, &regex, context, remaining)?;
+            if !lines.is_empty() {
+                remaining -= lines.iter().filter(|line| line.matches).count();
+                matches.insert(task_id, lines);
+            }
+        }
+        GrepResponseMessage {
+            matches,
+            truncated: remaining == 0,
+        }
+    } else {
+        let message = GrepRequestMessage {
+            task_ids,
+            pattern: pattern.to_string(),
+            fixed_strings,
+            ignore_case,
+            context,
+            max_matches,
+        };
+        send_message(message, stream).await?;
+        match receive_message(stream).await? {
+            Message::GrepResponse(response) => response,
+            Message::Failure(text) => bail!(text),
+            _ => bail!("Received unexpected response while searching logs"),
+        }
+    };
+
+    print_log_matches(&response.matches, context, style);
+    if response.truncated {
+        eprintln!("Stopped searching after {max_matches} matching lines.");
+    }
+
+    if response.matches.is_empty() {
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
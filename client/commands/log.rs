@@ -0,0 +1,188 @@
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+
+use anyhow::{bail, Result};
+use snap::read::FrameDecoder;
+
+use pueue_lib::network::message::*;
+use pueue_lib::network::protocol::*;
+use pueue_lib::settings::Settings;
+use pueue_lib::task::TaskStatus;
+
+use crate::cli::SubCommand;
+use crate::display::{decompress_and_print_remote_log, print_log, OutputFilter, OutputStyle};
+
+/// The maximum amount of uncompressed bytes, that the daemon sends in a single chunk.
+const LOG_CHUNK_SIZE: usize = 256 * 1024;
+
+/// Request the logs of tasks from the daemon and print them while they're being received.
+///
+/// The daemon sends each task on its own, followed by the task's output in chunks.
+/// That way, only a single chunk has to be kept in memory at any time.
+pub async fn print_chunked_logs(
+    stream: &mut GenericStream,
+    mut message: LogRequestMessage,
+    cli_command: &SubCommand,
+    style: &OutputStyle,
+    settings: &Settings,
+) -> Result<()> {
+    message.chunk_size = Some(LOG_CHUNK_SIZE);
+    let specific_tasks = !message.task_ids.is_empty();
+    send_message(message, stream).await?;
+
+    let filter = OutputFilter::from_cli(cli_command);
+    let mut received_tasks = false;
+    // The header of a task's output is only printed, once its first chunk has been received.
+    // This holds whether the output of the current task is complete, until then.
+    let mut pending_header = None;
+    loop {
+        match receive_message(stream).await? {
+            Message::LogResponse(task_logs) => {
+                for task_log in task_logs.values() {
+                    // Add a newline between two tasks, if this task is going to be printed.
+                    let shown = matches!(
+                        &task_log.task.status,
+                        TaskStatus::Done(_) | TaskStatus::Running | TaskStatus::Paused,
+                    );
+                    if received_tasks && shown {
+                        println!();
+                    }
+                    received_tasks = true;
+
+                    print_log(task_log, style, settings, filter);
+                    pending_header = Some(task_log.output_complete);
+                }
+            }
+            Message::LogChunk(chunk) => {
+                if let Some(output_complete) = pending_header.take() {
+                    filter.print_header(style, output_complete);
+                }
+                if let Err(err) = decompress_and_print_remote_log(&chunk.output) {
+                    println!("Error while parsing stdout: {err}");
+                }
+            }
+            Message::Close => break,
+            Message::Failure(text) => bail!(text),
+            _ => bail!("Received unexpected response while receiving logs"),
+        }
+    }
+
+    if !received_tasks {
+        if specific_tasks {
+            println!("There are no finished tasks for your specified ids");
+        } else {
+            println!("There are no finished tasks");
+        }
+    }
+
+    Ok(())
+}
+
+/// Request the logs of tasks from the daemon and print them as json while they're being received.
+///
+/// The resulting json has the same format as the one of [crate::display::print_logs].
+/// The output of each task is written as a json string piece by piece, so only a single chunk
+/// has to be kept in memory at any time, even if the full output is requested.
+pub async fn print_chunked_json_logs(
+    stream: &mut GenericStream,
+    mut message: LogRequestMessage,
+) -> Result<()> {
+    message.chunk_size = Some(LOG_CHUNK_SIZE);
+    send_message(message, stream).await?;
+
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+    write!(stdout, "{{")?;
+
+    let mut received_tasks = false;
+    // Bytes at the end of the last chunk, which don't form a complete UTF-8 character yet.
+    let mut remainder = Vec::new();
+    loop {
+        match receive_message(stream).await? {
+            Message::LogResponse(task_logs) => {
+                for (id, task_log) in task_logs {
+                    if received_tasks {
+                        finish_json_output(&mut stdout, &mut remainder)?;
+                        write!(stdout, ",")?;
+                    }
+                    received_tasks = true;
+
+                    let mut task = task_log.task;
+                    task.envs = HashMap::new();
+                    let task = serde_json::to_string(&task)?;
+                    write!(stdout, "\"{id}\":{{\"task\":{task},\"output\":\"")?;
+                }
+            }
+            Message::LogChunk(chunk) => {
+                let mut output = Vec::new();
+                if let Err(err) = FrameDecoder::new(&chunk.output[..]).read_to_end(&mut output) {
+                    output =
+                        format!("(Pueue error) Failed to decompress remote log output: {err:?}")
+                            .into_bytes();
+                }
+                remainder.extend_from_slice(&output);
+                write_json_output(&mut stdout, &mut remainder)?;
+            }
+            Message::Close => break,
+            Message::Failure(text) => bail!(text),
+            _ => bail!("Received unexpected response while receiving logs"),
+        }
+    }
+
+    if received_tasks {
+        finish_json_output(&mut stdout, &mut remainder)?;
+    }
+    writeln!(stdout, "}}")?;
+
+    Ok(())
+}
+
+/// Write the given output as part of a json string.
+/// A trailing incomplete UTF-8 character is kept in `output`, as the rest of it is still to come.
+/// Invalid UTF-8 is replaced, just like it's done by [String::from_utf8_lossy].
+fn write_json_output(stdout: &mut impl Write, output: &mut Vec<u8>) -> Result<()> {
+    let mut text = String::new();
+    let mut rest = &output[..];
+    while !rest.is_empty() {
+        match std::str::from_utf8(rest) {
+            Ok(valid) => {
+                text.push_str(valid);
+                rest = &[];
+            }
+            Err(err) => {
+                let (valid, invalid) = rest.split_at(err.valid_up_to());
+                text.push_str(&String::from_utf8_lossy(valid));
+                rest = invalid;
+                match err.error_len() {
+                    Some(len) => {
+                        text.push(char::REPLACEMENT_CHARACTER);
+                        rest = &rest[len..];
+                    }
+                    // The character is incomplete, keep it for the next chunk.
+                    None => break,
+                }
+            }
+        }
+    }
+    *output = rest.to_vec();
+
+    write_json_text(stdout, &text)
+}
+
+/// Write the rest of a task's output and close its json object.
+fn finish_json_output(stdout: &mut impl Write, output: &mut Vec<u8>) -> Result<()> {
+    write_json_text(stdout, &String::from_utf8_lossy(output))?;
+    output.clear();
+    write!(stdout, "\"}}")?;
+
+    Ok(())
+}
+
+/// Write the escaped text without the surrounding quotes, as the json string is continued
+/// with the next chunk.
+fn write_json_text(stdout: &mut impl Write, text: &str) -> Result<()> {
+    let json = serde_json::to_string(text)?;
+    write!(stdout, "{}", &json[1..json.len() - 1])?;
+
+    Ok(())
+}
@@ -0,0 +1,167 @@
+use std::collections::HashSet;
+
+use anyhow::{bail, Result};
+
+use pueue_lib::network::message::*;
+
+/// Expand an [AddMessage] into one task per combination of the matrix parameters.
+///
+/// The first parameter is the outermost one, i.e. it changes the slowest.
+/// Each task gets its parameters
+/// - substituted for any `{name}` in the command,
+/// - injected as environment variables with the parameter's name,
+/// - listed in its label.
+///
+/// The tasks are returned as a [SubmitMessage], which puts all of them into a new batch.
+pub fn expand_matrix(
+    message: AddMessage,
+    matrix: &[(String, Vec<String>)],
+) -> Result<SubmitMessage> {
+    let mut names = HashSet::new();
+    for (name, _) in matrix.iter() {
+        if !names.insert(name) {
+            bail!("Matrix parameter \"{name}\" is specified multiple times");
+        }
+    }
+
+    // Build the cartesian product of all parameter values.
+    let mut combinations: Vec<Vec<(&str, &str)>> = vec![Vec::new()];
+    for (name, values) in matrix.iter() {
+        let mut expanded = Vec::new();
+        for combination in combinations.iter() {
+            for value in values.iter() {
+                let mut combination = combination.clone();
+                combination.push((name.as_str(), value.as_str()));
+                expanded.push(combination);
+            }
+        }
+        combinations = expanded;
+    }
+
+    let mut tasks = Vec::new();
+    for (index, combination) in combinations.into_iter().enumerate() {
+        let mut task = message.clone();
+
+        let mut parameters = Vec::new();
+        for (name, value) in combination {
+            task.command = task.command.replace(&format!("{{{name}}}"), value);
+            task.envs.insert(name.to_string(), value.to_string());
+            parameters.push(format!("{name}={value}"));
+        }
+
+        let parameters = parameters.join(" ");
+        task.label = match &message.label {
+            Some(label) => Some(format!("{label} {parameters}")),
+            None => Some(parameters),
+        };
+
+        tasks.push(SubmittedTask {
+            name: index.to_string(),
+            needs: Vec::new(),
+            task,
+        });
+    }
+
+    Ok(SubmitMessage {
+        tasks,
+        create_batch: true,
+        batch_label: Some(message.label.unwrap_or(message.command)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+
+    use pretty_assertions::assert_eq;
+
+    use pueue_lib::state::PUEUE_DEFAULT_GROUP;
+
+    use super::*;
+
+    fn get_add_message(command: &str) -> AddMessage {
+        AddMessage {
+            command: command.to_string(),
+            path: PathBuf::from("/tmp"),
+            envs: HashMap::new(),
+            start_immediately: false,
+            stashed: false,
+            group: PUEUE_DEFAULT_GROUP.to_string(),
+            enqueue_at: None,
+            dependencies: Vec::new(),
+            label: None,
+            print_task_id: false,
+            batch: None,
+            timeout: None,
+            separate_output: false,
+            timestamps: false,
+            log_limit: None,
+            exit_code_policy: None,
+            output_rules: None,
+            stall_timeout: None,
+        }
+    }
+
+    #[test]
+    fn expand_cartesian_product() -> Result<()> {
+        let matrix = vec![
+            (
+                "lr".to_string(),
+                vec!["0.1".to_string(), "0.01".to_string()],
+            ),
+            ("seed".to_string(), vec!["1".to_string(), "2".to_string()]),
+        ];
+        let message = expand_matrix(get_add_message("train --lr {lr} --seed {seed}"), &matrix)?;
+
+        assert!(message.create_batch);
+        assert_eq!(
+            message.batch_label,
+            Some("train --lr {lr} --seed {seed}".to_string())
+        );
+
+        let commands: Vec<&str> = message
+            .tasks
+            .iter()
+            .map(|task| task.task.command.as_str())
+            .collect();
+        assert_eq!(
+            commands,
+            vec![
+                "train --lr 0.1 --seed 1",
+                "train --lr 0.1 --seed 2",
+                "train --lr 0.01 --seed 1",
+                "train --lr 0.01 --seed 2",
+            ]
+        );
+
+        let last = &message.tasks[3].task;
+        assert_eq!(last.label, Some("lr=0.01 seed=2".to_string()));
+        assert_eq!(last.envs.get("lr"), Some(&"0.01".to_string()));
+        assert_eq!(last.envs.get("seed"), Some(&"2".to_string()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn expand_keeps_user_label() -> Result<()> {
+        let mut add_message = get_add_message("sleep {time}");
+        add_message.label = Some("nap".to_string());
+        let matrix = vec![("time".to_string(), vec!["1".to_string()])];
+
+        let message = expand_matrix(add_message, &matrix)?;
+        assert_eq!(message.batch_label, Some("nap".to_string()));
+        assert_eq!(message.tasks[0].task.label, Some("nap time=1".to_string()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn expand_rejects_duplicate_parameters() {
+        let matrix = vec![
+            ("seed".to_string(), vec!["1".to_string()]),
+            ("seed".to_string(), vec!["2".to_string()]),
+        ];
+        assert!(expand_matrix(get_add_message("echo {seed}"), &matrix).is_err());
+    }
+}
@@ -12,14 +12,22 @@ use pueue_lib::state::State;
 
 mod edit;
 mod format_state;
+mod grep;
 mod local_follow;
+mod log;
+mod matrix;
 mod restart;
+mod submit;
 mod wait;
 
-pub use edit::edit;
+pub use edit::{bulk_edit, edit};
 pub use format_state::format_state;
+pub use grep::grep;
 pub use local_follow::local_follow;
+pub use log::{print_chunked_json_logs, print_chunked_logs};
+pub use matrix::expand_matrix;
 pub use restart::restart;
+pub use submit::submit;
 pub use wait::wait;
 
 // This is a helper function for easy retrieval of the current daemon state.
@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+
 use anyhow::{bail, Result};
 
 use pueue_lib::network::message::*;
@@ -18,12 +20,14 @@ pub async fn restart(
     task_ids: Vec<usize>,
     all_failed: bool,
     failed_in_group: Option<String>,
+    failed_in_batch: Option<usize>,
     start_immediately: bool,
     stashed: bool,
     in_place: bool,
     edit_command: bool,
     edit_path: bool,
     edit_label: bool,
+    if_revision: Option<u64>,
 ) -> Result<()> {
     let new_status = if stashed {
         TaskStatus::Stashed { enqueue_at: None }
@@ -36,34 +40,38 @@ pub async fn restart(
     // Filter to get done tasks
     let done_filter = |task: &Task| task.is_done();
 
-    let (matching, mismatching) = if all_failed || failed_in_group.is_some() {
-        // Either all failed tasks or all failed tasks of a specific group need to be restarted.
-
-        // First we have to get all finished tasks (Done)
-        let (matching, _) = if let Some(group) = failed_in_group {
-            state.filter_tasks_of_group(done_filter, &group)
+    let (matching, mismatching) =
+        if all_failed || failed_in_group.is_some() || failed_in_batch.is_some() {
+            // Either all failed tasks or all failed tasks of a specific group or batch need to be
+            // restarted.
+
+            // First we have to get all finished tasks (Done)
+            let (matching, _) = if let Some(group) = failed_in_group {
+                state.filter_tasks_of_group(done_filter, &group)
+            } else if let Some(batch) = failed_in_batch {
+                state.filter_tasks_of_batch(done_filter, batch)
+            } else {
+                state.filter_tasks(done_filter, None)
+            };
+
+            // now pick the failed tasks
+            let failed = matching
+                .into_iter()
+                .filter(|task_id| {
+                    let task = state.tasks.get(task_id).unwrap();
+                    !matches!(task.status, TaskStatus::Done(TaskResult::Success))
+                })
+                .collect();
+
+            // We return an empty vec for the mismatching tasks, since there shouldn't be any.
+            // Any User provided ids are ignored in this mode.
+            (failed, Vec::new())
+        } else if task_ids.is_empty() {
+            bail!("Please provide the ids of the tasks you want to restart.");
         } else {
-            state.filter_tasks(done_filter, None)
+            state.filter_tasks(done_filter, Some(task_ids))
         };
 
-        // now pick the failed tasks
-        let failed = matching
-            .into_iter()
-            .filter(|task_id| {
-                let task = state.tasks.get(task_id).unwrap();
-                !matches!(task.status, TaskStatus::Done(TaskResult::Success))
-            })
-            .collect();
-
-        // We return an empty vec for the mismatching tasks, since there shouldn't be any.
-        // Any User provided ids are ignored in this mode.
-        (failed, Vec::new())
-    } else if task_ids.is_empty() {
-        bail!("Please provide the ids of the tasks you want to restart.");
-    } else {
-        state.filter_tasks(done_filter, Some(task_ids))
-    };
-
     // Build a RestartMessage, if the tasks should be replaced instead of creating a copy of the
     // original task. This is only important, if replace is `True`.
     let mut restart_message = RestartMessage {
@@ -71,6 +79,9 @@ pub async fn restart(
         stashed,
         start_immediately,
     };
+    // If the tasks should only be restarted for a specific revision of the state, all new tasks
+    // are sent at once in a single batch.
+    let mut add_messages = Vec::new();
 
     // Go through all Done commands we found and restart them
     for task_id in &matching {
@@ -115,8 +126,21 @@ pub async fn restart(
             dependencies: Vec::new(),
             label: edited_props.label.or_else(|| task.label.clone()),
             print_task_id: false,
+            batch: task.batch,
+            timeout: task.timeout,
+            separate_output: task.separate_output,
+            timestamps: task.timestamps,
+            log_limit: task.log_limit,
+            exit_code_policy: task.exit_code_policy.clone(),
+            output_rules: task.output_rules.clone(),
+            stall_timeout: task.stall_timeout,
         };
 
+        if if_revision.is_some() {
+            add_messages.push(add_task_message.into());
+            continue;
+        }
+
         // Send the cloned task to the daemon and abort on any failure messages.
         send_message(add_task_message, stream).await?;
         if let Message::Failure(message) = receive_message(stream).await? {
@@ -124,12 +148,35 @@ pub async fn restart(
         };
     }
 
-    // Send the singular in-place restart message to the daemon.
-    if in_place {
-        send_message(restart_message, stream).await?;
-        if let Message::Failure(message) = receive_message(stream).await? {
-            bail!(message);
-        };
+    // Send the singular in-place restart message or the batch of new tasks to the daemon.
+    let message = if in_place {
+        Some(Message::from(restart_message))
+    } else if if_revision.is_some() {
+        Some(Message::Batch(add_messages))
+    } else {
+        None
+    };
+    if let Some(mut message) = message {
+        if let Some(revision) = if_revision {
+            message = ConditionalMessage {
+                if_revision: Some(revision),
+                if_status: BTreeMap::new(),
+                message: Box::new(message),
+            }
+            .into();
+        }
+
+        send_message(message, stream).await?;
+        match receive_message(stream).await? {
+            Message::Failure(message) => bail!(message),
+            Message::BatchResponse(response) if !response.applied => {
+                if let Some(Message::Failure(message)) = response.responses.last() {
+                    bail!(message.clone());
+                }
+                bail!("Failed to restart tasks");
+            }
+            _ => (),
+        }
     }
 
     if !matching.is_empty() {
@@ -0,0 +1,184 @@
+use std::collections::{BTreeMap, HashMap};
+use std::env::{current_dir, vars};
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use serde_derive::Deserialize;
+
+use pueue_lib::network::message::*;
+use pueue_lib::network::protocol::*;
+use pueue_lib::state::PUEUE_DEFAULT_GROUP;
+
+/// The representation of a workflow file.
+///
+/// The tasks are kept as a raw mapping, as their order in the file should be preserved.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Workflow {
+    tasks: serde_yaml::Mapping,
+}
+
+/// A single task of a workflow file.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WorkflowTask {
+    command: String,
+    path: Option<PathBuf>,
+    group: Option<String>,
+    label: Option<String>,
+    #[serde(default)]
+    envs: HashMap<String, String>,
+    #[serde(default)]
+    needs: Vec<String>,
+}
+
+/// Add all tasks of a workflow file at once.
+///
+/// The workflow file is read and converted into a single [SubmitMessage].
+/// The daemon then resolves the symbolic names of the `needs` to the actual task ids.
+/// If `batch` is set, all tasks are put into a new batch, which is labeled with the file name.
+/// If `if_revision` is set, the tasks are only added, if the daemon's state still has this revision.
+pub async fn submit(
+    stream: &mut GenericStream,
+    workflow: &Path,
+    stashed: bool,
+    batch: bool,
+    if_revision: Option<u64>,
+) -> Result<Message> {
+    let content = read_to_string(workflow)
+        .with_context(|| format!("Failed to read workflow file at {workflow:?}"))?;
+    let mut message = parse_workflow(&content, stashed)?;
+    if batch {
+        message.create_batch = true;
+        message.batch_label = workflow
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string());
+    }
+
+    let message = match if_revision {
+        Some(revision) => ConditionalMessage {
+            if_revision: Some(revision),
+            if_status: BTreeMap::new(),
+            message: Box::new(message.into()),
+        }
+        .into(),
+        None => Message::from(message),
+    };
+
+    send_message(message, stream).await?;
+    let response = receive_message(stream).await?;
+
+    Ok(response)
+}
+
+/// Convert the content of a workflow file into a [SubmitMessage].
+fn parse_workflow(content: &str, stashed: bool) -> Result<SubmitMessage> {
+    let workflow: Workflow =
+        serde_yaml::from_str(content).context("Failed to deserialize workflow file")?;
+
+    let cwd = current_dir()?;
+    let mut tasks = Vec::new();
+    for (name, task) in workflow.tasks {
+        let name = match name.as_str() {
+            Some(name) => name.to_string(),
+            None => bail!("Task names in workflow files have to be strings, found {name:?}"),
+        };
+        let task: WorkflowTask = serde_yaml::from_value(task)
+            .with_context(|| format!("Failed to deserialize task \"{name}\""))?;
+
+        // Catch the current environment for later injection into the task's process.
+        // Variables of the workflow file take precedence.
+        let mut envs = HashMap::from_iter(vars());
+        envs.extend(task.envs);
+
+        tasks.push(SubmittedTask {
+            name: name.clone(),
+            needs: task.needs,
+            task: AddMessage {
+                command: task.command,
+                path: match task.path {
+                    Some(path) => cwd.join(path),
+                    None => cwd.clone(),
+                },
+                envs,
+                start_immediately: false,
+                stashed,
+                group: task
+                    .group
+                    .unwrap_or_else(|| PUEUE_DEFAULT_GROUP.to_string()),
+                enqueue_at: None,
+                dependencies: Vec::new(),
+                label: Some(task.label.unwrap_or(name)),
+                print_task_id: false,
+                batch: None,
+                timeout: None,
+                separate_output: false,
+                timestamps: false,
+                log_limit: None,
+                exit_code_policy: None,
+                output_rules: None,
+                stall_timeout: None,
+            },
+        });
+    }
+
+    Ok(SubmitMessage {
+        tasks,
+        create_batch: false,
+        batch_label: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn parse_keeps_order_and_names() -> Result<()> {
+        let content = r#"
+tasks:
+  test:
+    command: make test
+    needs: [build]
+  build:
+    command: make
+    path: /tmp
+    group: compile
+    label: Build it
+    envs:
+      CC: clang
+"#;
+        let message = parse_workflow(content, false)?;
+
+        let test = &message.tasks[0];
+        assert_eq!(test.name, "test");
+        assert_eq!(test.needs, vec!["build".to_string()]);
+        assert_eq!(test.task.label, Some("test".to_string()));
+        assert_eq!(test.task.group, PUEUE_DEFAULT_GROUP);
+        assert_eq!(test.task.path, current_dir()?);
+
+        let build = &message.tasks[1];
+        assert_eq!(build.name, "build");
+        assert_eq!(build.task.command, "make");
+        assert_eq!(build.task.path, PathBuf::from("/tmp"));
+        assert_eq!(build.task.group, "compile");
+        assert_eq!(build.task.label, Some("Build it".to_string()));
+        assert_eq!(build.task.envs.get("CC"), Some(&"clang".to_string()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_rejects_unknown_fields() {
+        let content = r#"
+tasks:
+  build:
+    command: make
+    after: [fetch]
+"#;
+        assert!(parse_workflow(content, false).is_err());
+    }
+}
@@ -15,6 +15,7 @@ use crate::{commands::get_state, display::OutputStyle};
 /// Tasks can be specified by:
 /// - Default queue (no parameter given)
 /// - Group
+/// - Batch
 /// - A list of task ids
 /// - All tasks (`all == true`)
 ///
@@ -24,6 +25,7 @@ pub async fn wait(
     stream: &mut GenericStream,
     task_ids: &[usize],
     group: &str,
+    batch: Option<usize>,
     all: bool,
     quiet: bool,
     style: &OutputStyle,
@@ -44,6 +46,21 @@ pub async fn wait(
                 .filter(|(id, _)| task_ids.contains(id))
                 .map(|(_, task)| task.clone())
                 .collect()
+        } else if let Some(batch) = batch {
+            // Get all tasks of a specific batch
+            let tasks = state
+                .tasks
+                .iter()
+                .filter(|(_, task)| task.batch == Some(batch))
+                .map(|(_, task)| task.clone())
+                .collect::<Vec<Task>>();
+
+            if tasks.is_empty() {
+                println!("No tasks found for batch {batch}");
+                return Ok(());
+            }
+
+            tasks
         } else if all {
             // Get all tasks
             state.tasks.iter().map(|(_, task)| task.clone()).collect()
@@ -162,6 +179,11 @@ fn log_status_change(
                 let status = style.style_text(exit_code, Some(Color::Red), Some(Attribute::Bold));
                 format!("Task {task_id} failed with {status}")
             }
+            TaskResult::TemporaryFailure(exit_code) => {
+                let status =
+                    style.style_text(exit_code, Some(Color::Yellow), Some(Attribute::Bold));
+                format!("Task {task_id} failed temporarily with {status}")
+            }
             TaskResult::Errored => {
                 let status = style.style_text("IO error", Some(Color::Red), Some(Attribute::Bold));
                 format!("Task {task_id} experienced an {status}.")
@@ -170,6 +192,10 @@ fn log_status_change(
                 let status = style.style_text("killed", Some(Color::Red), None);
                 format!("Task {task_id} has been {status}")
             }
+            TaskResult::LogLimitExceeded => {
+                let status = style.style_text("log size limit", Some(Color::Red), None);
+                format!("Task {task_id} has been killed, as it exceeded its {status}")
+            }
         };
         println!("{current_time} - {text}");
 
@@ -0,0 +1,74 @@
+use comfy_table::Attribute;
+
+use pueue_lib::aliasing::Alias;
+use pueue_lib::network::message::AliasResponseMessage;
+
+use super::OutputStyle;
+
+/// Print the aliases of the daemon's alias file.
+/// This is used when calling `pueue alias`.
+///
+/// If `detailed` is set, the parameters and defaults of each alias are shown as well.
+pub fn print_aliases(message: AliasResponseMessage, detailed: bool, style: &OutputStyle) {
+    if message.aliases.is_empty() {
+        println!("No aliases have been defined.");
+        return;
+    }
+
+    let mut text = Vec::new();
+    for (name, alias) in message.aliases.iter() {
+        let styled_name = style.style_text(name, None, Some(Attribute::Bold));
+        text.push(format!("{styled_name}: {}", alias.command()));
+
+        if detailed {
+            text.push(get_alias_details(alias));
+        } else if let Alias::Template(template) = alias {
+            if let Some(description) = &template.description {
+                text.push(format!("    {description}"));
+            }
+        }
+    }
+
+    println!("{}", text.join("\n").trim_end());
+}
+
+/// Return the description, parameters and defaults of a single alias.
+fn get_alias_details(alias: &Alias) -> String {
+    let template = match alias {
+        Alias::Command(_) => return "    Replaces the first word of the command.".to_string(),
+        Alias::Template(template) => template,
+    };
+
+    let mut lines = Vec::new();
+    if let Some(description) = &template.description {
+        lines.push(format!("    {description}"));
+    }
+
+    let parameters = alias.parameters();
+    if !parameters.is_empty() {
+        lines.push("    Parameters:".to_string());
+        for (name, default) in parameters {
+            match default {
+                Some(default) => lines.push(format!("        {name} (default: {default})")),
+                None => lines.push(format!("        {name}")),
+            }
+        }
+    }
+
+    if let Some(group) = &template.group {
+        lines.push(format!("    Group: {group}"));
+    }
+    if let Some(path) = &template.path {
+        lines.push(format!("    Path: {}", path.to_string_lossy()));
+    }
+    if !template.envs.is_empty() {
+        lines.push("    Environment variables:".to_string());
+        let mut envs: Vec<_> = template.envs.iter().collect();
+        envs.sort();
+        for (key, value) in envs {
+            lines.push(format!("        {key}={value}"));
+        }
+    }
+
+    lines.join("\n")
+}
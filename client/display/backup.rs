@@ -0,0 +1,20 @@
+use comfy_table::Attribute;
+
+use pueue_lib::network::message::BackupResponseMessage;
+
+use super::OutputStyle;
+
+/// Print the names of all state backups, oldest first.
+/// This is used when calling `pueue backup`.
+pub fn print_backups(message: BackupResponseMessage, style: &OutputStyle) {
+    if message.backups.is_empty() {
+        println!("No state backups exist yet.");
+        return;
+    }
+
+    let headline = style.style_text("State backups (UTC):", None, Some(Attribute::Bold));
+    println!("{headline}");
+    for name in message.backups {
+        println!("{name}");
+    }
+}
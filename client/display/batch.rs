@@ -0,0 +1,139 @@
+use chrono::{DateTime, Local};
+use comfy_table::{Attribute, Color};
+
+use pueue_lib::settings::Settings;
+use pueue_lib::state::{Batch, GroupStatus, State};
+use pueue_lib::task::{TaskResult, TaskStatus};
+
+use super::OutputStyle;
+
+/// The aggregated status of all tasks of a batch.
+#[derive(Default)]
+struct BatchSummary {
+    queued: usize,
+    running: usize,
+    succeeded: usize,
+    failed: usize,
+    /// The start of the first task of the batch.
+    start: Option<DateTime<Local>>,
+    /// The end of the last task of the batch. Only set, if all tasks finished.
+    end: Option<DateTime<Local>>,
+}
+
+/// Print the aggregated status and timings of all batches or a single batch.
+/// This is used when calling `pueue batch`.
+pub fn print_batches(
+    state: &State,
+    batch: Option<usize>,
+    settings: &Settings,
+    style: &OutputStyle,
+) {
+    if let Some(batch_id) = batch {
+        match state.batches.get(&batch_id) {
+            Some(batch) => println!(
+                "{}",
+                get_batch_text(state, batch_id, batch, settings, style)
+            ),
+            None => println!("Batch {batch_id} doesn't exist."),
+        }
+        return;
+    }
+
+    if state.batches.is_empty() {
+        println!("No batches exist.");
+        return;
+    }
+
+    let batches: Vec<String> = state
+        .batches
+        .iter()
+        .map(|(batch_id, batch)| get_batch_text(state, *batch_id, batch, settings, style))
+        .collect();
+    println!("{}", batches.join("\n"));
+}
+
+/// Return the headline and the timings of a single batch.
+fn get_batch_text(
+    state: &State,
+    batch_id: usize,
+    batch: &Batch,
+    settings: &Settings,
+    style: &OutputStyle,
+) -> String {
+    let summary = summarize_batch(state, batch_id);
+
+    let mut name = format!("Batch {batch_id}");
+    if let Some(label) = &batch.label {
+        name.push_str(&format!(" \"{label}\""));
+    }
+    let name = style.style_text(name, None, Some(Attribute::Bold));
+
+    let status = match batch.status {
+        GroupStatus::Running => style.style_text("running", Some(Color::Green), None),
+        GroupStatus::Paused => style.style_text("paused", Some(Color::Yellow), None),
+    };
+
+    let datetime_format = &settings.client.status_datetime_format;
+    let mut text = format!(
+        "{name} ({status}): {} queued, {} running, {} succeeded, {} failed\n    created {}",
+        summary.queued,
+        summary.running,
+        summary.succeeded,
+        summary.failed,
+        batch.created.format(datetime_format),
+    );
+    if let Some(start) = summary.start {
+        text.push_str(&format!(", started {}", start.format(datetime_format)));
+    }
+    if let (Some(start), Some(end)) = (summary.start, summary.end) {
+        let seconds = (end - start).num_seconds();
+        text.push_str(&format!(
+            ", finished {} (took {seconds}s)",
+            end.format(datetime_format)
+        ));
+    }
+
+    text
+}
+
+/// Aggregate the status and the timings of all tasks of a batch.
+fn summarize_batch(state: &State, batch_id: usize) -> BatchSummary {
+    let mut summary = BatchSummary::default();
+    let mut all_done = true;
+
+    for task in state
+        .tasks
+        .values()
+        .filter(|task| task.batch == Some(batch_id))
+    {
+        match &task.status {
+            TaskStatus::Queued | TaskStatus::Stashed { .. } | TaskStatus::Locked => {
+                summary.queued += 1
+            }
+            TaskStatus::Running | TaskStatus::Paused => summary.running += 1,
+            TaskStatus::Done(TaskResult::Success) => summary.succeeded += 1,
+            TaskStatus::Done(_) => summary.failed += 1,
+        }
+
+        if !task.is_done() {
+            all_done = false;
+        }
+        if let Some(start) = task.start {
+            if summary.start.map_or(true, |first| start < first) {
+                summary.start = Some(start);
+            }
+        }
+        if let Some(end) = task.end {
+            if summary.end.map_or(true, |last| end > last) {
+                summary.end = Some(end);
+            }
+        }
+    }
+
+    // The batch is only finished, once all of its tasks are done.
+    if !all_done {
+        summary.end = None;
+    }
+
+    summary
+}
@@ -1,14 +1,11 @@
-use std::io;
+use std::io::{self, Write};
 use std::path::Path;
 use std::time::Duration;
 
 use anyhow::Result;
 use tokio::time::sleep;
 
-use pueue_lib::{
-    log::{get_log_file_handle, get_log_path, seek_to_last_lines},
-    network::protocol::GenericStream,
-};
+use pueue_lib::{log::LogFollower, network::protocol::GenericStream};
 
 use crate::commands::get_state;
 
@@ -25,28 +22,19 @@ pub async fn follow_local_task_logs(
     task_id: usize,
     lines: Option<usize>,
 ) -> Result<()> {
-    let mut handle = match get_log_file_handle(task_id, pueue_directory) {
-        Ok(stdout) => stdout,
+    // If `lines` is passed as an option, we only want to show the last `X` lines.
+    // The follower then starts at the start of the `Xth` line from the end of the log.
+    // The log might be rotated into segments while we follow it, which the follower handles.
+    let mut follower = match LogFollower::open(task_id, pueue_directory, lines) {
+        Ok(follower) => follower,
         Err(err) => {
             println!("Failed to get log file handles: {err}");
             return Ok(());
         }
     };
-    let path = get_log_path(task_id, pueue_directory);
 
-    // Stdout handle to directly stream log file output to `io::stdout`.
-    // This prevents us from allocating any large amounts of memory.
     let mut stdout = io::stdout();
-
-    // If `lines` is passed as an option, we only want to show the last `X` lines.
-    // To achieve this, we seek the file handle to the start of the `Xth` line
-    // from the end of the file.
-    // The loop following this section will then only copy those last lines to stdout.
-    if let Some(lines) = lines {
-        if let Err(err) = seek_to_last_lines(&mut handle, lines) {
-            println!("Error seeking to last lines from log: {err}");
-        }
-    }
+    let mut buffer = Vec::new();
 
     // We check in regular intervals whether the task finished.
     // This is something we don't want to do in every loop, as we have to communicate with
@@ -57,15 +45,20 @@ pub async fn follow_local_task_logs(
     let log_check_interval = 100;
     loop {
         // Check whether the file still exists. Exit if it doesn't.
-        if !path.exists() {
+        if !follower.exists() {
             println!("Log file has gone away. Has the task been removed?");
             return Ok(());
         }
         // Read the next chunk of text from the last position.
-        if let Err(err) = io::copy(&mut handle, &mut stdout) {
+        buffer.clear();
+        if let Err(err) = follower.read_new_output(&mut buffer) {
             println!("Error while reading file: {err}");
             return Ok(());
         };
+        if let Err(err) = stdout.write_all(&buffer).and_then(|_| stdout.flush()) {
+            println!("Error while writing output: {err}");
+            return Ok(());
+        }
 
         // Do a check at the very beginning and every `task_check_interval`, whether the task:
         // 1. Still exist
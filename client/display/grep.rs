@@ -0,0 +1,40 @@
+use std::collections::BTreeMap;
+
+use crossterm::style::Color;
+
+use pueue_lib::log::LogLine;
+
+use super::OutputStyle;
+
+/// Print the matching lines of each task in the style of `grep`.
+/// This is used when calling `pueue grep`.
+///
+/// Lines that aren't directly adjacent are separated by `--`, if context lines are shown.
+pub fn print_log_matches(
+    matches: &BTreeMap<usize, Vec<LogLine>>,
+    context: usize,
+    style: &OutputStyle,
+) {
+    // The task id and number of the last printed line.
+    let mut previous: Option<(usize, usize)> = None;
+    for (task_id, lines) in matches {
+        for line in lines {
+            let adjacent = matches!(
+                previous,
+                Some((previous_id, number)) if previous_id == *task_id && number + 1 == line.number
+            );
+            if context > 0 && previous.is_some() && !adjacent {
+                println!("{}", style.style_text("--", Some(Color::Cyan), None));
+            }
+
+            let separator = if line.matches { ":" } else { "-" };
+            println!(
+                "{}{separator}{}{separator}{}",
+                style.style_text(task_id, Some(Color::Magenta), None),
+                style.style_text(line.number, Some(Color::Green), None),
+                line.text,
+            );
+            previous = Some((*task_id, line.number));
+        }
+    }
+}
@@ -5,11 +5,13 @@ use std::io::Read;
 use serde_derive::{Deserialize, Serialize};
 use snap::read::FrameDecoder;
 
-use pueue_lib::log::{get_log_file_handle, read_last_lines};
+use pueue_lib::log::{open_log_range, read_timestamped_log, LogRange, LogReader};
 use pueue_lib::network::message::TaskLogMessage;
 use pueue_lib::settings::Settings;
 use pueue_lib::task::Task;
 
+use super::OutputFilter;
+
 /// This is the output struct used for
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct TaskLog {
@@ -20,7 +22,7 @@ pub struct TaskLog {
 pub fn print_log_json(
     task_log_messages: BTreeMap<usize, TaskLogMessage>,
     settings: &Settings,
-    lines: Option<usize>,
+    filter: OutputFilter,
 ) {
     let mut tasks: BTreeMap<usize, Task> = BTreeMap::new();
     let mut task_log: BTreeMap<usize, String> = BTreeMap::new();
@@ -31,7 +33,7 @@ pub fn print_log_json(
         tasks.insert(id, message.task);
 
         if settings.client.read_local_logs {
-            let output = get_local_log(settings, id, lines);
+            let output = get_local_log(settings, id, filter);
             task_log.insert(id, output);
         } else {
             let output = get_remote_log(message.output);
@@ -52,27 +54,56 @@ pub fn print_log_json(
 }
 
 /// Read logs directly from local files for a specific task.
-fn get_local_log(settings: &Settings, id: usize, lines: Option<usize>) -> String {
-    let mut file = match get_log_file_handle(id, &settings.shared.pueue_directory()) {
-        Ok(file) => file,
+fn get_local_log(settings: &Settings, id: usize, filter: OutputFilter) -> String {
+    if !filter.is_available(id, settings) {
+        return format!("(Pueue error) {}", filter.missing_output_hint());
+    }
+
+    let pueue_directory = settings.shared.pueue_directory();
+    if filter.needs_index() {
+        let result = read_timestamped_log(
+            id,
+            &pueue_directory,
+            filter.stream,
+            filter.range,
+            filter.since,
+            filter.timestamps,
+        );
+        return match result {
+            Ok((output, _)) => String::from_utf8_lossy(&output).into_owned(),
+            Err(err) => format!("(Pueue error) Failed to read local log output file: {err}"),
+        };
+    }
+
+    if let Some(LogRange::Tail(lines)) = filter.range {
+        let mut file = match LogReader::open(id, &pueue_directory, filter.stream) {
+            Ok(file) => file,
+            Err(err) => {
+                return format!("(Pueue error) Failed to get log file handle: {err}");
+            }
+        };
+
+        return file.read_last_lines(lines).unwrap_or_else(|err| {
+            format!("(Pueue error) Failed to read local log output file: {err}")
+        });
+    }
+
+    let mut file = match open_log_range(id, &pueue_directory, filter.range, filter.stream) {
+        Ok((file, _)) => file,
         Err(err) => {
             return format!("(Pueue error) Failed to get log file handle: {err}");
         }
     };
 
-    if let Some(lines) = lines {
-        read_last_lines(&mut file, lines)
-    } else {
-        let mut output = String::new();
-        if let Err(error) = file.read_to_string(&mut output) {
-            let _ = write!(
-                output,
-                "(Pueue error) Failed to read local log output file: {error:?}"
-            );
-        };
+    let mut output = String::new();
+    if let Err(error) = file.read_to_string(&mut output) {
+        let _ = write!(
+            output,
+            "(Pueue error) Failed to read local log output file: {error:?}"
+        );
+    };
 
-        output
-    }
+    output
 }
 
 /// Read logs from from compressed remote logs.
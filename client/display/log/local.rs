@@ -1,11 +1,9 @@
-use std::fs::File;
-use std::io::{self, Stdout};
+use std::io::{self, Stdout, Take, Write};
 
-use comfy_table::*;
-
-use pueue_lib::log::{get_log_file_handle, seek_to_last_lines};
+use pueue_lib::log::{open_log_range, read_timestamped_log, LogReader};
 use pueue_lib::settings::Settings;
 
+use super::OutputFilter;
 use crate::display::OutputStyle;
 
 /// The daemon didn't send any log output, thereby we didn't request any.
@@ -14,59 +12,78 @@ pub fn print_local_log(
     task_id: usize,
     style: &OutputStyle,
     settings: &Settings,
-    lines: Option<usize>,
+    filter: OutputFilter,
 ) {
-    let mut file = match get_log_file_handle(task_id, &settings.shared.pueue_directory()) {
-        Ok(file) => file,
-        Err(err) => {
-            println!("Failed to get log file handle: {err}");
-            return;
+    if !filter.is_available(task_id, settings) {
+        println!("\n{}", filter.missing_output_hint());
+        return;
+    }
+
+    let pueue_directory = settings.shared.pueue_directory();
+    if filter.needs_index() {
+        let result = read_timestamped_log(
+            task_id,
+            &pueue_directory,
+            filter.stream,
+            filter.range,
+            filter.since,
+            filter.timestamps,
+        );
+        match result {
+            Ok((output, output_complete)) => {
+                print_output(&output, output_complete, style, filter);
+            }
+            Err(err) => println!("Failed reading local log file: {err}"),
         }
-    };
+        return;
+    }
+
+    // Only the requested part of the log is read.
+    let (mut file, output_complete) =
+        match open_log_range(task_id, &pueue_directory, filter.range, filter.stream) {
+            Ok(result) => result,
+            Err(err) => {
+                println!("Failed to get log file handle: {err}");
+                return;
+            }
+        };
     // Stdout handler to directly write log file output to io::stdout
     // without having to load anything into memory.
     let mut stdout = io::stdout();
 
-    print_local_file(
-        &mut stdout,
-        &mut file,
-        &lines,
-        style.style_text("output:", Some(Color::Green), Some(Attribute::Bold)),
-    );
+    print_local_file(&mut stdout, &mut file, output_complete, style, filter);
 }
 
-/// Print a local log file of a task.
-fn print_local_file(stdout: &mut Stdout, file: &mut File, lines: &Option<usize>, header: String) {
-    if let Ok(metadata) = file.metadata() {
-        if metadata.len() != 0 {
-            // Indicates whether the full log output is shown or just the last part of it.
-            let mut output_complete = true;
-
-            // Only print the last lines if requested
-            if let Some(lines) = lines {
-                match seek_to_last_lines(file, *lines) {
-                    Ok(complete) => output_complete = complete,
-                    Err(err) => {
-                        println!("Failed reading local log file: {err}");
-                        return;
-                    }
-                }
-            }
+/// Print the already read output of a task.
+fn print_output(output: &[u8], output_complete: bool, style: &OutputStyle, filter: OutputFilter) {
+    if output.is_empty() {
+        return;
+    }
 
-            // Add a hint if we should limit the output to X lines **and** there are actually more
-            // lines than that given limit.
-            let mut line_info = String::new();
-            if !output_complete {
-                line_info = lines.map_or(String::new(), |lines| format!(" (last {lines} lines)"));
-            }
+    filter.print_header(style, output_complete);
 
-            // Print a newline between the task information and the first output.
-            println!("\n{header}{line_info}");
+    if let Err(err) = io::stdout().write_all(output) {
+        println!("Failed printing log output: {err}");
+    };
+}
 
-            // Print everything
-            if let Err(err) = io::copy(file, stdout) {
-                println!("Failed reading local log file: {err}");
-            };
-        }
+/// Print a local log file of a task.
+/// The log might have been rotated into multiple segments, which are printed in order.
+fn print_local_file(
+    stdout: &mut Stdout,
+    file: &mut Take<LogReader>,
+    output_complete: bool,
+    style: &OutputStyle,
+    filter: OutputFilter,
+) {
+    if file.limit() == 0 {
+        return;
     }
+
+    filter.print_header(style, output_complete);
+
+    // Print everything
+    if let Err(err) = io::copy(file, stdout) {
+        println!("Failed reading local log file: {err}");
+    };
 }
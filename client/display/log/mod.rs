@@ -1,7 +1,9 @@
 use std::collections::BTreeMap;
 
+use chrono::prelude::*;
 use comfy_table::*;
 
+use pueue_lib::log::{has_separate_output, has_timestamps, LogRange, OutputStream};
 use pueue_lib::network::message::TaskLogMessage;
 use pueue_lib::settings::Settings;
 use pueue_lib::task::{Task, TaskResult, TaskStatus};
@@ -15,6 +17,7 @@ mod remote;
 
 use json::*;
 use local::*;
+pub use remote::decompress_and_print_remote_log;
 use remote::*;
 
 /// Determine how many lines of output should be printed/returned.
@@ -37,32 +40,158 @@ pub fn determine_log_line_amount(full: bool, lines: &Option<usize>) -> Option<us
     }
 }
 
+/// Determine which part of the output should be printed/returned.
+/// `None` implicates that all lines are printed.
+///
+/// An explicitly requested range takes precedence.
+/// Otherwise, the last lines are printed as determined by [determine_log_line_amount].
+pub fn determine_log_range(
+    full: bool,
+    lines: &Option<usize>,
+    head: &Option<usize>,
+    line_range: &Option<LogRange>,
+    byte_range: &Option<LogRange>,
+) -> Option<LogRange> {
+    if let Some(head) = head {
+        return Some(LogRange::Head(*head));
+    }
+
+    line_range
+        .or(*byte_range)
+        .or_else(|| determine_log_line_amount(full, lines).map(LogRange::Tail))
+}
+
+/// Determine which output stream should be shown.
+/// `None` implicates that the interleaved output of both streams is shown.
+pub fn log_stream(stdout: bool, stderr: bool) -> Option<OutputStream> {
+    if stdout {
+        Some(OutputStream::Stdout)
+    } else if stderr {
+        Some(OutputStream::Stderr)
+    } else {
+        None
+    }
+}
+
+/// Describes which part of a task's output should be shown.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct OutputFilter {
+    /// The output stream that should be shown. `None` shows the interleaved output.
+    pub stream: Option<OutputStream>,
+    /// Prefix each line with the time at which it has been captured.
+    pub timestamps: bool,
+    /// Only show lines that have been captured since this point in time.
+    pub since: Option<DateTime<Local>>,
+    /// Only show this part of the output. `None` shows the whole output.
+    pub range: Option<LogRange>,
+}
+
+impl OutputFilter {
+    /// Get the filter, that has been requested via the `log` subcommand.
+    pub fn from_cli(cli_command: &SubCommand) -> OutputFilter {
+        match cli_command {
+            SubCommand::Log {
+                lines,
+                full,
+                head,
+                line_range,
+                byte_range,
+                stdout,
+                stderr,
+                timestamps,
+                since,
+                ..
+            } => OutputFilter {
+                stream: log_stream(*stdout, *stderr),
+                timestamps: *timestamps,
+                since: *since,
+                range: determine_log_range(*full, lines, head, line_range, byte_range),
+            },
+            _ => OutputFilter::default(),
+        }
+    }
+
+    /// Whether the output has to be read from the timestamp index of the task's log.
+    fn needs_index(&self) -> bool {
+        self.timestamps || self.since.is_some()
+    }
+
+    /// The header that's shown above the output of a task.
+    fn header(&self) -> &'static str {
+        match self.stream {
+            Some(OutputStream::Stdout) => "stdout:",
+            Some(OutputStream::Stderr) => "stderr:",
+            None => "output:",
+        }
+    }
+
+    /// Print the header, that's shown above the output of a task.
+    /// If only a part of the output is shown, a hint is added, which part it is.
+    pub fn print_header(&self, style: &OutputStyle, output_complete: bool) {
+        let header = style.style_text(self.header(), Some(Color::Green), Some(Attribute::Bold));
+        let range_info = match self.range {
+            Some(_) if output_complete => String::new(),
+            None => String::new(),
+            Some(LogRange::Tail(lines)) => format!(" (last {lines} lines)"),
+            Some(LogRange::Head(lines)) => format!(" (first {lines} lines)"),
+            Some(LogRange::Lines { start, end }) => {
+                format!(
+                    " (lines {start}-{})",
+                    end.map_or(String::new(), |end| end.to_string())
+                )
+            }
+            Some(LogRange::Bytes { start, end }) => {
+                format!(
+                    " (bytes {start}-{})",
+                    end.map_or(String::new(), |end| end.to_string())
+                )
+            }
+        };
+
+        // Print a newline between the task information and the first output.
+        println!("\n{header}{range_info}");
+    }
+
+    /// The hint that's shown, if the output of a task hasn't been captured in the way
+    /// that's necessary for this filter.
+    fn missing_output_hint(&self) -> &'static str {
+        if self.needs_index() {
+            "The output of this task hasn't been captured with timestamps."
+        } else {
+            "The output of this task hasn't been captured separately."
+        }
+    }
+
+    /// Check whether the output of a local task has been captured in the way that's
+    /// necessary for this filter.
+    fn is_available(&self, task_id: usize, settings: &Settings) -> bool {
+        let pueue_directory = settings.shared.pueue_directory();
+        (self.stream.is_none() || has_separate_output(task_id, &pueue_directory))
+            && (!self.needs_index() || has_timestamps(task_id, &pueue_directory))
+    }
+}
+
 /// Print the log ouput of finished tasks.
 /// Either print the logs of every task
 /// or only print the logs of the specified tasks.
 pub fn print_logs(
-    mut task_logs: BTreeMap<usize, TaskLogMessage>,
+    task_logs: BTreeMap<usize, TaskLogMessage>,
     cli_command: &SubCommand,
     style: &OutputStyle,
     settings: &Settings,
 ) {
     // Get actual commandline options.
     // This is necessary to know how we should display/return the log information.
-    let (json, task_ids, lines, full) = match cli_command {
-        SubCommand::Log {
-            json,
-            task_ids,
-            lines,
-            full,
-        } => (*json, task_ids.clone(), *lines, *full),
+    let (json, task_ids, filter) = match cli_command {
+        SubCommand::Log { json, task_ids, .. } => {
+            (*json, task_ids.clone(), OutputFilter::from_cli(cli_command))
+        }
         _ => panic!("Got wrong Subcommand {cli_command:?} in print_log. This shouldn't happen"),
     };
 
-    let lines = determine_log_line_amount(full, &lines);
-
     // Return the server response in json representation.
     if json {
-        print_log_json(task_logs, settings, lines);
+        print_log_json(task_logs, settings, filter);
         return;
     }
 
@@ -78,9 +207,9 @@ pub fn print_logs(
     }
 
     // Iterate over each task and print the respective log.
-    let mut task_iter = task_logs.iter_mut().peekable();
+    let mut task_iter = task_logs.iter().peekable();
     while let Some((_, task_log)) = task_iter.next() {
-        print_log(task_log, style, settings, lines);
+        print_log(task_log, style, settings, filter);
 
         // Add a newline if there is another task that's going to be printed.
         if let Some((_, task_log)) = task_iter.peek() {
@@ -98,14 +227,12 @@ pub fn print_logs(
 ///
 /// message: The message returned by the daemon. This message includes all
 ///          requested tasks and the tasks' logs, if we don't read local logs.
-/// lines: Whether we should reduce the log output of each task to a specific number of lines.
-///         `None` implicates that everything should be printed.
-///         This is only important, if we read local lines.
-fn print_log(
-    message: &mut TaskLogMessage,
+/// filter: Which part of the output should be shown.
+pub fn print_log(
+    message: &TaskLogMessage,
     style: &OutputStyle,
     settings: &Settings,
-    lines: Option<usize>,
+    filter: OutputFilter,
 ) {
     let task = &message.task;
     // We only show logs of finished or running tasks.
@@ -119,9 +246,11 @@ fn print_log(
     print_task_info(task, style);
 
     if settings.client.read_local_logs {
-        print_local_log(message.task.id, style, settings, lines);
+        print_local_log(message.task.id, style, settings, filter);
     } else if message.output.is_some() {
-        print_remote_log(message, style, lines);
+        print_remote_log(message, style, filter);
+    } else if filter.stream.is_some() || filter.needs_index() {
+        println!("\n{}", filter.missing_output_hint());
     } else {
         println!("Logs requested from pueue daemon, but none received. Please report this bug.");
     }
@@ -140,10 +269,18 @@ fn print_task_info(task: &Task, style: &OutputStyle) {
             TaskResult::Failed(exit_code) => {
                 (format!("failed with exit code {}", exit_code), Color::Red)
             }
+            TaskResult::TemporaryFailure(exit_code) => (
+                format!("failed temporarily with exit code {}", exit_code),
+                Color::Yellow,
+            ),
             TaskResult::FailedToSpawn(err) => (format!("failed to spawn: {}", err), Color::Red),
             TaskResult::Killed => ("killed by system or user".into(), Color::Red),
             TaskResult::Errored => ("some IO error.\n Check daemon log.".into(), Color::Red),
             TaskResult::DependencyFailed => ("dependency failed".into(), Color::Red),
+            TaskResult::LogLimitExceeded => (
+                "killed, as its output exceeded the log size limit".into(),
+                Color::Red,
+            ),
         },
         _ => (task.status.to_string(), Color::White),
     };
@@ -182,6 +319,13 @@ fn print_task_info(task: &Task, style: &OutputStyle) {
         ]);
     }
 
+    if let Some(progress) = &task.progress {
+        table.add_row(vec![
+            style.styled_cell("Progress:", None, Some(Attribute::Bold)),
+            Cell::new(progress),
+        ]);
+    }
+
     // Start and end time
     if let Some(start) = task.start {
         table.add_row(vec![
@@ -195,6 +339,12 @@ fn print_task_info(task: &Task, style: &OutputStyle) {
             Cell::new(end.to_rfc2822()),
         ]);
     }
+    if let Some(reason) = &task.failure_reason {
+        table.add_row(vec![
+            style.styled_cell("Failure reason:", None, Some(Attribute::Bold)),
+            Cell::new(reason),
+        ]);
+    }
 
     // Set the padding of the left column to 0 align the keys to the right
     let first_column = table.column_mut(0).unwrap();
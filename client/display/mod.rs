@@ -2,22 +2,32 @@
 //! daemon.
 //!
 //! This includes formatting of task tables, group info, log inspection and log following.
+mod alias;
+mod backup;
+mod batch;
 mod follow;
+mod grep;
 mod group;
 pub mod helper;
 mod log;
 mod state;
 pub mod style;
 pub mod table_builder;
+mod template;
 
 use crossterm::style::Color;
 
 // Re-exports
+pub use self::alias::print_aliases;
+pub use self::backup::print_backups;
+pub use self::batch::print_batches;
 pub use self::follow::follow_local_task_logs;
+pub use self::grep::print_log_matches;
 pub use self::group::print_groups;
-pub use self::log::{determine_log_line_amount, print_logs};
+pub use self::log::{decompress_and_print_remote_log, print_log, print_logs, OutputFilter};
 pub use self::state::print_state;
 pub use self::style::OutputStyle;
+pub use self::template::print_templates;
 
 /// Used to style any generic success message from the daemon.
 pub fn print_success(_style: &OutputStyle, message: &str) {
@@ -1,7 +1,8 @@
 use anyhow::Result;
+use comfy_table::{Attribute, Color};
 
 use pueue_lib::settings::Settings;
-use pueue_lib::state::{State, PUEUE_DEFAULT_GROUP};
+use pueue_lib::state::{Drain, State, PUEUE_DEFAULT_GROUP};
 use pueue_lib::task::Task;
 
 use super::{helper::*, table_builder::TableBuilder, OutputStyle};
@@ -46,6 +47,10 @@ pub fn print_state<'a>(
         return Ok(());
     }
 
+    if let Some(drain) = &state.drain {
+        println!("{}\n", get_drain_headline(&state, drain, style, settings));
+    }
+
     if let Some(group) = group_only {
         print_single_group(state, tasks, style, group, table_builder);
         return Ok(());
@@ -56,6 +61,39 @@ pub fn print_state<'a>(
     Ok(())
 }
 
+/// Return some info about the progress of an ongoing drain of the daemon.
+fn get_drain_headline(
+    state: &State,
+    drain: &Drain,
+    style: &OutputStyle,
+    settings: &Settings,
+) -> String {
+    let running = state
+        .tasks
+        .values()
+        .filter(|task| task.is_running())
+        .count();
+    let headline = style.style_text(
+        "Daemon is draining",
+        Some(Color::Yellow),
+        Some(Attribute::Bold),
+    );
+
+    let datetime_format = &settings.client.status_datetime_format;
+    let mut text = format!(
+        "{headline}: {running} running task(s) left (since {})",
+        drain.start.format(datetime_format)
+    );
+    if let Some(deadline) = drain.deadline {
+        text.push_str(&format!(
+            ", remaining tasks will be killed at {}",
+            deadline.format(datetime_format)
+        ));
+    }
+
+    text
+}
+
 /// The user requested only a single group to be displayed.
 ///
 /// Print this group or show an error if this group doesn't exist.
@@ -205,6 +205,12 @@ impl<'a> TableBuilder<'a> {
                         }
                         TaskResult::FailedToSpawn(_) => ("Failed to spawn".to_string(), Color::Red),
                         TaskResult::Failed(code) => (format!("Failed ({code})"), Color::Red),
+                        TaskResult::TemporaryFailure(code) => {
+                            (format!("Temporary failure ({code})"), Color::Yellow)
+                        }
+                        TaskResult::LogLimitExceeded => {
+                            ("Log limit exceeded".to_string(), Color::Red)
+                        }
                         _ => (result.to_string(), Color::Red),
                     },
                     _ => (status_string, Color::Yellow),
@@ -0,0 +1,52 @@
+use comfy_table::Attribute;
+
+use pueue_lib::network::message::TemplateResponseMessage;
+use pueue_lib::template::DependencyPolicy;
+
+use super::OutputStyle;
+
+/// Print all task templates of the daemon and their defaults.
+/// This is used when calling `pueue template`.
+pub fn print_templates(message: TemplateResponseMessage, style: &OutputStyle) {
+    if message.templates.is_empty() {
+        println!("No templates have been added yet.");
+        return;
+    }
+
+    let mut lines = Vec::new();
+    for (name, template) in message.templates.iter() {
+        let styled_name = style.style_text(name, None, Some(Attribute::Bold));
+        lines.push(format!("{styled_name}: {}", template.command));
+
+        let parameters = template.parameters();
+        if !parameters.is_empty() {
+            lines.push(format!("    Parameters: {}", parameters.join(", ")));
+        }
+        if let Some(label) = &template.label {
+            lines.push(format!("    Label: {label}"));
+        }
+        if let Some(group) = &template.group {
+            lines.push(format!("    Group: {group}"));
+        }
+        if let Some(path) = &template.path {
+            lines.push(format!("    Path: {}", path.to_string_lossy()));
+        }
+        if !template.envs.is_empty() {
+            let mut envs: Vec<String> = template
+                .envs
+                .iter()
+                .map(|(key, value)| format!("{key}={value}"))
+                .collect();
+            envs.sort();
+            lines.push(format!("    Environment variables: {}", envs.join(" ")));
+        }
+        if let Some(timeout) = template.timeout {
+            lines.push(format!("    Timeout: {timeout}s"));
+        }
+        if template.dependencies == DependencyPolicy::AfterPrevious {
+            lines.push("    Runs after the previous task of this template".to_string());
+        }
+    }
+
+    println!("{}", lines.join("\n"));
+}
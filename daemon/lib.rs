@@ -14,7 +14,7 @@ use pueue_lib::network::secret::init_shared_secret;
 use pueue_lib::settings::Settings;
 use pueue_lib::state::State;
 
-use self::state_helper::{restore_state, save_state};
+use self::state_helper::{restore_state, save_state, EditLocks};
 use crate::network::socket::accept_incoming;
 use crate::task_handler::{TaskHandler, TaskSender};
 
@@ -62,6 +62,11 @@ pub async fn run(config_path: Option<PathBuf>, profile: Option<String>, test: bo
         )
     }
 
+    #[cfg(not(target_os = "linux"))]
+    if settings.daemon.detach_tasks {
+        warn!("Re-adopting detached tasks after a restart is only supported on Linux.");
+    }
+
     init_directories(&settings.shared.pueue_directory())?;
     if !settings.shared.daemon_key().exists() && !settings.shared.daemon_cert().exists() {
         create_certificates(&settings.shared).context("Failed to create certificates.")?;
@@ -73,7 +78,7 @@ pub async fn run(config_path: Option<PathBuf>, profile: Option<String>, test: bo
     // Restore the previous state and save any changes that might have happened during this
     // process. If no previous state exists, just create a new one.
     // Create a new empty state if any errors occur, but print the error message.
-    let state = match restore_state(&settings.shared.pueue_directory()) {
+    let mut state = match restore_state(&settings) {
         Ok(Some(state)) => state,
         Ok(None) => State::new(),
         Err(error) => {
@@ -84,12 +89,18 @@ pub async fn run(config_path: Option<PathBuf>, profile: Option<String>, test: bo
     };
 
     // Save the state once at the very beginning.
-    save_state(&state, &settings).context("Failed to save state on startup.")?;
+    save_state(&mut state, &settings).context("Failed to save state on startup.")?;
     let state = Arc::new(Mutex::new(state));
 
     let (sender, receiver) = unbounded();
     let sender = TaskSender::new(sender);
-    let mut task_handler = TaskHandler::new(state.clone(), settings.clone(), receiver);
+    let edit_locks = EditLocks::default();
+    let mut task_handler = TaskHandler::new(
+        state.clone(),
+        edit_locks.clone(),
+        settings.clone(),
+        receiver,
+    );
 
     // Don't set ctrlc and panic handlers during testing.
     // This is necessary for multithreaded integration testing, since multiple listener per process
@@ -102,7 +113,7 @@ pub async fn run(config_path: Option<PathBuf>, profile: Option<String>, test: bo
         task_handler.run();
     });
 
-    accept_incoming(sender, state.clone(), settings.clone()).await?;
+    accept_incoming(sender, state.clone(), edit_locks, settings.clone()).await?;
 
     Ok(())
 }
@@ -145,6 +156,9 @@ fn init_directories(pueue_dir: &Path) -> Result<()> {
 /// On SIGINT and SIGTERM, we exit gracefully by sending a DaemonShutdown message to the
 /// TaskHandler. This is to prevent dangling processes and other weird edge-cases.
 ///
+/// On SIGUSR1, the daemon starts to drain. I.e. it stops spawning new tasks and shuts down once
+/// all running tasks finished.
+///
 /// On panic, we want to cleanup existing unix sockets and the PID file.
 fn setup_signal_panic_handling(settings: &Settings, sender: &TaskSender) -> Result<()> {
     let sender_clone = sender.clone();
@@ -159,6 +173,20 @@ fn setup_signal_panic_handling(settings: &Settings, sender: &TaskSender) -> Resu
             .expect("Failed to send Message to TaskHandler on Shutdown");
     })?;
 
+    // This section handles draining via the SigUsr1 process signal.
+    #[cfg(unix)]
+    {
+        let sender_clone = sender.clone();
+        let mut signals = signal_hook::iterator::Signals::new([signal_hook::consts::SIGUSR1])?;
+        std::thread::spawn(move || {
+            for _ in signals.forever() {
+                sender_clone
+                    .send(Shutdown::Drain { deadline: None })
+                    .expect("Failed to send Message to TaskHandler on Drain");
+            }
+        });
+    }
+
     // Try to do some final cleanup, even if we panic.
     let settings_clone = settings.clone();
     let orig_hook = std::panic::take_hook();
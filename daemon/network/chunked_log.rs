@@ -0,0 +1,105 @@
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::Path;
+
+use anyhow::Result;
+
+use pueue_lib::log::{compress_log_output, open_log_range, open_timestamped_log};
+use pueue_lib::network::message::*;
+use pueue_lib::network::protocol::{send_message, GenericStream};
+use pueue_lib::state::SharedState;
+
+use crate::network::message_handler::log::{
+    is_output_missing, needs_index, requested_range, requested_tasks,
+};
+
+/// Send the logs of the requested tasks in chunks, so even huge logs never have to be kept in
+/// memory as a whole.
+///
+/// Each task is sent in its own [Message::LogResponse], which doesn't contain any output yet.
+/// If output is available, it's sent afterwards in [Message::LogChunk]s.
+/// The returned [Message::Close] ends the response.
+pub async fn handle_chunked_log(
+    pueue_directory: &Path,
+    stream: &mut GenericStream,
+    state: &SharedState,
+    message: LogRequestMessage,
+) -> Result<Message> {
+    let chunk_size = match message.chunk_size {
+        Some(chunk_size) if chunk_size > 0 => chunk_size as u64,
+        _ => {
+            return Ok(create_failure_message(
+                "The chunk size has to be greater than 0",
+            ))
+        }
+    };
+    let range = requested_range(&message);
+    let tasks = {
+        let state = state.lock().unwrap();
+        requested_tasks(&message, &state)
+    };
+
+    for task in tasks {
+        let task_id = task.id;
+        let reader: Option<(Box<dyn Read + Send>, bool)> =
+            if !message.send_logs || is_output_missing(&message, task_id, pueue_directory) {
+                None
+            } else if needs_index(&message) {
+                let result = open_timestamped_log(
+                    task_id,
+                    pueue_directory,
+                    message.stream,
+                    range,
+                    message.since,
+                    message.timestamps,
+                );
+                match result {
+                    Ok((reader, output_complete)) => Some((Box::new(reader), output_complete)),
+                    Err(err) => return Ok(read_failure(err)),
+                }
+            } else {
+                match open_log_range(task_id, pueue_directory, range, message.stream) {
+                    Ok((file, output_complete)) => Some((Box::new(file), output_complete)),
+                    Err(err) => return Ok(read_failure(err)),
+                }
+            };
+
+        // An empty output signals the client, that the output follows in chunks.
+        let task_log = TaskLogMessage {
+            task,
+            output: reader.as_ref().map(|_| Vec::new()),
+            output_complete: reader.as_ref().map_or(true, |(_, complete)| *complete),
+        };
+        send_message(
+            Message::LogResponse(BTreeMap::from([(task_id, task_log)])),
+            stream,
+        )
+        .await?;
+
+        let mut reader = match reader {
+            Some((reader, _)) => reader,
+            None => continue,
+        };
+        loop {
+            let mut chunk = Vec::new();
+            if let Err(err) = (&mut reader).take(chunk_size).read_to_end(&mut chunk) {
+                return Ok(read_failure(err));
+            }
+            if chunk.is_empty() {
+                break;
+            }
+
+            let output = match compress_log_output(&chunk) {
+                Ok(output) => output,
+                Err(err) => return Ok(read_failure(err)),
+            };
+            send_message(LogChunkMessage { task_id, output }, stream).await?;
+        }
+    }
+
+    Ok(Message::Close)
+}
+
+fn read_failure(err: impl std::fmt::Debug) -> Message {
+    create_failure_message(format!("Failed reading process output file: {err:?}"))
+}
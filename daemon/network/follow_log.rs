@@ -1,4 +1,3 @@
-use std::io::Read;
 use std::path::Path;
 use std::time::Duration;
 
@@ -49,33 +48,22 @@ pub async fn handle_follow(
         }
     };
 
-    let mut handle = match get_log_file_handle(task_id, pueue_directory) {
+    // If `lines` is passed as an option, we only want to show the last `X` lines.
+    // The follower then starts at the start of the `Xth` line from the end of the log.
+    // The log might be rotated into segments while we follow it, which the follower handles.
+    let mut follower = match LogFollower::open(task_id, pueue_directory, message.lines) {
         Err(_) => {
             return Ok(create_failure_message(
                 "Couldn't find output files for task. Maybe it finished? Try `log`",
             ))
         }
-        Ok(handle) => handle,
+        Ok(follower) => follower,
     };
 
-    // Get the output path.
-    // We need to check continuously, whether the file still exists,
-    // since the file can go away (e.g. due to finishing a task).
-    let path = get_log_path(task_id, pueue_directory);
-
-    // If `lines` is passed as an option, we only want to show the last `X` lines.
-    // To achieve this, we seek the file handle to the start of the `Xth` line
-    // from the end of the file.
-    // The loop following this section will then only copy those last lines to stdout.
-    if let Some(lines) = message.lines {
-        if let Err(err) = seek_to_last_lines(&mut handle, lines) {
-            println!("Error seeking to last lines from log: {err}");
-        }
-    }
-
     loop {
-        // Check whether the file still exists. Exit if it doesn't.
-        if !path.exists() {
+        // Check whether the log still exists, since it can go away (e.g. due to removing a task).
+        // Exit if it doesn't.
+        if !follower.exists() {
             return Ok(create_success_message(
                 "Log file has gone away. Has the task been removed?",
             ));
@@ -83,7 +71,7 @@ pub async fn handle_follow(
         // Read the next chunk of text from the last position.
         let mut buffer = Vec::new();
 
-        if let Err(err) = handle.read_to_end(&mut buffer) {
+        if let Err(err) = follower.read_new_output(&mut buffer) {
             return Ok(create_failure_message(format!("Error: {err}")));
         };
         let text = String::from_utf8_lossy(&buffer).to_string();
@@ -1,33 +1,42 @@
-use pueue_lib::aliasing::insert_alias;
+use std::path::PathBuf;
+
+use pueue_lib::aliasing::expand_alias;
+use pueue_lib::error::Error;
 use pueue_lib::network::message::*;
-use pueue_lib::state::{GroupStatus, SharedState};
+use pueue_lib::state::{GroupStatus, SharedState, PUEUE_DEFAULT_GROUP};
 use pueue_lib::task::{Task, TaskStatus};
 
 use super::*;
 use crate::ok_or_return_failure_message;
-use crate::state_helper::save_state;
+use crate::state_helper::{save_state_with_mode, SaveMode};
 
 /// Invoked when calling `pueue add`.
 /// Queues a new task to the state.
 /// If the start_immediately flag is set, send a StartMessage to the task handler.
 pub fn add_task(
-    message: AddMessage,
+    mut message: AddMessage,
     sender: &TaskSender,
     state: &SharedState,
     settings: &Settings,
+    save_mode: SaveMode,
 ) -> Message {
+    // The alias may change the group of the task, so it has to be applied before validation.
+    let command = match apply_alias(&mut message, settings) {
+        Ok(command) => command,
+        Err(error) => return create_failure_message(error.to_string()),
+    };
+
     let mut state = state.lock().unwrap();
     if let Err(message) = ensure_group_exists(&mut state, &message.group) {
         return message;
     }
 
-    let starting_status = if message.stashed || message.enqueue_at.is_some() {
-        TaskStatus::Stashed {
-            enqueue_at: message.enqueue_at,
+    // Ensure that the batch exists, if the task should be added to one.
+    if let Some(batch) = message.batch {
+        if let Err(message) = ensure_batch_exists(&mut state, batch) {
+            return message;
         }
-    } else {
-        TaskStatus::Queued
-    };
+    }
 
     // Ensure that specified dependencies actually exist.
     let not_found: Vec<_> = message
@@ -41,22 +50,10 @@ pub fn add_task(
         ));
     }
 
-    // Create a new task and add it to the state.
-    let mut task = Task::new(
-        message.command,
-        message.path,
-        message.envs,
-        message.group,
-        starting_status,
-        message.dependencies,
-        message.label,
-    );
-    // Insert the client alias if we applicable.
-    task.command = insert_alias(settings, task.original_command.clone());
-
-    // Sort and deduplicate dependency id.
-    task.dependencies.sort_unstable();
-    task.dependencies.dedup();
+    let start_immediately = message.start_immediately;
+    let print_task_id = message.print_task_id;
+    let enqueue_at = message.enqueue_at;
+    let task = task_from_add_message(message, command);
 
     // Check if the task's group is paused before we pass it to the state
     let group_status = state
@@ -68,10 +65,10 @@ pub fn add_task(
 
     // Add the task and persist the state.
     let task_id = state.add_task(task);
-    ok_or_return_failure_message!(save_state(&state, settings));
+    ok_or_return_failure_message!(save_state_with_mode(&mut state, settings, save_mode));
 
     // Notify the task handler, in case the client wants to start the task immediately.
-    if message.start_immediately {
+    if start_immediately {
         sender
             .send(StartMessage {
                 tasks: TaskSelection::TaskIds(vec![task_id]),
@@ -81,9 +78,9 @@ pub fn add_task(
     }
 
     // Create the customized response for the client.
-    let mut response = if message.print_task_id {
+    let mut response = if print_task_id {
         task_id.to_string()
-    } else if let Some(enqueue_at) = message.enqueue_at {
+    } else if let Some(enqueue_at) = enqueue_at {
         let enqueue_at = enqueue_at.format("%Y-%m-%d %H:%M:%S");
         format!("New task added (id {task_id}). It will be enqueued at {enqueue_at}")
     } else {
@@ -91,9 +88,91 @@ pub fn add_task(
     };
 
     // Notify the user if the task's group is paused
-    if !message.print_task_id && group_is_paused {
+    if !print_task_id && group_is_paused {
         response.push_str("\nThe group of this task is currently paused!")
     }
 
     create_success_message(response)
 }
+
+/// The command of a task after its alias has been applied.
+pub struct AliasedCommand {
+    /// The command, which is executed instead of the original command.
+    pub command: String,
+    /// The working directory of the task, before the alias' path has been applied.
+    /// This is only set, if the alias changed the path.
+    pub original_path: Option<PathBuf>,
+}
+
+/// Apply the alias of the message's command, if there's one.
+///
+/// The alias' defaults for group, path and environment variables are applied to the message.
+/// Its group is only used, if the task should be added to the default group.
+pub fn apply_alias(message: &mut AddMessage, settings: &Settings) -> Result<AliasedCommand, Error> {
+    let alias = match expand_alias(settings, &message.command)? {
+        Some(alias) => alias,
+        None => {
+            return Ok(AliasedCommand {
+                command: message.command.clone(),
+                original_path: None,
+            })
+        }
+    };
+
+    if let Some(group) = alias.group {
+        if message.group == PUEUE_DEFAULT_GROUP {
+            message.group = group;
+        }
+    }
+    let mut original_path = None;
+    if let Some(path) = alias.path {
+        let joined = message.path.join(path);
+        original_path = Some(std::mem::replace(&mut message.path, joined));
+    }
+    message.envs.extend(alias.envs);
+
+    Ok(AliasedCommand {
+        command: alias.command,
+        original_path,
+    })
+}
+
+/// Create a new task from an [AddMessage] and the command that should actually be executed.
+///
+/// The group, the batch and the dependencies of the task have to be validated beforehand.
+pub fn task_from_add_message(message: AddMessage, command: AliasedCommand) -> Task {
+    let starting_status = if message.stashed || message.enqueue_at.is_some() {
+        TaskStatus::Stashed {
+            enqueue_at: message.enqueue_at,
+        }
+    } else {
+        TaskStatus::Queued
+    };
+
+    // Create a new task.
+    let mut task = Task::new(
+        message.command,
+        message.path,
+        message.envs,
+        message.group,
+        starting_status,
+        message.dependencies,
+        message.label,
+    );
+    task.batch = message.batch;
+    task.timeout = message.timeout;
+    task.separate_output = message.separate_output;
+    task.timestamps = message.timestamps;
+    task.log_limit = message.log_limit;
+    task.exit_code_policy = message.exit_code_policy;
+    task.output_rules = message.output_rules;
+    task.stall_timeout = message.stall_timeout;
+    task.command = command.command;
+    task.original_path = command.original_path;
+
+    // Sort and deduplicate dependency id.
+    task.dependencies.sort_unstable();
+    task.dependencies.dedup();
+
+    task
+}
@@ -0,0 +1,23 @@
+use pueue_lib::aliasing::get_alias_definitions;
+use pueue_lib::network::message::*;
+use pueue_lib::settings::Settings;
+
+/// Invoked on `pueue alias`.
+/// Send the aliases of the daemon's alias file to the client.
+pub fn alias(message: AliasMessage, settings: &Settings) -> Message {
+    let mut aliases = match get_alias_definitions(settings) {
+        Ok(aliases) => aliases,
+        Err(error) => return create_failure_message(format!("{error}")),
+    };
+
+    match message {
+        AliasMessage::List => AliasResponseMessage { aliases }.into(),
+        AliasMessage::Show(name) => match aliases.remove_entry(&name) {
+            Some((name, alias)) => AliasResponseMessage {
+                aliases: [(name, alias)].into_iter().collect(),
+            }
+            .into(),
+            None => create_failure_message(format!("Alias \"{name}\" doesn't exist.")),
+        },
+    }
+}
@@ -0,0 +1,62 @@
+use pueue_lib::network::message::*;
+use pueue_lib::settings::Settings;
+use pueue_lib::state::SharedState;
+
+use super::TaskSender;
+use crate::network::message_handler::ok_or_failure_message;
+use crate::ok_or_return_failure_message;
+use crate::state_helper::{backup_state, list_state_backups, load_state_backup};
+
+/// Invoked on `pueue backup`.
+/// Manage state backups.
+/// - List backups
+/// - Create a backup
+/// - Restore a backup
+pub fn backup(
+    message: BackupMessage,
+    sender: &TaskSender,
+    state: &SharedState,
+    settings: &Settings,
+) -> Message {
+    match message {
+        BackupMessage::List => match list_state_backups(settings) {
+            Ok(backups) => BackupResponseMessage { backups }.into(),
+            Err(error) => create_failure_message(format!("Failed to list backups: {error:?}")),
+        },
+        BackupMessage::Create => {
+            let state = state.lock().unwrap();
+            match backup_state(&state, settings) {
+                Ok(name) => create_success_message(format!("Created state backup \"{name}\"")),
+                Err(error) => create_failure_message(format!("Failed to create backup: {error:?}")),
+            }
+        }
+        BackupMessage::Restore(name) => {
+            let state = state.lock().unwrap();
+
+            // Swapping the state is only safe, if no processes are currently managed by us.
+            let running: Vec<usize> = state
+                .tasks
+                .iter()
+                .filter(|(_, task)| task.is_running())
+                .map(|(id, _)| *id)
+                .collect();
+            if !running.is_empty() {
+                return create_failure_message(format!(
+                    "Cannot restore a backup while tasks are running: {running:?}"
+                ));
+            }
+
+            // Make sure the backup exists and can be loaded, before we bother the TaskHandler.
+            if let Err(error) = load_state_backup(settings, &name) {
+                return create_failure_message(format!("{error:#}"));
+            }
+
+            // Propagate the message to the TaskHandler, which is responsible for actually
+            // swapping the state and updating the worker pools.
+            let result = sender.send(BackupMessage::Restore(name.clone()));
+            ok_or_return_failure_message!(result);
+
+            create_success_message(format!("State backup \"{name}\" is being restored"))
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use std::sync::{Arc, Mutex};
+
+use crossbeam_channel::unbounded;
+
+use pueue_lib::network::message::*;
+use pueue_lib::settings::Settings;
+use pueue_lib::state::{GroupStatus, SharedState, State};
+
+use super::*;
+use crate::ok_or_return_failure_message;
+use crate::state_helper::{save_state_with_mode, EditLocks, SaveMode};
+
+/// Invoked for a batch of messages.
+/// All messages are handled one after another, without any other changes of the state in between.
+///
+/// The messages are handled on a copy of the state and the edit locks, which only replace them,
+/// if all messages succeed. Until then, saves are deferred and messages for the task handler are
+/// held back. Their effects on groups and batches are applied to the copy right away, so later
+/// messages of the batch already see them.
+pub fn batch(
+    messages: Vec<Message>,
+    connection: usize,
+    edit_locks: &EditLocks,
+    sender: &TaskSender,
+    state: &SharedState,
+    settings: &Settings,
+    save_mode: SaveMode,
+) -> Message {
+    if !messages.iter().all(can_be_undone) {
+        return create_failure_message(
+            "Removing or cleaning tasks and creating backups cannot be undone and isn't allowed in batches",
+        );
+    }
+
+    let mut state = state.lock().unwrap();
+    let mut edit_locks = edit_locks.lock().unwrap();
+    let scoped_state = Arc::new(Mutex::new(state.clone()));
+    let scoped_edit_locks = Arc::new(Mutex::new(edit_locks.clone()));
+    let (buffer, buffered) = unbounded();
+    let scoped_sender = TaskSender::new(buffer);
+
+    let mut responses = Vec::new();
+    let mut held_back = Vec::new();
+    for message in messages {
+        let response = handle_message(
+            message,
+            connection,
+            &scoped_edit_locks,
+            &scoped_sender,
+            &scoped_state,
+            settings,
+            SaveMode::Deferred,
+        );
+        let failed = matches!(response, Message::Failure(_));
+        responses.push(response);
+
+        if failed {
+            return BatchResponseMessage {
+                applied: false,
+                responses,
+            }
+            .into();
+        }
+
+        for message in buffered.try_iter() {
+            apply_to_state(&mut scoped_state.lock().unwrap(), &message);
+            held_back.push(message);
+        }
+    }
+
+    *state = std::mem::take(&mut *scoped_state.lock().unwrap());
+    *edit_locks = std::mem::take(&mut *scoped_edit_locks.lock().unwrap());
+    ok_or_return_failure_message!(save_state_with_mode(&mut state, settings, save_mode));
+    for message in held_back {
+        sender.send(message).expect(SENDER_ERR);
+    }
+
+    BatchResponseMessage {
+        applied: true,
+        responses,
+    }
+    .into()
+}
+
+/// Apply the effects of a message for the task handler on the groups and batches of the state.
+/// The task handler applies them again, once it receives the message.
+fn apply_to_state(state: &mut State, message: &Message) {
+    let (tasks, status) = match message {
+        Message::Pause(message) => (&message.tasks, GroupStatus::Paused),
+        Message::Start(message) => (&message.tasks, GroupStatus::Running),
+        // Killing the tasks of groups also pauses them.
+        Message::Kill(message) => (&message.tasks, GroupStatus::Paused),
+        Message::Group(GroupMessage::Add {
+            name,
+            parallel_tasks,
+        }) => {
+            let group = state.create_group(name);
+            if let Some(parallel_tasks) = parallel_tasks {
+                group.parallel_tasks = *parallel_tasks;
+            }
+            return;
+        }
+        Message::Group(GroupMessage::Remove(group)) => {
+            // The group has already been checked by the message handler.
+            let _ = state.remove_group(group);
+            return;
+        }
+        _ => return,
+    };
+
+    match tasks {
+        TaskSelection::Group(group) => {
+            if let Some(group) = state.groups.get_mut(group) {
+                group.status = status;
+            }
+        }
+        TaskSelection::Batch(batch) => {
+            if let Some(batch) = state.batches.get_mut(batch) {
+                batch.status = status;
+            }
+        }
+        TaskSelection::All => state.set_status_for_all_groups(status),
+        TaskSelection::TaskIds(_) => {}
+    }
+}
+
+/// Check whether the effects of a message are limited to the state and the task handler.
+/// Removing tasks also removes their logs right away and backups are written to disk right away,
+/// neither of which can be undone.
+fn can_be_undone(message: &Message) -> bool {
+    match message {
+        Message::Remove(_) | Message::Clean(_) | Message::Backup(BackupMessage::Create) => false,
+        Message::Conditional(message) => can_be_undone(&message.message),
+        Message::Batch(messages) => messages.iter().all(can_be_undone),
+        _ => true,
+    }
+}
@@ -20,7 +20,13 @@ fn construct_success_clean_message(message: CleanMessage) -> String {
         String::new()
     };
 
-    format!("All{successfull_only_fix} finished tasks have been removed{group_fix}")
+    let batch_fix = if let Some(batch) = message.batch {
+        format!(" from batch {batch}")
+    } else {
+        String::new()
+    };
+
+    format!("All{successfull_only_fix} finished tasks have been removed{group_fix}{batch_fix}")
 }
 
 /// Invoked when calling `pueue clean`.
@@ -36,7 +42,7 @@ pub fn clean(message: CleanMessage, state: &SharedState, settings: &Settings) ->
             continue;
         }
 
-        if message.successful_only || message.group.is_some() {
+        if message.successful_only || message.group.is_some() || message.batch.is_some() {
             if let Some(task) = state.tasks.get(task_id) {
                 // Check if we should ignore this task, if only successful tasks should be removed.
                 if message.successful_only
@@ -50,13 +56,19 @@ pub fn clean(message: CleanMessage, state: &SharedState, settings: &Settings) ->
                 if message.group.is_some() && message.group.as_deref() != Some(&task.group) {
                     continue;
                 }
+
+                // The same goes for batches.
+                if message.batch.is_some() && message.batch != task.batch {
+                    continue;
+                }
             }
         }
         let _ = state.tasks.remove(task_id).unwrap();
         clean_log_handles(*task_id, &settings.shared.pueue_directory());
     }
+    state.remove_empty_batches();
 
-    ok_or_return_failure_message!(save_state(&state, settings));
+    ok_or_return_failure_message!(save_state(&mut state, settings));
 
     create_success_message(construct_success_clean_message(message))
 }
@@ -73,6 +85,7 @@ mod tests {
         CleanMessage {
             successful_only,
             group,
+            batch: None,
         }
     }
 
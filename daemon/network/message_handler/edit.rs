@@ -1,92 +1,433 @@
-use pueue_lib::aliasing::insert_alias;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use chrono::Local;
+
+use pueue_lib::aliasing::{expand_alias, ExpandedAlias};
 use pueue_lib::network::message::*;
-use pueue_lib::state::SharedState;
+use pueue_lib::state::{SharedState, PUEUE_DEFAULT_GROUP};
 use pueue_lib::task::TaskStatus;
 
 use super::*;
 use crate::ok_or_return_failure_message;
-use crate::state_helper::save_state;
+use crate::state_helper::{save_state_with_mode, EditLock, EditLocks, LockedState, SaveMode};
 
 /// Invoked when calling `pueue edit`.
 /// If a user wants to edit a message, we need to send him the current command.
 /// Lock the task to prevent execution, before the user has finished editing the command.
-pub fn edit_request(task_id: usize, state: &SharedState) -> Message {
+///
+/// The lock is owned by the client's connection and is released, if the connection goes away
+/// or if the lease expires.
+pub fn edit_request(
+    task_id: usize,
+    connection: usize,
+    edit_locks: &EditLocks,
+    state: &SharedState,
+    settings: &Settings,
+) -> Message {
     // Check whether the task exists and is queued/stashed. Abort if that's not the case.
     let mut state = state.lock().unwrap();
-    match state.tasks.get_mut(&task_id) {
+    let mut edit_locks = edit_locks.lock().unwrap();
+    match state.tasks.get(&task_id) {
         Some(task) => {
             if !task.is_queued() {
                 return create_failure_message("You can only edit a queued/stashed task");
             }
-            task.prev_status = task.status.clone();
-            task.status = TaskStatus::Locked;
-
-            EditResponseMessage {
-                task_id: task.id,
-                command: task.original_command.clone(),
-                path: task.path.clone(),
-                label: task.label.clone(),
-            }
-            .into()
+            lock_task(&mut state, &mut edit_locks, task_id, connection, settings).into()
         }
         None => create_failure_message("No task with this id."),
     }
 }
 
+/// Invoked when calling `pueue edit` with multiple tasks.
+/// Lock all editable tasks at once. Tasks that cannot be edited are skipped and reported.
+pub fn bulk_edit_request(
+    mut task_ids: Vec<usize>,
+    connection: usize,
+    edit_locks: &EditLocks,
+    state: &SharedState,
+    settings: &Settings,
+) -> Message {
+    // Tasks must only be locked once, otherwise their previous status would be lost.
+    task_ids.sort_unstable();
+    task_ids.dedup();
+
+    let mut state = state.lock().unwrap();
+    let mut edit_locks = edit_locks.lock().unwrap();
+    let (editable, skipped): (Vec<usize>, Vec<usize>) = task_ids
+        .into_iter()
+        .partition(|id| state.tasks.get(id).map_or(false, |task| task.is_queued()));
+    if editable.is_empty() {
+        return create_failure_message(format!(
+            "You can only edit existing queued/stashed tasks. Can't edit tasks: {skipped:?}"
+        ));
+    }
+
+    let tasks = editable
+        .iter()
+        .map(|id| lock_task(&mut state, &mut edit_locks, *id, connection, settings))
+        .collect();
+
+    BulkEditResponseMessage { tasks, skipped }.into()
+}
+
+/// Move an existing task into the `Locked` status and return its editable details.
+fn lock_task(
+    state: &mut LockedState,
+    edit_locks: &mut BTreeMap<usize, EditLock>,
+    task_id: usize,
+    connection: usize,
+    settings: &Settings,
+) -> EditResponseMessage {
+    let lease = chrono::Duration::seconds(settings.daemon.edit_lock_lease_seconds as i64);
+    edit_locks.insert(
+        task_id,
+        EditLock {
+            connection,
+            expires: Local::now() + lease,
+        },
+    );
+
+    let task = state.tasks.get_mut(&task_id).unwrap();
+    task.prev_status = task.status.clone();
+    task.status = TaskStatus::Locked;
+
+    EditResponseMessage {
+        task_id: task.id,
+        command: task.original_command.clone(),
+        path: task.path.clone(),
+        label: task.label.clone(),
+        envs: task.envs.clone(),
+        group: task.group.clone(),
+        dependencies: task.dependencies.clone(),
+    }
+}
+
+/// Restore a task that has been locked by the given connection to its previous state.
+fn unlock_task(
+    state: &mut LockedState,
+    edit_locks: &mut BTreeMap<usize, EditLock>,
+    task_id: usize,
+    connection: usize,
+) -> Result<(), String> {
+    let owner = edit_locks.get(&task_id).map(|lock| lock.connection);
+    let task = match state.tasks.get_mut(&task_id) {
+        Some(task) => task,
+        None => return Err(format!("Task to edit has gone away: {task_id}")),
+    };
+    if task.status != TaskStatus::Locked {
+        return Err("Task is no longer locked.".to_string());
+    }
+    if owner != Some(connection) {
+        return Err(format!("Task {task_id} is being edited by another client."));
+    }
+
+    task.status = task.prev_status.clone();
+    edit_locks.remove(&task_id);
+
+    Ok(())
+}
+
 /// Invoked after closing the editor on `pueue edit`.
 /// Now we actually update the message with the updated command from the client.
-pub fn edit(message: EditMessage, state: &SharedState, settings: &Settings) -> Message {
+pub fn edit(
+    message: EditMessage,
+    connection: usize,
+    edit_locks: &EditLocks,
+    state: &SharedState,
+    settings: &Settings,
+    save_mode: SaveMode,
+) -> Message {
     // Check whether the task exists and is locked. Abort if that's not the case.
+    // Restore the task to its previous state.
+    // This also happens if the edit is invalid, so the task doesn't stay locked.
     let mut state = state.lock().unwrap();
-    match state.tasks.get_mut(&message.task_id) {
-        Some(task) => {
-            if !(task.status == TaskStatus::Locked) {
-                return create_failure_message("Task is no longer locked.");
-            }
+    let mut edit_locks = edit_locks.lock().unwrap();
+    if let Err(error) = unlock_task(&mut state, &mut edit_locks, message.task_id, connection) {
+        return create_failure_message(error);
+    }
+
+    // Validate the new group and dependencies, before anything is changed.
+    let edits = std::slice::from_ref(&message);
+    if let Err(error) = validate_edits(&state, edits) {
+        return reject_edit(
+            &mut state,
+            settings,
+            save_mode,
+            create_failure_message(error),
+        );
+    }
+    let mut aliases = match expand_aliases(&state, edits, settings) {
+        Ok(aliases) => aliases,
+        Err(error) => {
+            return reject_edit(
+                &mut state,
+                settings,
+                save_mode,
+                create_failure_message(error),
+            )
+        }
+    };
+    apply_edit(&mut state, message, aliases.remove(0));
+    ok_or_return_failure_message!(save_state_with_mode(&mut state, settings, save_mode));
 
-            // Restore the task to its previous state.
-            task.status = task.prev_status.clone();
+    create_success_message("Command has been updated")
+}
 
-            // Update command if applicable.
-            if let Some(command) = message.command {
-                task.original_command = command.clone();
-                task.command = insert_alias(settings, command);
-            }
-            // Update path if applicable.
-            if let Some(path) = message.path {
-                task.path = path;
-            }
-            // Update label if applicable.
-            if message.label.is_some() {
-                task.label = message.label;
-            } else if message.delete_label {
-                task.label = None;
+/// The edited tasks have already been unlocked, which has to be persisted, even if the edit
+/// itself is rejected.
+fn reject_edit(
+    state: &mut LockedState,
+    settings: &Settings,
+    save_mode: SaveMode,
+    message: Message,
+) -> Message {
+    ok_or_return_failure_message!(save_state_with_mode(state, settings, save_mode));
+
+    message
+}
+
+/// Invoked after closing the editor on `pueue edit` with multiple tasks.
+/// Either all edits are applied or none of them.
+pub fn bulk_edit(
+    message: BulkEditMessage,
+    connection: usize,
+    edit_locks: &EditLocks,
+    state: &SharedState,
+    settings: &Settings,
+    save_mode: SaveMode,
+) -> Message {
+    let mut state = state.lock().unwrap();
+    let mut edit_locks = edit_locks.lock().unwrap();
+
+    // Restore all tasks to their previous state, even if the edit turns out to be invalid.
+    // Each task is only unlocked once, even if it's edited several times.
+    let mut task_ids: Vec<usize> = message.tasks.iter().map(|edit| edit.task_id).collect();
+    task_ids.sort_unstable();
+    task_ids.dedup();
+    let mut not_locked = Vec::new();
+    for task_id in &task_ids {
+        if unlock_task(&mut state, &mut edit_locks, *task_id, connection).is_err() {
+            not_locked.push(*task_id);
+        }
+    }
+    if !not_locked.is_empty() {
+        let message = create_failure_message(format!(
+            "Tasks {not_locked:?} are no longer locked by this client. No task has been changed."
+        ));
+        return reject_edit(&mut state, settings, save_mode, message);
+    }
+    if task_ids.len() != message.tasks.len() {
+        let message = create_failure_message(
+            "Tasks can only be edited once at a time. No task has been changed.",
+        );
+        return reject_edit(&mut state, settings, save_mode, message);
+    }
+
+    if let Err(error) = validate_edits(&state, &message.tasks) {
+        return reject_edit(
+            &mut state,
+            settings,
+            save_mode,
+            create_failure_message(error),
+        );
+    }
+    let aliases = match expand_aliases(&state, &message.tasks, settings) {
+        Ok(aliases) => aliases,
+        Err(error) => {
+            return reject_edit(
+                &mut state,
+                settings,
+                save_mode,
+                create_failure_message(error),
+            )
+        }
+    };
+    let task_ids: Vec<String> = task_ids.iter().map(usize::to_string).collect();
+    for (edit, alias) in message.tasks.into_iter().zip(aliases) {
+        apply_edit(&mut state, edit, alias);
+    }
+    ok_or_return_failure_message!(save_state_with_mode(&mut state, settings, save_mode));
+
+    create_success_message(format!("Tasks have been updated: {}", task_ids.join(", ")))
+}
+
+/// Ensure that the new groups and dependencies of the given edits are valid.
+fn validate_edits(state: &LockedState, edits: &[EditMessage]) -> Result<(), String> {
+    for edit in edits {
+        if let Some(group) = &edit.group {
+            if !state.groups.contains_key(group) {
+                let groups: Vec<&String> = state.groups.keys().collect();
+                return Err(format!(
+                    "Group {group} doesn't exists. Use one of these: {groups:?}"
+                ));
             }
+        }
+    }
 
-            ok_or_return_failure_message!(save_state(&state, settings));
+    // All new dependencies have to be considered at once, as they may form a cycle together.
+    let new_dependencies: HashMap<usize, &Vec<usize>> = edits
+        .iter()
+        .filter_map(|edit| edit.dependencies.as_ref().map(|deps| (edit.task_id, deps)))
+        .collect();
+    for (task_id, dependencies) in &new_dependencies {
+        validate_dependencies(state, &new_dependencies, *task_id, dependencies)?;
+    }
 
-            create_success_message("Command has been updated")
+    Ok(())
+}
+
+/// Ensure that the given dependencies of a task exist and don't introduce a cycle.
+///
+/// A cycle exists, if the task can be reached by following the dependencies of its new
+/// dependencies. Such tasks would wait for each other forever.
+/// `new_dependencies` take precedence over the dependencies that are stored in the state.
+fn validate_dependencies(
+    state: &LockedState,
+    new_dependencies: &HashMap<usize, &Vec<usize>>,
+    task_id: usize,
+    dependencies: &[usize],
+) -> Result<(), String> {
+    let not_found: Vec<_> = dependencies
+        .iter()
+        .filter(|id| !state.tasks.contains_key(id))
+        .collect();
+    if !not_found.is_empty() {
+        return Err(format!(
+            "Unable to setup dependencies : task(s) {not_found:?} not found",
+        ));
+    }
+
+    let mut visited = HashSet::new();
+    let mut to_visit: Vec<usize> = dependencies.to_vec();
+    while let Some(id) = to_visit.pop() {
+        if id == task_id {
+            return Err(format!(
+                "Unable to setup dependencies : task {task_id} would depend on itself (dependency cycle)"
+            ));
+        }
+        if !visited.insert(id) {
+            continue;
+        }
+        if let Some(dependencies) = new_dependencies.get(&id) {
+            to_visit.extend(dependencies.iter().copied());
+        } else if let Some(task) = state.tasks.get(&id) {
+            to_visit.extend(task.dependencies.iter().copied());
         }
-        None => create_failure_message(format!("Task to edit has gone away: {}", message.task_id)),
     }
+
+    Ok(())
 }
 
-/// Invoked if a client fails to edit a task and asks the daemon to restore the task's status.
-pub fn edit_restore(task_id: usize, state: &SharedState) -> Message {
-    // Check whether the task exists and is queued/stashed. Abort if that's not the case.
-    let mut state = state.lock().unwrap();
-    match state.tasks.get_mut(&task_id) {
-        Some(task) => {
-            if task.status != TaskStatus::Locked {
-                return create_failure_message("The requested task isn't locked");
+/// Expand the aliases of the new commands of the given edits.
+/// Edits, that don't change the command or whose command doesn't use an alias, get `None`.
+fn expand_aliases(
+    state: &LockedState,
+    edits: &[EditMessage],
+    settings: &Settings,
+) -> Result<Vec<Option<ExpandedAlias>>, String> {
+    let mut aliases = Vec::new();
+    for edit in edits {
+        let alias = match &edit.command {
+            Some(command) => expand_alias(settings, command).map_err(|err| err.to_string())?,
+            None => None,
+        };
+        if let Some(group) = alias.as_ref().and_then(|alias| alias.group.as_ref()) {
+            if !state.groups.contains_key(group) {
+                return Err(format!(
+                    "Group {group} of the command's alias doesn't exist"
+                ));
             }
-            task.status = task.prev_status.clone();
+        }
+        aliases.push(alias);
+    }
 
-            create_success_message(format!(
-                "The requested task's status has been restored to '{}'",
-                task.status
-            ))
+    Ok(aliases)
+}
+
+/// Apply an already validated edit to its task.
+///
+/// The defaults of the new command's alias are applied the same way as for new tasks.
+fn apply_edit(state: &mut LockedState, message: EditMessage, alias: Option<ExpandedAlias>) {
+    let task = state
+        .tasks
+        .get_mut(&message.task_id)
+        .expect("We ensured that the task exists.");
+
+    // Update command if applicable.
+    let command_changed = message.command.is_some();
+    if let Some(command) = message.command {
+        task.command = alias
+            .as_ref()
+            .map_or_else(|| command.clone(), |alias| alias.command.clone());
+        task.original_command = command;
+    }
+    // Update path if applicable.
+    // An explicitly edited path takes precedence over the path of the command's alias.
+    // Otherwise, the path of a new command's alias is applied to the directory, in which the
+    // task has been added, rather than to the path of a previous alias.
+    if let Some(path) = message.path {
+        task.path = path;
+        task.original_path = None;
+    } else if command_changed {
+        let original_path = task
+            .original_path
+            .take()
+            .unwrap_or_else(|| task.path.clone());
+        match alias.as_ref().and_then(|alias| alias.path.as_ref()) {
+            Some(alias_path) => {
+                task.path = original_path.join(alias_path);
+                task.original_path = Some(original_path);
+            }
+            None => task.path = original_path,
         }
-        None => create_failure_message("No task with this id."),
     }
+    // Update label if applicable.
+    if message.label.is_some() {
+        task.label = message.label;
+    } else if message.delete_label {
+        task.label = None;
+    }
+    // Update environment variables if applicable.
+    if let Some(envs) = message.envs {
+        task.envs = envs;
+    }
+    // Update group if applicable.
+    if let Some(group) = message.group {
+        task.group = group;
+    }
+    // Update dependencies if applicable.
+    if let Some(mut dependencies) = message.dependencies {
+        dependencies.sort_unstable();
+        dependencies.dedup();
+        task.dependencies = dependencies;
+    }
+
+    if let Some(alias) = alias {
+        if let Some(group) = alias.group {
+            if task.group == PUEUE_DEFAULT_GROUP {
+                task.group = group;
+            }
+        }
+        task.envs.extend(alias.envs);
+    }
+}
+
+/// Invoked if a client fails to edit a task and asks the daemon to restore the task's status.
+pub fn edit_restore(
+    task_id: usize,
+    connection: usize,
+    edit_locks: &EditLocks,
+    state: &SharedState,
+) -> Message {
+    // Check whether the task exists and is locked by this client. Abort if that's not the case.
+    let mut state = state.lock().unwrap();
+    let mut edit_locks = edit_locks.lock().unwrap();
+    if let Err(error) = unlock_task(&mut state, &mut edit_locks, task_id, connection) {
+        return create_failure_message(error);
+    }
+
+    create_success_message(format!(
+        "The requested task's status has been restored to '{}'",
+        state.tasks[&task_id].status
+    ))
 }
@@ -0,0 +1,62 @@
+use std::collections::BTreeMap;
+
+use pueue_lib::log::{build_search_pattern, search_log};
+use pueue_lib::network::message::*;
+use pueue_lib::settings::Settings;
+use pueue_lib::state::SharedState;
+
+/// Invoked when calling `pueue grep`.
+/// Search the logs of the requested tasks and return the matching lines of each task.
+/// Tasks without any matching line aren't part of the response.
+///
+/// The search stops, once the requested maximum of matching lines has been found.
+pub fn grep(message: GrepRequestMessage, state: &SharedState, settings: &Settings) -> Message {
+    let pattern =
+        match build_search_pattern(&message.pattern, message.fixed_strings, message.ignore_case) {
+            Ok(pattern) => pattern,
+            Err(err) => return create_failure_message(format!("Invalid search pattern: {err}")),
+        };
+    let task_ids: Vec<usize> = {
+        let state = state.lock().unwrap();
+        message
+            .task_ids
+            .into_iter()
+            .filter(|task_id| state.tasks.contains_key(task_id))
+            .collect()
+    };
+
+    let pueue_directory = settings.shared.pueue_directory();
+    let mut matches = BTreeMap::new();
+    let mut remaining = message.max_matches;
+    for task_id in task_ids {
+        if remaining == 0 {
+            break;
+        }
+
+        let result = search_log(
+            task_id,
+            &pueue_directory,
+            &pattern,
+            message.context,
+            remaining,
+        );
+        match result {
+            Ok(lines) if lines.is_empty() => continue,
+            Ok(lines) => {
+                remaining -= lines.iter().filter(|line| line.matches).count();
+                matches.insert(task_id, lines);
+            }
+            Err(err) => {
+                return create_failure_message(format!(
+                    "Failed searching the log of task {task_id}: {err}"
+                ))
+            }
+        }
+    }
+
+    GrepResponseMessage {
+        matches,
+        truncated: remaining == 0,
+    }
+    .into()
+}
@@ -2,7 +2,7 @@ use pueue_lib::network::message::*;
 use pueue_lib::state::SharedState;
 
 use super::{TaskSender, SENDER_ERR};
-use crate::network::response_helper::{ensure_group_exists, task_action_response_helper};
+use crate::network::response_helper::*;
 
 /// Invoked when calling `pueue kill`.
 /// Forward the kill message to the task handler, which then kills the process.
@@ -16,6 +16,13 @@ pub fn kill(message: KillMessage, sender: &TaskSender, state: &SharedState) -> M
         }
     }
 
+    // If a batch is selected, make sure it exists.
+    if let TaskSelection::Batch(batch) = &message.tasks {
+        if let Err(message) = ensure_batch_exists(&mut state, *batch) {
+            return message;
+        }
+    }
+
     sender.send(message.clone()).expect(SENDER_ERR);
 
     if let Some(signal) = message.signal {
@@ -29,6 +36,9 @@ pub fn kill(message: KillMessage, sender: &TaskSender, state: &SharedState) -> M
             TaskSelection::Group(group) => create_success_message(format!(
                 "Sending signal {signal} to all running tasks of group {group}.",
             )),
+            TaskSelection::Batch(batch) => create_success_message(format!(
+                "Sending signal {signal} to all running tasks of batch {batch}.",
+            )),
             TaskSelection::All => {
                 create_success_message(format!("Sending signal {signal} to all running tasks."))
             }
@@ -44,6 +54,9 @@ pub fn kill(message: KillMessage, sender: &TaskSender, state: &SharedState) -> M
             TaskSelection::Group(group) => create_success_message(format!(
                 "All tasks of group \"{group}\" are being killed. The group will also be paused!!!"
             )),
+            TaskSelection::Batch(batch) => create_success_message(format!(
+                "All tasks of batch {batch} are being killed. The batch will also be paused!!!"
+            )),
             TaskSelection::All => {
                 create_success_message("All tasks are being killed. All groups will be paused!!!")
             }
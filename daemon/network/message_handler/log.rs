@@ -1,52 +1,103 @@
 use std::collections::BTreeMap;
+use std::path::Path;
 
-use pueue_lib::log::read_and_compress_log_file;
+use pueue_lib::log::{
+    compress_log_output, has_separate_output, has_timestamps, read_and_compress_log_file,
+    read_timestamped_log, LogRange,
+};
 use pueue_lib::network::message::*;
 use pueue_lib::settings::Settings;
-use pueue_lib::state::SharedState;
+use pueue_lib::state::{SharedState, State};
+use pueue_lib::task::Task;
 
 /// Invoked when calling `pueue log`.
 /// Return tasks and their output to the client.
 pub fn get_log(message: LogRequestMessage, state: &SharedState, settings: &Settings) -> Message {
     let state = { state.lock().unwrap().clone() };
-    // Return all logs, if no specific task id is specified.
-    let task_ids = if message.task_ids.is_empty() {
-        state.tasks.keys().cloned().collect()
-    } else {
-        message.task_ids
-    };
+    let pueue_directory = settings.shared.pueue_directory();
+    let range = requested_range(&message);
 
     let mut tasks = BTreeMap::new();
-    for task_id in task_ids.iter() {
-        if let Some(task) = state.tasks.get(task_id) {
-            // We send log output and the task at the same time.
-            // This isn't as efficient as sending the raw compressed data directly,
-            // but it's a lot more convenient for now.
-            let (output, output_complete) = if message.send_logs {
-                match read_and_compress_log_file(
-                    *task_id,
-                    &settings.shared.pueue_directory(),
-                    message.lines,
-                ) {
-                    Ok((output, output_complete)) => (Some(output), output_complete),
-                    Err(err) => {
-                        // Fail early if there's some problem with getting the log output
-                        return create_failure_message(format!(
-                            "Failed reading process output file: {err:?}"
-                        ));
-                    }
-                }
+    for task in requested_tasks(&message, &state) {
+        let task_id = task.id;
+        // We send log output and the task at the same time.
+        // This isn't as efficient as sending the raw compressed data directly,
+        // but it's a lot more convenient for now.
+        let (output, output_complete) = if is_output_missing(&message, task_id, &pueue_directory) {
+            (None, true)
+        } else if message.send_logs {
+            let result = if needs_index(&message) {
+                read_timestamped_log(
+                    task_id,
+                    &pueue_directory,
+                    message.stream,
+                    range,
+                    message.since,
+                    message.timestamps,
+                )
+                .and_then(|(output, output_complete)| {
+                    Ok((compress_log_output(&output)?, output_complete))
+                })
             } else {
-                (None, true)
+                read_and_compress_log_file(task_id, &pueue_directory, range, message.stream)
             };
+            match result {
+                Ok((output, output_complete)) => (Some(output), output_complete),
+                Err(err) => {
+                    // Fail early if there's some problem with getting the log output
+                    return create_failure_message(format!(
+                        "Failed reading process output file: {err:?}"
+                    ));
+                }
+            }
+        } else {
+            (None, true)
+        };
 
-            let task_log = TaskLogMessage {
-                task: task.clone(),
-                output,
-                output_complete,
-            };
-            tasks.insert(*task_id, task_log);
-        }
+        let task_log = TaskLogMessage {
+            task,
+            output,
+            output_complete,
+        };
+        tasks.insert(task_id, task_log);
     }
     Message::LogResponse(tasks)
 }
+
+/// Return all requested tasks in order.
+/// All tasks are returned, if no specific task id is specified.
+pub(crate) fn requested_tasks(message: &LogRequestMessage, state: &State) -> Vec<Task> {
+    if message.task_ids.is_empty() {
+        return state.tasks.values().cloned().collect();
+    }
+
+    let mut task_ids = message.task_ids.clone();
+    task_ids.sort_unstable();
+    task_ids.dedup();
+    task_ids
+        .iter()
+        .filter_map(|task_id| state.tasks.get(task_id).cloned())
+        .collect()
+}
+
+/// The part of the log, that has been requested.
+/// The `range` takes precedence over the amount of `lines`.
+pub(crate) fn requested_range(message: &LogRequestMessage) -> Option<LogRange> {
+    message.range.or_else(|| message.lines.map(LogRange::Tail))
+}
+
+/// Whether the output has to be read from the timestamp index of the task's log.
+pub(crate) fn needs_index(message: &LogRequestMessage) -> bool {
+    message.timestamps || message.since.is_some()
+}
+
+/// No output is sent, if a single stream or timestamps are requested, but the task's
+/// output hasn't been captured that way.
+pub(crate) fn is_output_missing(
+    message: &LogRequestMessage,
+    task_id: usize,
+    pueue_directory: &Path,
+) -> bool {
+    (message.stream.is_some() && !has_separate_output(task_id, pueue_directory))
+        || (needs_index(message) && !has_timestamps(task_id, pueue_directory))
+}
@@ -1,19 +1,25 @@
 use std::fmt::Display;
+use std::sync::{Arc, Mutex};
 
 use pueue_lib::network::message::*;
 use pueue_lib::settings::Settings;
-use pueue_lib::state::SharedState;
+use pueue_lib::state::{SharedState, State};
 
 use super::TaskSender;
 use crate::network::response_helper::*;
+use crate::state_helper::{EditLocks, SaveMode};
 
 mod add;
+mod alias;
+mod backup;
+mod batch;
 mod clean;
 mod edit;
 mod enqueue;
+mod grep;
 mod group;
 mod kill;
-mod log;
+pub(crate) mod log;
 mod parallel;
 mod pause;
 mod remove;
@@ -21,40 +27,223 @@ mod restart;
 mod send;
 mod start;
 mod stash;
+mod submit;
 mod switch;
+mod task_control;
+mod template;
 
 pub static SENDER_ERR: &str = "Failed to send message to task handler thread";
 
+/// Handle a message of a client.
+/// `connection` is the id of the client's connection, which owns any edit locks it requests.
+/// `save_mode` decides, whether changes of the state are saved to disk right away.
+///
+/// Successfully handled messages that change the state increase the state's revision.
 pub fn handle_message(
     message: Message,
+    connection: usize,
+    edit_locks: &EditLocks,
     sender: &TaskSender,
     state: &SharedState,
     settings: &Settings,
+    save_mode: SaveMode,
+) -> Message {
+    let changes_state = changes_state(&message);
+    let revision = state.lock().unwrap().revision;
+
+    let response = dispatch_message(
+        message, connection, edit_locks, sender, state, settings, save_mode,
+    );
+
+    if changes_state {
+        bump_revision(&response, revision, state);
+    }
+
+    response
+}
+
+/// Handle a message of a running task, whose connection has been authenticated with the token,
+/// whose hash is `token_hash`.
+/// Tasks may only act on themselves, so any other messages are rejected.
+pub fn handle_task_message(
+    message: Message,
+    token_hash: &str,
+    sender: &TaskSender,
+    state: &SharedState,
+    settings: &Settings,
+) -> Message {
+    let message = match message {
+        Message::TaskControl(message) => message,
+        _ => return create_failure_message("Tasks may only send task control messages"),
+    };
+    let revision = state.lock().unwrap().revision;
+
+    let response = task_control::task_control(message, token_hash, sender, state, settings);
+    bump_revision(&response, revision, state);
+
+    response
+}
+
+/// Increase the state's revision after a state changing message has been handled successfully.
+/// Handlers that save the state already increased the revision.
+fn bump_revision(response: &Message, revision: u64, state: &SharedState) {
+    if matches!(response, Message::Failure(_)) {
+        return;
+    }
+
+    let mut state = state.lock().unwrap();
+    if state.revision == revision {
+        state.revision += 1;
+    }
+}
+
+/// Call the respective handler of a message.
+fn dispatch_message(
+    message: Message,
+    connection: usize,
+    edit_locks: &EditLocks,
+    sender: &TaskSender,
+    state: &SharedState,
+    settings: &Settings,
+    save_mode: SaveMode,
 ) -> Message {
     match message {
-        Message::Add(message) => add::add_task(message, sender, state, settings),
+        Message::Add(message) => add::add_task(message, sender, state, settings, save_mode),
+        Message::Alias(message) => alias::alias(message, settings),
+        Message::Backup(message) => backup::backup(message, sender, state, settings),
+        Message::Batch(messages) => batch::batch(
+            messages, connection, edit_locks, sender, state, settings, save_mode,
+        ),
         Message::Clean(message) => clean::clean(message, state, settings),
-        Message::Edit(message) => edit::edit(message, state, settings),
-        Message::EditRequest(task_id) => edit::edit_request(task_id, state),
-        Message::EditRestore(task_id) => edit::edit_restore(task_id, state),
+        Message::Edit(message) => {
+            edit::edit(message, connection, edit_locks, state, settings, save_mode)
+        }
+        Message::EditRequest(task_id) => {
+            edit::edit_request(task_id, connection, edit_locks, state, settings)
+        }
+        Message::EditRestore(task_id) => edit::edit_restore(task_id, connection, edit_locks, state),
+        Message::BulkEdit(message) => {
+            edit::bulk_edit(message, connection, edit_locks, state, settings, save_mode)
+        }
+        Message::BulkEditRequest(task_ids) => {
+            edit::bulk_edit_request(task_ids, connection, edit_locks, state, settings)
+        }
         Message::Enqueue(message) => enqueue::enqueue(message, state),
+        Message::Grep(message) => grep::grep(message, state, settings),
         Message::Group(message) => group::group(message, sender, state),
         Message::Kill(message) => kill::kill(message, sender, state),
+        // Chunked logs are sent as multiple messages. Top-level requests are handled by the
+        // socket, but there's no way to do so for requests inside of batches or conditions.
+        Message::Log(message) if message.chunk_size.is_some() => {
+            create_failure_message("Logs can only be requested in chunks as a single message")
+        }
         Message::Log(message) => log::get_log(message, state, settings),
         Message::Parallel(message) => parallel::set_parallel_tasks(message, state),
         Message::Pause(message) => pause::pause(message, sender, state),
         Message::Remove(task_ids) => remove::remove(task_ids, state, settings),
         Message::Reset(message) => reset(message, sender),
         Message::Restart(message) => restart::restart_multiple(message, sender, state, settings),
+        Message::Run(message) => template::run(message, sender, state, settings, save_mode),
         Message::Send(message) => send::send(message, sender, state),
         Message::Start(message) => start::start(message, sender, state),
         Message::Stash(task_ids) => stash::stash(task_ids, state),
-        Message::Switch(message) => switch::switch(message, state, settings),
+        Message::Submit(message) => submit::submit(message, sender, state, settings, save_mode),
+        Message::Switch(message) => switch::switch(message, state, settings, save_mode),
+        Message::Template(message) => template::template(message, state, settings, save_mode),
         Message::Status => get_status(state),
+        Message::Conditional(message) => conditional(
+            message, connection, edit_locks, sender, state, settings, save_mode,
+        ),
+        Message::TaskControl(_) => {
+            create_failure_message("Task control messages can only be sent by running tasks")
+        }
         _ => create_failure_message("Not yet implemented"),
     }
 }
 
+/// Check whether a message might change the state.
+/// Conditional messages and batches aren't included, as their inner messages are handled on
+/// their own.
+fn changes_state(message: &Message) -> bool {
+    !matches!(
+        message,
+        Message::Status
+            | Message::Log(_)
+            | Message::Grep(_)
+            | Message::Alias(_)
+            | Message::Conditional(_)
+            | Message::Batch(_)
+            | Message::Group(GroupMessage::List)
+            | Message::Backup(BackupMessage::List | BackupMessage::Create)
+            | Message::Template(TemplateMessage::List)
+    )
+}
+
+/// Invoked for messages with preconditions.
+/// Check the preconditions and handle the inner message, if they're met.
+///
+/// The state stays locked the whole time, so nothing can change in between.
+/// To do so, the inner message is handled on a copy of the state, which then replaces the state.
+/// The same happens for the edit locks, as they have to match the locked tasks of the state.
+fn conditional(
+    message: ConditionalMessage,
+    connection: usize,
+    edit_locks: &EditLocks,
+    sender: &TaskSender,
+    state: &SharedState,
+    settings: &Settings,
+    save_mode: SaveMode,
+) -> Message {
+    let mut state = state.lock().unwrap();
+    if let Err(error) = check_preconditions(&state, &message) {
+        return create_failure_message(error);
+    }
+
+    let mut edit_locks = edit_locks.lock().unwrap();
+    let scoped_state = Arc::new(Mutex::new(state.clone()));
+    let scoped_edit_locks = Arc::new(Mutex::new(edit_locks.clone()));
+    let response = handle_message(
+        *message.message,
+        connection,
+        &scoped_edit_locks,
+        sender,
+        &scoped_state,
+        settings,
+        save_mode,
+    );
+    *state = std::mem::take(&mut *scoped_state.lock().unwrap());
+    *edit_locks = std::mem::take(&mut *scoped_edit_locks.lock().unwrap());
+
+    response
+}
+
+/// Ensure that the state matches the preconditions of a message.
+fn check_preconditions(state: &State, message: &ConditionalMessage) -> Result<(), String> {
+    if let Some(revision) = message.if_revision {
+        if state.revision != revision {
+            return Err(format!(
+                "The state has changed in the meantime. Expected revision {revision}, but the current revision is {}",
+                state.revision
+            ));
+        }
+    }
+
+    for (task_id, status) in &message.if_status {
+        match state.tasks.get(task_id) {
+            Some(task) if &task.status == status => continue,
+            Some(task) => {
+                return Err(format!(
+                    "Task {task_id} has status {} instead of {status}",
+                    task.status
+                ))
+            }
+            None => return Err(format!("Task {task_id} doesn't exist")),
+        }
+    }
+
+    Ok(())
+}
+
 /// Invoked when calling `pueue reset`.
 /// Forward the reset request to the task handler.
 /// The handler then kills all children and clears the task queue.
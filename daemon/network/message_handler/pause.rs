@@ -16,6 +16,13 @@ pub fn pause(message: PauseMessage, sender: &TaskSender, state: &SharedState) ->
         }
     }
 
+    // If a batch is selected, make sure it exists.
+    if let TaskSelection::Batch(batch) = &message.tasks {
+        if let Err(message) = ensure_batch_exists(&mut state, *batch) {
+            return message;
+        }
+    }
+
     // Forward the message to the task handler.
     sender.send(message.clone()).expect(SENDER_ERR);
 
@@ -30,6 +37,9 @@ pub fn pause(message: PauseMessage, sender: &TaskSender, state: &SharedState) ->
         TaskSelection::Group(group) => {
             create_success_message(format!("Group \"{group}\" is being paused."))
         }
+        TaskSelection::Batch(batch) => {
+            create_success_message(format!("Batch {batch} is being paused."))
+        }
         TaskSelection::All => create_success_message("All queues are being paused."),
     }
 }
@@ -39,8 +39,9 @@ pub fn remove(task_ids: Vec<usize>, state: &SharedState, settings: &Settings) ->
 
         clean_log_handles(*task_id, &settings.shared.pueue_directory());
     }
+    state.remove_empty_batches();
 
-    ok_or_return_failure_message!(save_state(&state, settings));
+    ok_or_return_failure_message!(save_state(&mut state, settings));
 
     compile_task_response("Tasks removed from list", not_running, running)
 }
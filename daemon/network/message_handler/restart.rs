@@ -87,6 +87,7 @@ fn restart(
     // Update path if applicable.
     if let Some(path) = to_restart.path {
         task.path = path;
+        task.original_path = None;
     }
 
     // Update path if applicable.
@@ -99,4 +100,6 @@ fn restart(
     // Reset all variables of any previous run.
     task.start = None;
     task.end = None;
+    task.failure_reason = None;
+    task.retries = 0;
 }
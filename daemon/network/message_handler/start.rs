@@ -16,6 +16,13 @@ pub fn start(message: StartMessage, sender: &TaskSender, state: &SharedState) ->
         }
     }
 
+    // If a batch is selected, make sure it exists.
+    if let TaskSelection::Batch(batch) = &message.tasks {
+        if let Err(message) = ensure_batch_exists(&mut state, *batch) {
+            return message;
+        }
+    }
+
     // Forward the message to the task handler.
     sender.send(message.clone()).expect(SENDER_ERR);
 
@@ -35,6 +42,9 @@ pub fn start(message: StartMessage, sender: &TaskSender, state: &SharedState) ->
         TaskSelection::Group(group) => {
             create_success_message(format!("Group \"{group}\" is being resumed."))
         }
+        TaskSelection::Batch(batch) => {
+            create_success_message(format!("Batch {batch} is being resumed."))
+        }
         TaskSelection::All => create_success_message("All queues are being resumed."),
     }
 }
@@ -0,0 +1,287 @@
+use std::collections::HashMap;
+
+use pueue_lib::network::message::*;
+use pueue_lib::state::SharedState;
+
+use super::add::{apply_alias, task_from_add_message};
+use super::*;
+use crate::ok_or_return_failure_message;
+use crate::state_helper::{save_state_with_mode, SaveMode};
+
+/// Invoked when calling `pueue submit`.
+/// Add all tasks of a submission at once.
+///
+/// The symbolic `needs` of each task are resolved to the ids of the newly created tasks.
+/// If any task of the submission is invalid, no task will be added at all.
+pub fn submit(
+    mut message: SubmitMessage,
+    sender: &TaskSender,
+    state: &SharedState,
+    settings: &Settings,
+    save_mode: SaveMode,
+) -> Message {
+    let mut state = state.lock().unwrap();
+    if message.tasks.is_empty() {
+        return create_failure_message("The submission doesn't contain any tasks.");
+    }
+
+    // Aliases may change the group of a task, so they have to be applied before validation.
+    let mut commands = Vec::new();
+    for submitted in message.tasks.iter_mut() {
+        match apply_alias(&mut submitted.task, settings) {
+            Ok(command) => commands.push(Some(command)),
+            Err(error) => return create_failure_message(error.to_string()),
+        }
+    }
+
+    // Validate all tasks before anything is added to the state.
+    for submitted in message.tasks.iter() {
+        if let Err(message) = ensure_group_exists(&mut state, &submitted.task.group) {
+            return message;
+        }
+        if let Some(batch) = submitted.task.batch {
+            if let Err(message) = ensure_batch_exists(&mut state, batch) {
+                return message;
+            }
+        }
+
+        let not_found: Vec<_> = submitted
+            .task
+            .dependencies
+            .iter()
+            .filter(|id| !state.tasks.contains_key(id))
+            .collect();
+        if !not_found.is_empty() {
+            return create_failure_message(format!(
+                "Unable to setup dependencies of \"{}\": task(s) {not_found:?} not found",
+                submitted.name
+            ));
+        }
+    }
+
+    let order = match sort_submitted_tasks(&message.tasks) {
+        Ok(order) => order,
+        Err(error) => return create_failure_message(error),
+    };
+
+    let batch = if message.create_batch {
+        Some(state.add_batch(message.batch_label))
+    } else {
+        None
+    };
+
+    // Add the tasks in an order, in which all needed tasks already have an id.
+    let mut tasks: Vec<Option<SubmittedTask>> = message.tasks.into_iter().map(Some).collect();
+    let mut ids: HashMap<String, usize> = HashMap::new();
+    let mut added = Vec::new();
+    let mut start = Vec::new();
+    for index in order {
+        let submitted = tasks[index]
+            .take()
+            .expect("Each task is only part of the order once.");
+
+        let mut add_message = submitted.task;
+        for need in submitted.needs.iter() {
+            add_message.dependencies.push(ids[need]);
+        }
+        if batch.is_some() {
+            add_message.batch = batch;
+        }
+        let start_immediately = add_message.start_immediately;
+
+        let command = commands[index]
+            .take()
+            .expect("Each task is only part of the order once.");
+        let task = task_from_add_message(add_message, command);
+        let task_id = state.add_task(task);
+        if start_immediately {
+            start.push(task_id);
+        }
+
+        ids.insert(submitted.name.clone(), task_id);
+        added.push((submitted.name, task_id));
+    }
+    ok_or_return_failure_message!(save_state_with_mode(&mut state, settings, save_mode));
+
+    // Notify the task handler, in case some tasks should be started immediately.
+    if !start.is_empty() {
+        sender
+            .send(StartMessage {
+                tasks: TaskSelection::TaskIds(start),
+                children: false,
+            })
+            .expect(SENDER_ERR);
+    }
+
+    let mut response = match batch {
+        Some(batch) => format!("New tasks added to batch {batch} ({}):", added.len()),
+        None => format!("New tasks added ({}):", added.len()),
+    };
+    for (name, task_id) in added {
+        response.push_str(&format!("\n{name} (id {task_id})"));
+    }
+
+    create_success_message(response)
+}
+
+/// Determine an order of the submitted tasks, in which each task is placed after all tasks
+/// it needs. Tasks keep their original order, as long as their needs allow it.
+///
+/// Returns the indices of the tasks in that order or an error message, if names are used
+/// multiple times, unknown names are needed or the needs contain a cycle.
+fn sort_submitted_tasks(tasks: &[SubmittedTask]) -> Result<Vec<usize>, String> {
+    let mut indices: HashMap<&str, usize> = HashMap::new();
+    for (index, task) in tasks.iter().enumerate() {
+        if indices.insert(&task.name, index).is_some() {
+            return Err(format!(
+                "Task name \"{}\" is used multiple times.",
+                task.name
+            ));
+        }
+    }
+
+    // Resolve the names of all needed tasks to their indices.
+    let mut needs: Vec<Vec<usize>> = Vec::new();
+    for task in tasks.iter() {
+        let mut task_needs = Vec::new();
+        for need in task.needs.iter() {
+            match indices.get(need.as_str()) {
+                Some(index) => task_needs.push(*index),
+                None => {
+                    return Err(format!(
+                        "Task \"{}\" needs the unknown task \"{need}\".",
+                        task.name
+                    ))
+                }
+            }
+        }
+        needs.push(task_needs);
+    }
+
+    // Repeatedly pick the first task, whose needed tasks have all been placed.
+    let mut placed = vec![false; tasks.len()];
+    let mut order = Vec::new();
+    while order.len() < tasks.len() {
+        let next = (0..tasks.len())
+            .find(|index| !placed[*index] && needs[*index].iter().all(|need| placed[*need]));
+
+        match next {
+            Some(index) => {
+                placed[index] = true;
+                order.push(index);
+            }
+            None => {
+                let cyclic: Vec<&str> = tasks
+                    .iter()
+                    .enumerate()
+                    .filter(|(index, _)| !placed[*index])
+                    .map(|(_, task)| task.name.as_str())
+                    .collect();
+                return Err(format!(
+                    "The needs of these tasks contain a cycle: {cyclic:?}"
+                ));
+            }
+        }
+    }
+
+    Ok(order)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use pretty_assertions::assert_eq;
+
+    use super::super::fixtures::*;
+    use super::*;
+
+    fn get_submitted_task(name: &str, needs: &[&str]) -> SubmittedTask {
+        SubmittedTask {
+            name: name.to_string(),
+            needs: needs.iter().map(|need| need.to_string()).collect(),
+            task: AddMessage {
+                command: format!("echo {name}"),
+                path: PathBuf::from("/tmp"),
+                envs: HashMap::new(),
+                start_immediately: false,
+                stashed: false,
+                group: PUEUE_DEFAULT_GROUP.to_string(),
+                enqueue_at: None,
+                dependencies: Vec::new(),
+                label: None,
+                print_task_id: false,
+                batch: None,
+                timeout: None,
+                separate_output: false,
+                timestamps: false,
+                log_limit: None,
+                exit_code_policy: None,
+                output_rules: None,
+                stall_timeout: None,
+            },
+        }
+    }
+
+    #[test]
+    fn submit_resolves_needs() {
+        let (state, settings, _tempdir) = get_stub_state();
+        let (sender, _receiver) = crossbeam_channel::unbounded();
+        let sender = TaskSender::new(sender);
+
+        let message = SubmitMessage {
+            create_batch: false,
+            batch_label: None,
+            tasks: vec![
+                get_submitted_task("test", &["build"]),
+                get_submitted_task("build", &["fetch"]),
+                get_submitted_task("fetch", &[]),
+            ],
+        };
+
+        let response = submit(message, &sender, &state, &settings, SaveMode::Immediate);
+        assert!(matches!(response, Message::Success(_)));
+
+        // The stub state already contains five tasks.
+        let state = state.lock().unwrap();
+        assert_eq!(state.tasks[&5].original_command, "echo fetch");
+        assert_eq!(state.tasks[&6].dependencies, vec![5]);
+        assert_eq!(state.tasks[&7].dependencies, vec![6]);
+    }
+
+    #[test]
+    fn submit_rejects_cycles() {
+        let (state, settings, _tempdir) = get_stub_state();
+        let (sender, _receiver) = crossbeam_channel::unbounded();
+        let sender = TaskSender::new(sender);
+
+        let message = SubmitMessage {
+            create_batch: false,
+            batch_label: None,
+            tasks: vec![
+                get_submitted_task("fetch", &[]),
+                get_submitted_task("build", &["test"]),
+                get_submitted_task("test", &["build"]),
+            ],
+        };
+
+        let response = submit(message, &sender, &state, &settings, SaveMode::Immediate);
+        assert!(matches!(response, Message::Failure(_)));
+
+        // Nothing should have been added.
+        let state = state.lock().unwrap();
+        assert_eq!(state.tasks.len(), 5);
+    }
+
+    #[test]
+    fn sort_rejects_invalid_names() {
+        let tasks = vec![
+            get_submitted_task("build", &[]),
+            get_submitted_task("build", &[]),
+        ];
+        assert!(sort_submitted_tasks(&tasks).is_err());
+
+        let tasks = vec![get_submitted_task("build", &["fetch"])];
+        assert!(sort_submitted_tasks(&tasks).is_err());
+    }
+}
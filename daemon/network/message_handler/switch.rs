@@ -5,12 +5,17 @@ use pueue_lib::task::TaskStatus;
 
 use super::ok_or_failure_message;
 use crate::ok_or_return_failure_message;
-use crate::state_helper::save_state;
+use crate::state_helper::{save_state_with_mode, SaveMode};
 
 /// Invoked when calling `pueue switch`.
 /// Switch the position of two tasks in the upcoming queue.
 /// We have to ensure that those tasks are either `Queued` or `Stashed`
-pub fn switch(message: SwitchMessage, state: &SharedState, settings: &Settings) -> Message {
+pub fn switch(
+    message: SwitchMessage,
+    state: &SharedState,
+    settings: &Settings,
+    save_mode: SaveMode,
+) -> Message {
     let mut state = state.lock().unwrap();
 
     let task_ids = vec![message.task_id_1, message.task_id_2];
@@ -55,7 +60,7 @@ pub fn switch(message: SwitchMessage, state: &SharedState, settings: &Settings)
         }
     }
 
-    ok_or_return_failure_message!(save_state(&state, settings));
+    ok_or_return_failure_message!(save_state_with_mode(&mut state, settings, save_mode));
     create_success_message("Tasks have been switched")
 }
 
@@ -112,7 +117,7 @@ mod tests {
     fn switch_normal() {
         let (state, settings, _tempdir) = get_test_state();
 
-        let message = switch(get_message(1, 2), &state, &settings);
+        let message = switch(get_message(1, 2), &state, &settings, SaveMode::Immediate);
 
         // Return message is correct
         assert!(matches!(message, Message::Success(_)));
@@ -130,7 +135,7 @@ mod tests {
     fn switch_task_with_itself() {
         let (state, settings, _tempdir) = get_test_state();
 
-        let message = switch(get_message(1, 1), &state, &settings);
+        let message = switch(get_message(1, 1), &state, &settings, SaveMode::Immediate);
 
         // Return message is correct
         assert!(matches!(message, Message::Failure(_)));
@@ -145,7 +150,7 @@ mod tests {
     fn switch_task_with_dependant() {
         let (state, settings, _tempdir) = get_test_state();
 
-        switch(get_message(0, 3), &state, &settings);
+        switch(get_message(0, 3), &state, &settings, SaveMode::Immediate);
 
         let state = state.lock().unwrap();
         assert_eq!(state.tasks.get(&4).unwrap().dependencies, vec![0, 3]);
@@ -157,7 +162,7 @@ mod tests {
     fn switch_double_dependency() {
         let (state, settings, _tempdir) = get_test_state();
 
-        switch(get_message(1, 2), &state, &settings);
+        switch(get_message(1, 2), &state, &settings, SaveMode::Immediate);
 
         let state = state.lock().unwrap();
         assert_eq!(state.tasks.get(&5).unwrap().dependencies, vec![2]);
@@ -182,7 +187,12 @@ mod tests {
         ];
 
         for ids in combinations {
-            let message = switch(get_message(ids.0, ids.1), &state, &settings);
+            let message = switch(
+                get_message(ids.0, ids.1),
+                &state,
+                &settings,
+                SaveMode::Immediate,
+            );
 
             // Assert, that we get a Failure message with the correct text.
             assert!(matches!(message, Message::Failure(_)));
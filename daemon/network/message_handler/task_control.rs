@@ -0,0 +1,85 @@
+use pueue_lib::network::message::*;
+use pueue_lib::state::SharedState;
+use pueue_lib::task::Task;
+
+use super::*;
+use crate::ok_or_return_failure_message;
+use crate::state_helper::save_state;
+
+/// Invoked when a running task calls `pueue task`.
+/// The task is determined by the hash of its current run's token, so it can only act on itself.
+pub fn task_control(
+    message: TaskControlMessage,
+    token_hash: &str,
+    sender: &TaskSender,
+    state: &SharedState,
+    settings: &Settings,
+) -> Message {
+    let mut locked_state = state.lock().unwrap();
+    let task = match locked_state
+        .tasks
+        .values_mut()
+        .find(|task| task.is_running() && task.token_hash.as_deref() == Some(token_hash))
+    {
+        Some(task) => task,
+        None => return create_failure_message("The task is no longer running"),
+    };
+
+    match message {
+        TaskControlMessage::SetLabel(label) => {
+            task.label = Some(label);
+            ok_or_return_failure_message!(save_state(&mut locked_state, settings));
+            create_success_message("Label has been set")
+        }
+        TaskControlMessage::ReportProgress(progress) => {
+            task.progress = Some(progress);
+            ok_or_return_failure_message!(save_state(&mut locked_state, settings));
+            create_success_message("Progress has been reported")
+        }
+        TaskControlMessage::AddFollowUp(message) => {
+            let message = follow_up_message(message, task);
+            // Adding the task locks the state on its own.
+            drop(locked_state);
+            add::add_task(message, sender, state, settings, SaveMode::Immediate)
+        }
+        TaskControlMessage::Retry => {
+            let policy = task
+                .exit_code_policy
+                .as_ref()
+                .unwrap_or_else(|| settings.daemon.exit_code_policy(&task.group));
+            if task.retries >= policy.max_retries {
+                return create_failure_message("The task ran out of retries");
+            }
+            task.retry = true;
+            ok_or_return_failure_message!(save_state(&mut locked_state, settings));
+            create_success_message("The task will be restarted once it finished")
+        }
+    }
+}
+
+/// Build the task, that's added as a follow-up of `task`.
+/// Only the command and the label are requested by the task. The follow-up task always depends
+/// on the task and inherits its group, path and environment variables.
+/// It's never started right away, as it has to wait for the task anyway.
+fn follow_up_message(message: FollowUpMessage, task: &Task) -> AddMessage {
+    AddMessage {
+        command: message.command,
+        path: task.path.clone(),
+        envs: task.envs.clone(),
+        start_immediately: false,
+        stashed: false,
+        group: task.group.clone(),
+        enqueue_at: None,
+        dependencies: vec![task.id],
+        label: message.label,
+        print_task_id: false,
+        batch: None,
+        timeout: None,
+        separate_output: false,
+        timestamps: false,
+        log_limit: None,
+        exit_code_policy: None,
+        output_rules: None,
+        stall_timeout: None,
+    }
+}
@@ -0,0 +1,159 @@
+use pueue_lib::network::message::*;
+use pueue_lib::settings::Settings;
+use pueue_lib::state::{SharedState, PUEUE_DEFAULT_GROUP};
+use pueue_lib::template::DependencyPolicy;
+
+use super::add::{apply_alias, task_from_add_message};
+use super::*;
+use crate::ok_or_return_failure_message;
+use crate::state_helper::{save_state_with_mode, SaveMode};
+
+/// Invoked on `pueue template`.
+/// Manage task templates.
+/// - List templates
+/// - Add a template
+/// - Remove a template
+pub fn template(
+    message: TemplateMessage,
+    state: &SharedState,
+    settings: &Settings,
+    save_mode: SaveMode,
+) -> Message {
+    let mut state = state.lock().unwrap();
+
+    match message {
+        TemplateMessage::List => TemplateResponseMessage {
+            templates: state.templates.clone(),
+        }
+        .into(),
+        TemplateMessage::Add { name, mut template } => {
+            if state.templates.contains_key(&name) {
+                return create_failure_message(format!("Template \"{name}\" already exists"));
+            }
+            if template.command.trim().is_empty() {
+                return create_failure_message("The command of a template cannot be empty");
+            }
+
+            // Templates aren't related to any task yet.
+            template.last_task = None;
+            state.templates.insert(name.clone(), template);
+            ok_or_return_failure_message!(save_state_with_mode(&mut state, settings, save_mode));
+
+            create_success_message(format!("Template \"{name}\" has been added"))
+        }
+        TemplateMessage::Remove(name) => {
+            if state.templates.remove(&name).is_none() {
+                return create_failure_message(format!("Template \"{name}\" doesn't exist"));
+            }
+            ok_or_return_failure_message!(save_state_with_mode(&mut state, settings, save_mode));
+
+            create_success_message(format!("Template \"{name}\" has been removed"))
+        }
+    }
+}
+
+/// Invoked on `pueue run`.
+/// Create a new task from a template and queue it.
+/// If the start_immediately flag is set, send a StartMessage to the task handler.
+pub fn run(
+    message: RunMessage,
+    sender: &TaskSender,
+    state: &SharedState,
+    settings: &Settings,
+    save_mode: SaveMode,
+) -> Message {
+    let mut state = state.lock().unwrap();
+    let template = match state.templates.get(&message.template) {
+        Some(template) => template.clone(),
+        None => {
+            return create_failure_message(format!(
+                "Template \"{}\" doesn't exist",
+                message.template
+            ))
+        }
+    };
+
+    let (command, label) = match template.render(&message.parameters) {
+        Ok(rendered) => rendered,
+        Err(error) => {
+            return create_failure_message(format!(
+                "Failed to run template \"{}\": {error}",
+                message.template
+            ))
+        }
+    };
+
+    // Only depend on the previous task of this template, if it hasn't finished yet.
+    let mut dependencies = Vec::new();
+    if template.dependencies == DependencyPolicy::AfterPrevious {
+        if let Some(task_id) = template.last_task {
+            if state
+                .tasks
+                .get(&task_id)
+                .map_or(false, |task| !task.is_done())
+            {
+                dependencies.push(task_id);
+            }
+        }
+    }
+
+    let mut envs = message.envs;
+    envs.extend(template.envs);
+
+    let mut add_message = AddMessage {
+        command,
+        path: match template.path {
+            Some(path) => message.path.join(path),
+            None => message.path,
+        },
+        envs,
+        start_immediately: message.start_immediately,
+        stashed: message.stashed,
+        group: message
+            .group
+            .or(template.group)
+            .unwrap_or_else(|| PUEUE_DEFAULT_GROUP.to_string()),
+        enqueue_at: None,
+        dependencies,
+        label,
+        print_task_id: false,
+        batch: None,
+        timeout: template.timeout,
+        separate_output: false,
+        timestamps: false,
+        log_limit: None,
+        exit_code_policy: None,
+        output_rules: None,
+        stall_timeout: None,
+    };
+
+    let command = match apply_alias(&mut add_message, settings) {
+        Ok(command) => command,
+        Err(error) => return create_failure_message(error.to_string()),
+    };
+    if let Err(message) = ensure_group_exists(&mut state, &add_message.group) {
+        return message;
+    }
+
+    let task = task_from_add_message(add_message, command);
+    let task_id = state.add_task(task);
+    if let Some(template) = state.templates.get_mut(&message.template) {
+        template.last_task = Some(task_id);
+    }
+    ok_or_return_failure_message!(save_state_with_mode(&mut state, settings, save_mode));
+
+    // Notify the task handler, in case the client wants to start the task immediately.
+    if message.start_immediately {
+        sender
+            .send(StartMessage {
+                tasks: TaskSelection::TaskIds(vec![task_id]),
+                children: false,
+            })
+            .expect(SENDER_ERR);
+    }
+
+    create_success_message(format!(
+        "New task added from template \"{}\" (id {task_id}).",
+        message.template
+    ))
+}
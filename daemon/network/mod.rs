@@ -1,3 +1,4 @@
+pub mod chunked_log;
 pub mod follow_log;
 pub mod message_handler;
 pub mod response_helper;
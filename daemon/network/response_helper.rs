@@ -1,7 +1,7 @@
 use std::sync::MutexGuard;
 
 use pueue_lib::network::message::{create_failure_message, create_success_message, Message};
-use pueue_lib::state::{Group, State};
+use pueue_lib::state::{Batch, Group, State};
 use pueue_lib::task::Task;
 
 use crate::state_helper::LockedState;
@@ -21,6 +21,19 @@ pub fn ensure_group_exists<'state>(
     )))
 }
 
+/// Check whether the given batch exists. Return an failure message if it doesn't.
+pub fn ensure_batch_exists<'state>(
+    state: &'state mut LockedState,
+    batch: usize,
+) -> Result<&'state mut Batch, Message> {
+    match state.batches.get_mut(&batch) {
+        Some(batch) => Ok(batch),
+        None => Err(create_failure_message(format!(
+            "Batch {batch} doesn't exists."
+        ))),
+    }
+}
+
 /// Compile a response for actions that affect several given tasks.
 /// These actions can sometimes only succeed for a part of the given tasks.
 ///
@@ -8,12 +8,14 @@ use tokio::time::sleep;
 use pueue_lib::error::Error;
 use pueue_lib::network::message::*;
 use pueue_lib::network::protocol::*;
-use pueue_lib::network::secret::read_shared_secret;
+use pueue_lib::network::secret::{hash_task_token, read_shared_secret};
 use pueue_lib::settings::Settings;
 use pueue_lib::state::SharedState;
 
+use crate::network::chunked_log::handle_chunked_log;
 use crate::network::follow_log::handle_follow;
-use crate::network::message_handler::{handle_message, SENDER_ERR};
+use crate::network::message_handler::{handle_message, handle_task_message, SENDER_ERR};
+use crate::state_helper::{release_edit_locks, EditLocks, SaveMode};
 use crate::task_handler::TaskSender;
 
 /// Poll the listener and accept new incoming connections.
@@ -21,11 +23,14 @@ use crate::task_handler::TaskSender;
 pub async fn accept_incoming(
     sender: TaskSender,
     state: SharedState,
+    edit_locks: EditLocks,
     settings: Settings,
 ) -> Result<()> {
     let listener = get_listener(&settings.shared).await?;
     // Read secret once to prevent multiple disk reads.
     let secret = read_shared_secret(&settings.shared.shared_secret_path())?;
+    // Each connection gets a unique id, which is used to identify the owner of edit locks.
+    let mut next_connection_id: usize = 0;
 
     loop {
         // Poll incoming connections.
@@ -37,20 +42,35 @@ pub async fn accept_incoming(
             }
         };
 
+        let connection = next_connection_id;
+        next_connection_id = next_connection_id.wrapping_add(1);
+
         // Start a new task for the request
         let sender_clone = sender.clone();
         let state_clone = state.clone();
+        let edit_locks_clone = edit_locks.clone();
         let secret_clone = secret.clone();
         let settings_clone = settings.clone();
         tokio::spawn(async move {
             let _result = handle_incoming(
                 stream,
+                connection,
                 sender_clone,
-                state_clone,
+                state_clone.clone(),
+                edit_locks_clone.clone(),
                 settings_clone,
                 secret_clone,
             )
             .await;
+
+            // Unlock all tasks the client was editing, in case it went away while editing.
+            let mut state = state_clone.lock().unwrap();
+            let unlocked = release_edit_locks(&mut state, &edit_locks_clone, |lock| {
+                lock.connection == connection
+            });
+            if !unlocked.is_empty() {
+                info!("Client went away while editing. Unlocked tasks {unlocked:?}");
+            }
         });
     }
 }
@@ -60,8 +80,10 @@ pub async fn accept_incoming(
 /// The response future is added to unix_responses and handled in a separate function.
 async fn handle_incoming(
     mut stream: GenericStream,
+    connection: usize,
     sender: TaskSender,
     state: SharedState,
+    edit_locks: EditLocks,
     settings: Settings,
     secret: Vec<u8>,
 ) -> Result<()> {
@@ -76,6 +98,15 @@ async fn handle_incoming(
 
     let start = SystemTime::now();
 
+    // Running tasks authenticate with the token of their current run instead of the secret.
+    // Their connection is restricted to messages, with which they act on themselves.
+    if payload_bytes != secret {
+        if let Some(token_hash) = running_task_token(&payload_bytes, &state) {
+            send_bytes(crate_version!().as_bytes(), &mut stream).await?;
+            return handle_task_connection(stream, &token_hash, sender, state, settings).await;
+        }
+    }
+
     // Return immediately, if we got a wrong secret from the client.
     if payload_bytes != secret {
         let received_secret = String::from_utf8(payload_bytes)?;
@@ -126,6 +157,10 @@ async fn handle_incoming(
             Message::StreamRequest(message) => {
                 handle_follow(&pueue_directory, &mut stream, &state, message).await?
             }
+            // The client requested logs in chunks, which are sent as multiple messages.
+            Message::Log(message) if message.chunk_size.is_some() => {
+                handle_chunked_log(&pueue_directory, &mut stream, &state, message).await?
+            }
             // Initialize the shutdown procedure.
             // The message is forwarded to the TaskHandler, which is responsible for
             // gracefully shutting down.
@@ -134,7 +169,12 @@ async fn handle_incoming(
             // Otherwise it might happen, that the daemon shuts down too fast and we aren't
             // capable of actually sending the message back to the client.
             Message::DaemonShutdown(shutdown_type) => {
-                let response = create_success_message("Daemon is shutting down");
+                let response = match shutdown_type {
+                    Shutdown::Drain { .. } => create_success_message(
+                        "Daemon is draining and shuts down once all running tasks finished",
+                    ),
+                    _ => create_success_message("Daemon is shutting down"),
+                };
                 send_message(response, &mut stream).await?;
 
                 // Notify the task handler.
@@ -144,7 +184,15 @@ async fn handle_incoming(
             }
             _ => {
                 // Process a normal message.
-                handle_message(message, &sender, &state, &settings)
+                handle_message(
+                    message,
+                    connection,
+                    &edit_locks,
+                    &sender,
+                    &state,
+                    &settings,
+                    SaveMode::Immediate,
+                )
             }
         };
 
@@ -152,3 +200,46 @@ async fn handle_incoming(
         send_message(response, &mut stream).await?;
     }
 }
+
+/// Return the token's hash, if the received bytes are the token of a running task.
+fn running_task_token(payload_bytes: &[u8], state: &SharedState) -> Option<String> {
+    let token_hash = hash_task_token(std::str::from_utf8(payload_bytes).ok()?);
+    let state = state.lock().unwrap();
+    state
+        .tasks
+        .values()
+        .any(|task| task.is_running() && task.token_hash.as_ref() == Some(&token_hash))
+        .then_some(token_hash)
+}
+
+/// Handle the messages of a connection, that has been authenticated with a task's token.
+/// The token is checked again for each message, as the task might have finished in between.
+async fn handle_task_connection(
+    mut stream: GenericStream,
+    token_hash: &str,
+    sender: TaskSender,
+    state: SharedState,
+    settings: Settings,
+) -> Result<()> {
+    loop {
+        let message = match receive_message(&mut stream).await {
+            Ok(message) => message,
+            Err(Error::EmptyPayload) => {
+                debug!("Task went away");
+                return Ok(());
+            }
+            Err(Error::MessageDeserialization(err)) => {
+                send_message(
+                    create_failure_message(format!("Failed to deserialize message: {err}")),
+                    &mut stream,
+                )
+                .await?;
+                return Ok(());
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        let response = handle_task_message(message, token_hash, &sender, &state, &settings);
+        send_message(response, &mut stream).await?;
+    }
+}
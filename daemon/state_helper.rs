@@ -1,19 +1,49 @@
 use std::collections::BTreeMap;
 use std::fs;
-use std::path::{Path, PathBuf};
-use std::sync::MutexGuard;
-use std::time::SystemTime;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, SystemTime};
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use chrono::prelude::*;
-use log::{debug, info};
+use log::{debug, error, info};
 
-use pueue_lib::settings::Settings;
+use pueue_lib::log::get_exit_code_path;
+use pueue_lib::process_helper::process_start_time;
+use pueue_lib::settings::{CrashRecovery, InterruptedTaskPolicy, QueuedGroupPolicy, Settings};
 use pueue_lib::state::{Group, GroupStatus, State, PUEUE_DEFAULT_GROUP};
-use pueue_lib::task::{TaskResult, TaskStatus};
+use pueue_lib::task::{Task, TaskResult, TaskStatus};
 
 pub type LockedState<'a> = MutexGuard<'a, State>;
 
+/// The edit locks of all tasks that're currently being edited by their task id.
+///
+/// Edit locks only exist while the daemon is running. They're kept out of the state, so they're
+/// neither persisted nor sent to clients.
+/// If both are needed, the state has to be locked first.
+pub type EditLocks = Arc<Mutex<BTreeMap<usize, EditLock>>>;
+
+/// The owner and the lease of a task, that's locked while a client edits it.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct EditLock {
+    /// The id of the client connection that locked the task.
+    pub connection: usize,
+    /// The previous status is restored, if the client didn't finish editing until then.
+    pub expires: DateTime<Local>,
+}
+
+/// The suffix of all state backup files in the `log` directory.
+const BACKUP_SUFFIX: &str = "_state.json";
+
+/// Whether message handlers save the state to disk right away.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SaveMode {
+    Immediate,
+    /// The message is handled on a copy of the state, which might still be discarded.
+    /// The copy is saved at once, when it replaces the actual state.
+    Deferred,
+}
+
 /// Check if a task can be deleted. \
 /// We have to check all dependant tasks, that haven't finished yet.
 /// This is necessary to prevent deletion of tasks which are specified as a dependency.
@@ -63,28 +93,77 @@ pub fn pause_on_failure(state: &mut LockedState, settings: &Settings, group: &st
     }
 }
 
+/// Release all edit locks that match the given filter. \
+/// Tasks that are still locked are restored to the status they had before being edited.
+///
+/// Returns the ids of all tasks that have been unlocked.
+pub fn release_edit_locks<F>(
+    state: &mut LockedState,
+    edit_locks: &EditLocks,
+    filter: F,
+) -> Vec<usize>
+where
+    F: Fn(&EditLock) -> bool,
+{
+    let mut edit_locks = edit_locks.lock().unwrap();
+    let task_ids: Vec<usize> = edit_locks
+        .iter()
+        .filter(|(_, lock)| filter(lock))
+        .map(|(task_id, _)| *task_id)
+        .collect();
+
+    let mut unlocked = Vec::new();
+    for task_id in task_ids {
+        edit_locks.remove(&task_id);
+
+        // The task might have been removed or changed by other means in the meantime.
+        if let Some(task) = state.tasks.get_mut(&task_id) {
+            if task.status == TaskStatus::Locked {
+                task.status = task.prev_status.clone();
+                unlocked.push(task_id);
+            }
+        }
+    }
+
+    unlocked
+}
+
 /// Do a full reset of the state.
 /// This doesn't reset any processes!
 pub fn reset_state(state: &mut LockedState, settings: &Settings) -> Result<()> {
     backup_state(state, settings)?;
     state.tasks = BTreeMap::new();
+    state.batches = BTreeMap::new();
     state.set_status_for_all_groups(GroupStatus::Running);
 
     save_state(state, settings)
 }
 
 /// Convenience wrapper around save_to_file.
-pub fn save_state(state: &State, settings: &Settings) -> Result<()> {
-    save_state_to_file(state, settings, false)
+/// Every saved change gets a new revision, which allows clients to detect changes.
+pub fn save_state(state: &mut State, settings: &Settings) -> Result<()> {
+    save_state_with_mode(state, settings, SaveMode::Immediate)
+}
+
+/// Save the state, unless saves are deferred, in which case only the revision is increased.
+pub fn save_state_with_mode(state: &mut State, settings: &Settings, mode: SaveMode) -> Result<()> {
+    state.revision += 1;
+    if mode == SaveMode::Deferred {
+        return Ok(());
+    }
+    save_state_to_file(state, settings, false)?;
+    Ok(())
 }
 
 /// Save the current current state in a file with a timestamp.
 /// At the same time remove old state logs from the log directory.
 /// This function is called, when large changes to the state are applied, e.g. clean/reset.
-pub fn backup_state(state: &LockedState, settings: &Settings) -> Result<()> {
-    save_state_to_file(state, settings, true)?;
+///
+/// Returns the name of the newly created backup.
+pub fn backup_state(state: &LockedState, settings: &Settings) -> Result<String> {
+    let name = save_state_to_file(state, settings, true)?;
     rotate_state(settings).context("Failed to rotate old log files")?;
-    Ok(())
+    Ok(name)
 }
 
 /// Save the current state to disk. \
@@ -93,21 +172,34 @@ pub fn backup_state(state: &LockedState, settings: &Settings) -> Result<()> {
 ///
 /// In comparison to the daemon -> client communication, the state is saved
 /// as JSON for readability and debugging purposes.
-fn save_state_to_file(state: &State, settings: &Settings, log: bool) -> Result<()> {
+///
+/// Returns the name of the written file without the `_state.json`/`.json` suffix.
+fn save_state_to_file(state: &State, settings: &Settings, log: bool) -> Result<String> {
     let serialized = serde_json::to_string(&state).context("Failed to serialize state:");
 
     let serialized = serialized.unwrap();
     let path = settings.shared.pueue_directory();
-    let (temp, real) = if log {
+    let (name, temp, real) = if log {
         let path = path.join("log");
+        // Backups are created in quick succession, e.g. right before a backup is restored.
+        // Use sub-second precision, so they don't end up with the same name.
         let now: DateTime<Utc> = Utc::now();
-        let time = now.format("%Y-%m-%d_%H-%M-%S");
+        let time = now.format("%Y-%m-%d_%H-%M-%S%.6f").to_string();
+        let real = path.join(format!("{time}{BACKUP_SUFFIX}"));
+        if real.exists() {
+            bail!("State backup \"{time}\" already exists");
+        }
         (
-            path.join(format!("{time}_state.json.partial")),
-            path.join(format!("{time}_state.json")),
+            time.clone(),
+            path.join(format!("{time}{BACKUP_SUFFIX}.partial")),
+            real,
         )
     } else {
-        (path.join("state.json.partial"), path.join("state.json"))
+        (
+            "state".to_string(),
+            path.join("state.json.partial"),
+            path.join("state.json"),
+        )
     };
 
     // Write to temporary log file first, to prevent loss due to crashes.
@@ -122,16 +214,17 @@ fn save_state_to_file(state: &State, settings: &Settings, log: bool) -> Result<(
         debug!("State saved at: {real:?}");
     }
 
-    Ok(())
+    Ok(name)
 }
 
 /// Restore the last state from a previous session. \
 /// The state is stored as json in the `pueue_directory`.
 ///
 /// If the state cannot be deserialized, an empty default state will be used instead. \
-/// All groups with queued tasks will be automatically paused to prevent unwanted execution.
-pub fn restore_state(pueue_directory: &Path) -> Result<Option<State>> {
-    let path = pueue_directory.join("state.json");
+/// Interrupted tasks and groups with queued tasks are handled according to the
+/// `crash_recovery` settings.
+pub fn restore_state(settings: &Settings) -> Result<Option<State>> {
+    let path = settings.shared.pueue_directory().join("state.json");
 
     // Ignore if the file doesn't exist. It doesn't have to.
     if !path.exists() {
@@ -145,26 +238,28 @@ pub fn restore_state(pueue_directory: &Path) -> Result<Option<State>> {
 
     // Try to deserialize the state file.
     let mut state: State = serde_json::from_str(&data).context("Failed to deserialize state.")?;
+    sanitize_restored_state(&mut state, settings, true);
 
-    // Restore all tasks.
-    // While restoring the tasks, check for any invalid/broken stati.
-    for (_, task) in state.tasks.iter_mut() {
-        // Handle ungraceful shutdowns while executing tasks.
-        if task.status == TaskStatus::Running || task.status == TaskStatus::Paused {
-            info!(
-                "Setting task {} with previous status {:?} to new status {:?}",
-                task.id,
-                task.status,
-                TaskResult::Killed
-            );
-            task.status = TaskStatus::Done(TaskResult::Killed);
-        }
+    Ok(Some(state))
+}
 
-        // Handle crash during editing of the task command.
-        if task.status == TaskStatus::Locked {
-            task.status = TaskStatus::Stashed { enqueue_at: None };
-        }
+/// Prepare a state from a previous session or a backup for usage in this daemon.
+///
+/// While restoring the tasks, check for any invalid/broken stati.
+///
+/// If `after_restart` is true, the state is from a previous session of the daemon.
+/// - Running detached tasks whose processes are still alive are kept as they are.
+///   The TaskHandler will then re-adopt those processes.
+/// - Interrupted tasks and groups with queued tasks are handled according to the
+///   `crash_recovery` settings of their group.
+///
+/// Otherwise, interrupted tasks are marked as killed and all groups with queued tasks will be
+/// paused to prevent unwanted execution.
+fn sanitize_restored_state(state: &mut State, settings: &Settings, after_restart: bool) {
+    // A previous drain ended with the shutdown of the daemon.
+    state.drain = None;
 
+    for (_, task) in state.tasks.iter_mut() {
         // Go trough all tasks and set all groups that are no longer
         // listed in the configuration file to the default.
         let group = match state.groups.get_mut(&task.group) {
@@ -181,9 +276,60 @@ pub fn restore_state(pueue_directory: &Path) -> Result<Option<State>> {
             }
         };
 
+        let recovery = if after_restart {
+            settings.daemon.crash_recovery(&task.group)
+        } else {
+            CrashRecovery::default()
+        };
+
+        // Detached tasks might have survived the restart of the daemon.
+        let process_alive = match &task.detached_process {
+            Some(process) if after_restart => {
+                process_start_time(process.pid) == Some(process.start_time)
+            }
+            _ => false,
+        };
+
+        let detached_exited = after_restart && task.detached_process.is_some() && !process_alive;
+        if task.is_running() && process_alive {
+            info!(
+                "Keeping task {} with detached process, as it's still alive",
+                task.id
+            );
+        } else if task.is_running() && detached_exited && finish_detached_task(task, settings) {
+            info!(
+                "Detached process of task {} exited while the daemon wasn't running",
+                task.id
+            );
+        } else if task.is_running() {
+            // Handle ungraceful shutdowns while executing tasks.
+            let new_status = match recovery.interrupted_tasks {
+                InterruptedTaskPolicy::Fail => TaskStatus::Done(TaskResult::Killed),
+                InterruptedTaskPolicy::Requeue => TaskStatus::Queued,
+                InterruptedTaskPolicy::Stash => TaskStatus::Stashed { enqueue_at: None },
+            };
+            info!(
+                "Setting task {} with previous status {:?} to new status {:?}",
+                task.id, task.status, new_status
+            );
+            // Tasks that will be started again shouldn't keep the times of the interrupted run.
+            if !matches!(new_status, TaskStatus::Done(_)) {
+                task.start = None;
+                task.end = None;
+            }
+            task.status = new_status;
+            task.detached_process = None;
+            task.token_hash = None;
+        }
+
+        // Handle crash during editing of the task command.
+        if task.status == TaskStatus::Locked {
+            task.status = TaskStatus::Stashed { enqueue_at: None };
+        }
+
         // If there are any queued tasks, pause the group.
         // This should prevent any unwanted execution of tasks due to a system crash.
-        if task.status == TaskStatus::Queued {
+        if task.status == TaskStatus::Queued && recovery.queued_groups == QueuedGroupPolicy::Pause {
             info!(
                 "Pausing group {} to prevent unwanted execution of previous tasks",
                 &task.group
@@ -191,33 +337,134 @@ pub fn restore_state(pueue_directory: &Path) -> Result<Option<State>> {
             group.status = GroupStatus::Paused;
         }
     }
+}
 
-    Ok(Some(state))
+/// Finish a detached task, whose process exited while the daemon couldn't watch it.
+/// Its result is determined by the exit code, that the process wrote to its exit code file.
+///
+/// Returns `false`, if there's no exit code, e.g. because the process has been killed by a signal.
+/// The task is left untouched in that case.
+pub fn finish_detached_task(task: &mut Task, settings: &Settings) -> bool {
+    let path = get_exit_code_path(task.id, &settings.shared.pueue_directory());
+    let exit_code = fs::read_to_string(&path)
+        .ok()
+        .and_then(|content| content.trim().parse::<i32>().ok());
+    let exit_code = match exit_code {
+        Some(exit_code) => exit_code,
+        None => return false,
+    };
+
+    // The file has been written, once the process exited.
+    let end = fs::metadata(&path)
+        .and_then(|metadata| metadata.modified())
+        .map(DateTime::<Local>::from)
+        .unwrap_or_else(|_| Local::now());
+    if let Err(err) = fs::remove_file(&path) {
+        error!(
+            "Failed to remove exit code file of task {}: {err:?}",
+            task.id
+        );
+    }
+
+    let result = task
+        .exit_code_policy
+        .as_ref()
+        .unwrap_or_else(|| settings.daemon.exit_code_policy(&task.group))
+        .result(exit_code);
+    task.status = TaskStatus::Done(result);
+    task.end = Some(end);
+    task.detached_process = None;
+    task.token_hash = None;
+
+    true
+}
+
+/// Get the names of all state backups in the `log` directory, ordered from oldest to newest.
+///
+/// The name of a backup is the UTC timestamp of its creation, which is why a lexical
+/// order is also a chronological one.
+pub fn list_state_backups(settings: &Settings) -> Result<Vec<String>> {
+    let path = settings.shared.pueue_directory().join("log");
+
+    let mut backups = Vec::new();
+    for entry in fs::read_dir(path).context("Failed to read state backup directory")? {
+        let file_name = entry?.file_name();
+        if let Some(name) = file_name.to_string_lossy().strip_suffix(BACKUP_SUFFIX) {
+            backups.push(name.to_string());
+        }
+    }
+    backups.sort();
+
+    Ok(backups)
+}
+
+/// Load a state backup by its name.
+/// The returned state is already sanitized and can be used as a drop-in replacement for the
+/// current state.
+pub fn load_state_backup(settings: &Settings, name: &str) -> Result<State> {
+    // Only allow names of existing backups. This also prevents any path traversal.
+    if !list_state_backups(settings)?
+        .iter()
+        .any(|backup| backup == name)
+    {
+        bail!("There's no state backup with name \"{name}\"");
+    }
+
+    let path = settings
+        .shared
+        .pueue_directory()
+        .join("log")
+        .join(format!("{name}{BACKUP_SUFFIX}"));
+    let data = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read state backup at {path:?}"))?;
+
+    let mut state: State =
+        serde_json::from_str(&data).context("Failed to deserialize state backup.")?;
+    sanitize_restored_state(&mut state, settings, false);
+
+    Ok(state)
 }
 
-/// Remove old logs that aren't needed any longer.
+/// Remove old state backups that aren't needed any longer.
+///
+/// Only the newest `state_backup_count` backups are kept.
+/// If `state_backup_max_age_hours` is set, any backups older than that are removed as well.
 fn rotate_state(settings: &Settings) -> Result<()> {
     let path = settings.shared.pueue_directory().join("log");
 
-    // Get all log files in the directory with their respective system time.
-    let mut entries: BTreeMap<SystemTime, PathBuf> = BTreeMap::new();
+    // Get all backup files in the directory with their respective system time.
+    let mut entries: Vec<(SystemTime, PathBuf)> = Vec::new();
     let mut directory_list = fs::read_dir(path)?;
     while let Some(Ok(entry)) = directory_list.next() {
         let path = entry.path();
+        if !path.to_string_lossy().ends_with(BACKUP_SUFFIX) {
+            continue;
+        }
 
         let metadata = entry.metadata()?;
         let time = metadata.modified()?;
-        entries.insert(time, path);
+        entries.push((time, path));
     }
 
-    // Remove all files above the threshold.
-    // Old files are removed first (implictly by the BTree order).
-    let mut number_entries = entries.len();
-    let mut iter = entries.iter();
-    while number_entries > 10 {
-        if let Some((_, path)) = iter.next() {
+    // Sort the backups from newest to oldest.
+    entries.sort();
+    entries.reverse();
+
+    let max_age = settings
+        .daemon
+        .state_backup_max_age_hours
+        .map(|hours| Duration::from_secs(hours * 60 * 60));
+
+    for (index, (time, path)) in entries.iter().enumerate() {
+        let too_many = index >= settings.daemon.state_backup_count;
+        let too_old = match max_age {
+            Some(max_age) => time.elapsed().map(|age| age > max_age).unwrap_or(false),
+            None => false,
+        };
+
+        if too_many || too_old {
+            debug!("Removing old state backup: {path:?}");
             fs::remove_file(path)?;
-            number_entries -= 1;
         }
     }
 
@@ -6,6 +6,12 @@ impl TaskHandler {
     /// Users can specify a callback that's fired whenever a task finishes.
     /// Execute the callback by spawning a new subprocess.
     pub fn spawn_callback(&mut self, task: &Task) {
+        self.spawn_event_callback(task, "finished");
+    }
+
+    /// Execute the callback for some other event than a finished task, e.g. `stalled`.
+    /// The event is passed to the callback via the `event` parameter.
+    pub fn spawn_event_callback(&mut self, task: &Task, event: &str) {
         // Return early, if there's no callback specified
         let template_string = if let Some(callback) = &self.settings.daemon.callback {
             callback
@@ -14,7 +20,7 @@ impl TaskHandler {
         };
 
         // Build the command to be called from the template string in the configuration file.
-        let callback_command = match self.build_callback_command(task, template_string) {
+        let callback_command = match self.build_callback_command(task, event, template_string) {
             Ok(callback_command) => callback_command,
             Err(err) => {
                 error!("Failed to create callback command from template with error: {err}");
@@ -35,7 +41,7 @@ impl TaskHandler {
         };
 
         debug!("Spawned callback for task {}", task.id);
-        self.callbacks.push(child);
+        self.callbacks.push((task.id, child));
     }
 
     /// Take the callback template string from the configuration and insert all parameters from the
@@ -43,6 +49,7 @@ impl TaskHandler {
     pub fn build_callback_command(
         &self,
         task: &Task,
+        event: &str,
         template_string: &str,
     ) -> Result<String, RenderError> {
         // Init Handlebars. We set to strict, as we want to show an error on missing variables.
@@ -55,6 +62,7 @@ impl TaskHandler {
         parameters.insert("command", task.command.clone());
         parameters.insert("path", (*task.path.to_string_lossy()).to_owned());
         parameters.insert("group", task.group.clone());
+        parameters.insert("event", event.to_string());
 
         // Result takes the TaskResult Enum strings, unless it didn't finish yet.
         if let TaskStatus::Done(result) = &task.status {
@@ -70,18 +78,37 @@ impl TaskHandler {
         };
         parameters.insert("start", print_time(task.start));
         parameters.insert("end", print_time(task.end));
+        parameters.insert(
+            "failure_reason",
+            task.failure_reason.clone().unwrap_or_default(),
+        );
 
         // Read the last lines of the process' output and make it available.
         if let Ok(output) = read_last_log_file_lines(
             task.id,
             &self.pueue_directory,
             self.settings.daemon.callback_log_lines,
+            None,
         ) {
             parameters.insert("output", output);
         } else {
             parameters.insert("output", "".to_string());
         }
 
+        // The same goes for the separate streams, if they have been captured separately.
+        // Otherwise, they're empty.
+        let read_stream = |stream| {
+            read_last_log_file_lines(
+                task.id,
+                &self.pueue_directory,
+                self.settings.daemon.callback_log_lines,
+                Some(stream),
+            )
+            .unwrap_or_default()
+        };
+        parameters.insert("stdout", read_stream(OutputStream::Stdout));
+        parameters.insert("stderr", read_stream(OutputStream::Stderr));
+
         let out_path = get_log_path(task.id, &self.pueue_directory);
         // Using Display impl of PathBuf which isn't necessarily a perfect
         // representation of the path but should work for most cases here
@@ -91,7 +118,9 @@ impl TaskHandler {
         if let TaskStatus::Done(result) = &task.status {
             match result {
                 TaskResult::Success => parameters.insert("exit_code", "0".into()),
-                TaskResult::Failed(code) => parameters.insert("exit_code", code.to_string()),
+                TaskResult::Failed(code) | TaskResult::TemporaryFailure(code) => {
+                    parameters.insert("exit_code", code.to_string())
+                }
                 _ => parameters.insert("exit_code", "None".into()),
             };
         } else {
@@ -105,7 +134,7 @@ impl TaskHandler {
     /// If everything went smoothly, simply remove them from the list.
     pub fn check_callbacks(&mut self) {
         let mut finished = Vec::new();
-        for (id, child) in self.callbacks.iter_mut().enumerate() {
+        for (id, (_, child)) in self.callbacks.iter_mut().enumerate() {
             match child.try_wait() {
                 // Handle a child error.
                 Err(error) => {
@@ -1,12 +1,66 @@
 use std::collections::BTreeMap;
+use std::io;
 use std::process::Child;
 
+use anyhow::Result;
+
+use pueue_lib::network::message::Signal;
+use pueue_lib::process_helper::*;
+
+/// The OS process of a task.
+/// This is either a normal child of the daemon or a process that has been started detached by a
+/// previous daemon session and has been re-adopted on startup.
+pub enum TaskProcess {
+    Child(Child),
+    Adopted(AdoptedProcess),
+}
+
+impl TaskProcess {
+    /// Check whether the process exited without blocking.
+    ///
+    /// Returns `Ok(None)`, if the process is still running.
+    /// Otherwise, the exit code of the process is returned.
+    /// The exit code is `None`, if the process has been killed by a signal.
+    pub fn try_wait(&mut self) -> io::Result<Option<Option<i32>>> {
+        match self {
+            TaskProcess::Child(child) => Ok(child.try_wait()?.map(|status| status.code())),
+            TaskProcess::Adopted(process) => Ok(process.try_wait()),
+        }
+    }
+
+    /// Pause or resume the process. See [run_action_on_child].
+    pub fn run_action(&self, action: &ProcessAction, send_to_children: bool) -> Result<bool> {
+        match self {
+            TaskProcess::Child(child) => run_action_on_child(child, action, send_to_children),
+            TaskProcess::Adopted(process) => process.run_action(action, send_to_children),
+        }
+    }
+
+    /// Send a unix signal to the process. See [send_internal_signal_to_child].
+    pub fn send_internal_signal(&self, signal: Signal, send_to_children: bool) -> Result<bool> {
+        match self {
+            TaskProcess::Child(child) => {
+                send_internal_signal_to_child(child, signal, send_to_children)
+            }
+            TaskProcess::Adopted(process) => process.send_internal_signal(signal, send_to_children),
+        }
+    }
+
+    /// Kill the process. See [kill_child].
+    pub fn kill(&mut self, task_id: usize, kill_children: bool) -> bool {
+        match self {
+            TaskProcess::Child(child) => kill_child(task_id, child, kill_children),
+            TaskProcess::Adopted(process) => process.kill(task_id, kill_children),
+        }
+    }
+}
+
 /// This structure is needed to manage worker pools for groups.
 /// It's a newtype pattern around a nested BTreeMap, which implements some convenience functions.
 ///
 /// The datastructure contains these types of data:
 /// BTreeMap<group, BTreeMap<group_worker_id, (task_id, Subprocess handle)>
-pub struct Children(pub BTreeMap<String, BTreeMap<usize, (usize, Child)>>);
+pub struct Children(pub BTreeMap<String, BTreeMap<usize, (usize, TaskProcess)>>);
 
 impl Children {
     /// Returns whether there are any active tasks across all groups.
@@ -31,7 +85,7 @@ impl Children {
     /// A convenience function to get a child by its respective task_id.
     /// We have to do a nested linear search over all children of all pools,
     /// beceause these datastructure aren't indexed via task_ids.
-    pub fn get_child(&self, task_id: usize) -> Option<&Child> {
+    pub fn get_child(&self, task_id: usize) -> Option<&TaskProcess> {
         for pool in self.0.values() {
             for (child_task_id, child) in pool.values() {
                 if child_task_id == &task_id {
@@ -46,7 +100,7 @@ impl Children {
     /// A convenience function to get a mutable child by its respective task_id.
     /// We have to do a nested linear search over all children of all pools,
     /// beceause these datastructure aren't indexed via task_ids.
-    pub fn get_child_mut(&mut self, task_id: usize) -> Option<&mut Child> {
+    pub fn get_child_mut(&mut self, task_id: usize) -> Option<&mut TaskProcess> {
         for pool in self.0.values_mut() {
             for (child_task_id, child) in pool.values_mut() {
                 if child_task_id == &task_id {
@@ -107,7 +161,7 @@ impl Children {
     /// This function should only be called when spawning a new process.
     /// At this point, we're sure that the worker pool for the given group already exists, hence
     /// the expect call.
-    pub fn add_child(&mut self, group: &str, worker_id: usize, task_id: usize, child: Child) {
+    pub fn add_child(&mut self, group: &str, worker_id: usize, task_id: usize, child: TaskProcess) {
         let pool = self
             .0
             .get_mut(group)
@@ -0,0 +1,63 @@
+use std::thread;
+
+use super::*;
+
+impl TaskHandler {
+    /// Queue the logs of all finished tasks for compression.
+    /// This picks up logs, that haven't been compressed before the daemon has been stopped.
+    pub fn queue_log_compressions(&mut self) {
+        if !self.settings.daemon.compress_logs {
+            return;
+        }
+
+        let state = self.state.lock().unwrap();
+        for (task_id, task) in state.tasks.iter() {
+            if task.is_done() {
+                self.pending_log_compressions.insert(*task_id);
+            }
+        }
+    }
+
+    /// Compress the logs of finished tasks in a background thread.
+    ///
+    /// Only the logs of a single task are compressed at a time, so running tasks don't have to
+    /// compete for IO. Logs are only compressed, once the task's callback finished, as the
+    /// callback might still read the plain log file via `{{output_path}}`.
+    pub fn compress_finished_logs(&mut self) {
+        self.log_compressions
+            .retain(|_, compression| !compression.is_finished());
+        if !self.log_compressions.is_empty() {
+            return;
+        }
+
+        let callbacks = &self.callbacks;
+        let task_id = match self.pending_log_compressions.iter().find(|task_id| {
+            !callbacks
+                .iter()
+                .any(|(callback_task, _)| callback_task == *task_id)
+        }) {
+            Some(task_id) => *task_id,
+            None => return,
+        };
+        self.pending_log_compressions.remove(&task_id);
+
+        let pueue_directory = self.pueue_directory.clone();
+        let compression = thread::spawn(move || {
+            if let Err(err) = compress_task_logs(task_id, &pueue_directory) {
+                error!("Failed to compress logs of task {task_id}: {err}");
+            }
+        });
+        self.log_compressions.insert(task_id, compression);
+    }
+
+    /// Make sure that the logs of a task aren't compressed, as it's about to be started again.
+    /// A compression that's already running is waited for.
+    pub fn cancel_log_compression(&mut self, task_id: usize) {
+        self.pending_log_compressions.remove(&task_id);
+        if let Some(compression) = self.log_compressions.remove(&task_id) {
+            if compression.join().is_err() {
+                error!("Compressing the logs of task {task_id} panicked");
+            }
+        }
+    }
+}
@@ -1,10 +1,15 @@
-use anyhow::Context;
+use std::path::Path;
+
+use pueue_lib::task::OutputRules;
 
 use super::*;
 
 use crate::ok_or_shutdown;
 use crate::state_helper::{pause_on_failure, save_state};
 
+/// A finished task as `((task_id, group, worker_id), exit code or error)`.
+type FinishedTask = ((usize, String, usize), std::io::Result<Option<i32>>);
+
 impl TaskHandler {
     /// Check whether there are any finished processes
     /// In case there are, handle them and update the shared state
@@ -16,66 +21,120 @@ impl TaskHandler {
             return;
         }
 
+        // The output of tasks is checked before the state is locked, as their logs might be huge.
+        let output_failures = self.check_output_of_finished(&finished);
+
         // Clone the state ref, so we don't have two mutable borrows later on.
         let state_ref = self.state.clone();
         let mut state = state_ref.lock().unwrap();
 
-        for ((task_id, group, worker_id), error) in finished.iter() {
+        for ((task_id, group, worker_id), result) in finished.iter() {
             // Handle std::io errors on child processes.
             // I have never seen something like this, but it might happen.
-            if let Some(error) = error {
-                let (_taks_id, _child) = self
-                    .children
-                    .0
-                    .get_mut(group)
-                    .expect("Worker group must exist when handling finished tasks.")
-                    .remove(worker_id)
-                    .expect("Errored child went missing while handling finished task.");
-
-                let group = {
-                    let mut task = state.tasks.get_mut(task_id).unwrap();
-                    task.status = TaskStatus::Done(TaskResult::Errored);
-                    task.end = Some(Local::now());
-                    self.spawn_callback(task);
-
-                    task.group.clone()
-                };
-                error!("Child {} failed with io::Error: {:?}", task_id, error);
+            let exit_code = match result {
+                Err(error) => {
+                    let (_taks_id, _child) = self
+                        .children
+                        .0
+                        .get_mut(group)
+                        .expect("Worker group must exist when handling finished tasks.")
+                        .remove(worker_id)
+                        .expect("Errored child went missing while handling finished task.");
 
-                pause_on_failure(&mut state, &self.settings, &group);
-                continue;
-            }
+                    let group = {
+                        let task = state.tasks.get_mut(task_id).unwrap();
+                        task.status = TaskStatus::Done(TaskResult::Errored);
+                        task.end = Some(Local::now());
+                        task.detached_process = None;
+                        task.token_hash = None;
+                        self.spawn_callback(task);
+
+                        task.group.clone()
+                    };
+                    error!("Child {} failed with io::Error: {:?}", task_id, error);
+                    self.manually_killed.remove(task_id);
+
+                    pause_on_failure(&mut state, &self.settings, &group);
+                    continue;
+                }
+                Ok(exit_code) => *exit_code,
+            };
 
             // Handle any tasks that exited with some kind of exit code
-            let (_task_id, mut child) = self
-                .children
+            self.children
                 .0
                 .get_mut(group)
                 .expect("Worker group must exist when handling finished tasks.")
                 .remove(worker_id)
                 .expect("Child of task {} went away while handling finished task.");
 
-            // Get the exit code of the child.
-            // Errors really shouldn't happen in here, since we already checked if it's finished
-            // with try_wait() before.
-            let exit_code_result = child.wait();
-            let exit_code = exit_code_result
-                .context(format!(
-                    "Failed on wait() for finished task {task_id} with error: {error:?}"
-                ))
-                .unwrap()
-                .code();
-
-            // Processes with exit code 0 exited successfully
+            // Detached tasks write their exit code to a file, which is no longer needed.
+            let exit_code_path = get_exit_code_path(*task_id, &self.pueue_directory);
+            if exit_code_path.exists() {
+                if let Err(err) = std::fs::remove_file(&exit_code_path) {
+                    error!("Failed to remove exit code file of task {task_id}: {err:?}");
+                }
+            }
+
+            // Tasks that have been killed, as they stopped writing output or exceeded their
+            // timeout, get a failure reason.
+            let stalled = self
+                .output_activity
+                .remove(task_id)
+                .and_then(|activity| activity.killed_after);
+            let timed_out = self.timed_out.remove(task_id);
+            let manually_killed = self.manually_killed.remove(task_id);
+            let mut failure_reason = stalled
+                .map(|seconds| format!("Killed, as it didn't write any output for {seconds}s"))
+                .or_else(|| {
+                    timed_out
+                        .map(|seconds| format!("Killed, as it exceeded its timeout of {seconds}s"))
+                });
+
+            // Exit codes are classified by the task's exit code policy, which falls back to
+            // the policy of its group. By default, only exit code 0 counts as success.
             // Processes with `None` have been killed by a Signal
-            let result = match exit_code {
-                Some(0) => TaskResult::Success,
-                Some(exit_code) => TaskResult::Failed(exit_code),
+            // Tasks that have been killed due to their log size limit are marked as such.
+            let mut result = match exit_code {
+                _ if self.log_limit_exceeded.remove(task_id) => TaskResult::LogLimitExceeded,
+                _ if stalled.is_some() || timed_out.is_some() => TaskResult::Killed,
+                Some(exit_code) => {
+                    let task = state
+                        .tasks
+                        .get(task_id)
+                        .expect("Task was removed before child process has finished!");
+                    task.exit_code_policy
+                        .as_ref()
+                        .unwrap_or_else(|| self.settings.daemon.exit_code_policy(&task.group))
+                        .result(exit_code)
+                }
                 None => TaskResult::Killed,
             };
 
+            // Tasks that exited successfully might still have failed, according to their output.
+            if let (TaskResult::Success, Some(exit_code)) = (&result, exit_code) {
+                if let Some(reason) = output_failures.get(task_id) {
+                    failure_reason = Some(reason.clone());
+                    result = TaskResult::Failed(exit_code);
+                }
+            }
+
+            // Tasks are restarted in place, until they ran out of retries.
+            let retries_left = {
+                let task = state
+                    .tasks
+                    .get(task_id)
+                    .expect("Task was removed before child process has finished!");
+                let policy = task
+                    .exit_code_policy
+                    .as_ref()
+                    .unwrap_or_else(|| self.settings.daemon.exit_code_policy(&task.group));
+                task.retries < policy.max_retries
+            };
+            let retry_failure = matches!(result, TaskResult::TemporaryFailure(_)) && retries_left;
+
             // Update all properties on the task and get the group for later
-            let group = {
+            let (group, retry) = {
                 let mut task = state
                     .tasks
                     .get_mut(task_id)
@@ -83,40 +142,112 @@ impl TaskHandler {
 
                 task.status = TaskStatus::Done(result.clone());
                 task.end = Some(Local::now());
+                task.failure_reason = failure_reason;
+                task.detached_process = None;
+                task.token_hash = None;
                 self.spawn_callback(task);
 
-                task.group.clone()
+                // Tasks that requested a retry are restarted in place, unless they were killed.
+                let requested_retry = std::mem::take(&mut task.retry)
+                    && !matches!(result, TaskResult::Killed)
+                    && retries_left;
+                let retry = (requested_retry || retry_failure) && !self.full_reset;
+                if retry {
+                    task.retries += 1;
+                    task.status = TaskStatus::Queued;
+                    task.start = None;
+                    task.end = None;
+                    task.failure_reason = None;
+                }
+
+                (task.group.clone(), retry)
             };
 
-            if let TaskResult::Failed(_) = result {
+            if retry {
+                info!("Task {task_id} is retried and has been queued again");
+                continue;
+            }
+
+            // Tasks that have been killed by the user don't pause anything.
+            let killed_by_user = manually_killed && matches!(result, TaskResult::Killed);
+            if result.is_failure() && !killed_by_user {
                 pause_on_failure(&mut state, &self.settings, &group);
             }
 
             // Already remove the output files, if the daemon is being reset anyway
             if self.full_reset {
                 clean_log_handles(*task_id, &self.pueue_directory);
+            } else if self.settings.daemon.compress_logs {
+                self.pending_log_compressions.insert(*task_id);
             }
         }
 
-        ok_or_shutdown!(self, save_state(&state, &self.settings));
+        ok_or_shutdown!(self, save_state(&mut state, &self.settings));
+    }
+
+    /// Check the output of all finished tasks, that exited successfully and have output rules.
+    /// Returns the reasons why tasks failed according to their output.
+    ///
+    /// The state is only locked to get the output rules, not while the logs are being read.
+    fn check_output_of_finished(&self, finished: &[FinishedTask]) -> HashMap<usize, String> {
+        let to_check: Vec<(usize, OutputRules)> = {
+            let state = self.state.lock().unwrap();
+            finished
+                .iter()
+                .filter_map(|((task_id, _, _), result)| {
+                    let exit_code = match result {
+                        Ok(Some(exit_code)) => *exit_code,
+                        _ => return None,
+                    };
+                    let task = state.tasks.get(task_id)?;
+                    let policy = task
+                        .exit_code_policy
+                        .as_ref()
+                        .unwrap_or_else(|| self.settings.daemon.exit_code_policy(&task.group));
+                    if policy.result(exit_code) != TaskResult::Success {
+                        return None;
+                    }
+                    Some((*task_id, task.output_rules.clone()?))
+                })
+                .collect()
+        };
+
+        to_check
+            .into_iter()
+            .filter_map(|(task_id, rules)| {
+                check_output_rules(task_id, &rules, &self.pueue_directory)
+                    .map(|reason| (task_id, reason))
+            })
+            .collect()
     }
 
-    /// Gather all finished tasks and sort them by finished and errored.
-    /// Returns a list of finished task ids and whether they errored or not.
-    fn get_finished(&mut self) -> Vec<((usize, String, usize), Option<std::io::Error>)> {
+    /// Gather all finished tasks.
+    /// Returns a list of finished task ids and their exit code or the error that occurred
+    /// while checking on the process.
+    /// Exit codes are `None`, if the process has been killed by a signal.
+    fn get_finished(&mut self) -> Vec<FinishedTask> {
         let mut finished = Vec::new();
         for (group, children) in self.children.0.iter_mut() {
             for (worker_id, (task_id, child)) in children.iter_mut() {
                 match child.try_wait() {
                     // Handle a child error.
                     Err(error) => {
-                        finished.push(((*task_id, group.clone(), *worker_id), Some(error)));
+                        self.output_relays.remove(task_id);
+                        finished.push(((*task_id, group.clone(), *worker_id), Err(error)));
                     }
                     // Child process did not exit yet
                     Ok(None) => continue,
-                    Ok(_exit_status) => {
+                    Ok(Some(exit_code)) => {
+                        // Wait for the remaining output, if it's copied by the daemon.
+                        if let Some(relay) = self.output_relays.get_mut(task_id) {
+                            if !relay.is_done() {
+                                continue;
+                            }
+                            self.output_relays.remove(task_id);
+                        }
+
                         info!("Task {task_id} just finished");
-                        finished.push(((*task_id, group.clone(), *worker_id), None));
+                        finished.push(((*task_id, group.clone(), *worker_id), Ok(exit_code)));
                     }
                 }
             }
@@ -125,3 +256,29 @@ impl TaskHandler {
         finished
     }
 }
+
+/// Check the output of a finished task against its [pueue_lib::task::OutputRules].
+/// Returns the reason why the task failed, if any of the rules has been violated.
+fn check_output_rules(
+    task_id: usize,
+    rules: &OutputRules,
+    pueue_directory: &Path,
+) -> Option<String> {
+    if let Some(pattern) = &rules.fail_if {
+        match log_contains(task_id, pueue_directory, pattern) {
+            Ok(true) => return Some(format!("Output matches \"{pattern}\"")),
+            Ok(false) => (),
+            Err(err) => return Some(format!("Couldn't check the output: {err}")),
+        }
+    }
+
+    if let Some(pattern) = &rules.succeed_if {
+        match log_contains(task_id, pueue_directory, pattern) {
+            Ok(true) => (),
+            Ok(false) => return Some(format!("Output doesn't match \"{pattern}\"")),
+            Err(err) => return Some(format!("Couldn't check the output: {err}")),
+        }
+    }
+
+    None
+}
@@ -0,0 +1,60 @@
+use std::collections::BTreeMap;
+
+use log::{error, info};
+
+use pueue_lib::network::message::BackupMessage;
+
+use crate::ok_or_shutdown;
+use crate::state_helper::{backup_state, load_state_backup, save_state};
+use crate::task_handler::{Shutdown, TaskHandler};
+
+impl TaskHandler {
+    /// Handle the restoration of state backups.
+    ///
+    /// This is done in the TaskHandler, as the worker pools have to match the groups of the
+    /// restored state.
+    pub fn handle_backup_message(&mut self, message: BackupMessage) {
+        let name = match message {
+            BackupMessage::Restore(name) => name,
+            _ => return,
+        };
+
+        let cloned_state_mutex = self.state.clone();
+        let mut state = cloned_state_mutex.lock().unwrap();
+
+        // The state might have changed since the message has been validated.
+        if self.children.has_active_tasks() || state.tasks.values().any(|task| task.is_running()) {
+            error!("Refusing to restore backup \"{name}\", as there are running tasks.");
+            return;
+        }
+
+        let mut restored = match load_state_backup(&self.settings, &name) {
+            Ok(restored) => restored,
+            Err(error) => {
+                error!("Failed to restore state backup \"{name}\": {error:?}");
+                return;
+            }
+        };
+
+        // Backup the current state first, so the restore can be undone.
+        if let Err(error) = backup_state(&state, &self.settings) {
+            error!("Failed to backup state before restoring \"{name}\": {error:?}");
+            return;
+        }
+
+        // The revision must never decrease, otherwise clients might miss changes.
+        restored.revision = state.revision;
+        *state = restored;
+
+        // Rebuild the worker pools, as the groups may have changed.
+        // All pools are empty, since there are no active tasks.
+        self.children.0 = state
+            .groups
+            .keys()
+            .map(|group| (group.clone(), BTreeMap::new()))
+            .collect();
+
+        ok_or_shutdown!(self, save_state(&mut state, &self.settings));
+        info!("State backup \"{name}\" has been restored");
+    }
+}
@@ -26,13 +26,16 @@ impl TaskHandler {
                 name,
                 parallel_tasks,
             } => {
-                if state.groups.contains_key(&name) {
+                if self.children.0.contains_key(&name) {
                     error!("Group \"{name}\" already exists");
                     return;
                 }
-                let mut group = state.create_group(&name);
-                if let Some(parallel_tasks) = parallel_tasks {
-                    group.parallel_tasks = parallel_tasks;
+                // Groups that are added in a batch already exist in the state.
+                if !state.groups.contains_key(&name) {
+                    let group = state.create_group(&name);
+                    if let Some(parallel_tasks) = parallel_tasks {
+                        group.parallel_tasks = parallel_tasks;
+                    }
                 }
                 info!("New group \"{name}\" has been created");
 
@@ -40,10 +43,12 @@ impl TaskHandler {
                 self.children.0.insert(name, BTreeMap::new());
 
                 // Persist the state.
-                ok_or_shutdown!(self, save_state(&state, &self.settings));
+                ok_or_shutdown!(self, save_state(&mut state, &self.settings));
             }
             GroupMessage::Remove(group) => {
-                if !state.groups.contains_key(&group) {
+                // Groups that are removed in a batch are already gone from the state.
+                let in_state = state.groups.contains_key(&group);
+                if !in_state && !self.children.0.contains_key(&group) {
                     error!("Group \"{group}\" to be remove doesn't exists");
                     return;
                 }
@@ -54,9 +59,11 @@ impl TaskHandler {
                     return;
                 }
 
-                if let Err(error) = state.remove_group(&group) {
-                    error!("Error while removing group: \"{error}\"");
-                    return;
+                if in_state {
+                    if let Err(error) = state.remove_group(&group) {
+                        error!("Error while removing group: \"{error}\"");
+                        return;
+                    }
                 }
 
                 // Make sure the worker pool exists and is empty.
@@ -78,7 +85,7 @@ impl TaskHandler {
                 self.children.0.remove(&group);
 
                 // Persist the state.
-                ok_or_shutdown!(self, save_state(&state, &self.settings));
+                ok_or_shutdown!(self, save_state(&mut state, &self.settings));
 
                 info!("Group \"{group}\" has been removed");
             }
@@ -1,7 +1,6 @@
 use log::{error, info, warn};
 
 use pueue_lib::network::message::{Signal, TaskSelection};
-use pueue_lib::process_helper::*;
 use pueue_lib::state::GroupStatus;
 use pueue_lib::task::TaskStatus;
 
@@ -16,8 +15,9 @@ impl TaskHandler {
     /// However, the user can decide to send unix signals to the processes as well.
     ///
     /// `kill_children` Kill all direct child processes as well
-    /// `pause_groups` If `group` or `all` is given, the groups should be paused under some
-    ///     circumstances. This is mostly to prevent any further task execution during an emergency
+    /// `pause_groups` If `group`, `batch` or `all` is given, the groups or the batch should be
+    ///     paused under some circumstances.
+    ///     This is mostly to prevent any further task execution during an emergency
     /// `signal` Don't kill the task as usual, but rather send a unix process signal.
     pub fn kill(
         &mut self,
@@ -50,6 +50,25 @@ impl TaskHandler {
                 );
                 matching
             }
+            TaskSelection::Batch(batch_id) => {
+                // Ensure that a given batch exists. (Might not happen due to concurrency)
+                let batch = match state.batches.get_mut(&batch_id) {
+                    Some(batch) => batch,
+                    None => return,
+                };
+
+                // Pause this specific batch.
+                if pause_groups {
+                    batch.status = GroupStatus::Paused;
+                }
+                info!("Killing tasks of batch {batch_id}");
+
+                let (matching, _) = state.filter_tasks_of_batch(
+                    |task| matches!(task.status, TaskStatus::Running | TaskStatus::Paused),
+                    batch_id,
+                );
+                matching
+            }
             TaskSelection::All => {
                 // Pause all running tasks
                 if pause_groups {
@@ -62,6 +81,7 @@ impl TaskHandler {
         };
 
         for task_id in task_ids {
+            self.manually_killed.insert(task_id);
             if let Some(signal) = signal.clone() {
                 self.send_internal_signal(task_id, signal, kill_children);
             } else {
@@ -69,11 +89,11 @@ impl TaskHandler {
             }
         }
 
-        ok_or_shutdown!(self, save_state(&state, &self.settings));
+        ok_or_shutdown!(self, save_state(&mut state, &self.settings));
     }
 
     /// Send a signal to a specific child process.
-    /// This is a wrapper around [send_internal_signal_to_child], which does a little bit of
+    /// This is a wrapper around `TaskProcess::send_internal_signal`, which does a little bit of
     /// additional error handling.
     pub fn send_internal_signal(&mut self, task_id: usize, signal: Signal, send_to_children: bool) {
         let child = match self.children.get_child_mut(task_id) {
@@ -84,7 +104,7 @@ impl TaskHandler {
             }
         };
 
-        if let Err(err) = send_internal_signal_to_child(child, signal, send_to_children) {
+        if let Err(err) = child.send_internal_signal(signal, send_to_children) {
             warn!("Failed to send signal to task {task_id} with error: {err}");
         };
     }
@@ -93,7 +113,7 @@ impl TaskHandler {
     /// Triggered on `reset` and `kill`.
     pub fn kill_task(&mut self, task_id: usize, kill_children: bool) {
         if let Some(child) = self.children.get_child_mut(task_id) {
-            kill_child(task_id, child, kill_children);
+            child.kill(task_id, kill_children);
         } else {
             warn!("Tried to kill non-existing child: {task_id}");
         }
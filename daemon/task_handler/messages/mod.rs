@@ -6,6 +6,7 @@ use pueue_lib::network::message::*;
 
 use crate::task_handler::TaskHandler;
 
+mod backup;
 mod group;
 mod kill;
 mod pause;
@@ -20,6 +21,11 @@ impl TaskHandler {
         let timeout = Duration::from_millis(200);
         if let Ok(message) = self.receiver.recv_timeout(timeout) {
             self.handle_message(message);
+
+            // Handle all messages that have been sent at once, e.g. by a batch, right away.
+            while let Ok(message) = self.receiver.try_recv() {
+                self.handle_message(message);
+            }
         };
     }
 
@@ -33,6 +39,7 @@ impl TaskHandler {
             Message::Send(message) => self.send(message.task_id, message.input),
             Message::Reset(message) => self.reset(message.children),
             Message::Group(message) => self.handle_group_message(message),
+            Message::Backup(message) => self.handle_backup_message(message),
             Message::DaemonShutdown(shutdown) => {
                 self.initiate_shutdown(shutdown);
             }
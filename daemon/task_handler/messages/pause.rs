@@ -37,6 +37,23 @@ impl TaskHandler {
                 );
                 matching
             }
+            TaskSelection::Batch(batch_id) => {
+                // Ensure that a given batch exists. (Might not happen due to concurrency)
+                let batch = match state.batches.get_mut(&batch_id) {
+                    Some(batch) => batch,
+                    None => return,
+                };
+
+                // Pause the batch, which prevents any further tasks of the batch from starting.
+                batch.status = GroupStatus::Paused;
+                info!("Pausing batch {batch_id}");
+
+                let (matching, _) = state.filter_tasks_of_batch(
+                    |task| matches!(task.status, TaskStatus::Running),
+                    batch_id,
+                );
+                matching
+            }
             TaskSelection::All => {
                 // Pause all groups, since we're pausing the whole daemon.
                 state.set_status_for_all_groups(GroupStatus::Paused);
@@ -53,7 +70,7 @@ impl TaskHandler {
             }
         }
 
-        ok_or_shutdown!(self, save_state(&state, &self.settings));
+        ok_or_shutdown!(self, save_state(&mut state, &self.settings));
     }
     /// Pause a specific task.
     /// Send a signal to the process to actually pause the OS process.
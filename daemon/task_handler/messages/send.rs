@@ -2,13 +2,17 @@ use std::io::Write;
 
 use log::{error, warn};
 
-use crate::task_handler::TaskHandler;
+use crate::task_handler::{TaskHandler, TaskProcess};
 
 impl TaskHandler {
     /// Send some input to a child process' stdin.
     pub fn send(&mut self, task_id: usize, input: String) {
         let child = match self.children.get_child_mut(task_id) {
-            Some(child) => child,
+            Some(TaskProcess::Child(child)) => child,
+            Some(TaskProcess::Adopted(_)) => {
+                warn!("Cannot send input to task {task_id}, as it has been re-adopted");
+                return;
+            }
             None => {
                 warn!("Task {task_id} finished before input could be sent");
                 return;
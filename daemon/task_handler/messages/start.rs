@@ -35,7 +35,7 @@ impl TaskHandler {
                         self.start_process(task_id, &mut state);
                     }
                 }
-                ok_or_shutdown!(self, save_state(&state, &self.settings));
+                ok_or_shutdown!(self, save_state(&mut state, &self.settings));
                 return;
             }
             TaskSelection::Group(group_name) => {
@@ -55,6 +55,23 @@ impl TaskHandler {
                 );
                 matching
             }
+            TaskSelection::Batch(batch_id) => {
+                // Ensure that a given batch exists. (Might not happen due to concurrency)
+                let batch = match state.batches.get_mut(&batch_id) {
+                    Some(batch) => batch,
+                    None => return,
+                };
+
+                // Set the batch to running.
+                batch.status = GroupStatus::Running;
+                info!("Resuming batch {batch_id}");
+
+                let (matching, _) = state.filter_tasks_of_batch(
+                    |task| matches!(task.status, TaskStatus::Paused),
+                    batch_id,
+                );
+                matching
+            }
             TaskSelection::All => {
                 // Resume all groups and the default queue
                 info!("Resuming everything");
@@ -69,7 +86,7 @@ impl TaskHandler {
             self.continue_task(&mut state, task_id, start_children);
         }
 
-        ok_or_shutdown!(self, save_state(&state, &self.settings));
+        ok_or_shutdown!(self, save_state(&mut state, &self.settings));
     }
 
     /// Send a start signal to a paused task to continue execution.
@@ -1,7 +1,8 @@
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::path::PathBuf;
 use std::process::Child;
 use std::process::Stdio;
+use std::thread::JoinHandle;
 
 use anyhow::Result;
 use chrono::prelude::*;
@@ -9,21 +10,26 @@ use crossbeam_channel::{Receiver, SendError, Sender};
 use handlebars::Handlebars;
 use log::{debug, error, info};
 
+use pueue_lib::environment;
 use pueue_lib::log::*;
 use pueue_lib::network::message::*;
 use pueue_lib::network::protocol::socket_cleanup;
 use pueue_lib::process_helper::*;
 use pueue_lib::settings::Settings;
-use pueue_lib::state::{GroupStatus, SharedState};
-use pueue_lib::task::{Task, TaskResult, TaskStatus};
+use pueue_lib::state::{Drain, GroupStatus, SharedState};
+use pueue_lib::task::{DetachedProcess, Task, TaskResult, TaskStatus};
 
 use crate::pid::cleanup_pid_file;
-use crate::state_helper::{reset_state, save_state};
+use crate::state_helper::{
+    finish_detached_task, release_edit_locks, reset_state, save_state, EditLocks,
+};
 
 mod callback;
 /// A helper newtype struct, which implements convenience methods for our child process management
 /// datastructure.
 mod children;
+/// Compressing the logs of finished tasks in the background.
+mod compress_logs;
 /// Logic for handling dependencies
 mod dependencies;
 /// Logic for finishing and cleaning up completed tasks.
@@ -31,10 +37,16 @@ mod finish_task;
 /// This module contains all logic that's triggered by messages received via the mpsc channel.
 /// These messages are sent by the threads that handle the client messages.
 mod messages;
+/// Copying the output of tasks, whose stdout and stderr are captured separately.
+mod output_relay;
 /// Everything regarding actually spawning task processes.
 mod spawn_task;
+/// Detecting running tasks, that stopped writing output.
+mod stalled_tasks;
 
-use self::children::Children;
+use self::children::{Children, TaskProcess};
+use self::output_relay::{LogWriter, OutputRelay};
+use self::stalled_tasks::OutputActivity;
 
 /// This is a little helper macro, which looks at a critical result and shuts the
 /// TaskHandler down, if an error occurred. This is mostly used if the state cannot.
@@ -78,14 +90,31 @@ impl TaskSender {
 pub struct TaskHandler {
     /// The state that's shared between the TaskHandler and the message handling logic.
     state: SharedState,
+    /// The edit locks of all tasks that're currently being edited by clients.
+    edit_locks: EditLocks,
     /// The receiver for the MPSC channel that's used to push notificatoins from our message
     /// handling to the TaskHandler.
     receiver: Receiver<Message>,
     /// A mapping with this structure:
     /// BTreeMap<group, BTreeMap<group_worker_id, (task_id, Subprocess handle)>
     children: Children,
-    /// These are the currently running callbacks. They're usually very short-lived.
-    callbacks: Vec<Child>,
+    /// These are the currently running callbacks and the ids of their tasks.
+    /// They're usually very short-lived.
+    callbacks: Vec<(usize, Child)>,
+    /// The ids of tasks that have been killed due to their timeout and the timeout in seconds.
+    timed_out: HashMap<usize, u64>,
+    /// The ids of tasks that have been killed, as their output exceeded the log size limit.
+    log_limit_exceeded: HashSet<usize>,
+    /// The ids of tasks that have been killed or signaled by the user.
+    manually_killed: HashSet<usize>,
+    /// The output relays of all tasks, whose stdout and stderr are captured separately.
+    output_relays: HashMap<usize, OutputRelay>,
+    /// The output activity of all running tasks with a stall timeout.
+    output_activity: HashMap<usize, OutputActivity>,
+    /// The ids of finished tasks, whose logs are going to be compressed.
+    pending_log_compressions: BTreeSet<usize>,
+    /// The background threads, that are currently compressing the logs of a task.
+    log_compressions: HashMap<usize, JoinHandle<()>>,
     /// A simple flag which is used to signal that we're currently doing a full reset of the daemon.
     /// This flag prevents new tasks from being spawned.
     full_reset: bool,
@@ -100,27 +129,43 @@ pub struct TaskHandler {
 }
 
 impl TaskHandler {
-    pub fn new(shared_state: SharedState, settings: Settings, receiver: Receiver<Message>) -> Self {
-        // Clone the pointer, as we need to regularly access it inside the TaskHandler.
-        let state_clone = shared_state.clone();
-        let state = state_clone.lock().unwrap();
-
+    pub fn new(
+        shared_state: SharedState,
+        edit_locks: EditLocks,
+        settings: Settings,
+        receiver: Receiver<Message>,
+    ) -> Self {
         // Initialize the subprocess management structure.
         let mut pools = BTreeMap::new();
-        for group in state.groups.keys() {
-            pools.insert(group.clone(), BTreeMap::new());
+        {
+            let state = shared_state.lock().unwrap();
+            for group in state.groups.keys() {
+                pools.insert(group.clone(), BTreeMap::new());
+            }
         }
 
-        TaskHandler {
+        let mut handler = TaskHandler {
             state: shared_state,
+            edit_locks,
             receiver,
             children: Children(pools),
             callbacks: Vec::new(),
+            timed_out: HashMap::new(),
+            log_limit_exceeded: HashSet::new(),
+            manually_killed: HashSet::new(),
+            output_relays: HashMap::new(),
+            output_activity: HashMap::new(),
+            pending_log_compressions: BTreeSet::new(),
+            log_compressions: HashMap::new(),
             full_reset: false,
             shutdown: None,
             pueue_directory: settings.shared.pueue_directory(),
             settings,
-        }
+        };
+        handler.adopt_detached_processes();
+        handler.queue_log_compressions();
+
+        handler
     }
 
     /// Main loop of the task handler.
@@ -129,7 +174,10 @@ impl TaskHandler {
     /// - Receive and handle instructions from the client.
     /// - Handle finished tasks, i.e. cleanup processes, update statuses.
     /// - Callback handling logic. This is rather uncritical.
+    /// - Compress the logs of finished tasks in the background.
     /// - Enqueue any stashed processes which are ready for being queued.
+    /// - Kill tasks that exceeded their timeout.
+    /// - Handle tasks that stopped writing output.
     /// - Ensure tasks with dependencies have no failed ancestors
     /// - Whether whe should perform a shutdown.
     /// - If the client requested a reset: reset the state if all children have been killed and handled.
@@ -143,7 +191,12 @@ impl TaskHandler {
             self.receive_messages();
             self.handle_finished_tasks();
             self.check_callbacks();
+            self.compress_finished_logs();
             self.enqueue_delayed_tasks();
+            self.kill_timed_out_tasks();
+            self.kill_tasks_exceeding_log_limit();
+            self.handle_stalled_tasks();
+            self.release_expired_edit_locks();
             self.check_failed_dependencies();
 
             if self.shutdown.is_some() {
@@ -164,18 +217,79 @@ impl TaskHandler {
     /// Initiate shutdown, which includes killing all children and pausing all groups.
     /// We don't have to pause any groups, as no new tasks will be spawned during shutdown anyway.
     /// Any groups with queued tasks, will be automatically paused on state-restoration.
+    ///
+    /// Detached tasks are supposed to survive the daemon and are thereby left untouched.
     fn initiate_shutdown(&mut self, shutdown: Shutdown) {
+        if let Shutdown::Drain { deadline } = shutdown {
+            self.initiate_drain(deadline);
+            return;
+        }
         self.shutdown = Some(shutdown);
 
-        self.kill(TaskSelection::All, false, false, None);
+        let attached = self.attached_task_ids();
+        self.kill(TaskSelection::TaskIds(attached), false, false, None);
+    }
+
+    /// Initiate a drain. No new tasks will be spawned and the daemon shuts down as soon as all
+    /// running tasks finished. Tasks that still run after the deadline will be killed.
+    ///
+    /// Draining again while already draining only updates the deadline.
+    /// A drain cannot be initiated, if a normal shutdown is already in progress.
+    fn initiate_drain(&mut self, deadline: Option<DateTime<Local>>) {
+        if matches!(
+            self.shutdown,
+            Some(Shutdown::Emergency | Shutdown::Graceful)
+        ) {
+            info!("Ignoring drain request, as the daemon is already shutting down.");
+            return;
+        }
+        info!("Draining daemon. Deadline: {deadline:?}");
+        self.shutdown = Some(Shutdown::Drain { deadline });
+
+        let cloned_state_mutex = self.state.clone();
+        let mut state = cloned_state_mutex.lock().unwrap();
+        let start = match &state.drain {
+            Some(drain) => drain.start,
+            None => Local::now(),
+        };
+        state.drain = Some(Drain { start, deadline });
+        ok_or_shutdown!(self, save_state(&mut state, &self.settings));
+    }
+
+    /// Get the ids of all tasks with an active process, that hasn't been started in detached mode.
+    fn attached_task_ids(&self) -> Vec<usize> {
+        let state = self.state.lock().unwrap();
+        self.children
+            .all_task_ids()
+            .into_iter()
+            .filter(|task_id| match state.tasks.get(task_id) {
+                Some(task) => task.detached_process.is_none(),
+                None => true,
+            })
+            .collect()
     }
 
-    /// Check if all tasks are killed.
+    /// Check if all tasks are killed, or in case of a drain, whether all tasks finished.
     /// If they aren't, we'll wait a little longer.
     /// Once they're, we do some cleanup and exit.
     fn handle_shutdown(&mut self) {
+        // While draining, we wait for all tasks to finish.
+        // Once the deadline is reached, continue with a normal shutdown, which kills all tasks.
+        if let Some(Shutdown::Drain { deadline }) = self.shutdown {
+            if !self.children.has_active_tasks() {
+                info!("All tasks finished. Shutting down.");
+            } else if deadline.map_or(false, |deadline| deadline <= Local::now()) {
+                info!("Drain deadline reached. Killing all remaining tasks.");
+                self.initiate_shutdown(Shutdown::Graceful);
+                return;
+            } else {
+                return;
+            }
+        }
+
         // There are still active tasks. Continue waiting until they're killed and cleaned up.
-        if self.children.has_active_tasks() {
+        // Detached tasks will be re-adopted on the next start, so we don't wait for them.
+        if !self.attached_task_ids().is_empty() {
             return;
         }
 
@@ -253,7 +367,131 @@ impl TaskHandler {
         }
         // Save the state if a task has been enqueued
         if changed {
-            ok_or_shutdown!(self, save_state(&state, &self.settings));
+            ok_or_shutdown!(self, save_state(&mut state, &self.settings));
+        }
+    }
+
+    /// Kill all running tasks, that have been running for longer than their timeout.
+    /// The tasks are remembered, so they get a failure reason once they finished.
+    fn kill_timed_out_tasks(&mut self) {
+        let state_clone = self.state.clone();
+        let state = state_clone.lock().unwrap();
+
+        let now = Local::now();
+        for task_id in self.children.all_task_ids() {
+            if self.timed_out.contains_key(&task_id) {
+                continue;
+            }
+            let task = match state.tasks.get(&task_id) {
+                Some(task) => task,
+                None => continue,
+            };
+            let (timeout, start) = match (task.timeout, task.start) {
+                (Some(timeout), Some(start)) => (timeout, start),
+                _ => continue,
+            };
+
+            if start + chrono::Duration::seconds(timeout as i64) <= now {
+                info!("Killing task {task_id}, as it exceeded its timeout of {timeout}s");
+                self.kill_task(task_id, true);
+                self.timed_out.insert(task_id, timeout);
+            }
+        }
+    }
+
+    /// Kill all running tasks, whose output exceeded the log size limit with the `kill` policy.
+    /// The tasks are remembered, so they can be marked as such once they finished.
+    fn kill_tasks_exceeding_log_limit(&mut self) {
+        let exceeded: Vec<usize> = self
+            .output_relays
+            .iter()
+            .filter(|(_, relay)| relay.log_limit_exceeded())
+            .map(|(task_id, _)| *task_id)
+            .filter(|task_id| !self.log_limit_exceeded.contains(task_id))
+            .collect();
+
+        for task_id in exceeded {
+            info!("Killing task {task_id}, as its output exceeded the log size limit");
+            self.kill_task(task_id, true);
+            self.log_limit_exceeded.insert(task_id);
+        }
+    }
+
+    /// Unlock all tasks, whose edit lock expired.
+    /// This happens, if a client doesn't finish editing in time, e.g. if the user walked away.
+    fn release_expired_edit_locks(&mut self) {
+        let state_clone = self.state.clone();
+        let mut state = state_clone.lock().unwrap();
+
+        let now = Local::now();
+        let unlocked = release_edit_locks(&mut state, &self.edit_locks, |lock| lock.expires <= now);
+        if !unlocked.is_empty() {
+            info!("Edit locks expired. Unlocked tasks {unlocked:?}");
+        }
+    }
+
+    /// Re-adopt the processes of detached tasks that survived a restart of the daemon.
+    ///
+    /// The state restoration only keeps detached tasks running, if their process is still alive.
+    /// If the process exited in the meantime, the task is finished with the exit code it wrote.
+    /// Without an exit code, the task is marked as killed.
+    fn adopt_detached_processes(&mut self) {
+        let cloned_state_mutex = self.state.clone();
+        let mut state = cloned_state_mutex.lock().unwrap();
+
+        let mut changed = false;
+        for (task_id, task) in state.tasks.iter_mut() {
+            if !task.is_running() {
+                continue;
+            }
+            let detached = match &task.detached_process {
+                Some(detached) => detached.clone(),
+                None => continue,
+            };
+
+            let exit_code_path = get_exit_code_path(*task_id, &self.pueue_directory);
+            let process =
+                match AdoptedProcess::adopt(detached.pid, detached.start_time, exit_code_path) {
+                    Some(process) => process,
+                    None => {
+                        info!("Process of task {task_id} went away before it could be re-adopted");
+                        if !finish_detached_task(task, &self.settings) {
+                            task.status = TaskStatus::Done(TaskResult::Killed);
+                            task.end = Some(Local::now());
+                            task.detached_process = None;
+                            task.token_hash = None;
+                        }
+                        changed = true;
+                        continue;
+                    }
+                };
+
+            // Try to reuse the previous worker id of the task.
+            let pool = self
+                .children
+                .0
+                .get(&task.group)
+                .expect("Restored tasks must have an existing group.");
+            let worker_id = match task
+                .envs
+                .get(environment::WORKER_ID)
+                .and_then(|id| id.parse::<usize>().ok())
+            {
+                Some(worker_id) if !pool.contains_key(&worker_id) => worker_id,
+                _ => self.children.get_next_group_worker(&task.group),
+            };
+
+            info!("Re-adopted process {} of task {task_id}", detached.pid);
+            self.children.add_child(
+                &task.group,
+                worker_id,
+                *task_id,
+                TaskProcess::Adopted(process),
+            );
+        }
+
+        if changed {
+            ok_or_shutdown!(self, save_state(&mut state, &self.settings));
         }
     }
 
@@ -263,7 +501,7 @@ impl TaskHandler {
         match self.children.get_child(id) {
             Some(child) => {
                 debug!("Executing action {action:?} to {id}");
-                run_action_on_child(child, &action, children)?;
+                child.run_action(&action, children)?;
 
                 Ok(true)
             }
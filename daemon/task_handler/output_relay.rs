@@ -0,0 +1,338 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, ErrorKind, Read, Write};
+use std::path::PathBuf;
+use std::process::Child;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use chrono::prelude::*;
+use log::error;
+
+use pueue_lib::log::{
+    append_to_log_segment, create_log_index_handle, get_log_file_path, remove_log_segment,
+    rotate_log_file, write_log_index_entry, LogLimit, LogLimitPolicy, OutputStream,
+    LOG_SEGMENT_COUNT,
+};
+
+/// How long the remaining output of a task is waited for, once its process exited.
+/// Background processes of the task might keep the pipes open for much longer.
+const GRACE_PERIOD: Duration = Duration::from_secs(1);
+
+/// Written to the log of a task, once it's killed due to its log size limit.
+const KILL_MARKER: &str =
+    "\n(Pueue: The task has been killed, as its output exceeded the log size limit)\n";
+
+/// Written to the end of the first segment, once the middle of a log has been dropped.
+const TRUNCATION_MARKER: &str =
+    "\n(Pueue: Output has been dropped, as it exceeded the log size limit)\n";
+
+/// Copies the output of a task from the pipes of its process into the task's log files.
+///
+/// If stdout and stderr share a single pipe, the interleaved output keeps its original order.
+/// Otherwise, each stream is written to the log file with the interleaved output and to its own
+/// log file. Both streams are read in chunks, so the interleaved output stays as close to the
+/// original order as possible.
+///
+/// Once the relay is dropped, nothing is written to the log files anymore.
+pub struct OutputRelay {
+    threads: Vec<JoinHandle<()>>,
+    /// The point in time, at which the task's process has been noticed to be finished.
+    exited: Option<Instant>,
+    /// Set by the log files, once they exceeded their size limit with the `kill` policy.
+    limit_exceeded: Vec<Arc<AtomicBool>>,
+    combined: Arc<Mutex<LogWriter>>,
+    /// Set while the combined log is locked, once no more output should be written.
+    stopped: Arc<AtomicBool>,
+}
+
+impl OutputRelay {
+    /// Start copying the output of the given process.
+    /// `shared_pipe` is the reading end of the pipe, that's used as both stdout and stderr.
+    /// Otherwise, the stdout and stderr pipes of the process are copied.
+    /// `streams` are the separate `(stdout, stderr)` log files, if any.
+    pub fn spawn(
+        child: &mut Child,
+        shared_pipe: Option<File>,
+        combined: LogWriter,
+        streams: Option<(LogWriter, LogWriter)>,
+    ) -> Self {
+        let mut limit_exceeded = vec![combined.exceeded.clone()];
+        let combined = Arc::new(Mutex::new(combined));
+        let (stdout_log, stderr_log) = match streams {
+            Some((stdout, stderr)) => {
+                limit_exceeded.push(stdout.exceeded.clone());
+                limit_exceeded.push(stderr.exceeded.clone());
+                (Some(stdout), Some(stderr))
+            }
+            None => (None, None),
+        };
+
+        let stopped = Arc::new(AtomicBool::new(false));
+        let mut threads = Vec::new();
+        if let Some(pipe) = shared_pipe {
+            threads.push(relay(pipe, None, combined.clone(), stopped.clone()));
+        }
+        if let Some(stdout) = child.stdout.take() {
+            threads.push(relay(stdout, stdout_log, combined.clone(), stopped.clone()));
+        }
+        if let Some(stderr) = child.stderr.take() {
+            threads.push(relay(stderr, stderr_log, combined.clone(), stopped.clone()));
+        }
+
+        OutputRelay {
+            threads,
+            exited: None,
+            limit_exceeded,
+            combined,
+            stopped,
+        }
+    }
+
+    /// Check whether any of the task's log files exceeded its size limit with the `kill` policy.
+    pub fn log_limit_exceeded(&self) -> bool {
+        self.limit_exceeded
+            .iter()
+            .any(|exceeded| exceeded.load(Ordering::Relaxed))
+    }
+
+    /// Check whether all output has been written, once the task's process exited.
+    ///
+    /// After the grace period, output is no longer waited for. Any output, that arrives after
+    /// the relay has been dropped, is discarded.
+    pub fn is_done(&mut self) -> bool {
+        if self.threads.iter().all(JoinHandle::is_finished) {
+            return true;
+        }
+
+        let exited = *self.exited.get_or_insert_with(Instant::now);
+        exited.elapsed() > GRACE_PERIOD
+    }
+}
+
+impl Drop for OutputRelay {
+    /// Stop writing to the log files, so they don't change anymore, once the task's result
+    /// has been committed. Background processes of the task might still hold the pipes open,
+    /// which is why the pipes are still drained.
+    fn drop(&mut self) {
+        let _combined = self.combined.lock().unwrap();
+        self.stopped.store(true, Ordering::Relaxed);
+    }
+}
+
+/// A log file, which optionally has an index with the capture time of each line.
+///
+/// If the log has a size limit, it's either rotated into segments or the task is killed, once
+/// the limit is reached.
+pub struct LogWriter {
+    task_id: usize,
+    /// The stream of the separate log file. `None` for the interleaved output.
+    stream: Option<OutputStream>,
+    pueue_directory: PathBuf,
+    file: File,
+    index: Option<File>,
+    /// The amount of bytes that have been written to the current log file.
+    offset: u64,
+    /// Whether the next byte starts a new line.
+    at_line_start: bool,
+    limit: Option<LogLimit>,
+    /// The amount of bytes that have been written in total.
+    written: u64,
+    /// The number of the segment, into which the log file is rotated next.
+    next_segment: usize,
+    /// The rotated segments, that still exist.
+    segments: VecDeque<usize>,
+    /// Whether the middle of the log has already been dropped.
+    truncated: bool,
+    /// Set, once the log exceeded its size limit with the `kill` policy.
+    exceeded: Arc<AtomicBool>,
+}
+
+impl LogWriter {
+    pub fn new(
+        task_id: usize,
+        stream: Option<OutputStream>,
+        pueue_directory: PathBuf,
+        file: File,
+        index: Option<File>,
+        limit: Option<LogLimit>,
+    ) -> Self {
+        LogWriter {
+            task_id,
+            stream,
+            pueue_directory,
+            file,
+            index,
+            offset: 0,
+            at_line_start: true,
+            limit,
+            written: 0,
+            next_segment: 1,
+            segments: VecDeque::new(),
+            truncated: false,
+            exceeded: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Write a chunk of output to the log, while respecting its size limit.
+    fn write(&mut self, chunk: &[u8], time: DateTime<Local>) -> io::Result<()> {
+        let limit = match self.limit {
+            Some(limit) => limit,
+            None => return self.write_chunk(chunk, time),
+        };
+
+        if limit.policy == LogLimitPolicy::Kill {
+            if self.exceeded.load(Ordering::Relaxed) {
+                return Ok(());
+            }
+
+            let remaining = limit.size.saturating_sub(self.written);
+            if chunk.len() as u64 <= remaining {
+                return self.write_chunk(chunk, time);
+            }
+
+            // Write everything up to the limit and drop the rest of the output.
+            self.write_chunk(&chunk[..remaining as usize], time)?;
+            self.file.write_all(KILL_MARKER.as_bytes())?;
+            self.exceeded.store(true, Ordering::Relaxed);
+            return Ok(());
+        }
+
+        // The limit is shared equally between the segments of the log.
+        let segment_size = (limit.size / LOG_SEGMENT_COUNT).max(1);
+        let mut chunk = chunk;
+        while !chunk.is_empty() {
+            if self.offset >= segment_size {
+                self.rotate(limit.policy)?;
+            }
+
+            let length = chunk.len().min((segment_size - self.offset) as usize);
+            self.write_chunk(&chunk[..length], time)?;
+            chunk = &chunk[length..];
+        }
+
+        Ok(())
+    }
+
+    /// Write a chunk of output to the current log file.
+    /// If there's an index, an entry is added for each line that starts in this chunk.
+    fn write_chunk(&mut self, chunk: &[u8], time: DateTime<Local>) -> io::Result<()> {
+        if let Some(index) = self.index.as_mut() {
+            let mut entries = Vec::new();
+            for (position, byte) in chunk.iter().enumerate() {
+                if self.at_line_start {
+                    write_log_index_entry(&mut entries, self.offset + position as u64, time)?;
+                }
+                self.at_line_start = *byte == b'\n';
+            }
+
+            // Write the output before the index, so every indexed line exists in the log file.
+            self.file.write_all(chunk)?;
+            index.write_all(&entries)?;
+        } else {
+            self.file.write_all(chunk)?;
+        }
+
+        self.offset += chunk.len() as u64;
+        self.written += chunk.len() as u64;
+        Ok(())
+    }
+
+    /// Move the current log file into the next segment and start a new one.
+    ///
+    /// Once there are too many segments, the oldest one is removed.
+    /// With the `head_tail` policy, the first segment is always kept.
+    fn rotate(&mut self, policy: LogLimitPolicy) -> io::Result<()> {
+        let directory = &self.pueue_directory;
+        rotate_log_file(self.task_id, self.stream, self.next_segment, directory)?;
+        self.segments.push_back(self.next_segment);
+        self.next_segment += 1;
+
+        // Together with the current log file, all segments have to fit into the limit.
+        while self.segments.len() as u64 >= LOG_SEGMENT_COUNT {
+            let segment = if policy == LogLimitPolicy::HeadTail {
+                if !self.truncated {
+                    let head = self.segments[0];
+                    append_to_log_segment(
+                        self.task_id,
+                        self.stream,
+                        head,
+                        directory,
+                        TRUNCATION_MARKER,
+                    )?;
+                    self.truncated = true;
+                }
+                self.segments.remove(1)
+            } else {
+                self.segments.pop_front()
+            };
+
+            if let Some(segment) = segment {
+                remove_log_segment(self.task_id, self.stream, segment, directory)?;
+            }
+        }
+
+        self.file = File::create(get_log_file_path(self.task_id, self.stream, directory))?;
+        if self.index.is_some() {
+            let index = create_log_index_handle(self.task_id, self.stream, directory)
+                .map_err(|err| io::Error::new(ErrorKind::Other, err.to_string()))?;
+            self.index = Some(index);
+        }
+        self.offset = 0;
+        self.at_line_start = true;
+
+        Ok(())
+    }
+}
+
+/// Copy everything from a pipe into the stream's log file and the log file of the combined
+/// output, until the pipe is closed or the relay is stopped.
+fn relay<R>(
+    mut pipe: R,
+    mut log: Option<LogWriter>,
+    combined: Arc<Mutex<LogWriter>>,
+    stopped: Arc<AtomicBool>,
+) -> JoinHandle<()>
+where
+    R: Read + Send + 'static,
+{
+    thread::spawn(move || {
+        let mut buffer = [0; 8192];
+        let mut failed = false;
+        loop {
+            let read = match pipe.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(read) => read,
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) => {
+                    error!("Failed to read task output: {err}");
+                    break;
+                }
+            };
+
+            // The pipe has to be drained in any case, otherwise the process would be blocked.
+            // That's why we keep going, even if the output cannot be written.
+            // The time is taken while the combined log is locked. Otherwise, the other stream's
+            // relay could write a later time first and the combined index would be unordered.
+            let chunk = &buffer[..read];
+            let mut combined = combined.lock().unwrap();
+            if stopped.load(Ordering::Relaxed) {
+                continue;
+            }
+            let time = Local::now();
+            let result = log
+                .as_mut()
+                .map_or(Ok(()), |log| log.write(chunk, time))
+                .and_then(|_| combined.write(chunk, time));
+            drop(combined);
+            if let Err(err) = result {
+                if !failed {
+                    error!("Failed to write task output: {err}");
+                    failed = true;
+                }
+            }
+        }
+    })
+}
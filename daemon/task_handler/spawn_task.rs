@@ -1,3 +1,9 @@
+use std::fs::File;
+
+use pueue_lib::error::Error;
+use pueue_lib::network::secret::{generate_task_token, hash_task_token};
+use pueue_lib::state::State;
+
 use super::*;
 
 use crate::ok_or_shutdown;
@@ -20,6 +26,7 @@ impl TaskHandler {
     /// - is in Queued state
     /// - There are free slots in the task's group
     /// - The group is running
+    /// - The task's batch, if it has one, is running
     /// - has all its dependencies in `Done` state
     pub fn get_next_task_id(&mut self, state: &LockedState) -> Option<usize> {
         state
@@ -44,6 +51,12 @@ impl TaskHandler {
                     return false;
                 }
 
+                // The same goes for the task's batch.
+                let batch = task.batch.and_then(|batch| state.batches.get(&batch));
+                if batch.map_or(false, |batch| batch.status != GroupStatus::Running) {
+                    return false;
+                }
+
                 // Get the currently running tasks by looking at the actually running processes.
                 // They're sorted by group, which makes this quite convenient.
                 let running_tasks = match self.children.0.get(&task.group) {
@@ -90,6 +103,9 @@ impl TaskHandler {
             }
         };
 
+        // The logs of a previous run mustn't be compressed, while the task writes new ones.
+        self.cancel_log_compression(task_id);
+
         // Try to get the log file to which the output of the process will be written to.
         // Panic if this doesn't work! This is unrecoverable.
         let (stdout_log, stderr_log) = match create_log_file_handles(task_id, &self.pueue_directory)
@@ -101,24 +117,116 @@ impl TaskHandler {
         };
 
         // Get all necessary info for starting the task
-        let (command, path, group, mut envs) = {
+        let (command, path, group, mut envs, separate_output, timestamps, log_limit) = {
             let task = state.tasks.get(&task_id).unwrap();
             (
                 task.command.clone(),
                 task.path.clone(),
                 task.group.clone(),
                 task.envs.clone(),
+                task.separate_output,
+                task.timestamps,
+                task.log_limit,
             )
         };
 
+        // Detached tasks write their exit code into a file, as a restarted daemon won't be able
+        // to `wait` on a re-adopted process.
+        // Detaching tasks is only supported on the platforms, where they can be re-adopted.
+        let detach = self.settings.daemon.detach_tasks
+            && cfg!(any(target_os = "linux", target_os = "freebsd"));
+
+        // To capture stdout and stderr separately, with timestamps or with a size limit, the
+        // daemon copies the output from the pipes of the process into the log files.
+        // Detached tasks have to outlive the daemon, which is why they write to the files directly.
+        let separate_output = (separate_output || self.settings.daemon.separate_output) && !detach;
+        let timestamps = (timestamps || self.settings.daemon.log_timestamps) && !detach;
+        let log_limit = log_limit
+            .or_else(|| self.settings.daemon.log_limit(&group))
+            .filter(|_| !detach);
+
+        // Remove the separate output and indices of previous runs.
+        if !separate_output {
+            clean_stream_log_files(task_id, &self.pueue_directory);
+        }
+        if !timestamps {
+            clean_log_index_files(task_id, &self.pueue_directory);
+        }
+        clean_log_segments(task_id, &self.pueue_directory);
+
+        // Unless the streams are captured separately, both of them share a single pipe, which
+        // keeps the interleaved output in its original order.
+        let mut shared_pipe = None;
+        let (stdout, stderr, relay_logs) = if separate_output || timestamps || log_limit.is_some() {
+            let writers = match self.create_log_writers(
+                task_id,
+                stdout_log,
+                separate_output,
+                timestamps,
+                log_limit,
+            ) {
+                Ok(writers) => writers,
+                Err(err) => {
+                    panic!("Failed to create child log files: {err:?}");
+                }
+            };
+            let pipe = if separate_output {
+                None
+            } else {
+                match create_output_pipe() {
+                    Ok(pipe) => pipe,
+                    Err(err) => panic!("Failed to create output pipe: {err:?}"),
+                }
+            };
+
+            match pipe {
+                Some((reader, writer)) => {
+                    let stdout = match writer.try_clone() {
+                        Ok(stdout) => stdout,
+                        Err(err) => panic!("Failed to create output pipe: {err:?}"),
+                    };
+                    shared_pipe = Some(reader);
+                    (Stdio::from(stdout), Stdio::from(writer), Some(writers))
+                }
+                None => (Stdio::piped(), Stdio::piped(), Some(writers)),
+            }
+        } else {
+            (Stdio::from(stdout_log), Stdio::from(stderr_log), None)
+        };
+        // Run the command in a subshell, so it cannot interfere with recording its exit code,
+        // e.g. by setting its own `EXIT` trap.
+        // Signals reach the command nonetheless, as they're sent to the whole process group of
+        // detached tasks.
+        #[cfg(unix)]
+        let command = if detach {
+            let exit_code_path = get_exit_code_path(task_id, &self.pueue_directory);
+            let exit_code_path = exit_code_path.to_string_lossy();
+            let exit_code_path = shell_escape::escape(exit_code_path);
+            format!("(\n{command}\n)\nexit_code=$?\necho $exit_code > {exit_code_path}\nexit $exit_code")
+        } else {
+            command
+        };
+
         // Build the shell command that should be executed.
         let mut command = compile_shell_command(&command);
+        if detach {
+            detach_command(&mut command);
+        }
 
         // Determine the worker's id depending on the current group.
-        // Inject that info into the environment.
+        // Inject that info and everything else a task might want to know about itself into the
+        // environment.
         let worker_id = self.children.get_next_group_worker(&group);
-        envs.insert("PUEUE_GROUP".into(), group.clone());
-        envs.insert("PUEUE_WORKER_ID".into(), worker_id.to_string());
+        let attempt = {
+            let task = state.tasks.get_mut(&task_id).unwrap();
+            task.attempt += 1;
+            task.attempt
+        };
+        envs.extend(self.task_environment(state, task_id, worker_id, attempt));
+
+        // The token is only passed to the process, so it never ends up in the task's
+        // environment variables, which are persisted and sent to clients.
+        let token = generate_task_token();
 
         // Spawn the actual subprocess
         let spawned_command = command
@@ -126,12 +234,13 @@ impl TaskHandler {
             .stdin(Stdio::piped())
             .env_clear()
             .envs(envs.clone())
-            .stdout(Stdio::from(stdout_log))
-            .stderr(Stdio::from(stderr_log))
+            .env(environment::TASK_TOKEN, &token)
+            .stdout(stdout)
+            .stderr(stderr)
             .spawn();
 
         // Check if the task managed to spawn
-        let child = match spawned_command {
+        let mut child = match spawned_command {
             Ok(child) => child,
             Err(err) => {
                 let error = format!("Failed to spawn child {task_id} with err: {err:?}");
@@ -155,17 +264,125 @@ impl TaskHandler {
             }
         };
 
+        // Remember the identity of detached processes, so they can be re-adopted after a restart.
+        let detached_process = if detach {
+            process_start_time(child.id()).map(|start_time| DetachedProcess {
+                pid: child.id(),
+                start_time,
+            })
+        } else {
+            None
+        };
+
+        if let Some((combined, streams)) = relay_logs {
+            let relay = OutputRelay::spawn(&mut child, shared_pipe, combined, streams);
+            self.output_relays.insert(task_id, relay);
+        }
+
         // Save the process handle in our self.children datastructure.
-        self.children.add_child(&group, worker_id, task_id, child);
+        self.children
+            .add_child(&group, worker_id, task_id, TaskProcess::Child(child));
 
         let task = state.tasks.get_mut(&task_id).unwrap();
         task.start = Some(Local::now());
         task.status = TaskStatus::Running;
+        task.detached_process = detached_process;
+        task.token_hash = Some(hash_task_token(&token));
+        task.progress = None;
         // Overwrite the task's environment variables with the new ones, containing the
-        // PUEUE_WORKER_ID and PUEUE_GROUP variables.
+        // injected `PUEUE_*` variables.
         task.envs = envs;
 
         info!("Started task: {}", task.command);
         ok_or_shutdown!(self, save_state(state, &self.settings));
     }
+
+    /// Get the environment variables, that are injected into the process of a task.
+    /// Their names are defined in [pueue_lib::environment].
+    fn task_environment(
+        &self,
+        state: &State,
+        task_id: usize,
+        worker_id: usize,
+        attempt: usize,
+    ) -> HashMap<String, String> {
+        let task = state.tasks.get(&task_id).unwrap();
+        let dependency_results: Vec<String> = task
+            .dependencies
+            .iter()
+            .map(|id| match state.tasks.get(id).map(|task| &task.status) {
+                Some(TaskStatus::Done(result)) => result.to_string(),
+                Some(status) => status.to_string(),
+                None => "Removed".to_string(),
+            })
+            .collect();
+        let dependencies: Vec<String> = task.dependencies.iter().map(|id| id.to_string()).collect();
+        let log_path = get_log_path(task_id, &self.pueue_directory);
+
+        HashMap::from([
+            (environment::TASK_ID.into(), task_id.to_string()),
+            (
+                environment::TASK_LABEL.into(),
+                task.label.clone().unwrap_or_default(),
+            ),
+            (environment::GROUP.into(), task.group.clone()),
+            (environment::WORKER_ID.into(), worker_id.to_string()),
+            (
+                environment::LOG_PATH.into(),
+                log_path.to_string_lossy().into_owned(),
+            ),
+            (environment::DEPENDENCIES.into(), dependencies.join(",")),
+            (
+                environment::DEPENDENCY_RESULTS.into(),
+                dependency_results.join(","),
+            ),
+            (environment::ATTEMPT.into(), attempt.to_string()),
+            (
+                environment::SOCKET.into(),
+                self.settings.shared.socket_address(),
+            ),
+        ])
+    }
+
+    /// Create the log writers for a task, whose output is copied by the daemon.
+    /// Returns the writer for the interleaved output and, if the output is captured separately,
+    /// the writers for the `(stdout, stderr)` log files.
+    fn create_log_writers(
+        &self,
+        task_id: usize,
+        combined: File,
+        separate_output: bool,
+        timestamps: bool,
+        log_limit: Option<LogLimit>,
+    ) -> Result<(LogWriter, Option<(LogWriter, LogWriter)>), Error> {
+        let create_index = |stream| {
+            if timestamps {
+                create_log_index_handle(task_id, stream, &self.pueue_directory).map(Some)
+            } else {
+                Ok(None)
+            }
+        };
+
+        let create_writer = |stream, file| -> Result<LogWriter, Error> {
+            Ok(LogWriter::new(
+                task_id,
+                stream,
+                self.pueue_directory.clone(),
+                file,
+                create_index(stream)?,
+                log_limit,
+            ))
+        };
+
+        let combined = create_writer(None, combined)?;
+        if !separate_output {
+            return Ok((combined, None));
+        }
+
+        let (stdout, stderr) = create_stream_log_file_handles(task_id, &self.pueue_directory)?;
+        let stdout = create_writer(Some(OutputStream::Stdout), stdout)?;
+        let stderr = create_writer(Some(OutputStream::Stderr), stderr)?;
+
+        Ok((combined, Some((stdout, stderr))))
+    }
 }
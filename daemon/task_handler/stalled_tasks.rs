@@ -0,0 +1,99 @@
+use std::fs::metadata;
+
+use pueue_lib::task::StallAction;
+
+use super::*;
+
+/// The output activity of a running task, which is used to detect stalled tasks.
+pub struct OutputActivity {
+    /// The size of the task's log file, when it has last been checked.
+    log_size: u64,
+    /// The last time the task wrote output or has been paused.
+    last_activity: DateTime<Local>,
+    /// Whether the current stall has already been handled.
+    /// This is reset, once the task writes output again.
+    handled: bool,
+    /// The stall timeout in seconds, if the task has been killed due to it.
+    pub killed_after: Option<u64>,
+}
+
+impl TaskHandler {
+    /// Check whether any running tasks stopped writing output for longer than their stall timeout.
+    /// The configured action is only performed once per stall.
+    ///
+    /// Any change of the log file's size counts as output, as rotated logs might shrink.
+    /// The time a task is paused doesn't count, as it cannot write any output.
+    pub fn handle_stalled_tasks(&mut self) {
+        let running = self.children.all_task_ids();
+        self.output_activity
+            .retain(|task_id, _| running.contains(task_id));
+
+        let mut stalled = Vec::new();
+        {
+            let state = self.state.lock().unwrap();
+            let now = Local::now();
+            for task_id in running {
+                let task = match state.tasks.get(&task_id) {
+                    Some(task) => task,
+                    None => continue,
+                };
+                let stall_timeout = match task
+                    .stall_timeout
+                    .or_else(|| self.settings.daemon.stall_timeout(&task.group))
+                {
+                    Some(stall_timeout) => stall_timeout,
+                    None => continue,
+                };
+
+                let log_size = metadata(get_log_path(task_id, &self.pueue_directory))
+                    .map(|metadata| metadata.len())
+                    .unwrap_or_default();
+                let activity = self
+                    .output_activity
+                    .entry(task_id)
+                    .or_insert(OutputActivity {
+                        log_size,
+                        last_activity: now,
+                        handled: false,
+                        killed_after: None,
+                    });
+
+                if activity.log_size != log_size || task.status == TaskStatus::Paused {
+                    activity.log_size = log_size;
+                    activity.last_activity = now;
+                    activity.handled = false;
+                    continue;
+                }
+
+                let timeout = chrono::Duration::seconds(stall_timeout.seconds as i64);
+                if activity.handled || activity.last_activity + timeout > now {
+                    continue;
+                }
+
+                activity.handled = true;
+                if stall_timeout.action == StallAction::Kill {
+                    activity.killed_after = Some(stall_timeout.seconds);
+                }
+                stalled.push((task.clone(), stall_timeout));
+            }
+        }
+
+        for (task, stall_timeout) in stalled {
+            let (task_id, seconds) = (task.id, stall_timeout.seconds);
+            match stall_timeout.action {
+                StallAction::Notify => {
+                    info!("Task {task_id} didn't write any output for {seconds}s");
+                    self.spawn_event_callback(&task, "stalled");
+                }
+                StallAction::Pause => {
+                    info!("Pausing task {task_id}, as it didn't write any output for {seconds}s");
+                    self.pause(TaskSelection::TaskIds(vec![task_id]), false, false);
+                }
+                StallAction::Kill => {
+                    info!("Killing task {task_id}, as it didn't write any output for {seconds}s");
+                    self.kill_task(task_id, true);
+                }
+            }
+        }
+    }
+}
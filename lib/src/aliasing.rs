@@ -1,22 +1,257 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs::File;
 use std::io::prelude::*;
+use std::path::PathBuf;
 
 use log::info;
+use serde_derive::{Deserialize, Serialize};
 
 use crate::error::Error;
 use crate::settings::Settings;
 
-/// Return the contents of the alias file, if it exists and can be parsed. \
+/// A single entry of the alias file.
+///
+/// An alias is either a plain command, which simply replaces the first word of a task's command,
+/// or a template with parameters and defaults for the task.
+#[derive(PartialEq, Eq, Clone, Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum Alias {
+    Command(String),
+    Template(AliasTemplate),
+}
+
+/// An alias with parameters and defaults for the task.
+///
+/// The command may contain positional (`{1}`, `{2}`, ...) and named (`{name}`) parameters.
+/// Parameters may have a default value, e.g. `{1=small}` or `{lr=0.01}`.
+#[derive(PartialEq, Eq, Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct AliasTemplate {
+    pub command: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// The group tasks are added to, unless another group is specified.
+    #[serde(default)]
+    pub group: Option<String>,
+    /// The working directory of the task. Relative paths are resolved against the task's path.
+    #[serde(default)]
+    pub path: Option<PathBuf>,
+    /// Environment variables that are set for the task.
+    #[serde(default)]
+    pub envs: HashMap<String, String>,
+}
+
+/// The result of applying an alias to a command.
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+pub struct ExpandedAlias {
+    pub command: String,
+    pub group: Option<String>,
+    pub path: Option<PathBuf>,
+    pub envs: HashMap<String, String>,
+}
+
+/// A parameter inside an alias template, such as `{1}` or `{lr=0.01}`.
+pub(crate) struct Placeholder<'a> {
+    /// The byte range of the placeholder including its braces.
+    start: usize,
+    end: usize,
+    pub name: &'a str,
+    pub default: Option<&'a str>,
+}
+
+impl Alias {
+    /// The command or command template of this alias.
+    pub fn command(&self) -> &str {
+        match self {
+            Alias::Command(command) => command,
+            Alias::Template(template) => &template.command,
+        }
+    }
+
+    /// All parameters of this alias and their default values in order of their first usage.
+    pub fn parameters(&self) -> Vec<(String, Option<String>)> {
+        let mut parameters: Vec<(String, Option<String>)> = Vec::new();
+        if let Alias::Template(template) = self {
+            for placeholder in find_placeholders(&template.command) {
+                if !parameters.iter().any(|(name, _)| name == placeholder.name) {
+                    parameters.push((
+                        placeholder.name.to_string(),
+                        placeholder.default.map(String::from),
+                    ));
+                }
+            }
+        }
+
+        parameters
+    }
+
+    /// Apply this alias to the given arguments, i.e. everything after the alias' name.
+    ///
+    /// Plain aliases are simply followed by the arguments. \
+    /// For templates, the arguments are split like a shell would do it, which means that quoted
+    /// arguments stay intact. Arguments of the form `name=value` are used for the respective
+    /// named parameter, all other arguments are used as positional parameters.
+    /// Positional arguments that aren't used by the template are appended.
+    /// All values are quoted again when they're inserted into the command.
+    pub fn expand(&self, arguments: &str) -> Result<ExpandedAlias, String> {
+        let template = match self {
+            Alias::Command(command) => {
+                return Ok(ExpandedAlias {
+                    command: format!("{command}{arguments}"),
+                    ..Default::default()
+                })
+            }
+            Alias::Template(template) => template,
+        };
+
+        let placeholders = find_placeholders(&template.command);
+
+        let arguments = shell_words::split(arguments)
+            .map_err(|err| format!("Failed to parse arguments: {err}"))?;
+
+        // Sort the arguments into named and positional ones.
+        let mut named: HashMap<&str, String> = HashMap::new();
+        let mut positional: Vec<String> = Vec::new();
+        for argument in &arguments {
+            if let Some((name, value)) = argument.split_once('=') {
+                if placeholders
+                    .iter()
+                    .any(|placeholder| placeholder.name == name)
+                {
+                    named.insert(name, shell_words::quote(value).into_owned());
+                    continue;
+                }
+            }
+            positional.push(shell_words::quote(argument).into_owned());
+        }
+
+        // Replace all placeholders with their respective values.
+        let mut used_positional = 0;
+        let mut command = fill_placeholders(&template.command, |name| {
+            if let Ok(index) = name.parse::<usize>() {
+                used_positional = used_positional.max(index);
+                index
+                    .checked_sub(1)
+                    .and_then(|index| positional.get(index))
+                    .map(String::as_str)
+            } else {
+                named.get(name).map(String::as_str)
+            }
+        })?;
+
+        // Append all positional arguments that aren't used by the template.
+        for argument in positional.iter().skip(used_positional) {
+            command.push(' ');
+            command.push_str(argument);
+        }
+
+        Ok(ExpandedAlias {
+            command,
+            group: template.group.clone(),
+            path: template.path.clone(),
+            envs: template.envs.clone(),
+        })
+    }
+}
+
+/// Replace all parameters of a template with the values returned by `lookup`.
+///
+/// Parameters, for which `lookup` doesn't return a value, fall back to their default.
+/// An error is returned, if a parameter has neither a value nor a default.
+pub(crate) fn fill_placeholders<'a, F>(template: &'a str, mut lookup: F) -> Result<String, String>
+where
+    F: FnMut(&str) -> Option<&'a str>,
+{
+    let mut filled = String::new();
+    let mut last_end = 0;
+    for placeholder in find_placeholders(template) {
+        let value = match lookup(placeholder.name).or(placeholder.default) {
+            Some(value) => value,
+            None => {
+                return Err(format!(
+                    "Missing value for parameter \"{}\"",
+                    placeholder.name
+                ))
+            }
+        };
+
+        filled.push_str(&template[last_end..placeholder.start]);
+        filled.push_str(value);
+        last_end = placeholder.end;
+    }
+    filled.push_str(&template[last_end..]);
+
+    Ok(filled)
+}
+
+/// Find all parameters in a template.
+///
+/// Braces that are preceded by a `$` are left untouched, as they're most likely part of a shell
+/// variable such as `${HOME}`.
+pub(crate) fn find_placeholders(template: &str) -> Vec<Placeholder<'_>> {
+    let mut placeholders = Vec::new();
+    let mut search_start = 0;
+    while let Some(offset) = template[search_start..].find('{') {
+        let start = search_start + offset;
+        search_start = start + 1;
+
+        if template[..start].ends_with('$') {
+            continue;
+        }
+        let end = match template[start..].find('}') {
+            Some(offset) => start + offset + 1,
+            None => break,
+        };
+
+        let inner = &template[start + 1..end - 1];
+        let (name, default) = match inner.split_once('=') {
+            Some((name, default)) => (name, Some(default)),
+            None => (inner, None),
+        };
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|char| char.is_ascii_alphanumeric() || char == '_')
+        {
+            continue;
+        }
+
+        placeholders.push(Placeholder {
+            start,
+            end,
+            name,
+            default,
+        });
+        search_start = end;
+    }
+
+    placeholders
+}
+
+/// Return the commands of all aliases of the alias file, if it exists and can be parsed. \
 /// The file has to be located in `pueue_directory` and named `pueue_aliases.yml`.
+///
+/// For templates, this is the command template. Use [get_alias_definitions] to get
+/// their parameters and defaults as well.
 pub fn get_aliases(settings: &Settings) -> Result<HashMap<String, String>, Error> {
+    let aliases = get_alias_definitions(settings)?;
+
+    Ok(aliases
+        .into_iter()
+        .map(|(name, alias)| (name, alias.command().to_string()))
+        .collect())
+}
+
+/// Return the contents of the alias file, if it exists and can be parsed. \
+/// The file has to be located in `pueue_directory` and named `pueue_aliases.yml`.
+pub fn get_alias_definitions(settings: &Settings) -> Result<BTreeMap<String, Alias>, Error> {
     // Go through all config directories and check for a alias file.
     let path = settings.shared.alias_file();
 
     // Return early if we cannot find the file
     if !path.exists() {
         info!("Didn't find pueue alias file at {path:?}.");
-        return Ok(HashMap::new());
+        return Ok(BTreeMap::new());
     };
 
     // Read the file content
@@ -32,26 +267,139 @@ pub fn get_aliases(settings: &Settings) -> Result<HashMap<String, String>, Error
     })
 }
 
-/// Check if there exists an alias for a given command.
-/// Only the first word will be replaced.
-pub fn insert_alias(settings: &Settings, command: String) -> String {
+/// Check if there exists an alias for the first word of a given command and apply it.
+///
+/// Returns `Ok(None)`, if no alias applies to the command.
+/// Errors are only returned, if the command doesn't fit the alias' template.
+pub fn expand_alias(settings: &Settings, command: &str) -> Result<Option<ExpandedAlias>, Error> {
     // Get the first word of the command.
     let first = match command.split_whitespace().next() {
         Some(first) => first,
-        None => return command,
+        None => return Ok(None),
     };
 
-    let aliases = match get_aliases(settings) {
+    let aliases = match get_alias_definitions(settings) {
         Err(err) => {
             info!("Couldn't read aliases file: {err}");
-            return command;
+            return Ok(None);
         }
         Ok(aliases) => aliases,
     };
 
-    if let Some(alias) = aliases.get(first) {
-        return command.replacen(first, alias, 1);
+    let alias = match aliases.get(first) {
+        Some(alias) => alias,
+        None => return Ok(None),
+    };
+
+    // Everything behind the first word are the arguments of the alias.
+    let arguments_start = command.find(first).unwrap_or(0) + first.len();
+    alias
+        .expand(&command[arguments_start..])
+        .map(Some)
+        .map_err(|err| Error::Alias(format!("{first}: {err}")))
+}
+
+/// Check if there exists an alias for a given command.
+/// Only the command is changed, any other defaults of the alias are ignored.
+pub fn insert_alias(settings: &Settings, command: String) -> String {
+    match expand_alias(settings, &command) {
+        Ok(Some(alias)) => alias.command,
+        Ok(None) => command,
+        Err(err) => {
+            info!("Couldn't apply alias: {err}");
+            command
+        }
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
 
-    command
+    fn template(command: &str) -> Alias {
+        Alias::Template(AliasTemplate {
+            command: command.to_string(),
+            description: None,
+            group: Some("gpu".to_string()),
+            path: None,
+            envs: HashMap::new(),
+        })
+    }
+
+    #[test]
+    fn plain_alias_keeps_arguments() {
+        let alias = Alias::Command("echo".to_string());
+        let expanded = alias.expand(" test  this").unwrap();
+        assert_eq!(expanded.command, "echo test  this");
+        assert_eq!(expanded.group, None);
+    }
+
+    #[test]
+    fn template_positional_and_named() {
+        let alias = template("train.py --model {1} --lr {lr=0.01} --epochs {epochs}");
+
+        let expanded = alias.expand(" resnet epochs=10 --verbose").unwrap();
+        assert_eq!(
+            expanded.command,
+            "train.py --model resnet --lr 0.01 --epochs 10 --verbose"
+        );
+        assert_eq!(expanded.group, Some("gpu".to_string()));
+
+        let expanded = alias.expand(" vit lr=0.1 epochs=5").unwrap();
+        assert_eq!(expanded.command, "train.py --model vit --lr 0.1 --epochs 5");
+    }
+
+    #[test]
+    fn template_missing_parameter() {
+        let alias = template("train.py --model {1=resnet} --epochs {epochs}");
+        assert!(alias.expand("").is_err());
+    }
+
+    #[test]
+    fn template_keeps_quoted_arguments() {
+        let alias = template("echo {1} {msg}");
+
+        let expanded = alias.expand(r#" 'hello world' msg="a b" extra"#).unwrap();
+        assert_eq!(expanded.command, "echo 'hello world' 'a b' extra");
+
+        assert!(alias.expand(" 'unbalanced").is_err());
+    }
+
+    #[test]
+    fn template_ignores_shell_syntax() {
+        let alias = template("cp {1} ${HOME}/{a,b} {2=out}");
+        assert_eq!(
+            alias.parameters(),
+            vec![
+                ("1".to_string(), None),
+                ("2".to_string(), Some("out".to_string()))
+            ]
+        );
+
+        let expanded = alias.expand(" file").unwrap();
+        assert_eq!(expanded.command, "cp file ${HOME}/{a,b} out");
+    }
+
+    #[test]
+    fn deserialize_mixed_aliases() {
+        let content = r#"
+ls: ls -al
+train:
+  command: python train.py {1}
+  group: gpu
+  envs:
+    CUDA_VISIBLE_DEVICES: "0"
+"#;
+        let aliases: BTreeMap<String, Alias> = serde_yaml::from_str(content).unwrap();
+        assert_eq!(aliases["ls"], Alias::Command("ls -al".to_string()));
+        match &aliases["train"] {
+            Alias::Template(template) => {
+                assert_eq!(template.group, Some("gpu".to_string()));
+                assert_eq!(template.envs["CUDA_VISIBLE_DEVICES"], "0");
+            }
+            Alias::Command(_) => panic!("Expected a template alias"),
+        }
+    }
 }
@@ -0,0 +1,289 @@
+use std::fs::File;
+use std::io::{self, BufWriter, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use snap::read::FrameDecoder;
+use snap::write::FrameEncoder;
+
+/// Identifies compressed log files. It's located at the very start and the very end of a file.
+const MAGIC: &[u8; 8] = b"PUEUESZ1";
+
+/// The amount of uncompressed bytes in each block.
+/// Only a single block has to be decompressed to read from an arbitrary position.
+const BLOCK_SIZE: usize = 256 * 1024;
+
+/// The size of a single entry in the block table.
+const BLOCK_ENTRY_SIZE: u64 = 16;
+
+/// The size of the footer, which consists of the amount of blocks and the magic bytes.
+const FOOTER_SIZE: u64 = 8 + MAGIC.len() as u64;
+
+/// A single block of a compressed log file.
+#[derive(Clone, Copy, Debug)]
+struct Block {
+    /// The position of the compressed data in the file.
+    offset: u64,
+    /// The size of the compressed data.
+    compressed_len: u64,
+    /// The position of the block's first byte in the uncompressed log.
+    start: u64,
+    /// The size of the uncompressed data.
+    len: u64,
+}
+
+/// Compress a log file into the seekable format, that's read by [CompressedLog].
+///
+/// The log is split into blocks of [BLOCK_SIZE] bytes, each of which is compressed as a
+/// separate stream in the [snap] frame format. The blocks are followed by a table with the
+/// position and size of each block.
+/// As concatenated frame streams are a valid frame stream themselves, blocks can be sent to
+/// clients without decompressing them.
+///
+/// The layout of the file is:
+/// - The magic bytes.
+/// - The compressed blocks.
+/// - For each block: The `u64` offset of the block in the file, the `u32` compressed size and
+///   the `u32` uncompressed size.
+/// - The `u64` amount of blocks.
+/// - The magic bytes.
+///
+/// All integers are stored as little endian.
+pub fn compress_log(source: &Path, target: &Path) -> io::Result<()> {
+    let mut source = File::open(source)?;
+    let mut writer = BufWriter::new(File::create(target)?);
+    writer.write_all(MAGIC)?;
+
+    let mut offset = MAGIC.len() as u64;
+    let mut table = Vec::new();
+    let mut buffer = vec![0; BLOCK_SIZE];
+    loop {
+        let len = read_block(&mut source, &mut buffer)?;
+        if len == 0 {
+            break;
+        }
+
+        let mut compressed = Vec::new();
+        {
+            let mut encoder = FrameEncoder::new(&mut compressed);
+            encoder.write_all(&buffer[..len])?;
+            encoder.flush()?;
+        }
+        writer.write_all(&compressed)?;
+
+        table.write_u64::<LittleEndian>(offset)?;
+        table.write_u32::<LittleEndian>(compressed.len() as u32)?;
+        table.write_u32::<LittleEndian>(len as u32)?;
+        offset += compressed.len() as u64;
+    }
+
+    writer.write_all(&table)?;
+    writer.write_u64::<LittleEndian>(table.len() as u64 / BLOCK_ENTRY_SIZE)?;
+    writer.write_all(MAGIC)?;
+
+    let file = writer.into_inner().map_err(|err| err.into_error())?;
+    file.sync_all()
+}
+
+/// Fill the buffer with the next block of the source.
+/// Only the last block is smaller than the buffer.
+fn read_block(source: &mut File, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut len = 0;
+    while len < buffer.len() {
+        match source.read(&mut buffer[len..]) {
+            Ok(0) => break,
+            Ok(read) => len += read,
+            Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        }
+    }
+
+    Ok(len)
+}
+
+/// A log file, that has been compressed with [compress_log].
+///
+/// It can be read and seeked just like the original file.
+/// Only the block at the current position is decompressed.
+pub struct CompressedLog {
+    file: File,
+    blocks: Vec<Block>,
+    /// The size of the uncompressed log.
+    len: u64,
+    /// The current position in the uncompressed log.
+    position: u64,
+    /// The index and the content of the block, that has been decompressed last.
+    cache: Option<(usize, Vec<u8>)>,
+}
+
+impl CompressedLog {
+    /// Open a compressed log file and read its block table.
+    pub fn open(path: &Path) -> io::Result<CompressedLog> {
+        let mut file = File::open(path)?;
+        let file_len = file.metadata()?.len();
+        if file_len < MAGIC.len() as u64 + FOOTER_SIZE {
+            return Err(invalid_data("The compressed log file is too short"));
+        }
+
+        file.seek(SeekFrom::End(-(FOOTER_SIZE as i64)))?;
+        let count = file.read_u64::<LittleEndian>()?;
+        let mut magic = [0; MAGIC.len()];
+        file.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_data(
+                "The compressed log file has an unknown format",
+            ));
+        }
+
+        let table_len = count
+            .checked_mul(BLOCK_ENTRY_SIZE)
+            .filter(|len| len + MAGIC.len() as u64 + FOOTER_SIZE <= file_len)
+            .ok_or_else(|| invalid_data("The block table of the compressed log file is invalid"))?;
+        file.seek(SeekFrom::Start(file_len - FOOTER_SIZE - table_len))?;
+        let mut table = vec![0; table_len as usize];
+        file.read_exact(&mut table)?;
+
+        let mut blocks = Vec::new();
+        let mut start = 0;
+        for mut entry in table.chunks_exact(BLOCK_ENTRY_SIZE as usize) {
+            let offset = entry.read_u64::<LittleEndian>()?;
+            let compressed_len = entry.read_u32::<LittleEndian>()? as u64;
+            let len = entry.read_u32::<LittleEndian>()? as u64;
+            blocks.push(Block {
+                offset,
+                compressed_len,
+                start,
+                len,
+            });
+            start += len;
+        }
+
+        Ok(CompressedLog {
+            file,
+            blocks,
+            len: start,
+            position: 0,
+            cache: None,
+        })
+    }
+
+    /// The size of the uncompressed log.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Whether the uncompressed log is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Write the rest of the log from the current position to `writer` in the [snap] frame
+    /// format.
+    ///
+    /// Only the block at the current position has to be compressed again, if it's not read
+    /// from its start. All following blocks are copied as they are.
+    pub fn copy_compressed(&mut self, writer: &mut impl Write) -> io::Result<()> {
+        if self.position >= self.len {
+            return Ok(());
+        }
+
+        let mut index = self.block_at(self.position);
+        let skip = (self.position - self.blocks[index].start) as usize;
+        if skip > 0 {
+            let content = self.load_block(index)?;
+            let mut encoder = FrameEncoder::new(&mut *writer);
+            encoder.write_all(&content[skip..])?;
+            encoder.flush()?;
+            index += 1;
+        }
+
+        for block in &self.blocks[index..] {
+            self.file.seek(SeekFrom::Start(block.offset))?;
+            io::copy(&mut (&mut self.file).take(block.compressed_len), writer)?;
+        }
+        self.position = self.len;
+
+        Ok(())
+    }
+
+    /// Get the index of the block, which contains the given position of the uncompressed log.
+    fn block_at(&self, position: u64) -> usize {
+        self.blocks
+            .partition_point(|block| block.start + block.len <= position)
+    }
+
+    /// Decompress a block, unless it has already been decompressed last.
+    fn load_block(&mut self, index: usize) -> io::Result<&[u8]> {
+        if !matches!(&self.cache, Some((cached, _)) if *cached == index) {
+            let block = self.blocks[index];
+            self.file.seek(SeekFrom::Start(block.offset))?;
+            let mut decoder = FrameDecoder::new((&mut self.file).take(block.compressed_len));
+            let mut content = Vec::with_capacity(block.len as usize);
+            decoder.read_to_end(&mut content)?;
+            if content.len() as u64 != block.len {
+                return Err(invalid_data(
+                    "A block of the compressed log file is corrupted",
+                ));
+            }
+
+            self.cache = Some((index, content));
+        }
+
+        Ok(self
+            .cache
+            .as_ref()
+            .map(|(_, content)| content.as_slice())
+            .unwrap())
+    }
+}
+
+impl Read for CompressedLog {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position >= self.len || buf.is_empty() {
+            return Ok(0);
+        }
+
+        let index = self.block_at(self.position);
+        let skip = (self.position - self.blocks[index].start) as usize;
+        let content = self.load_block(index)?;
+        let read = (content.len() - skip).min(buf.len());
+        buf[..read].copy_from_slice(&content[skip..skip + read]);
+        self.position += read as u64;
+
+        Ok(read)
+    }
+}
+
+impl Seek for CompressedLog {
+    fn seek(&mut self, position: SeekFrom) -> io::Result<u64> {
+        let position = match position {
+            SeekFrom::Start(position) => Some(position),
+            SeekFrom::End(offset) => add_offset(self.len, offset),
+            SeekFrom::Current(offset) => add_offset(self.position, offset),
+        };
+
+        match position {
+            Some(position) => {
+                self.position = position;
+                Ok(position)
+            }
+            None => Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )),
+        }
+    }
+}
+
+/// Apply a relative seek offset to a position.
+pub(crate) fn add_offset(position: u64, offset: i64) -> Option<u64> {
+    if offset >= 0 {
+        position.checked_add(offset as u64)
+    } else {
+        position.checked_sub(offset.unsigned_abs())
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message)
+}
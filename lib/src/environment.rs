@@ -0,0 +1,29 @@
+//! The names of all environment variables, that the daemon injects into the processes of tasks.
+//!
+//! These names are a stable contract. Scripts can rely on them to label their own artifacts or
+//! to call back into the daemon.
+
+/// The id of the task.
+pub const TASK_ID: &str = "PUEUE_TASK_ID";
+/// The label of the task. Empty, if the task doesn't have a label.
+pub const TASK_LABEL: &str = "PUEUE_TASK_LABEL";
+/// The group of the task.
+pub const GROUP: &str = "PUEUE_GROUP";
+/// The id of the worker in the task's group, that runs the task. \
+/// Ids start at `0` and are reused, once a task finished.
+pub const WORKER_ID: &str = "PUEUE_WORKER_ID";
+/// The path to the log file, to which the task's output is written.
+pub const LOG_PATH: &str = "PUEUE_LOG_PATH";
+/// A comma separated list of the ids of all dependencies of the task.
+pub const DEPENDENCIES: &str = "PUEUE_DEPENDENCIES";
+/// A comma separated list of the results of all dependencies, in the same order as
+/// [DEPENDENCIES].
+pub const DEPENDENCY_RESULTS: &str = "PUEUE_DEPENDENCY_RESULTS";
+/// How often the task has been started, including the current run. Starts at `1`.
+pub const ATTEMPT: &str = "PUEUE_ATTEMPT";
+/// The address of the daemon. \
+/// This is either the path to the unix socket or `host:port`, if TCP is used.
+pub const SOCKET: &str = "PUEUE_SOCKET";
+/// A token, which is only valid for the task's current run. \
+/// It allows the task to talk to the daemon, without knowing the shared secret.
+pub const TASK_TOKEN: &str = "PUEUE_TASK_TOKEN";
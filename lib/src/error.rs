@@ -24,6 +24,10 @@ pub enum Error {
     #[error("Error while reading configuration:\n{}", .0)]
     ConfigDeserialization(String),
 
+    /// A command doesn't fit the template of its alias.
+    #[error("Failed to apply alias {}", .0)]
+    Alias(String),
+
     #[error("Some error occurred. {}", .0)]
     Generic(String),
 
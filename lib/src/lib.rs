@@ -13,6 +13,10 @@
 /// Shared module for internal logic!
 /// Contains helper for command aliasing.
 pub mod aliasing;
+/// A seekable compressed format, in which the logs of finished tasks are stored.
+pub mod compressed_log;
+/// The environment variables, that are injected into the processes of tasks.
+pub mod environment;
 /// Pueue lib's own Error implementation.
 pub mod error;
 /// Helper classes to read and write log files of Pueue's tasks.
@@ -30,3 +34,5 @@ pub mod settings;
 pub mod state;
 /// Everything regarding Pueue's task
 pub mod task;
+/// Reusable task templates, which are stored in the daemon's state.
+pub mod template;
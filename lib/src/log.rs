@@ -1,23 +1,209 @@
-use std::fs::{read_dir, remove_file, File};
-use std::io::{self, prelude::*, Read, SeekFrom};
+use std::collections::VecDeque;
+use std::fs::{read_dir, remove_file, rename, File, OpenOptions};
+use std::io::{self, prelude::*, BufReader, Cursor, Read, SeekFrom, Take};
 use std::path::{Path, PathBuf};
 
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use chrono::prelude::*;
 use log::error;
+use regex::{Regex, RegexBuilder};
 use rev_buf_reader::RevBufReader;
+use serde_derive::{Deserialize, Serialize};
 use snap::write::FrameEncoder;
+use strum_macros::{Display, EnumString};
 
+use crate::compressed_log::{add_offset, compress_log, CompressedLog};
 use crate::error::Error;
+use crate::task::OutputPattern;
+
+/// The output streams of a task, which can be captured in separate log files.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Display, Deserialize, Serialize)]
+#[strum(serialize_all = "lowercase")]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+/// What happens, once the log of a task exceeds its size limit.
+#[derive(
+    PartialEq, Eq, Clone, Copy, Debug, Default, Display, EnumString, Deserialize, Serialize,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum LogLimitPolicy {
+    /// Keep the start and the end of the output. Everything in between is dropped.
+    #[default]
+    HeadTail,
+    /// Rotate the log into numbered segments. Only the newest segments are kept.
+    Rotate,
+    /// Kill the task.
+    Kill,
+}
+
+/// The size limit for each log file of a task.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct LogLimit {
+    /// The maximum size of a log file in bytes.
+    pub size: u64,
+    #[serde(default = "Default::default")]
+    pub policy: LogLimitPolicy,
+}
+
+/// A part of a task's log, that's read instead of the whole log.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogRange {
+    /// The first `n` lines.
+    Head(usize),
+    /// The last `n` lines.
+    Tail(usize),
+    /// The lines from `start` up to and including `end`, counted from `1`.
+    /// Everything until the end of the log is read, if `end` isn't set.
+    Lines { start: usize, end: Option<usize> },
+    /// The bytes from `start` up to, but excluding, `end`.
+    /// Everything until the end of the log is read, if `end` isn't set.
+    Bytes { start: u64, end: Option<u64> },
+}
+
+/// The amount of segments, into which logs with a size limit are split.
+/// Each segment gets an equal share of the limit.
+pub const LOG_SEGMENT_COUNT: u64 = 4;
 
 /// Get the path to the log file of a task.
+/// This file contains the interleaved output of stdout and stderr.
 pub fn get_log_path(task_id: usize, path: &Path) -> PathBuf {
     let task_log_dir = path.join("task_logs");
     task_log_dir.join(format!("{task_id}.log"))
 }
 
+/// Get the path to the log file of a single output stream of a task. \
+/// These files only exist, if the output of the task is captured separately.
+pub fn get_stream_log_path(task_id: usize, stream: OutputStream, path: &Path) -> PathBuf {
+    let task_log_dir = path.join("task_logs");
+    task_log_dir.join(format!("{task_id}.{stream}.log"))
+}
+
+/// Get the path to the log file, to which a task's output is currently written. \
+/// If a stream is given, the path to that stream's separate log file is returned.
+pub fn get_log_file_path(task_id: usize, stream: Option<OutputStream>, path: &Path) -> PathBuf {
+    match stream {
+        Some(stream) => get_stream_log_path(task_id, stream, path),
+        None => get_log_path(task_id, path),
+    }
+}
+
+/// Get the path to a rotated segment of a task's log file. \
+/// Segments are numbered in the order in which they've been rotated, starting with `1`.
+pub fn get_log_segment_path(
+    task_id: usize,
+    stream: Option<OutputStream>,
+    segment: usize,
+    path: &Path,
+) -> PathBuf {
+    let task_log_dir = path.join("task_logs");
+    match stream {
+        Some(stream) => task_log_dir.join(format!("{task_id}.{stream}.{segment}.log")),
+        None => task_log_dir.join(format!("{task_id}.{segment}.log")),
+    }
+}
+
+/// Get the numbers of all rotated segments of a task's log file in ascending order.
+pub fn get_log_segments(task_id: usize, stream: Option<OutputStream>, path: &Path) -> Vec<usize> {
+    let prefix = match stream {
+        Some(stream) => format!("{task_id}.{stream}."),
+        None => format!("{task_id}."),
+    };
+
+    let mut segments: Vec<usize> = match read_dir(path.join("task_logs")) {
+        Ok(files) => files
+            .flatten()
+            .filter_map(|file| {
+                let name = file.file_name();
+                let name = name.to_str()?;
+                // Segments of finished tasks might have been compressed.
+                let name = name.strip_suffix(".sz").unwrap_or(name);
+                let segment = name.strip_prefix(&prefix)?.strip_suffix(".log")?;
+                segment.parse().ok()
+            })
+            .collect(),
+        Err(err) => {
+            error!("Failed to read task log directory: {err}");
+            Vec::new()
+        }
+    };
+    segments.sort_unstable();
+    // A segment exists twice for a short moment, while it's being compressed.
+    segments.dedup();
+
+    segments
+}
+
+/// Get the path to the timestamp index of a log file.
+fn get_index_path(log_path: &Path) -> PathBuf {
+    let mut index_path = log_path.as_os_str().to_owned();
+    index_path.push(".index");
+    PathBuf::from(index_path)
+}
+
+/// Get the path to the compressed version of a log file.
+/// Once a finished task's log file has been compressed, only this version of it remains.
+fn get_compressed_path(log_path: &Path) -> PathBuf {
+    let mut compressed_path = log_path.as_os_str().to_owned();
+    compressed_path.push(".sz");
+    PathBuf::from(compressed_path)
+}
+
+/// Check whether a log file exists, either plain or compressed.
+fn log_file_exists(log_path: &Path) -> bool {
+    log_path.exists() || get_compressed_path(log_path).exists()
+}
+
+/// Check whether the output streams of a task have been captured separately.
+pub fn has_separate_output(task_id: usize, path: &Path) -> bool {
+    log_file_exists(&get_stream_log_path(task_id, OutputStream::Stdout, path))
+}
+
+/// Get the path to the timestamp index of a task's log file. \
+/// If a stream is given, the path to the index of that stream's separate log file is returned.
+///
+/// The index contains an entry of [LOG_INDEX_ENTRY_SIZE] bytes for each line of the log file.
+/// Each entry consists of the byte offset of the line in the log file and the time at which
+/// the line has been captured, in milliseconds since the unix epoch.
+/// Both are stored as little endian 64 bit integers.
+/// Rotated segments have an index of their own.
+pub fn get_log_index_path(task_id: usize, stream: Option<OutputStream>, path: &Path) -> PathBuf {
+    get_index_path(&get_log_file_path(task_id, stream, path))
+}
+
+/// Check whether the output of a task has been captured with timestamps.
+pub fn has_timestamps(task_id: usize, path: &Path) -> bool {
+    get_log_index_path(task_id, None, path).exists()
+}
+
+/// The size of a single entry in a log index file.
+pub const LOG_INDEX_ENTRY_SIZE: usize = 16;
+
+/// The format of the timestamps with which log lines are prefixed.
+const LOG_TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.3f";
+
+/// Output patterns are only matched against the first bytes of each line, as lines can be
+/// arbitrarily long.
+pub const MAX_MATCHED_LINE_LENGTH: u64 = 64 * 1024;
+
+/// Get the path to the file, to which detached tasks write their exit code.
+/// This is necessary, as the exit code of a re-adopted process cannot be received via `wait`.
+pub fn get_exit_code_path(task_id: usize, path: &Path) -> PathBuf {
+    let task_log_dir = path.join("task_logs");
+    task_log_dir.join(format!("{task_id}.exit_code"))
+}
+
 /// Create and return the two file handles for the `(stdout, stderr)` log file of a task.
 /// These are two handles to the same file.
+/// The compressed log file of a previous run is removed.
 pub fn create_log_file_handles(task_id: usize, path: &Path) -> Result<(File, File), Error> {
     let log_path = get_log_path(task_id, path);
+    remove_log_file(&log_path)
+        .map_err(|err| Error::IoPathError(log_path.clone(), "removing previous log file", err))?;
     let stdout_handle = File::create(&log_path)
         .map_err(|err| Error::IoPathError(log_path, "getting stdout handle", err))?;
     let stderr_handle = stdout_handle
@@ -27,25 +213,277 @@ pub fn create_log_file_handles(task_id: usize, path: &Path) -> Result<(File, Fil
     Ok((stdout_handle, stderr_handle))
 }
 
-/// Return the file handle for the log file of a task.
-pub fn get_log_file_handle(task_id: usize, path: &Path) -> Result<File, Error> {
-    let path = get_log_path(task_id, path);
-    let handle = File::open(&path)
+/// Create and return the file handles for the separate `(stdout, stderr)` log files of a task.
+pub fn create_stream_log_file_handles(task_id: usize, path: &Path) -> Result<(File, File), Error> {
+    clean_stream_log_files(task_id, path);
+    let stdout_path = get_stream_log_path(task_id, OutputStream::Stdout, path);
+    let stdout_handle = File::create(&stdout_path)
+        .map_err(|err| Error::IoPathError(stdout_path, "creating stdout log file", err))?;
+    let stderr_path = get_stream_log_path(task_id, OutputStream::Stderr, path);
+    let stderr_handle = File::create(&stderr_path)
+        .map_err(|err| Error::IoPathError(stderr_path, "creating stderr log file", err))?;
+
+    Ok((stdout_handle, stderr_handle))
+}
+
+/// Create and return the file handle for the timestamp index of a task's log file.
+pub fn create_log_index_handle(
+    task_id: usize,
+    stream: Option<OutputStream>,
+    path: &Path,
+) -> Result<File, Error> {
+    let index_path = get_log_index_path(task_id, stream, path);
+    File::create(&index_path)
+        .map_err(|err| Error::IoPathError(index_path, "creating log index file", err))
+}
+
+/// Write a single entry to a log index.
+/// `offset` is the position of the line in the log file.
+pub fn write_log_index_entry(
+    writer: &mut impl Write,
+    offset: u64,
+    time: DateTime<Local>,
+) -> io::Result<()> {
+    writer.write_u64::<LittleEndian>(offset)?;
+    writer.write_i64::<LittleEndian>(time.timestamp_millis())
+}
+
+/// Read all entries of the timestamp index of a log file.
+/// A partially written entry at the end of the file is ignored.
+fn read_log_index(log_path: &Path) -> Result<Vec<(u64, DateTime<Local>)>, Error> {
+    let index_path = get_index_path(log_path);
+    let mut content = Vec::new();
+    File::open(&index_path)
+        .and_then(|mut file| file.read_to_end(&mut content))
+        .map_err(|err| Error::IoPathError(index_path, "reading log index file", err))?;
+
+    let mut entries = Vec::new();
+    for mut entry in content.chunks_exact(LOG_INDEX_ENTRY_SIZE) {
+        let offset = entry.read_u64::<LittleEndian>().unwrap();
+        let millis = entry.read_i64::<LittleEndian>().unwrap();
+        let time = match Local.timestamp_millis_opt(millis).single() {
+            Some(time) => time,
+            None => {
+                return Err(Error::Generic(format!(
+                    "Found invalid timestamp {millis} in log index {log_path:?}"
+                )))
+            }
+        };
+        entries.push((offset, time));
+    }
+
+    Ok(entries)
+}
+
+/// Return the file handle for the log file of a task. \
+/// If a stream is given, the handle for that stream's separate log file is returned instead.
+pub fn get_log_file_handle(
+    task_id: usize,
+    path: &Path,
+    stream: Option<OutputStream>,
+) -> Result<LogFile, Error> {
+    let path = get_log_file_path(task_id, stream, path);
+    let handle = LogFile::open(&path)
         .map_err(|err| Error::IoPathError(path, "getting log file handle", err))?;
 
     Ok(handle)
 }
 
+/// A single log file, which is either plain or has been compressed after its task finished.
+pub enum LogFile {
+    Plain(File),
+    Compressed(CompressedLog),
+}
+
+impl LogFile {
+    /// Open a log file. If there's no plain log file, its compressed version is opened.
+    pub fn open(log_path: &Path) -> io::Result<LogFile> {
+        let err = match File::open(log_path) {
+            Ok(file) => return Ok(LogFile::Plain(file)),
+            Err(err) => err,
+        };
+        if err.kind() != io::ErrorKind::NotFound {
+            return Err(err);
+        }
+
+        // The plain log file is only removed, once the compressed file is complete.
+        match CompressedLog::open(&get_compressed_path(log_path)) {
+            Ok(log) => Ok(LogFile::Compressed(log)),
+            Err(compressed_err) if compressed_err.kind() == io::ErrorKind::NotFound => Err(err),
+            Err(compressed_err) => Err(compressed_err),
+        }
+    }
+
+    /// The size of the (uncompressed) log.
+    pub fn len(&self) -> u64 {
+        match self {
+            LogFile::Plain(file) => file.metadata().map(|metadata| metadata.len()).unwrap_or(0),
+            LogFile::Compressed(log) => log.len(),
+        }
+    }
+
+    /// Whether the log is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Write the rest of the log from the current position to `writer`, compressed with [snap].
+    /// Compressed log files are mostly copied as they are.
+    pub fn copy_compressed(&mut self, writer: &mut impl Write) -> io::Result<()> {
+        match self {
+            LogFile::Plain(file) => {
+                let mut compressor = FrameEncoder::new(writer);
+                io::copy(file, &mut compressor)?;
+                compressor.flush()
+            }
+            LogFile::Compressed(log) => log.copy_compressed(writer),
+        }
+    }
+}
+
+impl Read for LogFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            LogFile::Plain(file) => file.read(buf),
+            LogFile::Compressed(log) => log.read(buf),
+        }
+    }
+}
+
+impl Seek for LogFile {
+    fn seek(&mut self, position: SeekFrom) -> io::Result<u64> {
+        match self {
+            LogFile::Plain(file) => file.seek(position),
+            LogFile::Compressed(log) => log.seek(position),
+        }
+    }
+}
+
+/// Compress all log files of a finished task, including its separate output and rotated
+/// segments. The timestamp indices stay as they are, as they point into the uncompressed log.
+///
+/// Each log file is first compressed into a temporary file. Only once that's complete, it
+/// replaces the plain log file.
+pub fn compress_task_logs(task_id: usize, path: &Path) -> Result<(), Error> {
+    for stream in [None, Some(OutputStream::Stdout), Some(OutputStream::Stderr)] {
+        let mut log_paths: Vec<PathBuf> = get_log_segments(task_id, stream, path)
+            .into_iter()
+            .map(|segment| get_log_segment_path(task_id, stream, segment, path))
+            .collect();
+        log_paths.push(get_log_file_path(task_id, stream, path));
+
+        for log_path in log_paths {
+            if log_path.exists() {
+                compress_log_file(&log_path)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Compress a single log file and remove the plain log file afterwards.
+fn compress_log_file(log_path: &Path) -> Result<(), Error> {
+    let compressed_path = get_compressed_path(log_path);
+    let mut temporary_path = compressed_path.as_os_str().to_owned();
+    temporary_path.push(".tmp");
+    let temporary_path = PathBuf::from(temporary_path);
+
+    if let Err(err) = compress_log(log_path, &temporary_path) {
+        let _ = remove_file(&temporary_path);
+        // The log has been removed before it could be compressed.
+        if err.kind() == io::ErrorKind::NotFound {
+            return Ok(());
+        }
+        return Err(Error::IoPathError(
+            log_path.to_path_buf(),
+            "compressing log file",
+            err,
+        ));
+    }
+
+    rename(&temporary_path, &compressed_path).map_err(|err| {
+        Error::IoPathError(compressed_path.clone(), "moving compressed log file", err)
+    })?;
+    match remove_file(log_path) {
+        Ok(()) => Ok(()),
+        // The log has been removed while it was compressed. Its compressed version has to go too.
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            let _ = remove_file(&compressed_path);
+            Ok(())
+        }
+        Err(err) => Err(Error::IoPathError(
+            log_path.to_path_buf(),
+            "removing log file",
+            err,
+        )),
+    }
+}
+
+/// Remove a log file in both its plain and compressed form.
+fn remove_log_file(log_path: &Path) -> io::Result<()> {
+    for path in [log_path.to_path_buf(), get_compressed_path(log_path)] {
+        match remove_file(path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+            _ => (),
+        }
+    }
+
+    Ok(())
+}
+
 /// Remove the the log files of a task.
 pub fn clean_log_handles(task_id: usize, path: &Path) {
+    let exit_code_path = get_exit_code_path(task_id, path);
+    if exit_code_path.exists() {
+        if let Err(err) = remove_file(exit_code_path) {
+            error!("Failed to remove exit code file for task {task_id} with error {err:?}");
+        };
+    }
+
+    clean_log_segments(task_id, path);
+    clean_stream_log_files(task_id, path);
+    clean_log_index_files(task_id, path);
+
     let path = get_log_path(task_id, path);
-    if path.exists() {
-        if let Err(err) = remove_file(path) {
-            error!("Failed to remove stdout file for task {task_id} with error {err:?}");
+    if let Err(err) = remove_log_file(&path) {
+        error!("Failed to remove stdout file for task {task_id} with error {err:?}");
+    };
+}
+
+/// Remove the separate log files of a task's output streams, if they exist.
+pub fn clean_stream_log_files(task_id: usize, path: &Path) {
+    for stream in [OutputStream::Stdout, OutputStream::Stderr] {
+        let path = get_stream_log_path(task_id, stream, path);
+        if let Err(err) = remove_log_file(&path) {
+            error!("Failed to remove {stream} file for task {task_id} with error {err:?}");
         };
     }
 }
 
+/// Remove all rotated segments of a task's log files and their indices.
+pub fn clean_log_segments(task_id: usize, path: &Path) {
+    for stream in [None, Some(OutputStream::Stdout), Some(OutputStream::Stderr)] {
+        for segment in get_log_segments(task_id, stream, path) {
+            if let Err(err) = remove_log_segment(task_id, stream, segment, path) {
+                error!("Failed to remove log segment of task {task_id}: {err:?}");
+            };
+        }
+    }
+}
+
+/// Remove the timestamp indices of a task's log files, if they exist.
+pub fn clean_log_index_files(task_id: usize, path: &Path) {
+    for stream in [None, Some(OutputStream::Stdout), Some(OutputStream::Stderr)] {
+        let path = get_log_index_path(task_id, stream, path);
+        if path.exists() {
+            if let Err(err) = remove_file(path) {
+                error!("Failed to remove log index for task {task_id} with error {err:?}");
+            };
+        }
+    }
+}
+
 /// Return the output of a task. \
 /// Task output is compressed using [snap] to save some memory and bandwidth.
 /// Return type is `(Vec<u8>, bool)`
@@ -55,28 +493,312 @@ pub fn clean_log_handles(task_id: usize, path: &Path) {
 pub fn read_and_compress_log_file(
     task_id: usize,
     path: &Path,
-    lines: Option<usize>,
+    range: Option<LogRange>,
+    stream: Option<OutputStream>,
 ) -> Result<(Vec<u8>, bool), Error> {
-    let mut file = get_log_file_handle(task_id, path)?;
+    let (mut file, output_complete) = open_log_range(task_id, path, range, stream)?;
 
     let mut content = Vec::new();
+    let position = file
+        .get_mut()
+        .stream_position()
+        .map_err(|err| Error::IoError("getting log position".to_string(), err))?;
+    if position + file.limit() < file.get_ref().len() {
+        // The range ends before the end of the log, which is why it has to be compressed anew.
+        let mut compressor = FrameEncoder::new(&mut content);
+        io::copy(&mut file, &mut compressor)
+            .map_err(|err| Error::IoError("compressing log output".to_string(), err))?;
+    } else {
+        // Compress the rest of the log and pipe it into the snappy compressor.
+        // Log files, that are already compressed, are mostly copied as they are.
+        file.get_mut()
+            .copy_compressed(&mut content)
+            .map_err(|err| Error::IoError("compressing log output".to_string(), err))?;
+    }
 
-    // Indicates whether the full log output is shown or just the last part of it.
+    Ok((content, output_complete))
+}
+
+/// Open the log of a task and move the reader to the start of the given range.
+/// The returned reader stops at the end of the range.
+///
+/// Return type is `(Take<LogReader>, bool)`
+/// - `Take<LogReader>` the reader for the requested part of the log.
+/// - `bool` Whether the range covers the task's full output.
+pub fn open_log_range(
+    task_id: usize,
+    path: &Path,
+    range: Option<LogRange>,
+    stream: Option<OutputStream>,
+) -> Result<(Take<LogReader>, bool), Error> {
+    let mut file = LogReader::open(task_id, path, stream)?;
+
+    let (len, output_complete) = match range {
+        Some(range) => seek_to_range(&mut file, range)?,
+        None => (file.len(), true),
+    };
+
+    Ok((file.take(len), output_complete))
+}
+
+/// Open the output of a task, whose log has been indexed with timestamps. \
+/// Only lines that have been captured at or after `since` are returned.
+/// If `timestamps` is set, each line is prefixed with the time at which it has been captured.
+/// If a range is given, only that part of the resulting output is returned.
+///
+/// The output is read line by line, so it never has to be kept in memory as a whole.
+///
+/// Return type is `(Take<TimestampedLogReader>, bool)`
+/// - `Take<TimestampedLogReader>` the reader for the uncompressed task output.
+/// - `bool` Whether all requested lines are read.
+///   `false` indicate that the log output has been truncated to the range.
+pub fn open_timestamped_log(
+    task_id: usize,
+    path: &Path,
+    stream: Option<OutputStream>,
+    range: Option<LogRange>,
+    since: Option<DateTime<Local>>,
+    timestamps: bool,
+) -> Result<(Take<TimestampedLogReader>, bool), Error> {
+    // The log might have been rotated into multiple segments, which are read in order.
+    let mut log_paths: Vec<PathBuf> = get_log_segments(task_id, stream, path)
+        .into_iter()
+        .map(|segment| get_log_segment_path(task_id, stream, segment, path))
+        .collect();
+    log_paths.push(get_log_file_path(task_id, stream, path));
+
+    // Read the indices first, so every indexed line is also present in the log files.
+    // Each entry is remembered together with the log file it belongs to.
+    let mut index = Vec::new();
+    for (file_index, log_path) in log_paths.iter().enumerate() {
+        let entries = match read_log_index(log_path) {
+            Ok(entries) => entries,
+            // Old segments might have been dropped in the meantime.
+            Err(_) if file_index + 1 < log_paths.len() => continue,
+            Err(err) => return Err(err),
+        };
+        index.extend(
+            entries
+                .into_iter()
+                .map(|(offset, time)| (file_index, offset, time)),
+        );
+    }
+
+    // The index is ordered by time, which allows us to search for the first requested line.
+    let first = since.map_or(0, |since| {
+        index.partition_point(|(_, _, time)| *time < since)
+    });
+    index.drain(..first);
+
+    // Only return the last few lines if requested.
+    // They're selected via the index, so only the requested lines have to be read.
     let mut output_complete = true;
+    if let Some(LogRange::Tail(lines)) = range {
+        if index.len() > lines {
+            index.drain(..index.len() - lines);
+            output_complete = false;
+        }
+    }
+
+    let range = match range {
+        None | Some(LogRange::Tail(_)) => {
+            let reader = TimestampedLogReader::new(log_paths, index, timestamps);
+            return Ok((reader.take(u64::MAX), output_complete));
+        }
+        Some(range) => range,
+    };
+
+    // The bounds of all other ranges are only known after reading the output once.
+    let reader = TimestampedLogReader::new(log_paths.clone(), index.clone(), timestamps);
+    let (start, end, len) = find_range(reader, range)
+        .map_err(|err| Error::IoError("reading log file".to_string(), err))?;
+
+    let mut reader = TimestampedLogReader::new(log_paths, index, timestamps);
+    io::copy(&mut (&mut reader).take(start), &mut io::sink())
+        .map_err(|err| Error::IoError("reading log file".to_string(), err))?;
+
+    Ok((reader.take(end - start), start == 0 && end == len))
+}
+
+/// Return the output of a task, whose log has been indexed with timestamps. \
+/// This is the same as [open_timestamped_log], but the whole output is read into memory.
+///
+/// Return type is `(Vec<u8>, bool)`
+/// - `Vec<u8>` the uncompressed task output.
+/// - `bool` Whether all requested lines have been read.
+///   `false` indicate that the log output has been truncated to the range.
+pub fn read_timestamped_log(
+    task_id: usize,
+    path: &Path,
+    stream: Option<OutputStream>,
+    range: Option<LogRange>,
+    since: Option<DateTime<Local>>,
+    timestamps: bool,
+) -> Result<(Vec<u8>, bool), Error> {
+    let (mut reader, output_complete) =
+        open_timestamped_log(task_id, path, stream, range, since, timestamps)?;
+
+    let mut output = Vec::new();
+    reader
+        .read_to_end(&mut output)
+        .map_err(|err| Error::IoError("reading log file".to_string(), err))?;
+
+    Ok((output, output_complete))
+}
+
+/// Find the bounds of a range of lines or bytes in the output of a reader, that can't seek.
+/// The semantics are the same as those of [seek_to_range].
+///
+/// Return type is `(u64, u64, u64)`: the start and the end of the range and the total size.
+fn find_range(mut reader: impl Read, range: LogRange) -> io::Result<(u64, u64, u64)> {
+    // The amount of newlines in front of the start and the end of the range.
+    let targets = match range {
+        LogRange::Head(lines) => [Some(0), Some(lines)],
+        LogRange::Lines { start, end } => {
+            let first = start.saturating_sub(1);
+            let last = end.map(|end| first + (end + 1).saturating_sub(start.max(1)));
+            [Some(first), last]
+        }
+        LogRange::Tail(_) | LogRange::Bytes { .. } => [None, None],
+    };
+    // Remember the position right after the targeted amount of newlines.
+    let mark = |bounds: &mut [Option<u64>; 2], newlines: usize, position: u64| {
+        for (bound, target) in bounds.iter_mut().zip(targets) {
+            if bound.is_none() && target == Some(newlines) {
+                *bound = Some(position);
+            }
+        }
+    };
+
+    let mut bounds = [None, None];
+    mark(&mut bounds, 0, 0);
+    let mut newlines = 0;
+    let mut len = 0;
+    let mut buffer = vec![0; 4096];
+    loop {
+        let read_bytes = reader.read(&mut buffer)?;
+        if read_bytes == 0 {
+            break;
+        }
+
+        for (index, byte) in buffer[..read_bytes].iter().enumerate() {
+            if *byte == b'\n' {
+                newlines += 1;
+                mark(&mut bounds, newlines, len + index as u64 + 1);
+            }
+        }
+        len += read_bytes as u64;
+    }
+
+    let (start, end) = match range {
+        LogRange::Tail(_) => (0, len),
+        LogRange::Head(_) | LogRange::Lines { .. } => {
+            let start = bounds[0].unwrap_or(len);
+            let end = match targets[1] {
+                Some(_) => bounds[1].unwrap_or(len),
+                None => len,
+            };
+            (start, end)
+        }
+        LogRange::Bytes { start, end } => {
+            let start = start.min(len);
+            (start, end.map_or(len, |end| end.clamp(start, len)))
+        }
+    };
+
+    Ok((start, end, len))
+}
+
+/// Reads the output of a task line by line via the timestamp index of its log.
+/// Each line may be prefixed with the time at which it has been captured.
+pub struct TimestampedLogReader {
+    log_paths: Vec<PathBuf>,
+    /// The selected index entries, each with the log file it belongs to.
+    entries: Vec<(usize, u64, DateTime<Local>)>,
+    /// The index of the next entry that's read.
+    next: usize,
+    timestamps: bool,
+    /// The log file that's currently read, together with its index in `log_paths`.
+    file: Option<(usize, LogFile)>,
+    /// The remaining part of the line that's currently read.
+    line: Cursor<Vec<u8>>,
+}
+
+impl TimestampedLogReader {
+    fn new(
+        log_paths: Vec<PathBuf>,
+        entries: Vec<(usize, u64, DateTime<Local>)>,
+        timestamps: bool,
+    ) -> Self {
+        TimestampedLogReader {
+            log_paths,
+            entries,
+            next: 0,
+            timestamps,
+            file: None,
+            line: Cursor::new(Vec::new()),
+        }
+    }
+
+    /// Read the line of the next entry. Return `false`, once all entries have been read.
+    fn read_next_line(&mut self) -> io::Result<bool> {
+        let (file_index, offset, time) = match self.entries.get(self.next) {
+            Some(entry) => *entry,
+            None => return Ok(false),
+        };
+        self.next += 1;
+
+        // Lines of the same file follow each other, so we only have to seek in new files.
+        let file = match self.file.take() {
+            Some((index, file)) if index == file_index => file,
+            _ => {
+                let mut file = LogFile::open(&self.log_paths[file_index])?;
+                file.seek(SeekFrom::Start(offset))?;
+                file
+            }
+        };
+        let file = &mut self.file.insert((file_index, file)).1;
+
+        // The last line of a file reaches to its end.
+        let len = match self.entries.get(self.next) {
+            Some((next_file, next, _)) if *next_file == file_index => next - offset,
+            _ => u64::MAX,
+        };
+
+        let mut line = Vec::new();
+        if self.timestamps {
+            let _ = write!(line, "[{}] ", time.format(LOG_TIMESTAMP_FORMAT));
+        }
+        // The file might have been truncated in the meantime.
+        file.take(len).read_to_end(&mut line)?;
+        self.line = Cursor::new(line);
 
-    // Move the cursor to the last few lines of both files.
-    if let Some(lines) = lines {
-        output_complete = seek_to_last_lines(&mut file, lines)?;
+        Ok(true)
     }
+}
+
+impl Read for TimestampedLogReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let read_bytes = self.line.read(buf)?;
+            if read_bytes > 0 || buf.is_empty() || !self.read_next_line()? {
+                return Ok(read_bytes);
+            }
+        }
+    }
+}
 
-    // Compress the full log input and pipe it into the snappy compressor
+/// Compress the output of a task using [snap].
+pub fn compress_log_output(output: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut content = Vec::new();
     {
         let mut compressor = FrameEncoder::new(&mut content);
-        io::copy(&mut file, &mut compressor)
+        compressor
+            .write_all(output)
             .map_err(|err| Error::IoError("compressing log output".to_string(), err))?;
     }
 
-    Ok((content, output_complete))
+    Ok(content)
 }
 
 /// Return the last lines of of a task's output. \
@@ -86,11 +808,169 @@ pub fn read_last_log_file_lines(
     task_id: usize,
     path: &Path,
     lines: usize,
+    stream: Option<OutputStream>,
 ) -> Result<String, Error> {
-    let mut file = get_log_file_handle(task_id, path)?;
+    let mut file = LogReader::open(task_id, path, stream)?;
 
     // Get the last few lines of both files
-    Ok(read_last_lines(&mut file, lines))
+    file.read_last_lines(lines)
+}
+
+/// A single line of a task's log, that's returned by [search_log].
+#[derive(PartialEq, Eq, Clone, Debug, Deserialize, Serialize)]
+pub struct LogLine {
+    /// The number of the line in the log, starting at `1`.
+    pub number: usize,
+    pub text: String,
+    /// Whether the line matches the search or is only part of the context of a match.
+    pub matches: bool,
+}
+
+/// Build the pattern, with which logs are searched by [search_log]. \
+/// The pattern is a regular expression, unless `fixed_strings` is set.
+pub fn build_search_pattern(
+    pattern: &str,
+    fixed_strings: bool,
+    ignore_case: bool,
+) -> Result<Regex, regex::Error> {
+    let pattern = if fixed_strings {
+        regex::escape(pattern)
+    } else {
+        pattern.to_string()
+    };
+
+    RegexBuilder::new(&pattern)
+        .case_insensitive(ignore_case)
+        .build()
+}
+
+/// Search the log of a task for lines, that match `pattern`.
+///
+/// Each matching line is returned together with up to `context` lines before and after it.
+/// Lines are returned in order and only once, even if the context of several matches overlaps.
+/// The search stops after `max_matches` matching lines.
+/// Nothing is returned, if the task doesn't have a log.
+pub fn search_log(
+    task_id: usize,
+    path: &Path,
+    pattern: &Regex,
+    context: usize,
+    max_matches: usize,
+) -> Result<Vec<LogLine>, Error> {
+    if max_matches == 0 || !log_file_exists(&get_log_path(task_id, path)) {
+        return Ok(Vec::new());
+    }
+
+    let mut reader = BufReader::new(LogReader::open(task_id, path, None)?);
+
+    let mut lines = Vec::new();
+    // The lines before the current line, which are returned as context of the next match.
+    let mut before: VecDeque<LogLine> = VecDeque::new();
+    // The amount of lines after the last match, that are still returned as its context.
+    let mut after = 0;
+    let mut buffer = Vec::new();
+    let mut number = 0;
+    let mut found = 0;
+    // Once enough lines matched, only the context of the last match is still read.
+    while found < max_matches || after > 0 {
+        buffer.clear();
+        let read = reader
+            .read_until(b'\n', &mut buffer)
+            .map_err(|err| Error::IoError("reading log file".to_string(), err))?;
+        if read == 0 {
+            break;
+        }
+        number += 1;
+
+        if buffer.last() == Some(&b'\n') {
+            buffer.pop();
+        }
+        let text = String::from_utf8_lossy(&buffer).into_owned();
+        let matches = found < max_matches && pattern.is_match(&text);
+
+        let line = LogLine {
+            number,
+            text,
+            matches,
+        };
+        if matches {
+            lines.extend(before.drain(..));
+            lines.push(line);
+            after = context;
+            found += 1;
+        } else if after > 0 {
+            lines.push(line);
+            after -= 1;
+        } else if context > 0 {
+            if before.len() == context {
+                before.pop_front();
+            }
+            before.push_back(line);
+        }
+    }
+
+    Ok(lines)
+}
+
+/// Check whether any line in the log of a task matches `pattern`. \
+/// The log is read line by line, so it's never kept in memory as a whole.
+/// Only the first [MAX_MATCHED_LINE_LENGTH] bytes of each line are matched.
+/// A task without a log doesn't contain anything.
+pub fn log_contains(task_id: usize, path: &Path, pattern: &OutputPattern) -> Result<bool, Error> {
+    if !log_file_exists(&get_log_path(task_id, path)) {
+        return Ok(false);
+    }
+
+    let mut reader = BufReader::new(LogReader::open(task_id, path, None)?);
+    let mut buffer = Vec::new();
+    loop {
+        buffer.clear();
+        let read = read_bounded_line(&mut reader, &mut buffer)
+            .map_err(|err| Error::IoError("reading log file".to_string(), err))?;
+        if read == 0 {
+            return Ok(false);
+        }
+
+        if pattern.is_match(&String::from_utf8_lossy(&buffer)) {
+            return Ok(true);
+        }
+    }
+}
+
+/// Read the next line without its newline into `buffer`.
+/// Only the first [MAX_MATCHED_LINE_LENGTH] bytes of a line are kept, the rest of it is skipped.
+/// That way, a single huge line never has to be kept in memory.
+///
+/// Returns the amount of bytes that have been read, including the skipped ones.
+fn read_bounded_line<R: BufRead>(reader: &mut R, buffer: &mut Vec<u8>) -> io::Result<usize> {
+    let read = reader
+        .by_ref()
+        .take(MAX_MATCHED_LINE_LENGTH)
+        .read_until(b'\n', buffer)?;
+    if buffer.last() == Some(&b'\n') {
+        buffer.pop();
+        return Ok(read);
+    }
+
+    let mut skipped = 0;
+    loop {
+        let available = reader.fill_buf()?;
+        if available.is_empty() {
+            return Ok(read + skipped);
+        }
+
+        match available.iter().position(|byte| *byte == b'\n') {
+            Some(position) => {
+                reader.consume(position + 1);
+                return Ok(read + skipped + position + 1);
+            }
+            None => {
+                let length = available.len();
+                reader.consume(length);
+                skipped += length;
+            }
+        }
+    }
 }
 
 /// Remove all files in the log directory.
@@ -116,7 +996,7 @@ pub fn reset_task_log_directory(path: &Path) -> Result<(), Error> {
 // We allow this clippy check.
 // The iterators cannot be chained, as RevBufReader.lines doesn't implement the necessary traits.
 #[allow(clippy::needless_collect)]
-pub fn read_last_lines(file: &mut File, amount: usize) -> String {
+pub fn read_last_lines<R: Read + Seek>(file: &mut R, amount: usize) -> String {
     let reader = RevBufReader::new(file);
 
     let lines: Vec<String> = reader
@@ -133,7 +1013,94 @@ pub fn read_last_lines(file: &mut File, amount: usize) -> String {
 ///
 /// The `bool` return value indicates whether we seeked to the start of the file (there were less
 /// lines than the limit). `true` means that the handle is now at the very start of the file.
-pub fn seek_to_last_lines(file: &mut File, amount: usize) -> Result<bool, Error> {
+pub fn seek_to_last_lines<R: Read + Seek>(file: &mut R, amount: usize) -> Result<bool, Error> {
+    Ok(seek_to_last_newlines(file, amount + 1)?.is_some())
+}
+
+/// Move the cursor to the start of the given range of the file.
+///
+/// Return type is `(u64, bool)`
+/// - `u64` the size of the range in bytes, starting at the new position.
+/// - `bool` Whether the range covers the whole file.
+pub fn seek_to_range<R: Read + Seek>(file: &mut R, range: LogRange) -> Result<(u64, bool), Error> {
+    let seek_error = |err| Error::IoError("seeking in log file".to_string(), err);
+    // The file size might change while we're reading the file. Hence we have to save it now.
+    let len = file.seek(SeekFrom::End(0)).map_err(seek_error)?;
+
+    let (start, end) = match range {
+        LogRange::Tail(lines) => {
+            seek_to_last_lines(file, lines)?;
+            (file.stream_position().map_err(seek_error)?, len)
+        }
+        LogRange::Head(lines) => {
+            file.seek(SeekFrom::Start(0)).map_err(seek_error)?;
+            (0, skip_lines(file, lines)?.unwrap_or(len))
+        }
+        LogRange::Lines { start, end } => {
+            file.seek(SeekFrom::Start(0)).map_err(seek_error)?;
+            let first = skip_lines(file, start.saturating_sub(1))?.unwrap_or(len);
+            let last = match end {
+                Some(end) if end < start.max(1) => first,
+                Some(end) => skip_lines(file, end - start.max(1) + 1)?.unwrap_or(len),
+                None => len,
+            };
+            (first, last.min(len))
+        }
+        LogRange::Bytes { start, end } => {
+            let start = start.min(len);
+            (start, end.map_or(len, |end| end.clamp(start, len)))
+        }
+    };
+    file.seek(SeekFrom::Start(start)).map_err(seek_error)?;
+
+    Ok((end - start, start == 0 && end == len))
+}
+
+/// Move the cursor forward to the position right after the `amount`th newline.
+///
+/// The new position is returned. `None` is returned, if the file ends before.
+fn skip_lines<R: Read + Seek>(file: &mut R, amount: usize) -> Result<Option<u64>, Error> {
+    let mut position = file
+        .stream_position()
+        .map_err(|err| Error::IoError("seeking in log file".to_string(), err))?;
+    let mut found_lines = 0;
+    let mut buffer = vec![0; 4096];
+    while found_lines < amount {
+        let read_bytes = file
+            .read(&mut buffer)
+            .map_err(|err| Error::IoError("reading next log chunk".to_string(), err))?;
+        if read_bytes == 0 {
+            return Ok(None);
+        }
+
+        for (index, byte) in buffer[..read_bytes].iter().enumerate() {
+            if *byte != b'\n' {
+                continue;
+            }
+            found_lines += 1;
+            if found_lines == amount {
+                // We most likely read past the newline, so we have to go back to it.
+                let newline_position = position + index as u64 + 1;
+                file.seek(SeekFrom::Start(newline_position))
+                    .map_err(|err| Error::IoError("seeking in log file".to_string(), err))?;
+                return Ok(Some(newline_position));
+            }
+        }
+        position += read_bytes as u64;
+    }
+
+    Ok(Some(position))
+}
+
+/// Seek the cursor of the current file to the position right after the `amount`th newline
+/// from the back of the file.
+///
+/// If there are fewer newlines, the cursor is moved to the start of the file and the amount of
+/// found newlines is returned.
+fn seek_to_last_newlines<R: Read + Seek>(
+    file: &mut R,
+    amount: usize,
+) -> Result<Option<usize>, Error> {
     let mut reader = RevBufReader::new(file);
     // The position from which the RevBufReader starts reading.
     // The file size might change while we're reading the file. Hence we have to save it now.
@@ -158,7 +1125,11 @@ pub fn seek_to_last_lines(file: &mut File, amount: usize) -> Result<bool, Error>
         // Return if there's nothing left to read.
         // We hit the start of the file and read fewer lines then specified.
         if read_bytes == 0 {
-            return Ok(true);
+            reader
+                .get_mut()
+                .seek(SeekFrom::Start(0))
+                .map_err(|err| Error::IoError("seeking to start of file".to_string(), err))?;
+            return Ok(Some(found_lines));
         }
 
         // Check each byte for a newline.
@@ -174,7 +1145,7 @@ pub fn seek_to_last_lines(file: &mut File, amount: usize) -> Result<bool, Error>
             found_lines += 1;
 
             // We haven't visited the requested amount of lines yet.
-            if found_lines != amount + 1 {
+            if found_lines != amount {
                 continue;
             }
 
@@ -200,5 +1171,292 @@ pub fn seek_to_last_lines(file: &mut File, amount: usize) -> Result<bool, Error>
         }
     }
 
-    Ok(false)
+    Ok(None)
+}
+
+/// Reads the log of a task, which might have been rotated into multiple segments.
+/// The rotated segments are read in order, followed by the current log file.
+pub struct LogReader {
+    files: Vec<LogFile>,
+    /// The index of the file that's currently read.
+    current: usize,
+}
+
+impl LogReader {
+    /// Open all segments of a task's log. \
+    /// If a stream is given, the segments of that stream's separate log file are opened.
+    pub fn open(
+        task_id: usize,
+        path: &Path,
+        stream: Option<OutputStream>,
+    ) -> Result<LogReader, Error> {
+        let mut files = Vec::new();
+        for segment in get_log_segments(task_id, stream, path) {
+            let segment_path = get_log_segment_path(task_id, stream, segment, path);
+            match LogFile::open(&segment_path) {
+                Ok(file) => files.push(file),
+                // The segment has been dropped in the meantime.
+                Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+                Err(err) => {
+                    return Err(Error::IoPathError(segment_path, "opening log segment", err))
+                }
+            }
+        }
+        files.push(get_log_file_handle(task_id, path, stream)?);
+
+        Ok(LogReader { files, current: 0 })
+    }
+
+    /// The combined size of all segments.
+    pub fn len(&self) -> u64 {
+        self.files.iter().map(LogFile::len).sum()
+    }
+
+    /// Whether all segments are empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Move the reader to the beginning of the line that's located `amount` newlines from the
+    /// back of the log. This works just like [seek_to_last_lines], but across all segments.
+    pub fn seek_to_last_lines(&mut self, amount: usize) -> Result<bool, Error> {
+        let mut newlines = amount + 1;
+        for (index, file) in self.files.iter_mut().enumerate().rev() {
+            match seek_to_last_newlines(file, newlines)? {
+                Some(found) => newlines -= found,
+                None => {
+                    self.current = index;
+                    return Ok(false);
+                }
+            }
+        }
+
+        self.current = 0;
+        Ok(true)
+    }
+
+    /// Write the rest of the log to `writer`, compressed with [snap].
+    /// This works just like [LogFile::copy_compressed], but across all segments.
+    pub fn copy_compressed(&mut self, writer: &mut impl Write) -> io::Result<()> {
+        for file in self.files.iter_mut().skip(self.current) {
+            file.copy_compressed(writer)?;
+        }
+        self.current = self.files.len();
+
+        Ok(())
+    }
+
+    /// Read the last `amount` lines of the log to a string.
+    /// This works just like [read_last_lines], but across all segments.
+    pub fn read_last_lines(&mut self, amount: usize) -> Result<String, Error> {
+        if self.files.len() == 1 {
+            return Ok(read_last_lines(&mut self.files[0], amount));
+        }
+
+        self.seek_to_last_lines(amount)?;
+        let mut output = Vec::new();
+        self.read_to_end(&mut output)
+            .map_err(|err| Error::IoError("reading log file".to_string(), err))?;
+        if output.last() == Some(&b'\n') {
+            output.pop();
+        }
+
+        Ok(String::from_utf8_lossy(&output).into_owned())
+    }
+}
+
+impl Read for LogReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while let Some(file) = self.files.get_mut(self.current) {
+            let read = file.read(buf)?;
+            if read > 0 || buf.is_empty() {
+                return Ok(read);
+            }
+            self.current += 1;
+        }
+
+        Ok(0)
+    }
+}
+
+impl Seek for LogReader {
+    /// Move to a position of the log, as if all segments were a single file.
+    fn seek(&mut self, position: SeekFrom) -> io::Result<u64> {
+        let target = match position {
+            SeekFrom::Start(position) => Some(position),
+            SeekFrom::End(offset) => add_offset(self.len(), offset),
+            SeekFrom::Current(offset) => {
+                let previous: u64 = self.files.iter().take(self.current).map(LogFile::len).sum();
+                let current = match self.files.get_mut(self.current) {
+                    Some(file) => file.stream_position()?,
+                    None => 0,
+                };
+                add_offset(previous + current, offset)
+            }
+        };
+        let target = target.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )
+        })?;
+
+        // Find the segment, that contains the target position.
+        // Positions past the end of the log are located in the last file.
+        let mut start = 0;
+        let last = self.files.len() - 1;
+        for (index, file) in self.files.iter_mut().enumerate() {
+            if index == last || target < start + file.len() {
+                self.current = index;
+                file.seek(SeekFrom::Start(target - start))?;
+                break;
+            }
+            start += file.len();
+        }
+        // All following segments have to be read from their start.
+        for file in self.files.iter_mut().skip(self.current + 1) {
+            file.seek(SeekFrom::Start(0))?;
+        }
+
+        Ok(target)
+    }
+}
+
+/// Follows the log of a running task, even if it's rotated into segments in the meantime.
+pub struct LogFollower {
+    task_id: usize,
+    path: PathBuf,
+    /// The handle to the log file, that's currently followed.
+    /// This is `None`, if the log has just been rotated and the new file doesn't exist yet.
+    file: Option<LogFile>,
+    /// The newest rotated segment, at the time the current file has been opened.
+    newest_segment: usize,
+}
+
+impl LogFollower {
+    /// Start following the log of a task.
+    /// If `lines` is given, the last few lines are read as well.
+    pub fn open(task_id: usize, path: &Path, lines: Option<usize>) -> Result<Self, Error> {
+        let newest_segment = newest_log_segment(task_id, path);
+        let mut file = get_log_file_handle(task_id, path, None)?;
+        if let Some(lines) = lines {
+            seek_to_last_lines(&mut file, lines)?;
+        }
+
+        Ok(LogFollower {
+            task_id,
+            path: path.to_path_buf(),
+            file: Some(file),
+            newest_segment,
+        })
+    }
+
+    /// Check whether the log still exists. It doesn't, if the task has been removed.
+    pub fn exists(&self) -> bool {
+        log_file_exists(&get_log_path(self.task_id, &self.path))
+            || !get_log_segments(self.task_id, None, &self.path).is_empty()
+    }
+
+    /// Read all output, that has been written since the last call.
+    pub fn read_new_output(&mut self, buffer: &mut Vec<u8>) -> Result<(), Error> {
+        let read_error = |err| Error::IoError("reading log file".to_string(), err);
+        if let Some(file) = self.file.as_mut() {
+            file.read_to_end(buffer).map_err(read_error)?;
+        }
+
+        // The log has been rotated. The followed file is now the next segment.
+        // Read everything that has been written to it and all segments that have been rotated
+        // since, before continuing with the new log file.
+        let newest_segment = newest_log_segment(self.task_id, &self.path);
+        if newest_segment > self.newest_segment {
+            let mut next_segment = self.newest_segment + 1;
+            if let Some(mut file) = self.file.take() {
+                file.read_to_end(buffer).map_err(read_error)?;
+                next_segment += 1;
+            }
+
+            for segment in next_segment..=newest_segment {
+                let segment_path = get_log_segment_path(self.task_id, None, segment, &self.path);
+                if let Ok(mut file) = LogFile::open(&segment_path) {
+                    file.read_to_end(buffer).map_err(read_error)?;
+                }
+            }
+            self.newest_segment = newest_segment;
+        }
+
+        if self.file.is_none() {
+            let log_path = get_log_path(self.task_id, &self.path);
+            match LogFile::open(&log_path) {
+                Ok(mut file) => {
+                    file.read_to_end(buffer).map_err(read_error)?;
+                    self.file = Some(file);
+                }
+                // The new log file hasn't been created yet.
+                Err(err) if err.kind() == io::ErrorKind::NotFound => (),
+                Err(err) => return Err(Error::IoPathError(log_path, "opening log file", err)),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Get the number of the newest rotated segment of a task's log. `0` if there is none.
+fn newest_log_segment(task_id: usize, path: &Path) -> usize {
+    get_log_segments(task_id, None, path)
+        .last()
+        .copied()
+        .unwrap_or(0)
+}
+
+/// Rotate a log file into the given segment. The index of the file is rotated as well.
+///
+/// The log file is moved, so anybody who's currently reading it continues to read the segment.
+/// A new log file has to be created afterwards.
+pub fn rotate_log_file(
+    task_id: usize,
+    stream: Option<OutputStream>,
+    segment: usize,
+    path: &Path,
+) -> io::Result<()> {
+    let log_path = get_log_file_path(task_id, stream, path);
+    let segment_path = get_log_segment_path(task_id, stream, segment, path);
+    rename(&log_path, &segment_path)?;
+
+    let index_path = get_index_path(&log_path);
+    if index_path.exists() {
+        rename(index_path, get_index_path(&segment_path))?;
+    }
+
+    Ok(())
+}
+
+/// Remove a rotated segment of a task's log file and its index.
+pub fn remove_log_segment(
+    task_id: usize,
+    stream: Option<OutputStream>,
+    segment: usize,
+    path: &Path,
+) -> io::Result<()> {
+    let segment_path = get_log_segment_path(task_id, stream, segment, path);
+    let index_path = get_index_path(&segment_path);
+    if index_path.exists() {
+        remove_file(index_path)?;
+    }
+    remove_log_file(&segment_path)
+}
+
+/// Append some text to a rotated segment of a task's log file.
+pub fn append_to_log_segment(
+    task_id: usize,
+    stream: Option<OutputStream>,
+    segment: usize,
+    path: &Path,
+    text: &str,
+) -> io::Result<()> {
+    let segment_path = get_log_segment_path(task_id, stream, segment, path);
+    OpenOptions::new()
+        .append(true)
+        .open(segment_path)?
+        .write_all(text.as_bytes())
 }
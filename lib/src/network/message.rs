@@ -5,8 +5,11 @@ use chrono::prelude::*;
 use serde_derive::{Deserialize, Serialize};
 use strum_macros::{Display, EnumString};
 
+use crate::aliasing::Alias;
+use crate::log::{LogLimit, LogLine, LogRange, OutputStream};
 use crate::state::{Group, State};
-use crate::task::Task;
+use crate::task::{ExitCodePolicy, OutputRules, StallTimeout, Task, TaskStatus};
+use crate::template::TaskTemplate;
 
 /// Macro to simplify creating From implementations for each variant-contained
 /// struct; e.g. `impl_into_message!(AddMessage, Message::Add)` to make it possible
@@ -26,6 +29,8 @@ macro_rules! impl_into_message {
 #[derive(PartialEq, Eq, Clone, Debug, Deserialize, Serialize)]
 pub enum Message {
     Add(AddMessage),
+    /// Add multiple tasks at once, which may depend on each other.
+    Submit(SubmitMessage),
     Remove(Vec<usize>),
     Switch(SwitchMessage),
     Stash(Vec<usize>),
@@ -49,14 +54,34 @@ pub enum Message {
     EditResponse(EditResponseMessage),
     /// The client sends the edited details to the daemon.
     Edit(EditMessage),
+    /// Same as the `Edit*` protocol, but for several tasks at once.
+    /// All tasks are locked and changed atomically.
+    /// `EditRestore` is used for each task, if something went wrong.
+    BulkEditRequest(Vec<usize>),
+    BulkEditResponse(BulkEditResponseMessage),
+    BulkEdit(BulkEditMessage),
 
     Group(GroupMessage),
     GroupResponse(GroupResponseMessage),
 
+    /// Manage the daemon's state backups.
+    Backup(BackupMessage),
+    BackupResponse(BackupResponseMessage),
+    Alias(AliasMessage),
+    AliasResponse(AliasResponseMessage),
+    Template(TemplateMessage),
+    TemplateResponse(TemplateResponseMessage),
+    Run(RunMessage),
+
     Status,
     StatusResponse(Box<State>),
     Log(LogRequestMessage),
     LogResponse(BTreeMap<usize, TaskLogMessage>),
+    /// The next part of a task's output, if the log has been requested in chunks.
+    LogChunk(LogChunkMessage),
+    /// Search the logs of several tasks.
+    Grep(GrepRequestMessage),
+    GrepResponse(GrepResponseMessage),
 
     /// The client requests a continuous stream of a task's log.
     StreamRequest(StreamRequestMessage),
@@ -75,6 +100,18 @@ pub enum Message {
     Close,
 
     Parallel(ParallelMessage),
+
+    /// Only handle the contained message, if the daemon's state still matches the given
+    /// preconditions.
+    Conditional(ConditionalMessage),
+    /// Handle multiple messages at once, without any other changes of the state in between.
+    /// Either all messages succeed or nothing is changed.
+    Batch(Vec<Message>),
+    BatchResponse(BatchResponseMessage),
+
+    /// A running task acts on itself.
+    /// This is only accepted from connections, that authenticated with a task's token.
+    TaskControl(TaskControlMessage),
 }
 
 /// This enum is used to express a selection of tasks.
@@ -84,6 +121,7 @@ pub enum Message {
 pub enum TaskSelection {
     TaskIds(Vec<usize>),
     Group(String),
+    Batch(usize),
     All,
 }
 
@@ -99,6 +137,31 @@ pub struct AddMessage {
     pub dependencies: Vec<usize>,
     pub label: Option<String>,
     pub print_task_id: bool,
+    /// Add the task to an existing batch.
+    #[serde(default)]
+    pub batch: Option<usize>,
+    /// Kill the task, if it runs longer than this amount of seconds.
+    /// This is set for tasks, that are created from a template with a timeout.
+    #[serde(default)]
+    pub timeout: Option<u64>,
+    /// Capture stdout and stderr in separate log files.
+    #[serde(default)]
+    pub separate_output: bool,
+    /// Capture the output with a timestamp for each line.
+    #[serde(default)]
+    pub timestamps: bool,
+    /// The size limit for the task's log files.
+    #[serde(default)]
+    pub log_limit: Option<LogLimit>,
+    /// Decides, which exit codes count as success or as temporary failure.
+    #[serde(default)]
+    pub exit_code_policy: Option<ExitCodePolicy>,
+    /// Rules, that decide whether the task succeeded, depending on its output.
+    #[serde(default)]
+    pub output_rules: Option<OutputRules>,
+    /// Detect, whether the task stopped writing output.
+    #[serde(default)]
+    pub stall_timeout: Option<StallTimeout>,
 }
 
 /// We use a custom `Debug` implementation for [AddMessage], as the `envs` field just has
@@ -119,12 +182,68 @@ impl std::fmt::Debug for AddMessage {
             .field("dependencies", &self.dependencies)
             .field("label", &self.label)
             .field("print_task_id", &self.print_task_id)
+            .field("batch", &self.batch)
+            .field("timeout", &self.timeout)
+            .field("separate_output", &self.separate_output)
+            .field("timestamps", &self.timestamps)
+            .field("log_limit", &self.log_limit)
+            .field("exit_code_policy", &self.exit_code_policy)
+            .field("output_rules", &self.output_rules)
+            .field("stall_timeout", &self.stall_timeout)
             .finish()
     }
 }
 
 impl_into_message!(AddMessage, Message::Add);
 
+/// The actions, that a running task can perform on itself.
+/// The task is determined by the token, with which the connection has been authenticated.
+#[derive(PartialEq, Eq, Clone, Debug, Deserialize, Serialize)]
+pub enum TaskControlMessage {
+    /// Set the label of the task.
+    SetLabel(String),
+    /// Report the progress of the task, e.g. `50%` or `step 3/5`.
+    ReportProgress(String),
+    /// Add a task, that depends on this task.
+    AddFollowUp(FollowUpMessage),
+    /// Restart the task in place, once it finished.
+    Retry,
+}
+
+impl_into_message!(TaskControlMessage, Message::TaskControl);
+
+/// A task, that's added by a running task and only depends on that task.
+/// Everything else, such as its group, path and environment variables, is inherited from the
+/// running task.
+#[derive(PartialEq, Eq, Clone, Debug, Deserialize, Serialize)]
+pub struct FollowUpMessage {
+    pub command: String,
+    pub label: Option<String>,
+}
+
+/// A set of tasks that should be added at once.
+/// Tasks can depend on other tasks of the same submission via their symbolic names.
+#[derive(PartialEq, Eq, Clone, Debug, Deserialize, Serialize)]
+pub struct SubmitMessage {
+    pub tasks: Vec<SubmittedTask>,
+    /// Put all tasks into a new batch with the given label.
+    #[serde(default)]
+    pub create_batch: bool,
+    #[serde(default)]
+    pub batch_label: Option<String>,
+}
+
+impl_into_message!(SubmitMessage, Message::Submit);
+
+#[derive(PartialEq, Eq, Clone, Debug, Deserialize, Serialize)]
+pub struct SubmittedTask {
+    /// The symbolic name of the task, which is unique in this submission.
+    pub name: String,
+    /// The names of all tasks of this submission, this task depends on.
+    pub needs: Vec<String>,
+    pub task: AddMessage,
+}
+
 #[derive(PartialEq, Eq, Clone, Debug, Deserialize, Serialize)]
 pub struct SwitchMessage {
     pub task_id_1: usize,
@@ -225,6 +344,12 @@ pub struct EditResponseMessage {
     pub command: String,
     pub path: PathBuf,
     pub label: Option<String>,
+    #[serde(default)]
+    pub envs: HashMap<String, String>,
+    #[serde(default)]
+    pub group: String,
+    #[serde(default)]
+    pub dependencies: Vec<usize>,
 }
 
 impl_into_message!(EditResponseMessage, Message::EditResponse);
@@ -238,10 +363,35 @@ pub struct EditMessage {
     /// Cbor cannot represent Option<Option<T>> yet, which is why we have to utilize a
     /// boolean to indicate that the label should be released, rather than an `Some(None)`.
     pub delete_label: bool,
+    /// The new environment variables. These replace all previous variables of the task.
+    #[serde(default)]
+    pub envs: Option<HashMap<String, String>>,
+    #[serde(default)]
+    pub group: Option<String>,
+    /// The new dependencies. These replace all previous dependencies of the task.
+    #[serde(default)]
+    pub dependencies: Option<Vec<usize>>,
 }
 
 impl_into_message!(EditMessage, Message::Edit);
 
+#[derive(PartialEq, Eq, Clone, Debug, Deserialize, Serialize)]
+pub struct BulkEditResponseMessage {
+    pub tasks: Vec<EditResponseMessage>,
+    /// The requested tasks that don't exist or cannot be edited.
+    #[serde(default)]
+    pub skipped: Vec<usize>,
+}
+
+impl_into_message!(BulkEditResponseMessage, Message::BulkEditResponse);
+
+#[derive(PartialEq, Eq, Clone, Debug, Deserialize, Serialize)]
+pub struct BulkEditMessage {
+    pub tasks: Vec<EditMessage>,
+}
+
+impl_into_message!(BulkEditMessage, Message::BulkEdit);
+
 #[derive(PartialEq, Eq, Clone, Debug, Deserialize, Serialize)]
 pub enum GroupMessage {
     Add {
@@ -261,6 +411,95 @@ pub struct GroupResponseMessage {
 
 impl_into_message!(GroupResponseMessage, Message::GroupResponse);
 
+#[derive(PartialEq, Eq, Clone, Debug, Deserialize, Serialize)]
+pub enum BackupMessage {
+    /// Create a new backup of the current state.
+    Create,
+    /// Replace the current state with the backup of the given name.
+    Restore(String),
+    List,
+}
+
+impl_into_message!(BackupMessage, Message::Backup);
+
+/// The names of all available state backups, ordered from oldest to newest.
+#[derive(PartialEq, Eq, Clone, Debug, Deserialize, Serialize)]
+pub struct BackupResponseMessage {
+    pub backups: Vec<String>,
+}
+
+impl_into_message!(BackupResponseMessage, Message::BackupResponse);
+
+#[derive(PartialEq, Eq, Clone, Debug, Deserialize, Serialize)]
+pub enum AliasMessage {
+    List,
+    /// Show a single alias by its name.
+    Show(String),
+}
+
+impl_into_message!(AliasMessage, Message::Alias);
+
+/// The requested aliases of the daemon's alias file, ordered by name.
+#[derive(PartialEq, Eq, Clone, Debug, Deserialize, Serialize)]
+pub struct AliasResponseMessage {
+    pub aliases: BTreeMap<String, Alias>,
+}
+
+impl_into_message!(AliasResponseMessage, Message::AliasResponse);
+
+#[derive(PartialEq, Eq, Clone, Debug, Deserialize, Serialize)]
+pub enum TemplateMessage {
+    Add {
+        name: String,
+        template: TaskTemplate,
+    },
+    Remove(String),
+    List,
+}
+
+impl_into_message!(TemplateMessage, Message::Template);
+
+/// All task templates of the daemon, ordered by name.
+#[derive(PartialEq, Eq, Clone, Debug, Deserialize, Serialize)]
+pub struct TemplateResponseMessage {
+    pub templates: BTreeMap<String, TaskTemplate>,
+}
+
+impl_into_message!(TemplateResponseMessage, Message::TemplateResponse);
+
+/// Create a new task from a template.
+#[derive(PartialEq, Eq, Clone, Deserialize, Serialize)]
+pub struct RunMessage {
+    pub template: String,
+    pub parameters: HashMap<String, String>,
+    /// The working directory of the client.
+    /// It's used if the template doesn't specify a path or if its path is relative.
+    pub path: PathBuf,
+    /// The environment of the client. Variables of the template take precedence.
+    pub envs: HashMap<String, String>,
+    /// Overwrite the group of the template.
+    pub group: Option<String>,
+    pub start_immediately: bool,
+    pub stashed: bool,
+}
+
+/// Just like for [AddMessage], the `envs` field is hidden in the `Debug` output.
+impl std::fmt::Debug for RunMessage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RunMessage")
+            .field("template", &self.template)
+            .field("parameters", &self.parameters)
+            .field("path", &self.path)
+            .field("envs", &"hidden")
+            .field("group", &self.group)
+            .field("start_immediately", &self.start_immediately)
+            .field("stashed", &self.stashed)
+            .finish()
+    }
+}
+
+impl_into_message!(RunMessage, Message::Run);
+
 #[derive(PartialEq, Eq, Clone, Debug, Deserialize, Serialize)]
 pub struct ResetMessage {
     pub children: bool,
@@ -275,6 +514,9 @@ pub struct CleanMessage {
 
     #[serde(default = "Option::default")]
     pub group: Option<String>,
+
+    #[serde(default = "Option::default")]
+    pub batch: Option<usize>,
 }
 
 impl_into_message!(CleanMessage, Message::Clean);
@@ -286,6 +528,9 @@ pub enum Shutdown {
     Emergency,
     /// Graceful is user initiated and expected.
     Graceful,
+    /// Drain stops spawning new tasks and shuts down once all running tasks finished.
+    /// Tasks that are still running after the deadline will be killed.
+    Drain { deadline: Option<DateTime<Local>> },
 }
 
 impl_into_message!(Shutdown, Message::DaemonShutdown);
@@ -303,15 +548,59 @@ impl_into_message!(StreamRequestMessage, Message::StreamRequest);
 /// `task_ids` specifies the requested tasks. If none are given, all tasks are selected.
 /// `send_logs` Determines whether logs should be sent at all.
 /// `lines` Determines whether only a few lines of log should be returned.
+///
+/// If `chunk_size` is set, the [Message::LogResponse] doesn't contain any output.
+/// Instead, the output of each task is sent in [Message::LogChunk]s of at most `chunk_size`
+/// uncompressed bytes afterwards, followed by a final [Message::Close].
 #[derive(PartialEq, Eq, Clone, Debug, Deserialize, Serialize)]
 pub struct LogRequestMessage {
     pub task_ids: Vec<usize>,
     pub send_logs: bool,
     pub lines: Option<usize>,
+    /// Only send this part of the log. Takes precedence over `lines`.
+    #[serde(default)]
+    pub range: Option<LogRange>,
+    #[serde(default)]
+    pub chunk_size: Option<usize>,
+    /// Only send the output of a single stream instead of the interleaved output.
+    #[serde(default)]
+    pub stream: Option<OutputStream>,
+    /// Prefix each line with the time at which it has been captured.
+    #[serde(default)]
+    pub timestamps: bool,
+    /// Only send lines that have been captured at or after this point in time.
+    #[serde(default)]
+    pub since: Option<DateTime<Local>>,
 }
 
 impl_into_message!(LogRequestMessage, Message::Log);
 
+/// Search the logs of the given tasks for a regular expression or, with `fixed_strings`, for a
+/// fixed string.
+/// `context` is the amount of lines, that's sent before and after each matching line.
+/// At most `max_matches` matching lines are sent in total.
+#[derive(PartialEq, Eq, Clone, Debug, Deserialize, Serialize)]
+pub struct GrepRequestMessage {
+    pub task_ids: Vec<usize>,
+    pub pattern: String,
+    pub fixed_strings: bool,
+    pub ignore_case: bool,
+    pub context: usize,
+    pub max_matches: usize,
+}
+
+impl_into_message!(GrepRequestMessage, Message::Grep);
+
+#[derive(PartialEq, Eq, Clone, Debug, Deserialize, Serialize)]
+pub struct GrepResponseMessage {
+    /// The matching lines and their context for each task with at least one match.
+    pub matches: BTreeMap<usize, Vec<LogLine>>,
+    /// Whether the search stopped early, as `max_matches` lines matched.
+    pub truncated: bool,
+}
+
+impl_into_message!(GrepResponseMessage, Message::GrepResponse);
+
 /// Helper struct for sending tasks and their log output to the client.
 #[derive(PartialEq, Eq, Clone, Deserialize, Serialize)]
 pub struct TaskLogMessage {
@@ -322,6 +611,25 @@ pub struct TaskLogMessage {
     pub output: Option<Vec<u8>>,
 }
 
+/// A part of a task's log output, which is compressed just like [TaskLogMessage::output].
+/// The chunks of a task are sent in order.
+#[derive(PartialEq, Eq, Clone, Deserialize, Serialize)]
+pub struct LogChunkMessage {
+    pub task_id: usize,
+    pub output: Vec<u8>,
+}
+
+impl_into_message!(LogChunkMessage, Message::LogChunk);
+
+impl std::fmt::Debug for LogChunkMessage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LogChunkMessage")
+            .field("task_id", &self.task_id)
+            .field("output", &"hidden")
+            .finish()
+    }
+}
+
 /// We use a custom `Debug` implementation for [TaskLogMessage], as the `output` field
 /// has too much info in it and renders log output unreadable.
 impl std::fmt::Debug for TaskLogMessage {
@@ -342,6 +650,36 @@ pub struct ParallelMessage {
 
 impl_into_message!(ParallelMessage, Message::Parallel);
 
+/// Wraps a message, which should only be handled, if nothing changed in the meantime.
+/// This allows clients to safely act on a state they received earlier.
+///
+/// The preconditions are checked and the message is handled, without any other changes of the
+/// state in between. If a precondition isn't met, a failure message is returned.
+#[derive(PartialEq, Eq, Clone, Debug, Deserialize, Serialize)]
+pub struct ConditionalMessage {
+    /// The expected revision of the state.
+    #[serde(default)]
+    pub if_revision: Option<u64>,
+    /// The expected status of specific tasks.
+    #[serde(default)]
+    pub if_status: BTreeMap<usize, TaskStatus>,
+    pub message: Box<Message>,
+}
+
+impl_into_message!(ConditionalMessage, Message::Conditional);
+
+/// The responses to all messages of a batch, in the same order as the messages.
+///
+/// The batch is aborted on the first failure, in which case the failure is the last response.
+#[derive(PartialEq, Eq, Clone, Debug, Deserialize, Serialize)]
+pub struct BatchResponseMessage {
+    /// Whether the changes of the batch have been applied.
+    pub applied: bool,
+    pub responses: Vec<Message>,
+}
+
+impl_into_message!(BatchResponseMessage, Message::BatchResponse);
+
 pub fn create_success_message<T: ToString>(text: T) -> Message {
     Message::Success(text.to_string())
 }
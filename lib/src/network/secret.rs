@@ -3,6 +3,7 @@ use std::io::prelude::*;
 use std::path::Path;
 
 use rand::{distributions::Alphanumeric, Rng};
+use ring::digest::{digest, SHA256};
 
 use crate::error::Error;
 
@@ -29,13 +30,7 @@ pub fn init_shared_secret(path: &Path) -> Result<(), Error> {
     }
 
     const PASSWORD_LEN: usize = 512;
-    let mut rng = rand::thread_rng();
-
-    let secret: String = std::iter::repeat(())
-        .map(|()| rng.sample(Alphanumeric))
-        .map(char::from)
-        .take(PASSWORD_LEN)
-        .collect();
+    let secret = random_string(PASSWORD_LEN);
 
     let mut file = File::create(&path)
         .map_err(|err| Error::IoPathError(path.to_path_buf(), "creating shared secret", err))?;
@@ -60,3 +55,29 @@ pub fn init_shared_secret(path: &Path) -> Result<(), Error> {
 
     Ok(())
 }
+
+/// Generate a random token, which allows a single run of a task to talk to the daemon.
+pub fn generate_task_token() -> String {
+    const TOKEN_LEN: usize = 64;
+    random_string(TOKEN_LEN)
+}
+
+/// Hash the token of a task, so it can be stored without revealing the token itself.
+pub fn hash_task_token(token: &str) -> String {
+    digest(&SHA256, token.as_bytes())
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Generate a random alphanumeric string of the given length.
+fn random_string(length: usize) -> String {
+    let mut rng = rand::thread_rng();
+
+    std::iter::repeat(())
+        .map(|()| rng.sample(Alphanumeric))
+        .map(char::from)
+        .take(length)
+        .collect()
+}
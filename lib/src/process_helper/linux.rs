@@ -1,4 +1,8 @@
 use std::convert::TryInto;
+use std::fs::File;
+use std::os::unix::io::FromRawFd;
+use std::os::unix::process::CommandExt;
+use std::path::PathBuf;
 use std::process::{Child, Command};
 
 // We allow anyhow in here, as this is a module that'll be strictly used internally.
@@ -7,8 +11,15 @@ use std::process::{Child, Command};
 use anyhow::{bail, Result};
 use log::{debug, info, warn};
 use nix::{
+    errno::Errno,
+    fcntl::OFlag,
     sys::signal::{self, Signal},
-    unistd::Pid,
+    unistd::{getpgid, pipe2, setsid, Pid},
+};
+#[cfg(target_os = "linux")]
+use nix::{
+    poll::{poll, PollFd, PollFlags},
+    unistd::close,
 };
 use procfs::process::{all_processes, Process};
 
@@ -94,7 +105,21 @@ pub fn run_action_on_child(
 /// Returns `Ok(true)`, if everything went alright
 /// Returns `Ok(false)`, if the process went away while we tried to send the signal.
 pub fn send_signal_to_child(child: &Child, signal: Signal, send_to_children: bool) -> Result<bool> {
-    let pid: i32 = child.id().try_into().unwrap();
+    send_signal_to_task_process(child.id().try_into().unwrap(), signal, send_to_children)
+}
+
+/// The actual logic of [send_signal_to_child], which only needs the pid of the task's process.
+/// This allows to also send signals to re-adopted processes, which aren't our children.
+fn send_signal_to_task_process(pid: i32, signal: Signal, send_to_children: bool) -> Result<bool> {
+    if is_detached_task_process(pid) {
+        debug!("Sending signal {signal} to process group {pid}");
+        return match signal::killpg(Pid::from_raw(pid), signal) {
+            Ok(()) => Ok(true),
+            Err(Errno::ESRCH) => Ok(false),
+            Err(error) => Err(error.into()),
+        };
+    }
+
     // Check whether this process actually spawned a shell.
     let is_shell = if let Ok(is_shell) = did_process_spawn_shell(pid) {
         is_shell
@@ -139,6 +164,24 @@ pub fn send_signal_to_child(child: &Child, signal: Signal, send_to_children: boo
 /// Returns `false`, if the process went away while we tried to send the signal.
 pub fn kill_child(task_id: usize, child: &mut Child, kill_children: bool) -> bool {
     let pid: i32 = child.id().try_into().unwrap();
+    kill_task_process(task_id, pid, kill_children, || child.kill().is_ok())
+}
+
+/// The actual logic of [kill_child], which only needs the pid of the task's process.
+///
+/// `kill_parent` is responsible for killing the root process of the task.
+/// It returns `false`, if the process has already been gone.
+fn kill_task_process<F>(task_id: usize, pid: i32, kill_children: bool, kill_parent: F) -> bool
+where
+    F: FnOnce() -> bool,
+{
+    if is_detached_task_process(pid) {
+        if let Err(error) = signal::killpg(Pid::from_raw(pid), Signal::SIGKILL) {
+            info!("Task {task_id} has already finished by itself: {error}");
+            return false;
+        }
+        return true;
+    }
 
     // Check whether this process actually spawned a shell.
     let is_shell = if let Ok(is_shell) = did_process_spawn_shell(pid) {
@@ -156,8 +199,7 @@ pub fn kill_child(task_id: usize, child: &mut Child, kill_children: bool) -> boo
     }
 
     // Kill the parent first
-    let kill_result = child.kill();
-    if kill_result.is_err() {
+    if !kill_parent() {
         info!("Task {task_id} has already finished by itself.");
         return false;
     }
@@ -188,6 +230,15 @@ pub fn kill_child(task_id: usize, child: &mut Child, kill_children: bool) -> boo
     true
 }
 
+/// Check whether the process of a task has been detached via [detach_command].
+///
+/// Detached tasks run in their own session, whose process group id is the pid of the task's
+/// process. The actual command of such a task runs in a subshell of that process, which is why
+/// signals are always sent to the whole process group. Otherwise, they wouldn't reach the command.
+fn is_detached_task_process(pid: i32) -> bool {
+    matches!(getpgid(Some(Pid::from_raw(pid))), Ok(pgid) if pgid.as_raw() == pid)
+}
+
 /// Check whether a process's commandline string is actually a shell or not
 fn did_process_spawn_shell(pid: i32) -> Result<bool> {
     // Get the /proc representation of the child, so we can do some checks
@@ -292,6 +343,154 @@ pub fn process_exists(pid: u32) -> bool {
     }
 }
 
+/// Start the process of a command in its own session. \
+/// That way the process isn't affected by any signals that are sent to the daemon's process group
+/// and keeps running, even if the daemon goes away.
+pub fn detach_command(command: &mut Command) {
+    // Safety: `setsid` is async-signal-safe and doesn't allocate.
+    unsafe {
+        command.pre_exec(|| {
+            setsid()?;
+            Ok(())
+        });
+    }
+}
+
+/// Create a pipe, whose writing end can be used as both stdout and stderr of a process. \
+/// Unlike two separate pipes, a single pipe keeps the output of both streams in its original order.
+///
+/// Returns the reading and the writing end of the pipe.
+pub fn create_output_pipe() -> Result<Option<(File, File)>> {
+    let (read, write) = pipe2(OFlag::O_CLOEXEC)?;
+    // Safety: Both file descriptors have just been created and aren't owned by anything else.
+    let pipe = unsafe { (File::from_raw_fd(read), File::from_raw_fd(write)) };
+    Ok(Some(pipe))
+}
+
+/// Get the start time of a process in clock ticks since system boot. \
+/// In combination with the pid, this uniquely identifies a process, since pids can be reused.
+pub fn process_start_time(pid: u32) -> Option<u64> {
+    let process = Process::new(pid.try_into().ok()?).ok()?;
+    process.stat().ok().map(|stat| stat.starttime)
+}
+
+/// A process of a detached task from a previous daemon session. \
+/// Since this process isn't a child of the current daemon, it cannot be handled via [Child].
+/// On Linux, its exit is tracked via a pidfd. Everywhere else, we fall back to polling procfs.
+///
+/// The exit code of the process cannot be received via `wait`, which is why detached tasks write
+/// their exit code into a file on exit.
+#[derive(Debug)]
+pub struct AdoptedProcess {
+    pid: u32,
+    start_time: u64,
+    #[cfg(target_os = "linux")]
+    pidfd: std::os::unix::io::RawFd,
+    exit_code_path: PathBuf,
+}
+
+impl AdoptedProcess {
+    /// Try to re-adopt a process.
+    /// Returns `None`, if there's no process with the given pid and start time.
+    pub fn adopt(pid: u32, start_time: u64, exit_code_path: PathBuf) -> Option<AdoptedProcess> {
+        #[cfg(target_os = "linux")]
+        let pidfd = {
+            // Safety: `pidfd_open` doesn't touch any memory of ours.
+            let fd = unsafe { nix::libc::syscall(nix::libc::SYS_pidfd_open, pid, 0) };
+            if fd < 0 {
+                info!("Couldn't open pidfd for process {pid}. It probably went away.");
+                return None;
+            }
+            fd as std::os::unix::io::RawFd
+        };
+
+        let process = AdoptedProcess {
+            pid,
+            start_time,
+            #[cfg(target_os = "linux")]
+            pidfd,
+            exit_code_path,
+        };
+
+        // Check the start time after opening the pidfd.
+        // Otherwise the process could be replaced in between.
+        if process_start_time(pid) != Some(start_time) {
+            info!("Process {pid} has been replaced by another process.");
+            return None;
+        }
+
+        Some(process)
+    }
+
+    pub fn id(&self) -> u32 {
+        self.pid
+    }
+
+    /// Check whether the process exited.
+    ///
+    /// Returns `None`, if the process is still running.
+    /// Otherwise, the exit code that has been written by the process is returned.
+    /// The exit code is `None`, if the process has been killed by a signal.
+    pub fn try_wait(&mut self) -> Option<Option<i32>> {
+        if !self.has_exited() {
+            return None;
+        }
+
+        let exit_code = std::fs::read_to_string(&self.exit_code_path)
+            .ok()
+            .and_then(|content| content.trim().parse::<i32>().ok());
+        Some(exit_code)
+    }
+
+    #[cfg(target_os = "linux")]
+    fn has_exited(&self) -> bool {
+        let mut fds = [PollFd::new(self.pidfd, PollFlags::POLLIN)];
+        match poll(&mut fds, 0) {
+            Ok(ready) => ready > 0,
+            Err(error) => {
+                warn!("Failed to poll pidfd of process {}: {error}", self.pid);
+                process_start_time(self.pid) != Some(self.start_time)
+            }
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn has_exited(&self) -> bool {
+        process_start_time(self.pid) != Some(self.start_time)
+    }
+
+    /// Same as [send_internal_signal_to_child], but for adopted processes.
+    pub fn send_internal_signal(
+        &self,
+        signal: InternalSignal,
+        send_to_children: bool,
+    ) -> Result<bool> {
+        let signal = map_internal_signal_to_nix_signal(signal);
+        send_signal_to_task_process(self.pid.try_into().unwrap(), signal, send_to_children)
+    }
+
+    /// Same as [run_action_on_child], but for adopted processes.
+    pub fn run_action(&self, action: &ProcessAction, send_to_children: bool) -> Result<bool> {
+        let signal = map_action_to_signal(action);
+        send_signal_to_task_process(self.pid.try_into().unwrap(), signal, send_to_children)
+    }
+
+    /// Same as [kill_child], but for adopted processes.
+    pub fn kill(&self, task_id: usize, kill_children: bool) -> bool {
+        let pid: i32 = self.pid.try_into().unwrap();
+        kill_task_process(task_id, pid, kill_children, || {
+            signal::kill(Pid::from_raw(pid), Signal::SIGKILL).is_ok()
+        })
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Drop for AdoptedProcess {
+    fn drop(&mut self) {
+        let _ = close(self.pidfd);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::thread::sleep;
@@ -352,6 +551,33 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    /// Ensure that the command of a detached task is killed, even though it runs in a subshell.
+    fn test_detached_command_is_killed() -> Result<()> {
+        let mut command = compile_shell_command("(\nsleep 60\ntrue\n)\nexit $?");
+        detach_command(&mut command);
+        let mut child = command.spawn().expect("Failed to spawn sleep");
+        let pid: i32 = child.id().try_into().unwrap();
+        // Sleep a little to give everything a chance to spawn.
+        sleep(Duration::from_millis(500));
+
+        // The actual command is a grandchild of the task's process.
+        let grandchildren: Vec<Process> = get_child_processes(pid)
+            .iter()
+            .flat_map(|process| get_child_processes(process.pid()))
+            .collect();
+        assert_eq!(grandchildren.len(), 1);
+
+        assert!(kill_child(0, &mut child, false));
+        child.wait()?;
+
+        // Sleep a little to give all processes time to shutdown.
+        sleep(Duration::from_millis(500));
+        assert!(!grandchildren[0].is_alive());
+
+        Ok(())
+    }
+
     #[test]
     /// Ensure a `sh -c` command will be properly killed without detached processes when using unix
     /// signals directly.
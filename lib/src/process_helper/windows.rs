@@ -1,3 +1,5 @@
+use std::fs::File;
+use std::path::PathBuf;
 use std::process::{Child, Command};
 
 // We allow anyhow in here, as this is a module that'll be strictly used internally.
@@ -264,6 +266,59 @@ pub fn process_exists(pid: u32) -> bool {
     false
 }
 
+/// Detaching tasks from the daemon isn't supported on this platform.
+pub fn detach_command(_command: &mut Command) {}
+
+/// Sharing a single pipe between stdout and stderr isn't supported on this platform.
+pub fn create_output_pipe() -> Result<Option<(File, File)>> {
+    Ok(None)
+}
+
+/// Re-adopting processes isn't supported on this platform.
+/// Hence, there's no way to uniquely identify a process.
+pub fn process_start_time(_pid: u32) -> Option<u64> {
+    None
+}
+
+/// A process of a detached task from a previous daemon session.
+/// Re-adopting processes isn't supported on this platform, which is why this struct can never
+/// be created.
+#[derive(Debug)]
+pub struct AdoptedProcess {
+    pid: u32,
+}
+
+impl AdoptedProcess {
+    /// Re-adopting processes isn't supported on this platform.
+    pub fn adopt(_pid: u32, _start_time: u64, _exit_code_path: PathBuf) -> Option<AdoptedProcess> {
+        None
+    }
+
+    pub fn id(&self) -> u32 {
+        self.pid
+    }
+
+    pub fn try_wait(&mut self) -> Option<Option<i32>> {
+        Some(None)
+    }
+
+    pub fn send_internal_signal(
+        &self,
+        _signal: InternalSignal,
+        _send_to_children: bool,
+    ) -> Result<bool> {
+        Ok(false)
+    }
+
+    pub fn run_action(&self, _action: &ProcessAction, _send_to_children: bool) -> Result<bool> {
+        Ok(false)
+    }
+
+    pub fn kill(&self, _task_id: usize, _kill_children: bool) -> bool {
+        false
+    }
+}
+
 #[cfg(test)]
 mod test {
     use std::thread::sleep;
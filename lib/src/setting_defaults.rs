@@ -23,3 +23,11 @@ pub(crate) fn default_status_datetime_format() -> String {
 pub(crate) fn default_callback_log_lines() -> usize {
     10
 }
+
+pub(crate) fn default_state_backup_count() -> usize {
+    10
+}
+
+pub(crate) fn default_edit_lock_lease_seconds() -> u64 {
+    3600
+}
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs::{create_dir_all, File};
 use std::io::{prelude::*, BufReader};
 use std::path::{Path, PathBuf};
@@ -8,7 +8,9 @@ use serde_derive::{Deserialize, Serialize};
 use shellexpand::tilde;
 
 use crate::error::Error;
+use crate::log::LogLimit;
 use crate::setting_defaults::*;
+use crate::task::{ExitCodePolicy, StallTimeout};
 
 /// All settings which are used by both, the client and the daemon
 #[derive(PartialEq, Eq, Clone, Debug, Default, Deserialize, Serialize)]
@@ -105,6 +107,49 @@ pub struct Client {
     pub status_datetime_format: String,
 }
 
+/// What happens to tasks that were still running, when the daemon shut down uncleanly.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InterruptedTaskPolicy {
+    /// Mark the task as killed.
+    #[default]
+    Fail,
+    /// Enqueue the task again. Only use this for tasks that can be safely run again.
+    Requeue,
+    /// Stash the task, so it can be manually enqueued again.
+    Stash,
+}
+
+/// What happens to groups with queued tasks, when the daemon starts.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueuedGroupPolicy {
+    /// Pause the group to prevent unwanted execution of tasks.
+    #[default]
+    Pause,
+    /// Keep the group in the state it had before.
+    KeepRunning,
+}
+
+/// How tasks and groups are recovered, when the daemon restores the state of a previous session.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default, Deserialize, Serialize)]
+pub struct CrashRecovery {
+    #[serde(default = "Default::default")]
+    pub interrupted_tasks: InterruptedTaskPolicy,
+    #[serde(default = "Default::default")]
+    pub queued_groups: QueuedGroupPolicy,
+}
+
+/// Group specific overrides of the [CrashRecovery] settings.
+/// Fields that aren't set fall back to the global setting.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default, Deserialize, Serialize)]
+pub struct CrashRecoveryOverride {
+    #[serde(default = "Default::default")]
+    pub interrupted_tasks: Option<InterruptedTaskPolicy>,
+    #[serde(default = "Default::default")]
+    pub queued_groups: Option<QueuedGroupPolicy>,
+}
+
 /// All settings which are used by the daemon
 #[derive(PartialEq, Eq, Clone, Debug, Default, Deserialize, Serialize)]
 pub struct Daemon {
@@ -114,11 +159,68 @@ pub struct Daemon {
     /// Whether the daemon (and all groups) should be paused as soon as a single task fails
     #[serde(default = "Default::default")]
     pub pause_all_on_failure: bool,
+    /// Start tasks detached from the daemon in their own session.
+    /// Detached tasks aren't killed when the daemon shuts down and are re-adopted by the daemon
+    /// on its next start, if they're still running.
+    /// This is currently only supported on Linux.
+    #[serde(default = "Default::default")]
+    pub detach_tasks: bool,
+    /// How interrupted tasks and groups with queued tasks are handled on startup.
+    #[serde(default = "Default::default")]
+    pub crash_recovery: CrashRecovery,
+    /// Group specific overrides for `crash_recovery`.
+    #[serde(default = "Default::default")]
+    pub group_crash_recovery: BTreeMap<String, CrashRecoveryOverride>,
     /// The callback that's called whenever a task finishes.
     pub callback: Option<String>,
     /// The amount of log lines from stdout/stderr that are passed to the callback command.
     #[serde(default = "default_callback_log_lines")]
     pub callback_log_lines: usize,
+    /// The amount of state backups that are kept in the `log` directory of the
+    /// `pueue_directory`. Older backups are removed first.
+    #[serde(default = "default_state_backup_count")]
+    pub state_backup_count: usize,
+    /// State backups that are older than this amount of hours are removed as well.
+    /// If this isn't set, backups are only removed depending on `state_backup_count`.
+    #[serde(default = "Default::default")]
+    pub state_backup_max_age_hours: Option<u64>,
+    /// Tasks are unlocked again, if a client doesn't finish editing them within this amount
+    /// of seconds. Tasks are also unlocked, as soon as the editing client disconnects.
+    #[serde(default = "default_edit_lock_lease_seconds")]
+    pub edit_lock_lease_seconds: u64,
+    /// Capture the stdout and stderr of all tasks in separate log files.
+    /// The interleaved output is still available. This doesn't apply to detached tasks.
+    #[serde(default = "Default::default")]
+    pub separate_output: bool,
+    /// Capture the output of all tasks with a timestamp for each line.
+    /// This allows to show when a line has been printed. This doesn't apply to detached tasks.
+    #[serde(default = "Default::default")]
+    pub log_timestamps: bool,
+    /// The size limit for each log file of a task, as well as what happens once a log
+    /// exceeds it. Logs aren't limited by default. This doesn't apply to detached tasks.
+    #[serde(default = "Default::default")]
+    pub log_limit: Option<LogLimit>,
+    /// Group specific overrides for `log_limit`.
+    #[serde(default = "Default::default")]
+    pub group_log_limits: BTreeMap<String, LogLimit>,
+    /// Decides, which exit codes of tasks count as success or as temporary failure.
+    /// By default, only `0` counts as success.
+    #[serde(default = "Default::default")]
+    pub exit_code_policy: ExitCodePolicy,
+    /// Group specific overrides for `exit_code_policy`.
+    /// Tasks can override these policies as well.
+    #[serde(default = "Default::default")]
+    pub group_exit_code_policies: BTreeMap<String, ExitCodePolicy>,
+    /// Detect running tasks, that didn't write any output for the given time.
+    #[serde(default = "Default::default")]
+    pub stall_timeout: Option<StallTimeout>,
+    /// Group specific overrides for `stall_timeout`.
+    #[serde(default = "Default::default")]
+    pub group_stall_timeouts: BTreeMap<String, StallTimeout>,
+    /// Compress the log files of tasks in the background, once they finished.
+    /// Compressed logs can still be read and followed as usual.
+    #[serde(default = "Default::default")]
+    pub compress_logs: bool,
     /// The legacy configuration for groups
     #[serde(skip_serializing)]
     #[deprecated(
@@ -128,6 +230,47 @@ pub struct Daemon {
     pub groups: Option<HashMap<String, i64>>,
 }
 
+impl Daemon {
+    /// Get the crash recovery settings for a specific group.
+    /// Group specific overrides take precedence over the global settings.
+    pub fn crash_recovery(&self, group: &str) -> CrashRecovery {
+        let mut recovery = self.crash_recovery;
+        if let Some(group_recovery) = self.group_crash_recovery.get(group) {
+            if let Some(interrupted_tasks) = group_recovery.interrupted_tasks {
+                recovery.interrupted_tasks = interrupted_tasks;
+            }
+            if let Some(queued_groups) = group_recovery.queued_groups {
+                recovery.queued_groups = queued_groups;
+            }
+        }
+
+        recovery
+    }
+
+    /// Get the log size limit for a specific group.
+    /// Group specific limits take precedence over the global limit.
+    pub fn log_limit(&self, group: &str) -> Option<LogLimit> {
+        self.group_log_limits.get(group).copied().or(self.log_limit)
+    }
+
+    /// Get the stall timeout for a specific group.
+    /// Group specific timeouts take precedence over the global timeout.
+    pub fn stall_timeout(&self, group: &str) -> Option<StallTimeout> {
+        self.group_stall_timeouts
+            .get(group)
+            .copied()
+            .or(self.stall_timeout)
+    }
+
+    /// Get the exit code policy for a specific group.
+    /// Group specific policies take precedence over the global policy.
+    pub fn exit_code_policy(&self, group: &str) -> &ExitCodePolicy {
+        self.group_exit_code_policies
+            .get(group)
+            .unwrap_or(&self.exit_code_policy)
+    }
+}
+
 impl Default for Settings {
     fn default() -> Self {
         Settings {
@@ -139,6 +282,8 @@ impl Default for Settings {
             },
             daemon: Daemon {
                 callback_log_lines: default_callback_log_lines(),
+                state_backup_count: default_state_backup_count(),
+                edit_lock_lease_seconds: default_edit_lock_lease_seconds(),
                 ..Default::default()
             },
             shared: Shared {
@@ -231,6 +376,17 @@ impl Shared {
         }
     }
 
+    /// The address of the daemon's socket.
+    /// This is either the path to the unix socket or `host:port`, if TCP is used.
+    pub fn socket_address(&self) -> String {
+        #[cfg(not(target_os = "windows"))]
+        if self.use_unix_socket {
+            return self.unix_socket_path().to_string_lossy().into_owned();
+        }
+
+        format!("{}:{}", self.host, self.port)
+    }
+
     /// The location of the alias file used by the daemon/client when working with
     /// task aliases.
     pub fn alias_file(&self) -> PathBuf {
@@ -438,4 +594,26 @@ mod test {
 
         panic!("Got unexpected result when expecting missing profile error: {result:?}");
     }
+
+    /// Group specific crash recovery settings should only override the fields that are set.
+    #[test]
+    fn test_group_crash_recovery_override() {
+        let mut settings = Settings::default();
+        settings.daemon.crash_recovery.queued_groups = QueuedGroupPolicy::KeepRunning;
+        settings.daemon.group_crash_recovery.insert(
+            "ingestion".to_string(),
+            CrashRecoveryOverride {
+                interrupted_tasks: Some(InterruptedTaskPolicy::Requeue),
+                queued_groups: None,
+            },
+        );
+
+        let recovery = settings.daemon.crash_recovery("ingestion");
+        assert_eq!(recovery.interrupted_tasks, InterruptedTaskPolicy::Requeue);
+        assert_eq!(recovery.queued_groups, QueuedGroupPolicy::KeepRunning);
+
+        let recovery = settings.daemon.crash_recovery("default");
+        assert_eq!(recovery.interrupted_tasks, InterruptedTaskPolicy::Fail);
+        assert_eq!(recovery.queued_groups, QueuedGroupPolicy::KeepRunning);
+    }
 }
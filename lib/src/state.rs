@@ -1,10 +1,12 @@
 use std::collections::BTreeMap;
 use std::sync::{Arc, Mutex};
 
+use chrono::prelude::*;
 use serde_derive::{Deserialize, Serialize};
 
 use crate::error::Error;
 use crate::task::{Task, TaskStatus};
+use crate::template::TaskTemplate;
 
 pub const PUEUE_DEFAULT_GROUP: &str = "default";
 
@@ -25,6 +27,28 @@ pub struct Group {
     pub parallel_tasks: usize,
 }
 
+/// A set of tasks that have been added together and can be managed as a unit.
+/// In contrast to groups, batches don't have their own queue.
+#[derive(PartialEq, Eq, Clone, Debug, Deserialize, Serialize)]
+pub struct Batch {
+    /// Tasks of paused batches aren't started.
+    pub status: GroupStatus,
+    pub label: Option<String>,
+    pub created: DateTime<Local>,
+}
+
+/// Information about an ongoing drain of the daemon.
+///
+/// While draining, the daemon doesn't start any new tasks and shuts down as soon as all running
+/// tasks finished.
+#[derive(PartialEq, Eq, Clone, Debug, Deserialize, Serialize)]
+pub struct Drain {
+    /// The point in time the drain has been requested.
+    pub start: DateTime<Local>,
+    /// Tasks that are still running after this point in time will be killed.
+    pub deadline: Option<DateTime<Local>>,
+}
+
 /// This is the full representation of the current state of the Pueue daemon.
 ///
 /// This includes
@@ -50,10 +74,23 @@ pub struct Group {
 /// information, such as status changes and incoming commands by the client.
 #[derive(PartialEq, Eq, Clone, Debug, Deserialize, Serialize)]
 pub struct State {
+    /// Increases with every change of the state.
+    /// Clients can use it to detect, whether the state changed since they last looked at it.
+    #[serde(default)]
+    pub revision: u64,
     /// All tasks currently managed by the daemon.
     pub tasks: BTreeMap<usize, Task>,
     /// All groups with their current state a configuration.
     pub groups: BTreeMap<String, Group>,
+    /// All batches that still have tasks.
+    #[serde(default)]
+    pub batches: BTreeMap<usize, Batch>,
+    /// Reusable task templates by their name.
+    #[serde(default)]
+    pub templates: BTreeMap<String, TaskTemplate>,
+    /// This is set, while the daemon is draining.
+    #[serde(default)]
+    pub drain: Option<Drain>,
 }
 
 impl Default for State {
@@ -66,8 +103,12 @@ impl State {
     /// Create a new default state.
     pub fn new() -> State {
         let mut state = State {
+            revision: 0,
             tasks: BTreeMap::new(),
             groups: BTreeMap::new(),
+            batches: BTreeMap::new(),
+            templates: BTreeMap::new(),
+            drain: None,
         };
         state.create_group(PUEUE_DEFAULT_GROUP);
         state
@@ -85,6 +126,31 @@ impl State {
         next_id
     }
 
+    /// Add a new batch and return its id.
+    pub fn add_batch(&mut self, label: Option<String>) -> usize {
+        let next_id = match self.batches.keys().max() {
+            None => 0,
+            Some(id) => id + 1,
+        };
+        self.batches.insert(
+            next_id,
+            Batch {
+                status: GroupStatus::Running,
+                label,
+                created: Local::now(),
+            },
+        );
+
+        next_id
+    }
+
+    /// Remove all batches that no longer contain any tasks.
+    pub fn remove_empty_batches(&mut self) {
+        let tasks = &self.tasks;
+        self.batches
+            .retain(|id, _| tasks.values().any(|task| task.batch == Some(*id)));
+    }
+
     /// A small helper to change the status of a specific task.
     pub fn change_status(&mut self, id: usize, new_status: TaskStatus) {
         if let Some(ref mut task) = self.tasks.get_mut(&id) {
@@ -184,6 +250,21 @@ impl State {
         self.filter_task_ids(task_ids, filter)
     }
 
+    /// Same as [State::filter_tasks], but only checks for tasks of a specific batch.
+    pub fn filter_tasks_of_batch<F>(&self, filter: F, batch: usize) -> (Vec<usize>, Vec<usize>)
+    where
+        F: Fn(&Task) -> bool,
+    {
+        let task_ids = self
+            .tasks
+            .iter()
+            .filter(|(_, task)| task.batch == Some(batch))
+            .map(|(id, _)| *id)
+            .collect();
+
+        self.filter_task_ids(task_ids, filter)
+    }
+
     /// Internal function used to check which of the given tasks match the provided filter.
     ///
     /// Returns a tuple of all (matching_task_ids, non_matching_task_ids).
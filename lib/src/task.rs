@@ -1,9 +1,11 @@
 use std::{collections::HashMap, path::PathBuf};
 
 use chrono::prelude::*;
+use regex::Regex;
 use serde_derive::{Deserialize, Serialize};
-use strum_macros::Display;
+use strum_macros::{Display, EnumString};
 
+use crate::log::LogLimit;
 use crate::state::PUEUE_DEFAULT_GROUP;
 
 /// This enum represents the status of the internal task handling of Pueue.
@@ -28,9 +30,9 @@ pub enum TaskStatus {
 /// It's only used, once a task finished or failed in some kind of way.
 #[derive(PartialEq, Eq, Clone, Debug, Display, Serialize, Deserialize)]
 pub enum TaskResult {
-    /// Task exited with 0
+    /// Task exited with a code, that counts as success. By default, that is only 0.
     Success,
-    /// The task failed in some other kind of way (error code != 0)
+    /// The task exited with any other code.
     Failed(i32),
     /// The task couldn't be spawned. Probably a typo in the command
     FailedToSpawn(String),
@@ -40,6 +42,164 @@ pub enum TaskResult {
     Errored,
     /// A dependency of the task failed.
     DependencyFailed,
+    /// The task has been killed, as its output exceeded the log size limit.
+    LogLimitExceeded,
+    /// The task exited with a code, that marks a temporary failure, and ran out of retries.
+    TemporaryFailure(i32),
+}
+
+impl TaskResult {
+    /// Whether the task failed in any way. \
+    /// Failed tasks fail the tasks that depend on them and might pause their group.
+    pub fn is_failure(&self) -> bool {
+        !matches!(self, TaskResult::Success)
+    }
+}
+
+/// Decides, which exit codes of a task count as success or as temporary failure.
+/// All other exit codes are regular failures.
+///
+/// Tasks with a temporary failure are restarted in place, until they've been restarted
+/// `max_retries` times. After that, they count as failed.
+/// Retries, that have been requested by the task itself, are limited in the same way.
+#[derive(PartialEq, Eq, Clone, Debug, Deserialize, Serialize)]
+pub struct ExitCodePolicy {
+    /// The exit codes, that count as success.
+    #[serde(default = "default_success_codes")]
+    pub success: Vec<i32>,
+    /// The exit codes, that mark a temporary failure.
+    #[serde(default)]
+    pub temporary: Vec<i32>,
+    /// How often a task is restarted after a temporary failure or a requested retry.
+    #[serde(default = "default_max_retries")]
+    pub max_retries: usize,
+}
+
+fn default_success_codes() -> Vec<i32> {
+    vec![0]
+}
+
+fn default_max_retries() -> usize {
+    3
+}
+
+impl Default for ExitCodePolicy {
+    fn default() -> Self {
+        ExitCodePolicy {
+            success: default_success_codes(),
+            temporary: Vec::new(),
+            max_retries: default_max_retries(),
+        }
+    }
+}
+
+impl ExitCodePolicy {
+    /// Get the result of a task, that exited with the given code.
+    pub fn result(&self, exit_code: i32) -> TaskResult {
+        if self.success.contains(&exit_code) {
+            TaskResult::Success
+        } else if self.temporary.contains(&exit_code) {
+            TaskResult::TemporaryFailure(exit_code)
+        } else {
+            TaskResult::Failed(exit_code)
+        }
+    }
+}
+
+/// Rules, that decide whether a task succeeded, depending on its output.
+/// They're checked once the task finished with a successful exit code.
+#[derive(PartialEq, Eq, Clone, Debug, Default, Deserialize, Serialize)]
+pub struct OutputRules {
+    /// The task fails, if any line of its output matches this pattern.
+    #[serde(default)]
+    pub fail_if: Option<OutputPattern>,
+    /// The task fails, unless any line of its output matches this pattern.
+    #[serde(default)]
+    pub succeed_if: Option<OutputPattern>,
+}
+
+/// A regular expression, that's matched against each line of a task's output. \
+/// It's serialized as its pattern and compiled again, once it's deserialized.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct OutputPattern(Regex);
+
+impl OutputPattern {
+    /// Compile a pattern. Fails, if the pattern isn't a valid regular expression.
+    pub fn new(pattern: &str) -> Result<Self, regex::Error> {
+        Regex::new(pattern).map(OutputPattern)
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+
+    pub fn is_match(&self, line: &str) -> bool {
+        self.0.is_match(line)
+    }
+}
+
+impl PartialEq for OutputPattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for OutputPattern {}
+
+impl std::fmt::Display for OutputPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl TryFrom<String> for OutputPattern {
+    type Error = regex::Error;
+
+    fn try_from(pattern: String) -> Result<Self, Self::Error> {
+        OutputPattern::new(&pattern)
+    }
+}
+
+impl From<OutputPattern> for String {
+    fn from(pattern: OutputPattern) -> Self {
+        pattern.as_str().to_string()
+    }
+}
+
+/// What happens, once a running task didn't write any output for its stall timeout.
+#[derive(
+    PartialEq, Eq, Clone, Copy, Debug, Default, Display, EnumString, Deserialize, Serialize,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum StallAction {
+    /// Call the callback with the `stalled` event.
+    #[default]
+    Notify,
+    /// Pause the task.
+    Pause,
+    /// Kill the task.
+    Kill,
+}
+
+/// Detect tasks, that stopped writing output, e.g. hung network transfers or deadlocks.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct StallTimeout {
+    /// The amount of seconds without any output, after which a task is considered stalled.
+    pub seconds: u64,
+    #[serde(default = "Default::default")]
+    pub action: StallAction,
+}
+
+/// The OS process of a task, that has been started detached from the daemon.
+/// This info is persisted, so a restarted daemon can re-adopt the still running process.
+#[derive(PartialEq, Eq, Clone, Debug, Deserialize, Serialize)]
+pub struct DetachedProcess {
+    pub pid: u32,
+    /// The start time of the process in clock ticks after system boot.
+    /// This is used to make sure that the pid hasn't been reused by another process.
+    pub start_time: u64,
 }
 
 /// Representation of a task.
@@ -51,10 +211,42 @@ pub struct Task {
     pub original_command: String,
     pub command: String,
     pub path: PathBuf,
+    /// The working directory, in which the task has been added.
+    /// This is only set, if the alias of the task's command changed the task's path.
+    #[serde(default)]
+    pub original_path: Option<PathBuf>,
     pub envs: HashMap<String, String>,
     pub group: String,
     pub dependencies: Vec<usize>,
     pub label: Option<String>,
+    /// The batch this task belongs to, if it has been added as part of one.
+    #[serde(default)]
+    pub batch: Option<usize>,
+    /// The amount of seconds after which the running task is killed.
+    /// This is the timeout of the template the task has been created from.
+    #[serde(default)]
+    pub timeout: Option<u64>,
+    /// Whether stdout and stderr are captured in separate log files.
+    #[serde(default)]
+    pub separate_output: bool,
+    /// Whether each line of the output is captured with a timestamp.
+    #[serde(default)]
+    pub timestamps: bool,
+    /// The size limit for the task's log files.
+    /// If this isn't set, the limit of the task's group or the global limit is used.
+    #[serde(default)]
+    pub log_limit: Option<LogLimit>,
+    /// Decides, which exit codes count as success or as temporary failure.
+    /// If this isn't set, the policy of the task's group or the global policy is used.
+    #[serde(default)]
+    pub exit_code_policy: Option<ExitCodePolicy>,
+    /// Rules, that decide whether the task succeeded, depending on its output.
+    #[serde(default)]
+    pub output_rules: Option<OutputRules>,
+    /// Detect, whether the task stopped writing output.
+    /// If this isn't set, the stall timeout of the task's group or the global one is used.
+    #[serde(default)]
+    pub stall_timeout: Option<StallTimeout>,
     pub status: TaskStatus,
     /// This field is only used when editing the path/command of a task.
     /// It's necessary, since we enter the `Locked` state during editing.
@@ -64,6 +256,33 @@ pub struct Task {
     pub prev_status: TaskStatus,
     pub start: Option<DateTime<Local>>,
     pub end: Option<DateTime<Local>>,
+    /// Why the task failed, if that isn't obvious from its result.
+    /// E.g. if its output violated one of its [OutputRules].
+    #[serde(default)]
+    pub failure_reason: Option<String>,
+    /// How often the task has been started.
+    #[serde(default)]
+    pub attempt: usize,
+    /// How often the task has been restarted in place, since it has last been started or
+    /// restarted by the user. This is limited by [ExitCodePolicy::max_retries].
+    #[serde(default)]
+    pub retries: usize,
+    /// The hash of the token of the task's current run, which allows it to talk to the daemon.
+    /// This is generated every time the task is started and removed once it finished.
+    ///
+    /// The token itself is a secret, which is only known to the task's process.
+    /// Only its hash is persisted, so re-adopted tasks can still talk to the daemon.
+    #[serde(default)]
+    pub token_hash: Option<String>,
+    /// The progress, that has last been reported by the task's current run.
+    #[serde(default)]
+    pub progress: Option<String>,
+    /// Whether the task requested to be restarted in place, once it finished.
+    #[serde(default)]
+    pub retry: bool,
+    /// Only set while the task is running in detached mode.
+    #[serde(default)]
+    pub detached_process: Option<DetachedProcess>,
 }
 
 impl Task {
@@ -82,14 +301,30 @@ impl Task {
             original_command: original_command.clone(),
             command: original_command,
             path,
+            original_path: None,
             envs,
             group,
             dependencies,
             label,
+            batch: None,
+            timeout: None,
+            separate_output: false,
+            timestamps: false,
+            log_limit: None,
+            exit_code_policy: None,
+            output_rules: None,
+            stall_timeout: None,
             status: starting_status.clone(),
             prev_status: starting_status,
             start: None,
             end: None,
+            failure_reason: None,
+            attempt: 0,
+            retries: 0,
+            token_hash: None,
+            progress: None,
+            retry: false,
+            detached_process: None,
         }
     }
 
@@ -100,14 +335,30 @@ impl Task {
             original_command: task.original_command.clone(),
             command: task.command.clone(),
             path: task.path.clone(),
+            original_path: task.original_path.clone(),
             envs: task.envs.clone(),
             group: task.group.clone(),
             dependencies: Vec::new(),
             label: task.label.clone(),
+            batch: task.batch,
+            timeout: task.timeout,
+            separate_output: task.separate_output,
+            timestamps: task.timestamps,
+            log_limit: task.log_limit,
+            exit_code_policy: task.exit_code_policy.clone(),
+            output_rules: task.output_rules.clone(),
+            stall_timeout: task.stall_timeout,
             status: TaskStatus::Queued,
             prev_status: TaskStatus::Queued,
             start: None,
             end: None,
+            failure_reason: None,
+            attempt: 0,
+            retries: 0,
+            token_hash: None,
+            progress: None,
+            retry: false,
+            detached_process: None,
         }
     }
 
@@ -127,7 +378,7 @@ impl Task {
     /// 2. Didn't finish yet.
     pub fn failed(&self) -> bool {
         match &self.status {
-            TaskStatus::Done(result) => !matches!(result, TaskResult::Success),
+            TaskStatus::Done(result) => result.is_failure(),
             _ => false,
         }
     }
@@ -158,14 +409,30 @@ impl std::fmt::Debug for Task {
             .field("original_command", &self.original_command)
             .field("command", &self.command)
             .field("path", &self.path)
+            .field("original_path", &self.original_path)
             .field("envs", &"hidden")
             .field("group", &self.group)
             .field("dependencies", &self.dependencies)
             .field("label", &self.label)
+            .field("batch", &self.batch)
+            .field("timeout", &self.timeout)
+            .field("separate_output", &self.separate_output)
+            .field("timestamps", &self.timestamps)
+            .field("log_limit", &self.log_limit)
+            .field("exit_code_policy", &self.exit_code_policy)
+            .field("output_rules", &self.output_rules)
+            .field("stall_timeout", &self.stall_timeout)
             .field("status", &self.status)
             .field("prev_status", &self.prev_status)
             .field("start", &self.start)
             .field("end", &self.end)
+            .field("failure_reason", &self.failure_reason)
+            .field("attempt", &self.attempt)
+            .field("retries", &self.retries)
+            .field("token_hash", &self.token_hash)
+            .field("progress", &self.progress)
+            .field("retry", &self.retry)
+            .field("detached_process", &self.detached_process)
             .finish()
     }
 }
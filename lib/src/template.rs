@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::aliasing::{fill_placeholders, find_placeholders};
+
+/// Determines which dependencies tasks get, that are created from a template.
+#[derive(PartialEq, Eq, Clone, Debug, Default, Deserialize, Serialize)]
+pub enum DependencyPolicy {
+    /// Tasks are independent of each other.
+    #[default]
+    Independent,
+    /// Each task depends on the previous task of the same template, if that task is still
+    /// unfinished. This way, tasks of a template are executed one after another.
+    AfterPrevious,
+}
+
+/// A reusable recipe for tasks, which is stored in the daemon's state.
+///
+/// The command and the label may contain named parameters (`{name}`) with optional default
+/// values (`{name=default}`), which are filled in when the template is instantiated.
+#[derive(PartialEq, Eq, Clone, Debug, Deserialize, Serialize)]
+pub struct TaskTemplate {
+    pub command: String,
+    pub group: Option<String>,
+    /// The working directory of the tasks. Relative paths are resolved against the working
+    /// directory of the client.
+    pub path: Option<PathBuf>,
+    pub envs: HashMap<String, String>,
+    pub label: Option<String>,
+    /// The amount of seconds after which tasks are killed.
+    pub timeout: Option<u64>,
+    pub dependencies: DependencyPolicy,
+    /// The id of the last task that has been created from this template.
+    pub last_task: Option<usize>,
+}
+
+impl TaskTemplate {
+    /// All parameters of the command and the label, in order of their first usage.
+    pub fn parameters(&self) -> Vec<String> {
+        let mut parameters: Vec<String> = Vec::new();
+        let label = self.label.as_deref().unwrap_or_default();
+        for placeholder in find_placeholders(&self.command)
+            .iter()
+            .chain(find_placeholders(label).iter())
+        {
+            if !parameters.iter().any(|name| name == placeholder.name) {
+                parameters.push(placeholder.name.to_string());
+            }
+        }
+
+        parameters
+    }
+
+    /// Fill in the given parameters and return the resulting command and label.
+    ///
+    /// Fails, if a parameter is missing or if an unknown parameter is given.
+    pub fn render(
+        &self,
+        parameters: &HashMap<String, String>,
+    ) -> Result<(String, Option<String>), String> {
+        let known = self.parameters();
+        let mut unknown: Vec<&String> = parameters
+            .keys()
+            .filter(|name| !known.contains(name))
+            .collect();
+        if !unknown.is_empty() {
+            unknown.sort();
+            return Err(format!("Unknown parameter(s) {unknown:?}"));
+        }
+
+        let lookup = |name: &str| parameters.get(name).map(String::as_str);
+        let command = fill_placeholders(&self.command, lookup)?;
+        let label = match &self.label {
+            Some(label) => Some(fill_placeholders(label, lookup)?),
+            None => None,
+        };
+
+        Ok((command, label))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn get_template() -> TaskTemplate {
+        TaskTemplate {
+            command: "backup.sh {target} --keep {keep=7}".to_string(),
+            group: None,
+            path: None,
+            envs: HashMap::new(),
+            label: Some("backup {target}".to_string()),
+            timeout: None,
+            dependencies: DependencyPolicy::Independent,
+            last_task: None,
+        }
+    }
+
+    #[test]
+    fn render_with_defaults() {
+        let template = get_template();
+        assert_eq!(template.parameters(), vec!["target", "keep"]);
+
+        let parameters = HashMap::from([("target".to_string(), "db".to_string())]);
+        let (command, label) = template.render(&parameters).unwrap();
+        assert_eq!(command, "backup.sh db --keep 7");
+        assert_eq!(label, Some("backup db".to_string()));
+    }
+
+    #[test]
+    fn render_rejects_invalid_parameters() {
+        let template = get_template();
+        assert!(template.render(&HashMap::new()).is_err());
+
+        let parameters = HashMap::from([
+            ("target".to_string(), "db".to_string()),
+            ("typo".to_string(), "1".to_string()),
+        ]);
+        assert!(template.render(&parameters).is_err());
+    }
+}
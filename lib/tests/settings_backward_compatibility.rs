@@ -31,6 +31,8 @@ fn test_restore_from_old_state() -> Result<()> {
     #[allow(deprecated)]
     let groups = settings.daemon.groups.unwrap();
     assert_eq!(*groups.get("webhook").unwrap(), 1);
+    // Logs are only compressed, if this is explicitly enabled.
+    assert!(!settings.daemon.compress_logs);
 
     Ok(())
 }
@@ -0,0 +1,150 @@
+use std::fs::write;
+
+use anyhow::Result;
+use pretty_assertions::assert_eq;
+
+use crate::fixtures::*;
+use crate::helper::*;
+
+/// Adding a task with a matrix creates one task per combination of parameters in a new batch.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn add_matrix() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    run_client_command(
+        shared,
+        &[
+            "add",
+            "--stashed",
+            "--matrix",
+            "size=small,large",
+            "--matrix",
+            "seed=1..2",
+            "--",
+            "train {size} {seed}",
+        ],
+    )?;
+
+    let state = get_state(shared).await?;
+    assert_eq!(state.tasks.len(), 4);
+    assert_eq!(state.batches.len(), 1);
+
+    let task = state.tasks.get(&3).unwrap();
+    assert_eq!(task.command, "train large 2");
+    assert_eq!(task.label, Some("size=large seed=2".to_string()));
+    assert_eq!(task.envs.get("size"), Some(&"large".to_string()));
+    assert_eq!(task.batch, Some(0));
+
+    Ok(())
+}
+
+/// Invalid matrix parameters are rejected by the client.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn add_invalid_matrix() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    let result = run_client_command(shared, &["add", "--matrix", "seed=5..1", "--", "ls"]);
+    assert!(result.is_err());
+
+    let state = get_state(shared).await?;
+    assert!(state.tasks.is_empty());
+
+    Ok(())
+}
+
+/// Output rules have to be valid regular expressions, which is checked by the client.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn add_invalid_output_rule() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    let result = run_client_command(
+        shared,
+        &["add", "--fail-if-output", "(unclosed", "--", "ls"],
+    );
+    assert!(result.is_err());
+
+    let state = get_state(shared).await?;
+    assert!(state.tasks.is_empty());
+
+    Ok(())
+}
+
+/// Durations that don't fit into a number of seconds are rejected by the client.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn add_overflowing_duration() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    let result = run_client_command(
+        shared,
+        &[
+            "add",
+            "--stall-timeout",
+            "18446744073709551615d",
+            "--",
+            "ls",
+        ],
+    );
+    let error = result.expect_err("The overflowing duration should be rejected");
+    assert!(error.to_string().contains("the duration is too large"));
+
+    let state = get_state(shared).await?;
+    assert!(state.tasks.is_empty());
+
+    Ok(())
+}
+
+/// Sizes that don't fit into a number of bytes are rejected by the client.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn add_overflowing_size() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    let result = run_client_command(
+        shared,
+        &["add", "--log-limit", "18446744073709551615G", "--", "ls"],
+    );
+    let error = result.expect_err("The overflowing size should be rejected");
+    assert!(error.to_string().contains("the size is too large"));
+
+    let state = get_state(shared).await?;
+    assert!(state.tasks.is_empty());
+
+    Ok(())
+}
+
+/// The parameters and defaults of an alias can be inspected.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn show_alias() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    let aliases = r#"
+ll: ls -al
+train:
+  command: python train.py --model {1} --lr {lr=0.01}
+  description: Train a model
+  group: gpu
+"#;
+    write(daemon.tempdir.path().join("pueue_aliases.yml"), aliases)?;
+
+    let output = run_client_command(shared, &["alias"])?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("ll: ls -al"), "Unexpected output: {stdout}");
+    assert!(stdout.contains("train: python train.py --model {1} --lr {lr=0.01}"));
+
+    let output = run_client_command(shared, &["alias", "show", "train"])?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("lr (default: 0.01)"),
+        "Unexpected output: {stdout}"
+    );
+    assert!(stdout.contains("Group: gpu"));
+
+    assert!(run_client_command(shared, &["alias", "show", "unknown"]).is_err());
+
+    Ok(())
+}
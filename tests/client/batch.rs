@@ -0,0 +1,77 @@
+use anyhow::Result;
+use pretty_assertions::assert_eq;
+use pueue_lib::task::{TaskResult, TaskStatus};
+
+use crate::fixtures::*;
+use crate::helper::*;
+
+/// Manage all tasks of a batch at once and inspect its aggregated status.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn batch_lifecycle() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    run_client_command(
+        shared,
+        &[
+            "add",
+            "--matrix",
+            "code=0,1",
+            "--label",
+            "sweep",
+            "--",
+            "exit {code}",
+        ],
+    )?;
+    run_client_command(shared, &["batch", "wait", "--quiet", "0"])?;
+
+    let output = run_client_command(shared, &["batch"])?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Batch 0 \"sweep\" (running): 0 queued, 0 running, 1 succeeded, 1 failed"),
+        "Unexpected batch output: {stdout}"
+    );
+
+    // Only the failed task is restarted and the new task is part of the same batch.
+    run_client_command(shared, &["batch", "restart-failed", "0"])?;
+    run_client_command(shared, &["batch", "wait", "--quiet", "0"])?;
+    let state = get_state(shared).await?;
+    assert_eq!(state.tasks.len(), 3);
+    let restarted = state.tasks.get(&2).unwrap();
+    assert_eq!(restarted.command, "exit 1");
+    assert_eq!(restarted.batch, Some(0));
+
+    // Cleaning the batch removes all of its finished tasks and thereby the batch itself.
+    run_client_command(shared, &["batch", "clean", "0"])?;
+    let state = get_state(shared).await?;
+    assert!(state.tasks.is_empty());
+    assert!(state.batches.is_empty());
+
+    Ok(())
+}
+
+/// Killing a batch only affects the tasks of that batch.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn batch_kill() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    run_client_command(
+        shared,
+        &["add", "--matrix", "time=60", "--", "sleep {time}"],
+    )?;
+    wait_for_task_condition(shared, 0, |task| task.is_running()).await?;
+    add_task(shared, "sleep 60", false).await?;
+
+    run_client_command(shared, &["batch", "kill", "0"])?;
+    wait_for_task_condition(shared, 0, |task| task.is_done()).await?;
+
+    let state = get_state(shared).await?;
+    assert_eq!(
+        state.tasks.get(&0).unwrap().status,
+        TaskStatus::Done(TaskResult::Killed)
+    );
+    assert!(!state.tasks.get(&1).unwrap().is_done());
+
+    Ok(())
+}
@@ -36,6 +36,51 @@ async fn edit_task_default() -> Result<()> {
     Ok(())
 }
 
+/// Test that edits for an outdated revision of the state are rejected.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn edit_if_revision() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    for _ in 0..2 {
+        let mut message = create_add_message(shared, "this is a test");
+        message.stashed = true;
+        send_message(shared, message)
+            .await
+            .context("Failed to to add stashed task.")?;
+    }
+    let revision = get_state(shared).await?.revision;
+    let outdated = (revision - 1).to_string();
+    let current = revision.to_string();
+
+    // Neither single nor multiple tasks are edited for an outdated revision.
+    for task_ids in ["0", "0-1"] {
+        let mut envs = HashMap::new();
+        envs.insert("EDITOR", "sed -i 's/this is a test/edited/'");
+        let result = run_client_command_with_env(
+            shared,
+            &["--if-revision", &outdated, "edit", task_ids],
+            envs,
+        );
+        assert!(result.is_err(), "Edit for an outdated revision succeeded");
+
+        let state = get_state(shared).await?;
+        assert_eq!(state.tasks[&0].command, "this is a test");
+        assert_eq!(
+            state.tasks[&0].status,
+            TaskStatus::Stashed { enqueue_at: None }
+        );
+    }
+
+    // Editing for the current revision works.
+    let mut envs = HashMap::new();
+    envs.insert("EDITOR", "echo 'edited' > ");
+    run_client_command_with_env(shared, &["--if-revision", &current, "edit", "0"], envs)?;
+    assert_eq!(get_state(shared).await?.tasks[&0].command, "edited");
+
+    Ok(())
+}
+
 /// Test that editing a multiple task properties works as expected.
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
 async fn edit_all_task_properties() -> Result<()> {
@@ -68,6 +113,127 @@ async fn edit_all_task_properties() -> Result<()> {
     Ok(())
 }
 
+/// Test that environment variables, the group and the dependencies of a task can be edited.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn edit_envs_group_and_dependencies() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    // Create two stashed tasks, the second one will be edited.
+    for _ in 0..2 {
+        let mut message = create_add_message(shared, "this is a test");
+        message.stashed = true;
+        message.envs = HashMap::new();
+        send_message(shared, message)
+            .await
+            .context("Failed to to add stashed task.")?;
+    }
+
+    let mut envs = HashMap::new();
+    envs.insert("EDITOR", "printf 'FIRST=1\\nSECOND=a=b\\n' > ");
+    run_client_command_with_env(shared, &["edit", "--envs", "1"], envs)?;
+
+    let mut envs = HashMap::new();
+    envs.insert("EDITOR", "echo 'test_2' > ");
+    run_client_command_with_env(shared, &["edit", "--group", "1"], envs)?;
+
+    let mut envs = HashMap::new();
+    envs.insert("EDITOR", "echo '0' > ");
+    run_client_command_with_env(shared, &["edit", "--dependencies", "1"], envs)?;
+
+    let state = get_state(shared).await?;
+    let task = state.tasks.get(&1).unwrap();
+    assert_eq!(task.command, "this is a test");
+    assert_eq!(task.envs.len(), 2);
+    assert_eq!(task.envs["FIRST"], "1");
+    assert_eq!(task.envs["SECOND"], "a=b");
+    assert_eq!(task.group, "test_2");
+    assert_eq!(task.dependencies, vec![0]);
+
+    // A task cannot depend on itself.
+    let mut envs = HashMap::new();
+    envs.insert("EDITOR", "echo '1' > ");
+    let command = run_client_command_with_env(shared, &["edit", "--dependencies", "1"], envs);
+    assert!(command.is_err(), "Self dependencies should be rejected");
+
+    Ok(())
+}
+
+/// Test that multiple tasks can be edited in a single file, selected by ids or by a query.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn edit_multiple_tasks() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    for label in ["first", "second", "third"] {
+        let mut message = create_add_message(shared, "this is a test");
+        message.stashed = true;
+        message.label = Some(label.to_string());
+        send_message(shared, message)
+            .await
+            .context("Failed to to add stashed task.")?;
+    }
+
+    // Edit the first two tasks via a range.
+    let mut envs = HashMap::new();
+    envs.insert(
+        "EDITOR",
+        "sed -i 's/command: this is a test/command: edited/'",
+    );
+    run_client_command_with_env(shared, &["edit", "0-1"], envs)?;
+
+    let state = get_state(shared).await?;
+    assert_eq!(state.tasks[&0].command, "edited");
+    assert_eq!(state.tasks[&1].command, "edited");
+    assert_eq!(state.tasks[&2].command, "this is a test");
+    for task in state.tasks.values() {
+        assert_eq!(task.status, TaskStatus::Stashed { enqueue_at: None });
+    }
+
+    // Edit the label of the last task via a query.
+    let mut envs = HashMap::new();
+    envs.insert("EDITOR", "sed -i 's/label: third/label: changed/'");
+    run_client_command_with_env(shared, &["edit", "--query", "label=third"], envs)?;
+
+    let state = get_state(shared).await?;
+    assert_eq!(state.tasks[&2].label, Some("changed".to_string()));
+    assert_eq!(state.tasks[&2].command, "this is a test");
+
+    // Tasks in the range that don't exist are skipped.
+    let mut envs = HashMap::new();
+    envs.insert("EDITOR", "sed -i 's/command: edited/command: again/'");
+    run_client_command_with_env(shared, &["edit", "1-5"], envs)?;
+    let state = get_state(shared).await?;
+    assert_eq!(state.tasks[&0].command, "edited");
+    assert_eq!(state.tasks[&1].command, "again");
+
+    // Huge ranges are limited to the existing tasks.
+    let mut envs = HashMap::new();
+    envs.insert("EDITOR", "sed -i 's/command: again/command: huge/'");
+    run_client_command_with_env(shared, &["edit", "1-18446744073709551615"], envs)?;
+    let state = get_state(shared).await?;
+    assert_eq!(state.tasks[&1].command, "huge");
+
+    // Flags that select single properties are rejected for multiple tasks.
+    let mut envs = HashMap::new();
+    envs.insert("EDITOR", "sed -i 's/command: huge/command: flags/'");
+    let command = run_client_command_with_env(shared, &["edit", "--command", "0-1"], envs);
+    assert!(command.is_err(), "Property flags should be rejected");
+
+    // Removing a task from the file aborts the edit.
+    let mut envs = HashMap::new();
+    envs.insert("EDITOR", "echo '[]' > ");
+    let command = run_client_command_with_env(shared, &["edit", "0", "1"], envs);
+    assert!(command.is_err(), "Removing tasks should be rejected");
+    let state = get_state(shared).await?;
+    assert_eq!(
+        state.tasks[&0].status,
+        TaskStatus::Stashed { enqueue_at: None }
+    );
+
+    Ok(())
+}
+
 /// Ensure that deleting the label in the editor result in the deletion of the task's label.
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
 async fn edit_delete_label() -> Result<()> {
@@ -70,6 +70,159 @@ async fn read_truncated(#[case] read_local_logs: bool) -> Result<()> {
     Ok(())
 }
 
+/// Test that `log --timestamps --since` shows the timestamped output for:
+/// - The log being streamed by the daemon.
+/// - The log being read from the local files.
+#[rstest]
+#[case(true)]
+#[case(false)]
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn read_timestamps(#[case] read_local_logs: bool) -> Result<()> {
+    let mut daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    // Force the client to read remote logs via config file.
+    daemon.settings.client.read_local_logs = read_local_logs;
+    // Persist the change, so it can be seen by the client.
+    daemon
+        .settings
+        .save(&Some(daemon.tempdir.path().join("pueue.yml")))
+        .context("Couldn't write pueue config to temporary directory")?;
+
+    // Add a task and wait until it finishes.
+    run_client_command(shared, &["add", "--timestamps", "echo test"])?;
+    wait_for_task_condition(shared, 0, |task| task.is_done()).await?;
+
+    let output = run_client_command(shared, &["log", "--timestamps", "--since", "1h"])?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let line = stdout.lines().last().context("Didn't get any output")?;
+    assert!(
+        line.starts_with('[') && line.ends_with("] test"),
+        "Expected timestamped output, got: {stdout}"
+    );
+
+    Ok(())
+}
+
+/// Only the requested part of the output is shown, together with a hint which part it is,
+/// for both:
+/// - The log being streamed by the daemon.
+/// - The log being read from the local files.
+#[rstest]
+#[case(true)]
+#[case(false)]
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn read_range(#[case] read_local_logs: bool) -> Result<()> {
+    let mut daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    daemon.settings.client.read_local_logs = read_local_logs;
+    daemon
+        .settings
+        .save(&Some(daemon.tempdir.path().join("pueue.yml")))
+        .context("Couldn't write pueue config to temporary directory")?;
+
+    assert_success(add_task(shared, "seq 1 20", false).await?);
+    wait_for_task_condition(shared, 0, |task| task.is_done()).await?;
+
+    let cases: [(&[&str], &str); 3] = [
+        (&["--head", "2"], "(first 2 lines)\n1\n2\n"),
+        (&["--line-range", "10-12"], "(lines 10-12)\n10\n11\n12\n"),
+        (&["--byte-range", "45-"], "(bytes 45-)\n19\n20\n"),
+    ];
+    for (args, expected) in cases {
+        let output = run_client_command(shared, &[&["log"], args].concat())?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.ends_with(expected), "Unexpected output: {stdout}");
+    }
+
+    Ok(())
+}
+
+/// Compressed logs of finished tasks are shown just like plain logs, both when they're read
+/// from the local files and when they're sent by the daemon.
+#[rstest]
+#[case(true)]
+#[case(false)]
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn read_compressed(#[case] read_local_logs: bool) -> Result<()> {
+    let (mut settings, tempdir) = daemon_base_setup()?;
+    settings.daemon.compress_logs = true;
+    settings.client.read_local_logs = read_local_logs;
+    settings
+        .save(&Some(tempdir.path().join("pueue.yml")))
+        .context("Couldn't write pueue config to temporary directory")?;
+    let daemon = daemon_with_settings(settings, tempdir).await?;
+    let shared = &daemon.settings.shared;
+
+    run_client_command(shared, &["add", "seq 1 20"])?;
+    wait_for_task_condition(shared, 0, |task| task.is_done()).await?;
+    wait_for_compressed_log(shared, 0).await?;
+
+    let output = run_client_command(shared, &["log", "--lines", "2"])?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.ends_with("(last 2 lines)\n19\n20\n"),
+        "Unexpected output: {stdout}"
+    );
+
+    Ok(())
+}
+
+/// `grep` prints the matching lines and their context of all selected tasks for:
+/// - The logs being searched by the daemon.
+/// - The logs being searched locally.
+#[rstest]
+#[case(true)]
+#[case(false)]
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn grep(#[case] read_local_logs: bool) -> Result<()> {
+    let mut daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    daemon.settings.client.read_local_logs = read_local_logs;
+    daemon
+        .settings
+        .save(&Some(daemon.tempdir.path().join("pueue.yml")))
+        .context("Couldn't write pueue config to temporary directory")?;
+
+    run_client_command(shared, &["add", "echo 'first\nSecond\nthird'"])?;
+    run_client_command(shared, &["add", "--label", "other", "echo 'second'"])?;
+    wait_for_task_condition(shared, 0, |task| task.is_done()).await?;
+    wait_for_task_condition(shared, 1, |task| task.is_done()).await?;
+
+    let output = run_client_command(shared, &["grep", "second"])?;
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "1:1:second\n");
+
+    let output = run_client_command(shared, &["grep", "-i", "-C", "1", "second"])?;
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "0-1-first\n0:2:Second\n0-3-third\n--\n1:1:second\n"
+    );
+
+    // Only tasks matching the query are searched.
+    let output = run_client_command(shared, &["grep", "-i", "-q", "label=other", "second"])?;
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "1:1:second\n");
+
+    // Patterns are regular expressions, unless they're searched as fixed strings.
+    let output = run_client_command(shared, &["grep", "^[st]"])?;
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "0:3:third\n1:1:second\n"
+    );
+    assert!(run_client_command(shared, &["grep", "-F", "^[st]"]).is_err());
+
+    // The search stops after the maximum of matching lines.
+    let output = run_client_command(shared, &["grep", "-m", "1", "^[st]"])?;
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "0:3:third\n");
+
+    // The command fails, if nothing matches or the pattern is invalid.
+    assert!(run_client_command(shared, &["grep", "fourth"]).is_err());
+    assert!(run_client_command(shared, &["grep", "("]).is_err());
+
+    Ok(())
+}
+
 /// If a task has a label, it is included in the log output
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
 async fn task_with_label() -> Result<()> {
@@ -88,6 +241,27 @@ async fn task_with_label() -> Result<()> {
     Ok(())
 }
 
+/// If a task failed due to its output, the reason is included in the log output.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn task_with_failure_reason() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    // Add a task, that exits successfully, but fails due to its output.
+    run_client_command(shared, &["add", "--fail-if-output", "ERROR", "echo ERROR"])?;
+    wait_for_task_condition(shared, 0, |task| task.is_done()).await?;
+
+    let output = run_client_command(shared, &["log"])?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("failed with exit code 0"), "{stdout}");
+    assert!(
+        stdout.contains("Failure reason: Output matches \"ERROR\""),
+        "{stdout}"
+    );
+
+    Ok(())
+}
+
 /// Calling `log` with the `--color=always` flag, colors the output as expected.
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
 async fn colored() -> Result<()> {
@@ -116,11 +290,20 @@ pub struct TaskLog {
 }
 
 /// Calling `pueue log --json` prints the expected json output to stdout.
+#[rstest]
+#[case(true)]
+#[case(false)]
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn json() -> Result<()> {
-    let daemon = daemon().await?;
+async fn json(#[case] read_local_logs: bool) -> Result<()> {
+    let mut daemon = daemon().await?;
     let shared = &daemon.settings.shared;
 
+    daemon.settings.client.read_local_logs = read_local_logs;
+    daemon
+        .settings
+        .save(&Some(daemon.tempdir.path().join("pueue.yml")))
+        .context("Couldn't write pueue config to temporary directory")?;
+
     // Add a task and wait until it finishes.
     assert_success(add_task(shared, "echo test", false).await?);
     wait_for_task_condition(shared, 0, |task| task.is_done()).await?;
@@ -145,10 +328,43 @@ async fn json() -> Result<()> {
     );
 
     // Append a newline to the deserialized task's output, which is automatically done when working
-    // with the shell.
-    task_log.output.push('\n');
+    // with the shell. The remote output already contains the task's final newline.
+    if read_local_logs {
+        task_log.output.push('\n');
+    }
 
     assert_stdout_matches("log__json", task_log.output.clone().into(), HashMap::new())?;
 
     Ok(())
 }
+
+/// The json output of remote logs is written while it's being received in chunks.
+/// Characters that are split between two chunks are still written correctly.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn json_full_chunked() -> Result<()> {
+    let mut daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    daemon.settings.client.read_local_logs = false;
+    daemon
+        .settings
+        .save(&Some(daemon.tempdir.path().join("pueue.yml")))
+        .context("Couldn't write pueue config to temporary directory")?;
+
+    // Print more than a single chunk of three-byte characters without any newlines.
+    assert_success(add_task(shared, "printf '€%.0s' $(seq 1 100000)", false).await?);
+    assert_success(add_task(shared, "echo test", false).await?);
+    wait_for_task_condition(shared, 1, |task| task.is_done()).await?;
+
+    let output = run_client_command(shared, &["log", "--json", "--full"])?;
+
+    let json = String::from_utf8_lossy(&output.stdout);
+    let task_logs: BTreeMap<usize, TaskLog> = serde_json::from_str(&json)
+        .context(format!("Failed to deserialize json tasks: \n{json}"))?;
+
+    assert_eq!(task_logs.len(), 2);
+    assert_eq!(task_logs[&0].output, "€".repeat(100000));
+    assert_eq!(task_logs[&1].output, "test\n");
+
+    Ok(())
+}
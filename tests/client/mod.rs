@@ -1,3 +1,5 @@
+mod add;
+mod batch;
 mod completions;
 mod edit;
 mod follow;
@@ -6,4 +8,8 @@ mod log;
 mod restart;
 mod status;
 mod status_query;
+mod submit;
+/// Tests for `pueue task`, which is called from within tasks.
+mod task_control;
+mod template;
 mod wait;
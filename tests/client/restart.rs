@@ -104,3 +104,66 @@ async fn restart_and_edit_task_path_and_command() -> Result<()> {
 
     Ok(())
 }
+
+/// Test that in-place restarts for an outdated revision of the state are rejected.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn restart_if_revision() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    // Create a task and wait for it to finish.
+    assert_success(add_task(shared, "ls", false).await?);
+    wait_for_task_condition(shared, 0, |task| task.is_done()).await?;
+    let state = get_state(shared).await?;
+    let outdated = (state.revision - 1).to_string();
+    let current = state.revision.to_string();
+
+    // The restart fails, as the state changed since the outdated revision.
+    let result = run_client_command(
+        shared,
+        &["--if-revision", &outdated, "restart", "--in-place", "0"],
+    );
+    assert!(
+        result.is_err(),
+        "Restart for an outdated revision succeeded"
+    );
+    assert_eq!(get_state(shared).await?.tasks[&0], state.tasks[&0]);
+
+    // Restarting for the current revision works.
+    run_client_command(
+        shared,
+        &["restart", "--in-place", "0", "--if-revision", &current],
+    )?;
+    assert!(get_state(shared).await?.revision > state.revision);
+
+    Ok(())
+}
+
+/// Test that restarts with new tasks for an outdated revision of the state are rejected.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn restart_not_in_place_if_revision() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    // Create two tasks and wait for them to finish.
+    assert_success(add_task(shared, "ls", false).await?);
+    assert_success(add_task(shared, "ls", false).await?);
+    wait_for_task_condition(shared, 1, |task| task.is_done()).await?;
+    let revision = get_state(shared).await?.revision;
+
+    // No task is added, as the state changed since the outdated revision.
+    let outdated = (revision - 1).to_string();
+    let result = run_client_command(shared, &["--if-revision", &outdated, "restart", "0", "1"]);
+    assert!(
+        result.is_err(),
+        "Restart for an outdated revision succeeded"
+    );
+    assert_eq!(get_state(shared).await?.tasks.len(), 2);
+
+    // Both tasks are added for the current revision.
+    let current = revision.to_string();
+    run_client_command(shared, &["--if-revision", &current, "restart", "0", "1"])?;
+    assert_eq!(get_state(shared).await?.tasks.len(), 4);
+
+    Ok(())
+}
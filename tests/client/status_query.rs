@@ -76,7 +76,7 @@ async fn daemon_with_test_state(with_label: bool) -> Result<PueueDaemon> {
     state.tasks.insert(scheduled.id, scheduled);
 
     // Save the state in our temporary directory. This makes it readable by the daemon.
-    save_state(&state, &settings)?;
+    save_state(&mut state, &settings)?;
 
     // ------ Daemon setup -------
     // Start the daemon. It will restore the state we just saved.
@@ -0,0 +1,159 @@
+use std::fs::write;
+
+use anyhow::Result;
+use pretty_assertions::assert_eq;
+use pueue_lib::task::TaskStatus;
+
+use crate::fixtures::*;
+use crate::helper::*;
+
+/// Submit a workflow file and make sure that the symbolic names are resolved to dependencies.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn submit_workflow() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    let workflow = r#"
+tasks:
+  test:
+    command: make test
+    needs: [build]
+  build:
+    command: make
+    label: Build it
+"#;
+    let workflow_path = daemon.tempdir.path().join("workflow.yml");
+    write(&workflow_path, workflow)?;
+
+    let output = run_client_command(
+        shared,
+        &["submit", "--stashed", workflow_path.to_str().unwrap()],
+    )?;
+    assert!(output.status.success());
+
+    // The build task has to be added first, as the test task needs it.
+    let state = get_state(shared).await?;
+    let build = state.tasks.get(&0).unwrap();
+    assert_eq!(build.command, "make");
+    assert_eq!(build.label, Some("Build it".to_string()));
+    assert_eq!(build.status, TaskStatus::Stashed { enqueue_at: None });
+
+    let test = state.tasks.get(&1).unwrap();
+    assert_eq!(test.command, "make test");
+    assert_eq!(test.label, Some("test".to_string()));
+    assert_eq!(test.dependencies, vec![0]);
+
+    Ok(())
+}
+
+/// Workflows aren't submitted for an outdated revision of the state.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn submit_workflow_if_revision() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    let workflow = r#"
+tasks:
+  build:
+    command: make
+"#;
+    let workflow_path = daemon.tempdir.path().join("workflow.yml");
+    write(&workflow_path, workflow)?;
+    let workflow_path = workflow_path.to_str().unwrap();
+
+    let mut message = create_add_message(shared, "ls");
+    message.stashed = true;
+    assert_success(send_message(shared, message).await?);
+    let revision = get_state(shared).await?.revision;
+
+    let outdated = (revision - 1).to_string();
+    let result = run_client_command(
+        shared,
+        &[
+            "--if-revision",
+            &outdated,
+            "submit",
+            "--stashed",
+            workflow_path,
+        ],
+    );
+    assert!(result.is_err(), "Submit for an outdated revision succeeded");
+    assert_eq!(get_state(shared).await?.tasks.len(), 1);
+
+    let current = revision.to_string();
+    run_client_command(
+        shared,
+        &[
+            "--if-revision",
+            &current,
+            "submit",
+            "--stashed",
+            workflow_path,
+        ],
+    )?;
+    assert_eq!(get_state(shared).await?.tasks.len(), 2);
+
+    Ok(())
+}
+
+/// Workflows with cyclic needs should be rejected without adding any tasks.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn submit_cyclic_workflow() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    let workflow = r#"
+tasks:
+  build:
+    command: make
+    needs: [test]
+  test:
+    command: make test
+    needs: [build]
+"#;
+    let workflow_path = daemon.tempdir.path().join("workflow.yml");
+    write(&workflow_path, workflow)?;
+
+    let result = run_client_command(shared, &["submit", workflow_path.to_str().unwrap()]);
+    assert!(result.is_err());
+
+    let state = get_state(shared).await?;
+    assert!(state.tasks.is_empty());
+
+    Ok(())
+}
+
+/// Workflows can be submitted as a batch, which is labeled with the workflow's file name.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn submit_workflow_as_batch() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    let workflow = r#"
+tasks:
+  build:
+    command: make
+  test:
+    command: make test
+    needs: [build]
+"#;
+    let workflow_path = daemon.tempdir.path().join("workflow.yml");
+    write(&workflow_path, workflow)?;
+
+    run_client_command(
+        shared,
+        &[
+            "submit",
+            "--stashed",
+            "--batch",
+            workflow_path.to_str().unwrap(),
+        ],
+    )?;
+
+    let state = get_state(shared).await?;
+    let batch = state.batches.get(&0).unwrap();
+    assert_eq!(batch.label, Some("workflow.yml".to_string()));
+    assert!(state.tasks.values().all(|task| task.batch == Some(0)));
+
+    Ok(())
+}
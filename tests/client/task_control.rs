@@ -0,0 +1,32 @@
+use anyhow::Result;
+use assert_cmd::cargo::cargo_bin;
+use pretty_assertions::assert_eq;
+
+use crate::fixtures::*;
+use crate::helper::*;
+
+/// Tasks can call `pueue task` to act on themselves, without knowing the shared secret.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn label_from_within_task() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    let config = shared.pueue_directory().join("pueue.yml");
+    let pueue = format!(
+        "{} --config {}",
+        cargo_bin("pueue").display(),
+        config.display()
+    );
+    let command =
+        format!("{pueue} task label 'set by task' && {pueue} task follow-up -- echo next");
+    run_client_command(shared, &["add", "--", &command])?;
+
+    let task = wait_for_task_condition(shared, 0, |task| task.is_done()).await?;
+    assert_eq!(task.label, Some("set by task".to_string()));
+
+    let follow_up = get_task(shared, 1).await?;
+    assert_eq!(follow_up.dependencies, vec![0]);
+    assert_eq!(follow_up.command, "echo next");
+
+    Ok(())
+}
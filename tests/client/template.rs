@@ -0,0 +1,50 @@
+use anyhow::Result;
+use pretty_assertions::assert_eq;
+
+use crate::fixtures::*;
+use crate::helper::*;
+
+/// Templates can be added, used and removed via the client.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn template_lifecycle() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    run_client_command(
+        shared,
+        &[
+            "template",
+            "add",
+            "greet",
+            "--label",
+            "greet {name}",
+            "--env",
+            "GREETING=hello",
+            "--timeout",
+            "5m",
+            "--",
+            "echo $GREETING {name}",
+        ],
+    )?;
+
+    let output = run_client_command(shared, &["template", "list"])?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("greet: echo $GREETING {name}"),
+        "Unexpected output: {stdout}"
+    );
+    assert!(stdout.contains("Timeout: 300s"));
+
+    run_client_command(shared, &["run", "greet", "name=world"])?;
+    let task = wait_for_task_condition(shared, 0, |task| task.is_done()).await?;
+    assert_eq!(task.command, "echo $GREETING world");
+    assert_eq!(task.label, Some("greet world".to_string()));
+    assert_eq!(task.timeout, Some(300));
+    let log = get_task_log(shared, 0, None).await?;
+    assert_eq!(log, "hello world\n");
+
+    run_client_command(shared, &["template", "remove", "greet"])?;
+    assert!(run_client_command(shared, &["run", "greet", "name=world"]).is_err());
+
+    Ok(())
+}
@@ -1,8 +1,11 @@
 use std::collections::HashMap;
+use std::fs::write;
+use std::path::PathBuf;
 
 use anyhow::Result;
 
 use pueue_lib::network::message::*;
+use pueue_lib::state::GroupStatus;
 use pueue_lib::task::*;
 
 use crate::fixtures::*;
@@ -85,3 +88,148 @@ async fn test_restart_with_alias() -> Result<()> {
 
     Ok(())
 }
+
+/// Test that template aliases replace their parameters and apply their defaults.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_add_with_template_alias() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+    add_group_with_slots(shared, "greeters", 1).await?;
+
+    let aliases = r#"
+greet:
+  command: echo {greeting=hello} {1} $GREETER
+  group: greeters
+  envs:
+    GREETER: pueue
+"#;
+    write(daemon.tempdir.path().join("pueue_aliases.yml"), aliases)?;
+
+    // Add a task and wait until it finished.
+    assert_success(add_task(shared, "greet world", false).await?);
+    wait_for_task_condition(shared, 0, |task| task.is_done()).await?;
+
+    let task = get_task(shared, 0).await?;
+    assert_eq!(task.command, "echo hello world $GREETER");
+    assert_eq!(task.original_command, "greet world");
+    assert_eq!(task.group, "greeters");
+    assert_eq!(task.envs.get("GREETER"), Some(&"pueue".to_string()));
+
+    let log = get_task_log(shared, 0, None).await?;
+    assert_eq!(log, "hello world pueue\n");
+
+    // Tasks that don't provide all parameters are rejected.
+    assert_failure(add_task(shared, "greet", false).await?);
+
+    Ok(())
+}
+
+/// Test that editing a task's command applies the defaults of its template alias.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_edit_with_template_alias() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+    add_group_with_slots(shared, "greeters", 1).await?;
+    pause_tasks(shared, TaskSelection::All).await?;
+    wait_for_group_status(shared, "greeters", GroupStatus::Paused).await?;
+
+    let aliases = r#"
+greet:
+  command: echo {greeting=hello} {1} $GREETER
+  group: greeters
+  envs:
+    GREETER: pueue
+"#;
+    write(daemon.tempdir.path().join("pueue_aliases.yml"), aliases)?;
+
+    assert_success(add_task(shared, "ls", false).await?);
+    let mut stream = get_authenticated_stream(shared).await?;
+    let response = send_message_with_stream(&mut stream, Message::EditRequest(0)).await?;
+    assert!(matches!(response, Message::EditResponse(_)));
+
+    let edit = EditMessage {
+        task_id: 0,
+        command: Some("greet world".into()),
+        path: None,
+        label: None,
+        delete_label: false,
+        envs: None,
+        group: None,
+        dependencies: None,
+    };
+    assert_success(send_message_with_stream(&mut stream, edit).await?);
+
+    let task = get_task(shared, 0).await?;
+    assert_eq!(task.command, "echo hello world $GREETER");
+    assert_eq!(task.original_command, "greet world");
+    assert_eq!(task.group, "greeters");
+    assert_eq!(task.envs.get("GREETER"), Some(&"pueue".to_string()));
+    assert_eq!(task.status, TaskStatus::Queued);
+
+    Ok(())
+}
+
+/// Test that editing a task's command applies the path of its alias to the task's original
+/// working directory, unless the path is edited explicitly.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_edit_with_alias_path() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+    pause_tasks(shared, TaskSelection::All).await?;
+    wait_for_group_status(shared, "default", GroupStatus::Paused).await?;
+
+    let aliases = r#"
+build:
+  command: make
+  path: build
+"#;
+    write(daemon.tempdir.path().join("pueue_aliases.yml"), aliases)?;
+
+    assert_success(add_task(shared, "build", false).await?);
+    let original_path = shared.pueue_directory();
+    let task = get_task(shared, 0).await?;
+    assert_eq!(task.path, original_path.join("build"));
+
+    let mut stream = get_authenticated_stream(shared).await?;
+    let mut edit = EditMessage {
+        task_id: 0,
+        command: Some("build all".into()),
+        path: None,
+        label: None,
+        delete_label: false,
+        envs: None,
+        group: None,
+        dependencies: None,
+    };
+
+    // Editing the command multiple times doesn't nest the alias' path.
+    for _ in 0..2 {
+        let response = send_message_with_stream(&mut stream, Message::EditRequest(0)).await?;
+        assert!(matches!(response, Message::EditResponse(_)));
+        assert_success(send_message_with_stream(&mut stream, edit.clone()).await?);
+
+        let task = get_task(shared, 0).await?;
+        assert_eq!(task.command, "make all");
+        assert_eq!(task.path, original_path.join("build"));
+    }
+
+    // Commands without an alias are executed in the original working directory.
+    edit.command = Some("ls".into());
+    let response = send_message_with_stream(&mut stream, Message::EditRequest(0)).await?;
+    assert!(matches!(response, Message::EditResponse(_)));
+    assert_success(send_message_with_stream(&mut stream, edit.clone()).await?);
+    let task = get_task(shared, 0).await?;
+    assert_eq!(task.path, original_path);
+
+    // An explicitly edited path takes precedence over the path of the alias.
+    edit.command = Some("build".into());
+    edit.path = Some("/tmp".into());
+    let response = send_message_with_stream(&mut stream, Message::EditRequest(0)).await?;
+    assert!(matches!(response, Message::EditResponse(_)));
+    assert_success(send_message_with_stream(&mut stream, edit).await?);
+    let task = get_task(shared, 0).await?;
+    assert_eq!(task.command, "make");
+    assert_eq!(task.path, PathBuf::from("/tmp"));
+
+    Ok(())
+}
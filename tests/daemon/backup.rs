@@ -0,0 +1,100 @@
+use anyhow::{bail, Result};
+use pretty_assertions::assert_eq;
+
+use pueue_lib::network::message::*;
+use pueue_lib::settings::Shared;
+
+use crate::fixtures::*;
+use crate::helper::*;
+
+/// Convenience function for getting the names of all state backups from the daemon.
+async fn list_backups(shared: &Shared) -> Result<Vec<String>> {
+    match send_message(shared, BackupMessage::List).await? {
+        Message::BackupResponse(message) => Ok(message.backups),
+        _ => bail!("Didn't get backup response"),
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Create a backup and restore it after the state has been changed.
+async fn test_create_and_restore() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    assert_success(send_message(shared, BackupMessage::Create).await?);
+    let backups = list_backups(shared).await?;
+    assert_eq!(backups.len(), 1);
+
+    // Add a task after the backup has been created.
+    assert_success(add_task(shared, "sleep 60", false).await?);
+    wait_for_task_condition(shared, 0, |task| task.is_running()).await?;
+
+    // Restoring isn't possible while a task is running.
+    let message = BackupMessage::Restore(backups[0].clone());
+    assert_failure(send_message(shared, message.clone()).await?);
+
+    // Kill the task, after which the restore should work.
+    send_message(
+        shared,
+        KillMessage {
+            tasks: TaskSelection::All,
+            children: false,
+            signal: None,
+        },
+    )
+    .await?;
+    wait_for_task_condition(shared, 0, |task| task.is_done()).await?;
+
+    assert_success(send_message(shared, message).await?);
+    wait_for_task_absence(shared, 0).await?;
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Restoring a backup that doesn't exist should fail.
+async fn test_restore_non_existing() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    let message = BackupMessage::Restore("../state".to_string());
+    assert_failure(send_message(shared, message).await?);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Only the configured amount of backups should be kept.
+async fn test_backup_rotation() -> Result<()> {
+    let (mut settings, tempdir) = daemon_base_setup()?;
+    settings.daemon.state_backup_count = 2;
+    settings
+        .save(&Some(tempdir.path().join("pueue.yml")))
+        .expect("Couldn't write pueue config to temporary directory");
+    let daemon = daemon_with_settings(settings, tempdir).await?;
+    let shared = &daemon.settings.shared;
+
+    for _ in 0..3 {
+        assert_success(send_message(shared, BackupMessage::Create).await?);
+    }
+
+    assert_eq!(list_backups(shared).await?.len(), 2);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Backups created within the same second don't overwrite each other.
+async fn test_backup_unique_names() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    assert_success(send_message(shared, BackupMessage::Create).await?);
+    assert_success(send_message(shared, BackupMessage::Create).await?);
+
+    let backups = list_backups(shared).await?;
+    assert_eq!(backups.len(), 2);
+    assert_ne!(backups[0], backups[1]);
+
+    Ok(())
+}
@@ -0,0 +1,192 @@
+use std::fs::read_to_string;
+
+use anyhow::{bail, Result};
+use pretty_assertions::assert_eq;
+
+use pueue_lib::network::message::*;
+use pueue_lib::settings::Shared;
+use pueue_lib::state::{GroupStatus, State, PUEUE_DEFAULT_GROUP};
+use pueue_lib::task::{TaskResult, TaskStatus};
+
+use crate::fixtures::*;
+use crate::helper::*;
+
+/// Send a batch of messages and return the daemon's response.
+async fn send_batch(shared: &Shared, messages: Vec<Message>) -> Result<BatchResponseMessage> {
+    match send_message(shared, Message::Batch(messages)).await? {
+        Message::BatchResponse(response) => Ok(response),
+        response => bail!("Expected a batch response, got {response:?}"),
+    }
+}
+
+/// Create an AddMessage for a stashed task.
+fn stashed_task(shared: &Shared, dependencies: Vec<usize>) -> Message {
+    let mut message = create_add_message(shared, "ls");
+    message.stashed = true;
+    message.dependencies = dependencies;
+    message.into()
+}
+
+/// Create a message that pauses the default group.
+fn pause_default_group() -> Message {
+    PauseMessage {
+        tasks: TaskSelection::Group(PUEUE_DEFAULT_GROUP.into()),
+        wait: false,
+        children: false,
+    }
+    .into()
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// All messages of a batch are handled in order and answered with their respective response.
+async fn test_batch() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    let response = send_batch(
+        shared,
+        vec![
+            stashed_task(shared, Vec::new()),
+            stashed_task(shared, vec![0]),
+            pause_default_group(),
+            EnqueueMessage {
+                task_ids: vec![0, 1],
+                enqueue_at: None,
+            }
+            .into(),
+        ],
+    )
+    .await?;
+    assert!(response.applied);
+    assert_eq!(response.responses.len(), 4);
+    for response in response.responses {
+        assert_success(response);
+    }
+
+    // The group has been paused before the tasks have been enqueued.
+    // That's already visible in the state, once the batch has been applied.
+    let state = get_state(shared).await?;
+    assert_eq!(
+        state.groups[PUEUE_DEFAULT_GROUP].status,
+        GroupStatus::Paused
+    );
+    sleep_ms(500).await;
+    let state = get_state(shared).await?;
+    assert_eq!(state.tasks[&0].status, TaskStatus::Queued);
+    assert_eq!(state.tasks[&1].status, TaskStatus::Queued);
+    assert_eq!(state.tasks[&1].dependencies, vec![0]);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Nothing is changed, if any message of a batch fails.
+async fn test_failed_batch() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+    let state = get_state(shared).await?;
+
+    let mut invalid = create_add_message(shared, "ls");
+    invalid.group = "doesnt_exist".to_string();
+    let response = send_batch(
+        shared,
+        vec![
+            stashed_task(shared, Vec::new()),
+            pause_default_group(),
+            invalid.into(),
+            stashed_task(shared, Vec::new()),
+        ],
+    )
+    .await?;
+
+    // The batch has been aborted on the failing message.
+    assert!(!response.applied);
+    assert_eq!(response.responses.len(), 3);
+    assert_failure(response.responses[2].clone());
+
+    // Neither has a task been added, nor has the group been paused.
+    sleep_ms(500).await;
+    let current = get_state(shared).await?;
+    assert!(current.tasks.is_empty());
+    assert_eq!(current.groups, state.groups);
+    assert_eq!(current.revision, state.revision);
+
+    // The discarded copy of the state hasn't been saved either.
+    let saved = read_to_string(shared.pueue_directory().join("state.json"))?;
+    let saved: State = serde_json::from_str(&saved)?;
+    assert!(saved.tasks.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Groups that are added in a batch can be used by later messages of the same batch.
+async fn test_batch_add_group_and_task() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    let mut message = create_add_message(shared, "ls");
+    message.group = "batch_group".to_string();
+    let response = send_batch(
+        shared,
+        vec![
+            GroupMessage::Add {
+                name: "batch_group".to_string(),
+                parallel_tasks: Some(2),
+            }
+            .into(),
+            message.into(),
+        ],
+    )
+    .await?;
+    assert!(response.applied);
+
+    // The task handler created the group's worker pool and runs the task.
+    let task = wait_for_task_condition(shared, 0, |task| task.is_done()).await?;
+    assert_eq!(task.group, "batch_group");
+    assert_eq!(task.status, TaskStatus::Done(TaskResult::Success));
+    let state = get_state(shared).await?;
+    assert_eq!(state.groups["batch_group"].parallel_tasks, 2);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Removing tasks cannot be undone and is thereby rejected.
+async fn test_batch_rejects_remove() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    let response = send_message(
+        shared,
+        Message::Batch(vec![
+            stashed_task(shared, Vec::new()),
+            Message::Remove(vec![0]),
+        ]),
+    )
+    .await?;
+    assert_failure(response);
+    assert!(get_state(shared).await?.tasks.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Backups are written right away, which cannot be undone either.
+async fn test_batch_rejects_backup() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    let response = send_message(
+        shared,
+        Message::Batch(vec![
+            stashed_task(shared, Vec::new()),
+            BackupMessage::Create.into(),
+        ]),
+    )
+    .await?;
+    assert_failure(response);
+    assert!(get_state(shared).await?.tasks.is_empty());
+
+    Ok(())
+}
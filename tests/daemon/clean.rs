@@ -21,6 +21,7 @@ async fn test_normal_clean() -> Result<()> {
     let clean_message = CleanMessage {
         successful_only: false,
         group: None,
+        batch: None,
     };
     send_message(shared, clean_message).await?;
 
@@ -49,6 +50,7 @@ async fn test_successful_only_clean() -> Result<()> {
     let clean_message = CleanMessage {
         successful_only: true,
         group: None,
+        batch: None,
     };
     send_message(shared, clean_message).await?;
 
@@ -82,6 +84,7 @@ async fn test_clean_in_selected_group() -> Result<()> {
     let clean_message = CleanMessage {
         successful_only: false,
         group: Some("other".to_string()),
+        batch: None,
     };
     send_message(shared, clean_message).await?;
 
@@ -120,6 +123,7 @@ async fn test_clean_successful_only_in_selected_group() -> Result<()> {
     let clean_message = CleanMessage {
         successful_only: true,
         group: Some("other".to_string()),
+        batch: None,
     };
     send_message(shared, clean_message).await?;
 
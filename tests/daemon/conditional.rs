@@ -0,0 +1,90 @@
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use pretty_assertions::assert_eq;
+
+use pueue_lib::network::message::*;
+use pueue_lib::settings::Shared;
+use pueue_lib::task::TaskStatus;
+
+use crate::fixtures::*;
+use crate::helper::*;
+
+/// Add a stashed task, so it stays untouched by the daemon.
+async fn add_stashed_task(shared: &Shared) -> Result<()> {
+    let mut message = create_add_message(shared, "ls");
+    message.stashed = true;
+    assert_success(send_message(shared, message).await?);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Every change increases the revision of the state.
+/// Messages for an outdated revision are rejected without changing anything.
+async fn test_if_revision() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    add_stashed_task(shared).await?;
+    let revision = get_state(shared).await?.revision;
+    add_stashed_task(shared).await?;
+    let state = get_state(shared).await?;
+    assert!(state.revision > revision);
+
+    // Switching the tasks for the outdated revision fails.
+    let switch = |revision| ConditionalMessage {
+        if_revision: Some(revision),
+        if_status: BTreeMap::new(),
+        message: Box::new(
+            SwitchMessage {
+                task_id_1: 0,
+                task_id_2: 1,
+            }
+            .into(),
+        ),
+    };
+    assert_failure(send_message(shared, switch(revision)).await?);
+    assert_eq!(get_state(shared).await?, state);
+
+    // Switching the tasks for the current revision succeeds.
+    assert_success(send_message(shared, switch(state.revision)).await?);
+    let switched = get_state(shared).await?;
+    assert_eq!(switched.tasks[&0].command, state.tasks[&1].command);
+    assert!(switched.revision > state.revision);
+
+    // Reading the state doesn't change the revision.
+    assert_eq!(get_state(shared).await?.revision, switched.revision);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Messages are only handled, if all tasks have the expected status.
+async fn test_if_status() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    add_stashed_task(shared).await?;
+    add_stashed_task(shared).await?;
+
+    let remove = |status: TaskStatus| ConditionalMessage {
+        if_revision: None,
+        if_status: BTreeMap::from([(0, TaskStatus::Stashed { enqueue_at: None }), (1, status)]),
+        message: Box::new(Message::Remove(vec![0, 1])),
+    };
+
+    // Task 1 isn't queued.
+    assert_failure(send_message(shared, remove(TaskStatus::Queued)).await?);
+    assert_eq!(get_state(shared).await?.tasks.len(), 2);
+
+    // Both tasks have the expected status.
+    let response = send_message(shared, remove(TaskStatus::Stashed { enqueue_at: None })).await?;
+    assert_success(response);
+    assert!(get_state(shared).await?.tasks.is_empty());
+
+    // Preconditions on tasks that don't exist fail.
+    assert_failure(send_message(shared, remove(TaskStatus::Queued)).await?);
+
+    Ok(())
+}
@@ -1,8 +1,10 @@
+use std::collections::HashMap;
 use std::path::PathBuf;
 
 use anyhow::{bail, Result};
 
 use pueue_lib::network::message::*;
+use pueue_lib::network::protocol::GenericStream;
 use pueue_lib::settings::Shared;
 use pueue_lib::state::GroupStatus;
 use pueue_lib::task::*;
@@ -10,7 +12,9 @@ use pueue_lib::task::*;
 use crate::fixtures::*;
 use crate::helper::*;
 
-async fn create_edited_task(shared: &Shared) -> Result<EditResponseMessage> {
+/// Add a task and request to edit it.
+/// The task stays locked as long as the returned stream is kept open.
+async fn create_edited_task(shared: &Shared) -> Result<(EditResponseMessage, GenericStream)> {
     // Add a task
     assert_success(add_task(shared, "ls", false).await?);
 
@@ -18,9 +22,10 @@ async fn create_edited_task(shared: &Shared) -> Result<EditResponseMessage> {
     assert_eq!(get_task_status(shared, 0).await?, TaskStatus::Queued);
 
     // Send a request to edit that task
-    let response = send_message(shared, Message::EditRequest(0)).await?;
+    let mut stream = get_authenticated_stream(shared).await?;
+    let response = send_message_with_stream(&mut stream, Message::EditRequest(0)).await?;
     if let Message::EditResponse(payload) = response {
-        Ok(payload)
+        Ok((payload, stream))
     } else {
         bail!("Didn't receive EditResponse after requesting edit.")
     }
@@ -36,7 +41,7 @@ async fn test_edit_flow() -> Result<()> {
     pause_tasks(shared, TaskSelection::All).await?;
     wait_for_group_status(shared, PUEUE_DEFAULT_GROUP, GroupStatus::Paused).await?;
 
-    let response = create_edited_task(shared).await?;
+    let (response, mut stream) = create_edited_task(shared).await?;
     assert_eq!(response.task_id, 0);
     assert_eq!(response.command, "ls");
     assert_eq!(response.path, daemon.tempdir.path());
@@ -46,17 +51,24 @@ async fn test_edit_flow() -> Result<()> {
 
     // You cannot start a locked task. It should still be locked afterwards.
     start_tasks(shared, TaskSelection::TaskIds(vec![0])).await?;
+    // The task handler handles its messages in order. Once the group is resumed, the start
+    // message has been handled as well.
+    start_tasks(shared, TaskSelection::Group("test_2".into())).await?;
+    wait_for_group_status(shared, "test_2", GroupStatus::Running).await?;
     assert_eq!(get_task_status(shared, 0).await?, TaskStatus::Locked);
 
     // Send the final message of the protocol and actually change the task.
-    let response = send_message(
-        shared,
+    let response = send_message_with_stream(
+        &mut stream,
         EditMessage {
             task_id: 0,
             command: Some("ls -ahl".into()),
             path: Some("/tmp".into()),
             label: Some("test".to_string()),
             delete_label: false,
+            envs: None,
+            group: None,
+            dependencies: None,
         },
     )
     .await?;
@@ -71,3 +83,214 @@ async fn test_edit_flow() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Environment variables, group and dependencies can be edited.
+/// Dependencies that would introduce a cycle are rejected and the task is unlocked again.
+async fn test_edit_envs_group_and_dependencies() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    pause_tasks(shared, TaskSelection::All).await?;
+    wait_for_group_status(shared, PUEUE_DEFAULT_GROUP, GroupStatus::Paused).await?;
+
+    // Task 1 depends on task 0.
+    let (_, mut stream) = create_edited_task(shared).await?;
+    let mut message = create_add_message(shared, "ls");
+    message.dependencies = vec![0];
+    assert_success(send_message(shared, message).await?);
+
+    // Letting task 0 depend on task 1 would create a cycle.
+    let mut edit = EditMessage {
+        task_id: 0,
+        command: None,
+        path: None,
+        label: None,
+        delete_label: false,
+        envs: Some(HashMap::from([("KEY".to_string(), "value".to_string())])),
+        group: Some("test_2".to_string()),
+        dependencies: Some(vec![1]),
+    };
+    assert_failure(send_message_with_stream(&mut stream, edit.clone()).await?);
+
+    // Nothing has been changed and the task is no longer locked.
+    let task = get_task(shared, 0).await?;
+    assert_eq!(task.status, TaskStatus::Queued);
+    assert_eq!(task.group, PUEUE_DEFAULT_GROUP);
+    assert!(task.dependencies.is_empty());
+    assert!(!task.envs.contains_key("KEY"));
+
+    // Without the cycle, the edit succeeds.
+    let response = send_message_with_stream(&mut stream, Message::EditRequest(0)).await?;
+    assert!(matches!(response, Message::EditResponse(_)));
+    edit.dependencies = Some(Vec::new());
+    assert_success(send_message_with_stream(&mut stream, edit).await?);
+
+    let task = get_task(shared, 0).await?;
+    assert_eq!(task.status, TaskStatus::Queued);
+    assert_eq!(task.group, "test_2");
+    assert_eq!(
+        task.envs,
+        HashMap::from([("KEY".to_string(), "value".to_string())])
+    );
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Multiple tasks can be locked and edited at once.
+/// Tasks that cannot be edited are skipped.
+/// Invalid edits are rejected as a whole and all tasks are unlocked again.
+async fn test_bulk_edit() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    for _ in 0..2 {
+        let mut message = create_add_message(shared, "ls");
+        message.stashed = true;
+        assert_success(send_message(shared, message).await?);
+    }
+
+    // Requesting only non-existing tasks fails.
+    assert_failure(send_message(shared, Message::BulkEditRequest(vec![2, 3])).await?);
+
+    // Non-existing tasks are skipped, while the other tasks are locked.
+    let mut stream = get_authenticated_stream(shared).await?;
+    let response =
+        send_message_with_stream(&mut stream, Message::BulkEditRequest(vec![0, 1, 2])).await?;
+    let response = match response {
+        Message::BulkEditResponse(message) => message,
+        _ => bail!("Didn't receive BulkEditResponse after requesting edit."),
+    };
+    assert_eq!(response.tasks.len(), 2);
+    assert_eq!(response.skipped, vec![2]);
+    assert_eq!(get_task_status(shared, 0).await?, TaskStatus::Locked);
+    assert_eq!(get_task_status(shared, 1).await?, TaskStatus::Locked);
+
+    // Both tasks depending on each other would create a cycle.
+    let edit = |task_id: usize, dependency: usize| EditMessage {
+        task_id,
+        command: Some(format!("echo {task_id}")),
+        path: None,
+        label: None,
+        delete_label: false,
+        envs: None,
+        group: None,
+        dependencies: Some(vec![dependency]),
+    };
+    let message = BulkEditMessage {
+        tasks: vec![edit(0, 1), edit(1, 0)],
+    };
+    assert_failure(send_message_with_stream(&mut stream, message).await?);
+    let state = get_state(shared).await?;
+    for task in state.tasks.values() {
+        assert_eq!(task.status, TaskStatus::Stashed { enqueue_at: None });
+        assert_eq!(task.command, "ls");
+    }
+
+    // Editing a task twice is rejected, but both tasks are still unlocked.
+    assert!(matches!(
+        send_message_with_stream(&mut stream, Message::BulkEditRequest(vec![0, 1])).await?,
+        Message::BulkEditResponse(_)
+    ));
+    let message = BulkEditMessage {
+        tasks: vec![edit(0, 1), edit(0, 1), edit(1, 1)],
+    };
+    assert_failure(send_message_with_stream(&mut stream, message).await?);
+    let state = get_state(shared).await?;
+    for task in state.tasks.values() {
+        assert_eq!(task.status, TaskStatus::Stashed { enqueue_at: None });
+    }
+
+    // Without the cycle, all changes are applied.
+    assert!(matches!(
+        send_message_with_stream(&mut stream, Message::BulkEditRequest(vec![0, 1])).await?,
+        Message::BulkEditResponse(_)
+    ));
+    let mut first = edit(0, 1);
+    first.dependencies = None;
+    let message = BulkEditMessage {
+        tasks: vec![first, edit(1, 0)],
+    };
+    assert_success(send_message_with_stream(&mut stream, message).await?);
+
+    let state = get_state(shared).await?;
+    assert_eq!(state.tasks[&0].command, "echo 0");
+    assert_eq!(state.tasks[&1].command, "echo 1");
+    assert_eq!(state.tasks[&1].dependencies, vec![0]);
+    assert_eq!(
+        state.tasks[&1].status,
+        TaskStatus::Stashed { enqueue_at: None }
+    );
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Only the client that locked a task can edit it.
+/// The task is unlocked, as soon as that client disconnects.
+async fn test_edit_lock_released_on_disconnect() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    pause_tasks(shared, TaskSelection::All).await?;
+    wait_for_group_status(shared, PUEUE_DEFAULT_GROUP, GroupStatus::Paused).await?;
+
+    let (_, stream) = create_edited_task(shared).await?;
+    assert_eq!(get_task_status(shared, 0).await?, TaskStatus::Locked);
+
+    // Other clients cannot finish or abort the edit.
+    assert_failure(send_message(shared, Message::EditRestore(0)).await?);
+    assert_eq!(get_task_status(shared, 0).await?, TaskStatus::Locked);
+
+    // Once the client goes away, the task is restored to its previous status.
+    drop(stream);
+    wait_for_task_condition(shared, 0, |task| task.status == TaskStatus::Queued).await?;
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Tasks are unlocked, if the client doesn't finish editing before the lease expires.
+async fn test_edit_lock_lease_expires() -> Result<()> {
+    let (mut settings, tempdir) = daemon_base_setup()?;
+    settings.daemon.edit_lock_lease_seconds = 1;
+    settings
+        .save(&Some(tempdir.path().join("pueue.yml")))
+        .expect("Couldn't write pueue config to temporary directory");
+    let daemon = daemon_with_settings(settings, tempdir).await?;
+    let shared = &daemon.settings.shared;
+
+    let mut message = create_add_message(shared, "ls");
+    message.stashed = true;
+    assert_success(send_message(shared, message).await?);
+
+    let mut stream = get_authenticated_stream(shared).await?;
+    let response = send_message_with_stream(&mut stream, Message::EditRequest(0)).await?;
+    assert!(matches!(response, Message::EditResponse(_)));
+    assert_eq!(get_task_status(shared, 0).await?, TaskStatus::Locked);
+
+    // The task is unlocked while the client is still connected.
+    sleep_ms(1000).await;
+    wait_for_task_condition(shared, 0, |task| task.is_queued()).await?;
+    assert_eq!(
+        get_task_status(shared, 0).await?,
+        TaskStatus::Stashed { enqueue_at: None }
+    );
+
+    // The late edit is rejected.
+    let edit = EditMessage {
+        task_id: 0,
+        command: Some("ls -ahl".into()),
+        path: None,
+        label: None,
+        delete_label: false,
+        envs: None,
+        group: None,
+        dependencies: None,
+    };
+    assert_failure(send_message_with_stream(&mut stream, edit).await?);
+    assert_eq!(get_task(shared, 0).await?.command, "ls");
+
+    Ok(())
+}
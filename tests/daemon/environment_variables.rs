@@ -1,7 +1,13 @@
-use anyhow::Result;
+use std::fs::read_to_string;
+
+use anyhow::{Context, Result};
+use pueue_lib::environment;
+use pueue_lib::log::get_log_path;
+use pueue_lib::network::message::*;
 
 use crate::fixtures::*;
 use crate::helper::*;
+
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
 /// Make sure that the daemon's environment variables don't bleed into the spawned subprocesses.
 async fn test_isolated_task_environment() -> Result<()> {
@@ -24,3 +30,71 @@ async fn test_isolated_task_environment() -> Result<()> {
     child.kill()?;
     Ok(())
 }
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Tasks know about their identity and context via the injected environment variables.
+async fn test_injected_task_environment() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    assert_success(add_task(shared, "true", false).await?);
+    wait_for_task_condition(shared, 0, |task| task.is_done()).await?;
+
+    let mut message = create_add_message(
+        shared,
+        "echo \"$PUEUE_TASK_ID|$PUEUE_TASK_LABEL|$PUEUE_DEPENDENCIES|$PUEUE_DEPENDENCY_RESULTS|$PUEUE_ATTEMPT|$PUEUE_LOG_PATH|$PUEUE_SOCKET\" && echo \"$PUEUE_TASK_TOKEN\"",
+    );
+    message.label = Some("second".to_string());
+    message.dependencies = vec![0];
+    assert_success(send_message(shared, message).await?);
+    let task = wait_for_task_condition(shared, 1, |task| task.is_done()).await?;
+
+    let log_path = get_log_path(1, &shared.pueue_directory());
+    let expected = format!(
+        "1|second|0|Success|1|{}|{}",
+        log_path.to_string_lossy(),
+        shared.socket_address()
+    );
+    let log = get_task_log(shared, 1, None).await?;
+    let (output, token) = log
+        .trim_end()
+        .split_once('\n')
+        .context("The task should print its token")?;
+    assert_eq!(output, expected);
+    assert_eq!(task.attempt, 1);
+
+    // The token of the task's run is only injected into its process.
+    // It's neither part of the task's environment variables nor persisted, and its hash is
+    // removed once the run finished.
+    assert!(!token.is_empty());
+    assert_eq!(task.envs.get(environment::TASK_TOKEN), None);
+    assert_eq!(task.token_hash, None);
+    let state = read_to_string(shared.pueue_directory().join("state.json"))?;
+    assert!(!state.contains(token));
+
+    // Every run has its own attempt number and token.
+    let restart_message = RestartMessage {
+        tasks: vec![TaskToRestart {
+            task_id: 1,
+            command: Some("echo \"$PUEUE_ATTEMPT|$PUEUE_TASK_TOKEN\"".to_string()),
+            path: None,
+            label: None,
+            delete_label: false,
+        }],
+        start_immediately: false,
+        stashed: false,
+    };
+    assert_success(send_message(shared, restart_message).await?);
+    let task = wait_for_task_condition(shared, 1, |task| task.is_done()).await?;
+    let log = get_task_log(shared, 1, None).await?;
+    let (attempt, new_token) = log
+        .trim_end()
+        .split_once('|')
+        .context("The task should print its token")?;
+    assert_eq!(attempt, "2");
+    assert_eq!(task.attempt, 2);
+    assert!(!new_token.is_empty());
+    assert_ne!(new_token, token);
+
+    Ok(())
+}
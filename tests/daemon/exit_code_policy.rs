@@ -0,0 +1,187 @@
+use anyhow::Result;
+use pueue_lib::network::message::*;
+use pueue_lib::state::{GroupStatus, PUEUE_DEFAULT_GROUP};
+use pueue_lib::task::*;
+
+use crate::fixtures::*;
+use crate::helper::*;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// The exit code policy of a task decides, which exit codes count as success.
+async fn test_success_codes() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    let mut message = create_add_message(shared, "exit 1");
+    message.exit_code_policy = Some(ExitCodePolicy {
+        success: vec![0, 1],
+        temporary: Vec::new(),
+        max_retries: 0,
+    });
+    assert_success(send_message(shared, message).await?);
+    let task = wait_for_task_condition(shared, 0, |task| task.is_done()).await?;
+    assert_eq!(task.status, TaskStatus::Done(TaskResult::Success));
+
+    // Other tasks still use the default policy.
+    assert_success(add_task(shared, "exit 1", false).await?);
+    let task = wait_for_task_condition(shared, 1, |task| task.is_done()).await?;
+    assert_eq!(task.status, TaskStatus::Done(TaskResult::Failed(1)));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Temporarily failed tasks are restarted in place.
+/// Their dependants wait until they succeeded.
+async fn test_temporary_failure() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    // The task only succeeds on its third attempt.
+    let mut message = create_add_message(shared, "[ \"$PUEUE_ATTEMPT\" -ge 3 ] || exit 75");
+    message.exit_code_policy = Some(ExitCodePolicy {
+        success: vec![0],
+        temporary: vec![75],
+        max_retries: 3,
+    });
+    assert_success(send_message(shared, message).await?);
+    let mut message = create_add_message(shared, "ls");
+    message.dependencies = vec![0];
+    assert_success(send_message(shared, message).await?);
+
+    let task = wait_for_task_condition(shared, 1, |task| task.is_done()).await?;
+    assert_eq!(task.status, TaskStatus::Done(TaskResult::Success));
+    let task = get_task(shared, 0).await?;
+    assert_eq!(task.status, TaskStatus::Done(TaskResult::Success));
+    assert_eq!(task.attempt, 3);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Tasks that ran out of retries count as failed.
+/// They fail their dependants and pause their group.
+async fn test_temporary_failure_without_retries() -> Result<()> {
+    let (mut settings, tempdir) = daemon_base_setup()?;
+    settings.daemon.pause_group_on_failure = true;
+    settings
+        .save(&Some(tempdir.path().join("pueue.yml")))
+        .expect("Couldn't write pueue config to temporary directory");
+    let daemon = daemon_with_settings(settings, tempdir).await?;
+    let shared = &daemon.settings.shared;
+
+    let mut message = create_add_message(shared, "exit 75");
+    message.exit_code_policy = Some(ExitCodePolicy {
+        success: vec![0],
+        temporary: vec![75],
+        max_retries: 1,
+    });
+    assert_success(send_message(shared, message).await?);
+    let mut message = create_add_message(shared, "ls");
+    message.dependencies = vec![0];
+    assert_success(send_message(shared, message).await?);
+
+    let task = wait_for_task_condition(shared, 0, |task| task.is_done()).await?;
+    assert_eq!(
+        task.status,
+        TaskStatus::Done(TaskResult::TemporaryFailure(75))
+    );
+    assert_eq!(task.attempt, 2);
+
+    let state = get_state(shared).await?;
+    assert_eq!(
+        state.groups.get(PUEUE_DEFAULT_GROUP).unwrap().status,
+        GroupStatus::Paused
+    );
+
+    // Dependants of failed tasks only fail, once their group is running again.
+    assert_success(
+        send_message(
+            shared,
+            StartMessage {
+                tasks: TaskSelection::Group(PUEUE_DEFAULT_GROUP.to_string()),
+                children: false,
+            },
+        )
+        .await?,
+    );
+    let task = wait_for_task_condition(shared, 1, |task| task.is_done()).await?;
+    assert_eq!(task.status, TaskStatus::Done(TaskResult::DependencyFailed));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Restarting a task in place gives it all of its retries again.
+async fn test_temporary_failure_after_restart() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    let mut message = create_add_message(shared, "exit 75");
+    message.exit_code_policy = Some(ExitCodePolicy {
+        success: vec![0],
+        temporary: vec![75],
+        max_retries: 1,
+    });
+    assert_success(send_message(shared, message).await?);
+    let task = wait_for_task_condition(shared, 0, |task| task.is_done()).await?;
+    assert_eq!(task.attempt, 2);
+    assert_eq!(task.retries, 1);
+
+    let message = RestartMessage {
+        tasks: vec![TaskToRestart {
+            task_id: 0,
+            command: None,
+            path: None,
+            label: None,
+            delete_label: false,
+        }],
+        start_immediately: false,
+        stashed: false,
+    };
+    assert_success(send_message(shared, message).await?);
+    let task =
+        wait_for_task_condition(shared, 0, |task| task.attempt == 4 && task.is_done()).await?;
+    assert_eq!(
+        task.status,
+        TaskStatus::Done(TaskResult::TemporaryFailure(75))
+    );
+    assert_eq!(task.retries, 1);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// The exit code policy of a group applies to all of its tasks.
+async fn test_group_policy() -> Result<()> {
+    let (mut settings, tempdir) = daemon_base_setup()?;
+    settings.daemon.group_exit_code_policies.insert(
+        PUEUE_DEFAULT_GROUP.to_string(),
+        ExitCodePolicy {
+            success: vec![0, 2],
+            temporary: vec![75],
+            max_retries: 0,
+        },
+    );
+    settings
+        .save(&Some(tempdir.path().join("pueue.yml")))
+        .expect("Couldn't write pueue config to temporary directory");
+    let daemon = daemon_with_settings(settings, tempdir).await?;
+    let shared = &daemon.settings.shared;
+
+    assert_success(add_task(shared, "exit 2", false).await?);
+    assert_success(add_task(shared, "exit 75", false).await?);
+    assert_success(add_task(shared, "exit 3", false).await?);
+
+    let task = wait_for_task_condition(shared, 0, |task| task.is_done()).await?;
+    assert_eq!(task.status, TaskStatus::Done(TaskResult::Success));
+    let task = wait_for_task_condition(shared, 1, |task| task.is_done()).await?;
+    assert_eq!(
+        task.status,
+        TaskStatus::Done(TaskResult::TemporaryFailure(75))
+    );
+    let task = wait_for_task_condition(shared, 2, |task| task.is_done()).await?;
+    assert_eq!(task.status, TaskStatus::Done(TaskResult::Failed(3)));
+
+    Ok(())
+}
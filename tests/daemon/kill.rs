@@ -79,3 +79,61 @@ async fn test_kill_tasks(
 
     Ok(())
 }
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Tasks are killed once they exceed the timeout of their template.
+async fn test_kill_on_timeout() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    let mut message = create_add_message(shared, "sleep 60");
+    message.timeout = Some(1);
+    assert_success(send_message(shared, message).await?);
+    wait_for_task_condition(shared, 0, |task| task.is_running()).await?;
+
+    // Give the timeout some time to pass.
+    sleep_ms(1000).await;
+    let task = wait_for_task_condition(shared, 0, |task| task.is_done()).await?;
+    assert_eq!(task.status, TaskStatus::Done(TaskResult::Killed));
+    assert_eq!(
+        task.failure_reason,
+        Some("Killed, as it exceeded its timeout of 1s".to_string())
+    );
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Tasks that have been killed by the user don't pause their group on failure.
+async fn test_manual_kill_does_not_pause_on_failure() -> Result<()> {
+    let (mut settings, tempdir) = daemon_base_setup()?;
+    settings.daemon.pause_group_on_failure = true;
+    settings
+        .save(&Some(tempdir.path().join("pueue.yml")))
+        .expect("Couldn't write pueue config to temporary directory");
+    let daemon = daemon_with_settings(settings, tempdir).await?;
+    let shared = &daemon.settings.shared;
+
+    assert_success(add_task(shared, "sleep 60", true).await?);
+    wait_for_task_condition(shared, 0, |task| task.is_running()).await?;
+
+    send_message(
+        shared,
+        KillMessage {
+            tasks: TaskSelection::TaskIds(vec![0]),
+            children: false,
+            signal: None,
+        },
+    )
+    .await?;
+    let task = wait_for_task_condition(shared, 0, |task| task.is_done()).await?;
+    assert_eq!(task.status, TaskStatus::Done(TaskResult::Killed));
+
+    let state = get_state(shared).await?;
+    assert_eq!(
+        state.groups.get(PUEUE_DEFAULT_GROUP).unwrap().status,
+        GroupStatus::Running
+    );
+
+    Ok(())
+}
@@ -1,9 +1,14 @@
+use std::collections::BTreeMap;
 use std::fs::read_to_string;
 use std::fs::File;
 use std::path::Path;
 
 use anyhow::{bail, Context, Result};
+use chrono::prelude::*;
+use pueue_lib::log::{LogRange, OutputStream};
 use pueue_lib::network::message::*;
+use pueue_lib::network::protocol::receive_message;
+use pueue_lib::settings::Shared;
 use tempfile::TempDir;
 
 use crate::fixtures::*;
@@ -99,6 +104,11 @@ async fn test_partial_log() -> Result<()> {
         task_ids: vec![0],
         send_logs: true,
         lines: Some(5),
+        range: None,
+        chunk_size: None,
+        stream: None,
+        timestamps: false,
+        since: None,
     };
     let response = send_message(shared, Message::Log(log_message)).await?;
     let logs = match response {
@@ -136,6 +146,11 @@ async fn test_correct_log_order() -> Result<()> {
         task_ids: vec![0],
         send_logs: true,
         lines: None,
+        range: None,
+        chunk_size: None,
+        stream: None,
+        timestamps: false,
+        since: None,
     };
     let response = send_message(shared, Message::Log(log_message)).await?;
     let logs = match response {
@@ -156,3 +171,445 @@ async fn test_correct_log_order() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Ensure that stdout and stderr can be captured separately, while the interleaved output
+/// is still available.
+async fn test_separate_output() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    let mut message = create_add_message(shared, "echo 'test' && echo 'error' >&2 && echo 'test'");
+    message.separate_output = true;
+    assert_success(send_message(shared, message).await?);
+    wait_for_task_condition(shared, 0, |task| task.is_done()).await?;
+
+    // The order of the interleaved output isn't guaranteed, as both streams are copied
+    // from separate pipes.
+    let output = get_task_log(shared, 0, None).await?;
+    let mut lines: Vec<&str> = output.lines().collect();
+    lines.sort_unstable();
+    assert_eq!(lines, vec!["error", "test", "test"]);
+
+    let stdout = get_task_stream_log(shared, 0, None, Some(OutputStream::Stdout)).await?;
+    assert_eq!(stdout, "test\ntest\n");
+
+    let stderr = get_task_stream_log(shared, 0, None, Some(OutputStream::Stderr)).await?;
+    assert_eq!(stderr, "error\n");
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[cfg(any(target_os = "linux", target_os = "freebsd"))]
+/// Output that's copied by the daemon keeps the order of stdout and stderr, unless both streams
+/// are captured separately.
+async fn test_copied_output_order() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    let command = "for i in 1 2 3 4 5; do echo \"out $i\"; echo \"err $i\" >&2; done";
+    let mut message = create_add_message(shared, command);
+    message.timestamps = true;
+    assert_success(send_message(shared, message).await?);
+    wait_for_task_condition(shared, 0, |task| task.is_done()).await?;
+
+    let expected: String = (1..=5).map(|i| format!("out {i}\nerr {i}\n")).collect();
+    let output = get_task_log(shared, 0, None).await?;
+    assert_eq!(output, expected);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// The log of a finished task doesn't change anymore, even if a background process of the task
+/// still writes to it.
+async fn test_copied_output_is_final() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    let mut message = create_add_message(shared, "echo 'done'; (sleep 2 && echo 'late') &");
+    message.timestamps = true;
+    assert_success(send_message(shared, message).await?);
+    // The remaining output is waited for a grace period, as the background process keeps the
+    // pipe open.
+    sleep_ms(1000).await;
+    wait_for_task_condition(shared, 0, |task| task.is_done()).await?;
+    assert_eq!(get_task_log(shared, 0, None).await?, "done\n");
+
+    // The background process has written its output in the meantime.
+    sleep_ms(1500).await;
+    assert_eq!(get_task_log(shared, 0, None).await?, "done\n");
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Requesting a single stream of a task, whose output hasn't been captured separately,
+/// doesn't return any output.
+async fn test_stream_without_separate_output() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    assert_success(add_task(shared, "echo 'test'", false).await?);
+    wait_for_task_condition(shared, 0, |task| task.is_done()).await?;
+
+    let result = get_task_stream_log(shared, 0, None, Some(OutputStream::Stderr)).await;
+    assert!(result.is_err(), "Expected no output, got {result:?}");
+
+    Ok(())
+}
+
+/// Request the log of task 0 with timestamps.
+async fn get_timestamped_log(
+    shared: &Shared,
+    timestamps: bool,
+    since: Option<DateTime<Local>>,
+) -> Result<Option<String>> {
+    let log_message = LogRequestMessage {
+        task_ids: vec![0],
+        send_logs: true,
+        lines: None,
+        range: None,
+        chunk_size: None,
+        stream: None,
+        timestamps,
+        since,
+    };
+    let response = send_message(shared, Message::Log(log_message)).await?;
+    let mut logs = match response {
+        Message::LogResponse(logs) => logs,
+        _ => bail!("Received non LogResponse: {:#?}", response),
+    };
+
+    let output = logs.remove(&0).context("Didn't find log of task 0")?.output;
+    output.map(decompress_log).transpose()
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Ensure that the output of tasks can be captured with a timestamp for each line.
+async fn test_timestamps() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    let start = Local::now();
+    let mut message = create_add_message(shared, "echo 'first' && echo 'second'");
+    message.timestamps = true;
+    assert_success(send_message(shared, message).await?);
+    wait_for_task_condition(shared, 0, |task| task.is_done()).await?;
+
+    // The plain output isn't changed.
+    let output = get_task_log(shared, 0, None).await?;
+    assert_eq!(output, "first\nsecond\n");
+
+    // Each line is prefixed with its timestamp.
+    let output = get_timestamped_log(shared, true, None)
+        .await?
+        .context("Didn't get timestamped output")?;
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines.len(), 2, "Unexpected output: {output}");
+    for (line, expected) in lines.iter().zip(["first", "second"]) {
+        let (timestamp, content) = line
+            .strip_prefix('[')
+            .and_then(|line| line.split_once("] "))
+            .context(format!("Line isn't prefixed with a timestamp: {line}"))?;
+        assert_eq!(content, expected);
+        let timestamp = Local
+            .datetime_from_str(timestamp, "%Y-%m-%d %H:%M:%S%.3f")
+            .context("Failed to parse timestamp")?;
+        assert!(timestamp >= start - chrono::Duration::seconds(1));
+    }
+
+    // Only lines captured since the given point in time are sent.
+    let output = get_timestamped_log(shared, false, Some(start)).await?;
+    assert_eq!(output.as_deref(), Some("first\nsecond\n"));
+    let output = get_timestamped_log(shared, false, Some(Local::now())).await?;
+    assert_eq!(output.as_deref(), Some(""));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Requesting timestamps of a task, whose output hasn't been captured with timestamps,
+/// doesn't return any output.
+async fn test_timestamps_without_index() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    assert_success(add_task(shared, "echo 'test'", false).await?);
+    wait_for_task_condition(shared, 0, |task| task.is_done()).await?;
+
+    let output = get_timestamped_log(shared, true, None).await?;
+    assert_eq!(output, None);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// The daemon searches the logs of the requested tasks and returns the matching lines
+/// together with their context.
+async fn test_grep() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    assert_success(add_task(shared, "seq 1 20", false).await?);
+    assert_success(add_task(shared, "echo 'nothing here'", false).await?);
+    wait_for_task_condition(shared, 0, |task| task.is_done()).await?;
+    wait_for_task_condition(shared, 1, |task| task.is_done()).await?;
+
+    let mut message = GrepRequestMessage {
+        task_ids: vec![0, 1],
+        pattern: "1".to_string(),
+        fixed_strings: false,
+        ignore_case: false,
+        context: 1,
+        max_matches: 100,
+    };
+    let response = send_message(shared, message.clone()).await?;
+    let matches = match response {
+        Message::GrepResponse(response) if !response.truncated => response.matches,
+        _ => bail!("Didn't get grep response: {response:?}"),
+    };
+
+    // Tasks without any match aren't part of the response.
+    assert_eq!(matches.keys().copied().collect::<Vec<_>>(), vec![0]);
+    let lines: Vec<(usize, bool)> = matches[&0]
+        .iter()
+        .map(|line| (line.number, line.matches))
+        .collect();
+    let mut expected = vec![(1, true), (2, false), (9, false)];
+    expected.extend((10..=19).map(|number| (number, true)));
+    expected.push((20, false));
+    assert_eq!(lines, expected);
+    assert_eq!(matches[&0][0].text, "1");
+
+    // Patterns are regular expressions and the search stops after the maximum of matches.
+    message.pattern = "^1[0-9]$".to_string();
+    message.max_matches = 2;
+    let response = send_message(shared, message.clone()).await?;
+    let response = match response {
+        Message::GrepResponse(response) => response,
+        _ => bail!("Didn't get grep response: {response:?}"),
+    };
+    assert!(response.truncated);
+    let lines: Vec<(usize, bool)> = response.matches[&0]
+        .iter()
+        .map(|line| (line.number, line.matches))
+        .collect();
+    assert_eq!(lines, vec![(9, false), (10, true), (11, true), (12, false)]);
+
+    // Invalid patterns are rejected.
+    message.pattern = "(".to_string();
+    assert_failure(send_message(shared, message).await?);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Only the requested part of the log is sent.
+async fn test_ranges() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    assert_success(add_task(shared, "seq 1 20", false).await?);
+    wait_for_task_condition(shared, 0, |task| task.is_done()).await?;
+
+    let cases = [
+        (LogRange::Head(3), "1\n2\n3\n", false),
+        (LogRange::Tail(2), "19\n20\n", false),
+        (LogRange::Head(30), &*sequence(20), true),
+        (
+            LogRange::Lines {
+                start: 5,
+                end: Some(7),
+            },
+            "5\n6\n7\n",
+            false,
+        ),
+        (
+            LogRange::Lines {
+                start: 19,
+                end: None,
+            },
+            "19\n20\n",
+            false,
+        ),
+        (
+            LogRange::Lines {
+                start: 25,
+                end: Some(30),
+            },
+            "",
+            false,
+        ),
+        (
+            LogRange::Bytes {
+                start: 2,
+                end: Some(6),
+            },
+            "2\n3\n",
+            false,
+        ),
+        (
+            LogRange::Bytes {
+                start: 0,
+                end: None,
+            },
+            &*sequence(20),
+            true,
+        ),
+    ];
+    for (range, expected, expected_complete) in cases {
+        let (output, output_complete) = get_task_log_range(shared, 0, range).await?;
+        assert_eq!(output, expected, "Unexpected output for {range:?}");
+        assert_eq!(
+            output_complete, expected_complete,
+            "Unexpected completeness for {range:?}"
+        );
+    }
+
+    Ok(())
+}
+
+/// Request logs in chunks and return the reassembled output of each task.
+async fn get_chunked_log(
+    shared: &Shared,
+    message: LogRequestMessage,
+) -> Result<BTreeMap<usize, String>> {
+    let chunk_size = message.chunk_size.unwrap_or_default();
+    let mut stream = get_authenticated_stream(shared).await?;
+    let mut response = send_message_with_stream(&mut stream, message).await?;
+
+    let mut outputs = BTreeMap::new();
+    let mut current = None;
+    loop {
+        match response {
+            Message::LogResponse(logs) => {
+                assert_eq!(logs.len(), 1, "Expected a single task per response");
+                let (task_id, log) = logs.into_iter().next().unwrap();
+                assert_eq!(log.output, Some(Vec::new()));
+                outputs.insert(task_id, String::new());
+                current = Some(task_id);
+            }
+            Message::LogChunk(chunk) => {
+                assert_eq!(Some(chunk.task_id), current);
+                let output = decompress_log(chunk.output)?;
+                assert!(output.len() <= chunk_size, "Chunk is too large: {output}");
+                outputs.get_mut(&chunk.task_id).unwrap().push_str(&output);
+            }
+            Message::Close => break,
+            _ => bail!("Received unexpected message: {response:?}"),
+        }
+        response = receive_message(&mut stream).await?;
+    }
+
+    Ok(outputs)
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Logs, that are requested in chunks, are sent as one response per task, which is followed
+/// by the task's output in chunks.
+async fn test_chunked_log() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    assert_success(add_task(shared, "seq 1 100", false).await?);
+    assert_success(add_task(shared, "true", false).await?);
+    wait_for_task_condition(shared, 0, |task| task.is_done()).await?;
+    wait_for_task_condition(shared, 1, |task| task.is_done()).await?;
+
+    let message = LogRequestMessage {
+        task_ids: vec![0, 1],
+        send_logs: true,
+        lines: None,
+        range: Some(LogRange::Lines {
+            start: 11,
+            end: None,
+        }),
+        chunk_size: Some(16),
+        stream: None,
+        timestamps: false,
+        since: None,
+    };
+    let outputs = get_chunked_log(shared, message).await?;
+
+    let expected: String = (11..=100).map(|number| format!("{number}\n")).collect();
+    assert_eq!(outputs.get(&0), Some(&expected));
+    assert_eq!(outputs.get(&1), Some(&String::new()));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Timestamped output can be requested in chunks, including a range of it.
+async fn test_chunked_timestamped_log() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    let start = Local::now();
+    let mut message = create_add_message(shared, "seq 1 100");
+    message.timestamps = true;
+    assert_success(send_message(shared, message).await?);
+    wait_for_task_condition(shared, 0, |task| task.is_done()).await?;
+
+    let message = LogRequestMessage {
+        task_ids: vec![0],
+        send_logs: true,
+        lines: None,
+        range: Some(LogRange::Lines {
+            start: 11,
+            end: Some(50),
+        }),
+        chunk_size: Some(16),
+        stream: None,
+        timestamps: false,
+        since: Some(start - chrono::Duration::seconds(1)),
+    };
+    let outputs = get_chunked_log(shared, message).await?;
+
+    let expected: String = (11..=50).map(|number| format!("{number}\n")).collect();
+    assert_eq!(outputs.get(&0), Some(&expected));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Logs can't be requested in chunks of size 0 or inside of a batch.
+async fn test_invalid_chunked_log() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    assert_success(add_task(shared, "seq 1 10", false).await?);
+    wait_for_task_condition(shared, 0, |task| task.is_done()).await?;
+
+    let message = LogRequestMessage {
+        task_ids: vec![0],
+        send_logs: true,
+        lines: None,
+        range: None,
+        chunk_size: Some(0),
+        stream: None,
+        timestamps: false,
+        since: None,
+    };
+    assert_failure(send_message(shared, message.clone()).await?);
+
+    let nested = LogRequestMessage {
+        chunk_size: Some(16),
+        ..message
+    };
+    let response = send_message(shared, Message::Batch(vec![nested.into()])).await?;
+    let response = match response {
+        Message::BatchResponse(response) => response,
+        _ => bail!("Expected a batch response, got {response:?}"),
+    };
+    assert!(!response.applied);
+    assert_failure(response.responses[0].clone());
+
+    Ok(())
+}
+
+/// The expected output of `seq 1 {count}`.
+fn sequence(count: usize) -> String {
+    (1..=count).map(|number| format!("{number}\n")).collect()
+}
@@ -0,0 +1,90 @@
+use anyhow::Result;
+use pueue_lib::log::{get_log_path, LogRange, OutputStream};
+use pueue_lib::network::message::*;
+
+use crate::fixtures::*;
+use crate::helper::*;
+
+/// Spawn a daemon, which compresses the logs of finished tasks.
+async fn compressing_daemon() -> Result<PueueDaemon> {
+    let (mut settings, tempdir) = daemon_base_setup()?;
+    settings.daemon.compress_logs = true;
+    settings
+        .save(&Some(tempdir.path().join("pueue.yml")))
+        .expect("Couldn't write pueue config to temporary directory");
+
+    daemon_with_settings(settings, tempdir).await
+}
+
+/// The expected output of `seq 1 {count}`.
+fn sequence(count: usize) -> String {
+    (1..=count).map(|number| format!("{number}\n")).collect()
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// The logs of finished tasks are compressed and can be read just like plain logs.
+async fn test_compressed_log() -> Result<()> {
+    let daemon = compressing_daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    // The output is large enough to be split into multiple blocks.
+    let mut message = create_add_message(shared, "seq 1 100000");
+    message.separate_output = true;
+    assert_success(send_message(shared, message).await?);
+    wait_for_task_condition(shared, 0, |task| task.is_done()).await?;
+    wait_for_compressed_log(shared, 0).await?;
+
+    let log_path = get_log_path(0, &shared.pueue_directory());
+    assert!(!log_path.exists(), "The plain log file should be removed");
+
+    let expected = sequence(100000);
+    assert_eq!(get_task_log(shared, 0, None).await?, expected);
+    assert_eq!(
+        get_task_stream_log(shared, 0, None, Some(OutputStream::Stdout)).await?,
+        expected
+    );
+
+    // The last lines are found without reading the whole log.
+    let output = get_task_log(shared, 0, Some(3)).await?;
+    assert_eq!(output, "99998\n99999\n100000\n");
+
+    // Ranges can be read from compressed logs as well.
+    let range = LogRange::Lines {
+        start: 90000,
+        end: Some(90001),
+    };
+    let (output, _) = get_task_log_range(shared, 0, range).await?;
+    assert_eq!(output, "90000\n90001\n");
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Restarted tasks write a new plain log and the compressed log of the previous run is removed.
+async fn test_restart_compressed_task() -> Result<()> {
+    let daemon = compressing_daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    assert_success(add_task(shared, "echo 'first'", false).await?);
+    wait_for_task_condition(shared, 0, |task| task.is_done()).await?;
+    wait_for_compressed_log(shared, 0).await?;
+
+    let restart_message = RestartMessage {
+        tasks: vec![TaskToRestart {
+            task_id: 0,
+            command: Some("echo 'second' && sleep 60".to_string()),
+            path: None,
+            label: None,
+            delete_label: false,
+        }],
+        start_immediately: false,
+        stashed: false,
+    };
+    assert_success(send_message(shared, restart_message).await?);
+    wait_for_task_condition(shared, 0, |task| task.is_running()).await?;
+    sleep_ms(500).await;
+
+    assert_eq!(get_task_log(shared, 0, None).await?, "second\n");
+
+    Ok(())
+}
@@ -0,0 +1,158 @@
+use anyhow::Result;
+use pueue_lib::log::{get_log_segments, LogLimit, LogLimitPolicy, LogRange};
+use pueue_lib::settings::Shared;
+use pueue_lib::state::{GroupStatus, PUEUE_DEFAULT_GROUP};
+use pueue_lib::task::*;
+
+use crate::fixtures::*;
+use crate::helper::*;
+
+/// Add a task, which prints the numbers from 1 to 200, with the given log size limit.
+/// The full output is 692 bytes long.
+async fn add_limited_task(shared: &Shared, limit: LogLimit) -> Result<()> {
+    let mut message = create_add_message(shared, "seq 1 200");
+    message.log_limit = Some(limit);
+    assert_success(send_message(shared, message).await?);
+    wait_for_task_condition(shared, 0, |task| task.is_done()).await?;
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Rotated logs only keep the newest output, which is read across all segments.
+async fn test_rotate() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    let limit = LogLimit {
+        size: 400,
+        policy: LogLimitPolicy::Rotate,
+    };
+    add_limited_task(shared, limit).await?;
+
+    let task = get_task(shared, 0).await?;
+    assert_eq!(task.status, TaskStatus::Done(TaskResult::Success));
+
+    // The log has been rotated into segments, but only the newest ones are kept.
+    let segments = get_log_segments(0, None, &shared.pueue_directory());
+    assert_eq!(segments.len(), 3, "Unexpected segments: {segments:?}");
+    assert!(segments[0] > 1);
+
+    let output = get_task_log(shared, 0, None).await?;
+    assert!(output.len() <= 400, "Log exceeds its limit: {output}");
+    assert!(
+        output.ends_with("\n199\n200\n"),
+        "Unexpected output: {output}"
+    );
+    assert!(!output.starts_with("1\n2\n"), "Unexpected output: {output}");
+
+    // The last lines are found, even if they're spread over multiple segments.
+    let output = get_task_log(shared, 0, Some(60)).await?;
+    let expected: Vec<String> = (141..=200).map(|number| number.to_string()).collect();
+    assert_eq!(output, format!("{}\n", expected.join("\n")));
+
+    // Ranges are read across segments as well.
+    let full_output = get_task_log(shared, 0, None).await?;
+    let (output, _) = get_task_log_range(shared, 0, LogRange::Head(100)).await?;
+    assert_eq!(output, full_output);
+    let (output, _) = get_task_log_range(
+        shared,
+        0,
+        LogRange::Bytes {
+            start: 100,
+            end: Some(300),
+        },
+    )
+    .await?;
+    assert_eq!(output, full_output[100..300]);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// The `head_tail` policy keeps the start and the end of the output.
+async fn test_head_tail() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    let limit = LogLimit {
+        size: 400,
+        policy: LogLimitPolicy::HeadTail,
+    };
+    add_limited_task(shared, limit).await?;
+
+    let segments = get_log_segments(0, None, &shared.pueue_directory());
+    assert_eq!(segments[0], 1, "Unexpected segments: {segments:?}");
+
+    let output = get_task_log(shared, 0, None).await?;
+    assert!(
+        output.starts_with("1\n2\n3\n"),
+        "Unexpected output: {output}"
+    );
+    assert!(output.contains("(Pueue: Output has been dropped"));
+    assert!(
+        output.ends_with("\n199\n200\n"),
+        "Unexpected output: {output}"
+    );
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Tasks are killed, once their output exceeds the limit with the `kill` policy.
+async fn test_kill() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    let mut message = create_add_message(shared, "seq 1 200 && sleep 60");
+    message.log_limit = Some(LogLimit {
+        size: 100,
+        policy: LogLimitPolicy::Kill,
+    });
+    assert_success(send_message(shared, message).await?);
+
+    let task = wait_for_task_condition(shared, 0, |task| task.is_done()).await?;
+    assert_eq!(task.status, TaskStatus::Done(TaskResult::LogLimitExceeded));
+
+    let output = get_task_log(shared, 0, None).await?;
+    assert!(
+        output.starts_with("1\n2\n3\n"),
+        "Unexpected output: {output}"
+    );
+    assert!(output.ends_with("exceeded the log size limit)\n"));
+    assert!(!output.contains("\n200\n"));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// The log size limit of a group applies to all of its tasks.
+/// Tasks that exceeded it count as failed and pause their group.
+async fn test_group_limit() -> Result<()> {
+    let (mut settings, tempdir) = daemon_base_setup()?;
+    settings.daemon.pause_group_on_failure = true;
+    settings.daemon.group_log_limits.insert(
+        PUEUE_DEFAULT_GROUP.to_string(),
+        LogLimit {
+            size: 100,
+            policy: LogLimitPolicy::Kill,
+        },
+    );
+    settings
+        .save(&Some(tempdir.path().join("pueue.yml")))
+        .expect("Couldn't write pueue config to temporary directory");
+    let daemon = daemon_with_settings(settings, tempdir).await?;
+    let shared = &daemon.settings.shared;
+
+    assert_success(add_task(shared, "seq 1 200 && sleep 60", false).await?);
+    let task = wait_for_task_condition(shared, 0, |task| task.is_done()).await?;
+    assert_eq!(task.status, TaskStatus::Done(TaskResult::LogLimitExceeded));
+
+    let state = get_state(shared).await?;
+    assert_eq!(
+        state.groups.get(PUEUE_DEFAULT_GROUP).unwrap().status,
+        GroupStatus::Paused
+    );
+
+    Ok(())
+}
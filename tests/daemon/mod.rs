@@ -1,13 +1,30 @@
 mod add;
 mod aliases;
+/// Tests for creating and restoring state backups.
+mod backup;
+/// Tests for handling multiple messages at once.
+mod batch;
 mod clean;
+/// Tests for messages with preconditions on the state.
+mod conditional;
 mod edit;
 mod environment_variables;
+/// Tests for classifying task results by their exit code.
+mod exit_code_policy;
 mod group;
 mod kill;
 mod log;
+/// Tests for compressing the logs of finished tasks.
+mod log_compression;
+/// Tests for the size limits of task logs.
+mod log_limit;
+/// Tests for rules, that decide whether a task succeeded, depending on its output.
+mod output_rules;
 mod parallel_tasks;
 mod pause;
+/// Tests for re-adopting detached tasks after a restart.
+#[cfg(target_os = "linux")]
+mod reattach;
 mod remove;
 mod reset;
 mod restart;
@@ -15,7 +32,13 @@ mod restart;
 mod restore;
 /// Tests for shutting down the daemon.
 mod shutdown;
+/// Tests for detecting tasks, that stopped writing output.
+mod stalled_tasks;
 mod start;
 mod stashed;
+/// Tests for tasks, that act on themselves via their token.
+mod task_control;
+/// Tests for task templates and `pueue run`.
+mod template;
 /// Test that the worker pool environment variables are properly injected.
 mod worker_environment_variables;
@@ -0,0 +1,105 @@
+use anyhow::Result;
+use pueue_lib::task::*;
+
+use crate::fixtures::*;
+use crate::helper::*;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Tasks fail, if their output matches the `fail_if` pattern.
+async fn test_fail_if_output() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    let rules = OutputRules {
+        fail_if: Some(OutputPattern::new("^ERROR:")?),
+        succeed_if: None,
+    };
+    for command in [
+        "echo 'start' && echo 'ERROR: broken'",
+        "echo 'no ERROR: all good'",
+    ] {
+        let mut message = create_add_message(shared, command);
+        message.output_rules = Some(rules.clone());
+        assert_success(send_message(shared, message).await?);
+    }
+
+    let task = wait_for_task_condition(shared, 0, |task| task.is_done()).await?;
+    assert_eq!(task.status, TaskStatus::Done(TaskResult::Failed(0)));
+    assert_eq!(
+        task.failure_reason,
+        Some("Output matches \"^ERROR:\"".to_string())
+    );
+
+    let task = wait_for_task_condition(shared, 1, |task| task.is_done()).await?;
+    assert_eq!(task.status, TaskStatus::Done(TaskResult::Success));
+    assert_eq!(task.failure_reason, None);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Tasks fail, unless their output matches the `succeed_if` pattern.
+/// Tasks that failed with an exit code keep their result.
+async fn test_succeed_if_output() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    let rules = OutputRules {
+        fail_if: None,
+        succeed_if: Some(OutputPattern::new(r"\d+ tests passed$")?),
+    };
+    for command in [
+        "echo 'some tests failed' && echo 'no tests passed (yet)'",
+        "echo '12 tests passed'",
+        "exit 3",
+    ] {
+        let mut message = create_add_message(shared, command);
+        message.output_rules = Some(rules.clone());
+        assert_success(send_message(shared, message).await?);
+    }
+
+    let task = wait_for_task_condition(shared, 0, |task| task.is_done()).await?;
+    assert_eq!(task.status, TaskStatus::Done(TaskResult::Failed(0)));
+    assert_eq!(
+        task.failure_reason,
+        Some("Output doesn't match \"\\d+ tests passed$\"".to_string())
+    );
+
+    let task = wait_for_task_condition(shared, 1, |task| task.is_done()).await?;
+    assert_eq!(task.status, TaskStatus::Done(TaskResult::Success));
+
+    let task = wait_for_task_condition(shared, 2, |task| task.is_done()).await?;
+    assert_eq!(task.status, TaskStatus::Done(TaskResult::Failed(3)));
+    assert_eq!(task.failure_reason, None);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Only the beginning of huge lines is matched, the following lines are matched as usual.
+async fn test_output_rules_long_lines() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    let rules = OutputRules {
+        fail_if: Some(OutputPattern::new("ERROR")?),
+        succeed_if: None,
+    };
+    let long_line = "head -c 100000 /dev/zero | tr '\\0' 'x'";
+    for command in [
+        format!("{long_line} && echo ' ERROR' && echo 'done'"),
+        format!("{long_line} && echo && echo 'ERROR'"),
+    ] {
+        let mut message = create_add_message(shared, &command);
+        message.output_rules = Some(rules.clone());
+        assert_success(send_message(shared, message).await?);
+    }
+
+    let task = wait_for_task_condition(shared, 0, |task| task.is_done()).await?;
+    assert_eq!(task.status, TaskStatus::Done(TaskResult::Success));
+
+    let task = wait_for_task_condition(shared, 1, |task| task.is_done()).await?;
+    assert_eq!(task.status, TaskStatus::Done(TaskResult::Failed(0)));
+
+    Ok(())
+}
@@ -78,3 +78,51 @@ async fn test_pause_with_wait() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Tasks of a paused batch won't be started, while the rest of the group continues.
+async fn test_pause_batch() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    // Submit two stashed tasks as a new batch.
+    let mut tasks = Vec::new();
+    for name in ["first", "second"] {
+        let mut task = create_add_message(shared, "sleep 60");
+        task.stashed = true;
+        tasks.push(SubmittedTask {
+            name: name.to_string(),
+            needs: Vec::new(),
+            task,
+        });
+    }
+    let message = SubmitMessage {
+        tasks,
+        create_batch: true,
+        batch_label: None,
+    };
+    assert_success(send_message(shared, message).await?);
+
+    // Pause the batch and enqueue its tasks.
+    pause_tasks(shared, TaskSelection::Batch(0)).await?;
+    let message = EnqueueMessage {
+        task_ids: vec![0, 1],
+        enqueue_at: None,
+    };
+    assert_success(send_message(shared, message).await?);
+
+    // A task outside of the batch should still be processed.
+    add_task(shared, "ls", false).await?;
+    wait_for_task(shared, 2).await?;
+
+    let state = get_state(shared).await?;
+    assert_eq!(state.batches.get(&0).unwrap().status, GroupStatus::Paused);
+    assert_eq!(state.tasks.get(&0).unwrap().status, TaskStatus::Queued);
+    assert_eq!(state.tasks.get(&1).unwrap().status, TaskStatus::Queued);
+
+    // Continuing the batch starts its tasks again.
+    start_tasks(shared, TaskSelection::Batch(0)).await?;
+    wait_for_task_condition(shared, 0, |task| task.is_running()).await?;
+
+    Ok(())
+}
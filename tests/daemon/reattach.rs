@@ -0,0 +1,116 @@
+use std::convert::TryInto;
+
+use anyhow::Result;
+use pretty_assertions::assert_eq;
+
+use pueue_lib::network::message::{KillMessage, TaskControlMessage, TaskSelection};
+use pueue_lib::task::{TaskResult, TaskStatus};
+
+use crate::fixtures::*;
+use crate::helper::*;
+
+#[tokio::test]
+/// Detached tasks should survive a restart of the daemon.
+/// The restarted daemon re-adopts the process and picks up its exit code.
+async fn test_reattach_after_restart() -> Result<()> {
+    let (mut settings, tempdir) = daemon_base_setup()?;
+    settings.daemon.detach_tasks = true;
+    settings.save(&Some(tempdir.path().join("pueue.yml")))?;
+    let shared = &settings.shared;
+
+    let child = standalone_daemon(shared).await?;
+    assert_success(add_task(shared, "sleep 2 && exit 3", false).await?);
+    let task = wait_for_task_condition(shared, 0, |task| task.is_running()).await?;
+    assert!(task.detached_process.is_some());
+
+    // Shut the daemon down. The task should keep running.
+    assert_success(shutdown_daemon(shared).await?);
+    wait_for_shutdown(child.id().try_into()?).await?;
+
+    // Boot it up again and make sure the task has been re-adopted.
+    let mut child = standalone_daemon(shared).await?;
+    let task = get_task(shared, 0).await?;
+    assert_eq!(task.status, TaskStatus::Running);
+
+    // Wait until the task finishes by itself.
+    sleep_ms(2000).await;
+    let task = wait_for_task_condition(shared, 0, |task| task.is_done()).await?;
+    assert_eq!(task.status, TaskStatus::Done(TaskResult::Failed(3)));
+    assert!(task.detached_process.is_none());
+
+    child.kill()?;
+    Ok(())
+}
+
+#[tokio::test]
+/// Detached tasks, that exited while the daemon wasn't running, get the exit code they wrote.
+/// Traps of the task's command don't interfere with that.
+async fn test_exit_while_daemon_down() -> Result<()> {
+    let (mut settings, tempdir) = daemon_base_setup()?;
+    settings.daemon.detach_tasks = true;
+    settings.save(&Some(tempdir.path().join("pueue.yml")))?;
+    let shared = &settings.shared;
+
+    let child = standalone_daemon(shared).await?;
+    let command = "trap 'echo done' EXIT; sleep 1; exit 3";
+    assert_success(add_task(shared, command, false).await?);
+    wait_for_task_condition(shared, 0, |task| task.is_running()).await?;
+
+    assert_success(shutdown_daemon(shared).await?);
+    wait_for_shutdown(child.id().try_into()?).await?;
+    sleep_ms(1500).await;
+
+    let mut child = standalone_daemon(shared).await?;
+    let task = wait_for_task_condition(shared, 0, |task| task.is_done()).await?;
+    assert_eq!(task.status, TaskStatus::Done(TaskResult::Failed(3)));
+    assert!(task.detached_process.is_none());
+    assert!(task.end.is_some());
+
+    child.kill()?;
+    Ok(())
+}
+
+#[tokio::test]
+/// Re-adopted tasks can still talk to the daemon with the token of their current run.
+async fn test_token_after_restart() -> Result<()> {
+    let (mut settings, tempdir) = daemon_base_setup()?;
+    settings.daemon.detach_tasks = true;
+    settings.save(&Some(tempdir.path().join("pueue.yml")))?;
+    let shared = &settings.shared;
+
+    let child = standalone_daemon(shared).await?;
+    let command = "echo \"$PUEUE_TASK_TOKEN\" && sleep 60";
+    assert_success(add_task(shared, command, false).await?);
+    wait_for_task_condition(shared, 0, |task| task.is_running()).await?;
+    sleep_ms(500).await;
+    let log = get_task_log(shared, 0, None).await?;
+    let token = log.trim_end().to_string();
+    assert!(!token.is_empty());
+
+    assert_success(shutdown_daemon(shared).await?);
+    wait_for_shutdown(child.id().try_into()?).await?;
+
+    let mut child = standalone_daemon(shared).await?;
+    let task = get_task(shared, 0).await?;
+    assert_eq!(task.status, TaskStatus::Running);
+
+    let mut stream = get_task_stream(shared, &token).await?;
+    let message = TaskControlMessage::SetLabel("re-adopted".to_string());
+    assert_success(send_message_with_stream(&mut stream, message).await?);
+    let task = get_task(shared, 0).await?;
+    assert_eq!(task.label, Some("re-adopted".to_string()));
+
+    // The token itself is never persisted.
+    let state = std::fs::read_to_string(shared.pueue_directory().join("state.json"))?;
+    assert!(!state.contains(&token));
+
+    let message = KillMessage {
+        tasks: TaskSelection::TaskIds(vec![0]),
+        children: false,
+        signal: None,
+    };
+    assert_success(send_message(shared, message).await?);
+    wait_for_task_condition(shared, 0, |task| task.is_done()).await?;
+    child.kill()?;
+    Ok(())
+}
@@ -4,7 +4,9 @@ use anyhow::Result;
 use pretty_assertions::assert_eq;
 
 use pueue_lib::network::message::TaskSelection;
+use pueue_lib::settings::{CrashRecoveryOverride, InterruptedTaskPolicy, QueuedGroupPolicy};
 use pueue_lib::state::GroupStatus;
+use pueue_lib::task::{TaskResult, TaskStatus};
 
 use crate::fixtures::*;
 use crate::helper::*;
@@ -63,3 +65,68 @@ async fn test_start_paused() -> Result<()> {
     child.kill()?;
     Ok(())
 }
+
+#[tokio::test]
+/// By default, tasks that were interrupted by a crash of the daemon are marked as killed.
+async fn test_interrupted_task_killed() -> Result<()> {
+    let (settings, _tempdir) = daemon_base_setup()?;
+    let mut child = standalone_daemon(&settings.shared).await?;
+    let shared = &settings.shared;
+
+    assert_success(add_task(shared, "sleep 10", false).await?);
+    wait_for_task_condition(shared, 0, |task| task.is_running()).await?;
+
+    // Simulate a crash of the daemon.
+    // The socket has to be removed manually, as the daemon cannot clean it up.
+    child.kill()?;
+    child.wait()?;
+    std::fs::remove_file(shared.unix_socket_path())?;
+
+    let mut child = standalone_daemon(&settings.shared).await?;
+    let task = get_task(shared, 0).await?;
+    assert_eq!(task.status, TaskStatus::Done(TaskResult::Killed));
+
+    child.kill()?;
+    Ok(())
+}
+
+#[tokio::test]
+/// Group specific crash recovery settings allow interrupted tasks to be started again, without
+/// pausing the group.
+async fn test_interrupted_task_requeued() -> Result<()> {
+    let (mut settings, tempdir) = daemon_base_setup()?;
+    settings.daemon.group_crash_recovery.insert(
+        PUEUE_DEFAULT_GROUP.to_string(),
+        CrashRecoveryOverride {
+            interrupted_tasks: Some(InterruptedTaskPolicy::Requeue),
+            queued_groups: Some(QueuedGroupPolicy::KeepRunning),
+        },
+    );
+    settings.save(&Some(tempdir.path().join("pueue.yml")))?;
+    let mut child = standalone_daemon(&settings.shared).await?;
+    let shared = &settings.shared;
+
+    assert_success(add_task(shared, "sleep 10", false).await?);
+    let task = wait_for_task_condition(shared, 0, |task| task.is_running()).await?;
+    let first_start = task.start;
+
+    // Simulate a crash of the daemon.
+    // The socket has to be removed manually, as the daemon cannot clean it up.
+    child.kill()?;
+    child.wait()?;
+    std::fs::remove_file(shared.unix_socket_path())?;
+
+    // The task should be started again.
+    let mut child = standalone_daemon(&settings.shared).await?;
+    let task = wait_for_task_condition(shared, 0, |task| task.is_running()).await?;
+    assert!(task.start > first_start);
+
+    let state = get_state(shared).await?;
+    assert_eq!(
+        state.groups.get(PUEUE_DEFAULT_GROUP).unwrap().status,
+        GroupStatus::Running
+    );
+
+    child.kill()?;
+    Ok(())
+}
@@ -1,6 +1,11 @@
 use std::convert::TryInto;
 
 use anyhow::{Context, Result};
+use chrono::{Duration, Local};
+use pretty_assertions::assert_eq;
+
+use pueue_lib::network::message::Shutdown;
+use pueue_lib::task::{TaskResult, TaskStatus};
 
 use crate::fixtures::*;
 use crate::helper::*;
@@ -49,3 +54,83 @@ async fn test_graceful_shutdown() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+/// Drain the daemon while a task is running.
+/// The task should be able to finish, while queued tasks are no longer started.
+/// Once the task finished, the daemon should shutdown normally.
+async fn test_drain_shutdown() -> Result<()> {
+    let (settings, _tempdir) = daemon_base_setup()?;
+    let shared = &settings.shared;
+    let mut child = standalone_daemon(shared).await?;
+
+    assert_success(add_task(shared, "sleep 1", false).await?);
+    wait_for_task_condition(shared, 0, |task| task.is_running()).await?;
+    assert_success(add_task(shared, "ls", false).await?);
+
+    assert_success(send_message(shared, Shutdown::Drain { deadline: None }).await?);
+
+    // The daemon should still be up and report the ongoing drain.
+    // The drain is initiated asynchronously by the TaskHandler, so give it a little time.
+    let mut state = get_state(shared).await?;
+    for _ in 0..10 {
+        if state.drain.is_some() {
+            break;
+        }
+        sleep_ms(50).await;
+        state = get_state(shared).await?;
+    }
+    assert!(state.drain.is_some());
+    assert_eq!(state.tasks.get(&0).unwrap().status, TaskStatus::Running);
+
+    // Wait for the task to finish and the daemon to shut down.
+    sleep_ms(500).await;
+    wait_for_shutdown(child.id().try_into()?).await?;
+    let code = child.wait()?;
+    assert_eq!(code.code(), Some(0));
+
+    // The running task finished, but the queued one has never been started.
+    let mut child = standalone_daemon(shared).await?;
+    let state = get_state(shared).await?;
+    assert!(state.drain.is_none());
+    assert_eq!(
+        state.tasks.get(&0).unwrap().status,
+        TaskStatus::Done(TaskResult::Success)
+    );
+    assert_eq!(state.tasks.get(&1).unwrap().status, TaskStatus::Queued);
+
+    child.kill()?;
+    Ok(())
+}
+
+#[tokio::test]
+/// Drain the daemon with a deadline.
+/// Tasks that are still running once the deadline is reached should be killed.
+async fn test_drain_deadline() -> Result<()> {
+    let (settings, _tempdir) = daemon_base_setup()?;
+    let shared = &settings.shared;
+    let mut child = standalone_daemon(shared).await?;
+
+    assert_success(add_task(shared, "sleep 60", false).await?);
+    wait_for_task_condition(shared, 0, |task| task.is_running()).await?;
+
+    let deadline = Local::now() + Duration::milliseconds(500);
+    let message = Shutdown::Drain {
+        deadline: Some(deadline),
+    };
+    assert_success(send_message(shared, message).await?);
+
+    // Wait for the deadline and the daemon to shut down.
+    sleep_ms(500).await;
+    wait_for_shutdown(child.id().try_into()?).await?;
+    let code = child.wait()?;
+    assert_eq!(code.code(), Some(0));
+
+    // The task should have been killed.
+    let mut child = standalone_daemon(shared).await?;
+    let task = get_task(shared, 0).await?;
+    assert_eq!(task.status, TaskStatus::Done(TaskResult::Killed));
+
+    child.kill()?;
+    Ok(())
+}
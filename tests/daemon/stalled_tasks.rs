@@ -0,0 +1,114 @@
+use std::fs::read_to_string;
+
+use anyhow::{bail, Result};
+use pueue_lib::settings::Shared;
+use pueue_lib::state::PUEUE_DEFAULT_GROUP;
+use pueue_lib::task::*;
+
+use crate::fixtures::*;
+use crate::helper::*;
+
+/// Add a task with the given stall timeout of one second.
+async fn add_stalling_task(shared: &Shared, command: &str, action: StallAction) -> Result<()> {
+    let mut message = create_add_message(shared, command);
+    message.stall_timeout = Some(StallTimeout { seconds: 1, action });
+    assert_success(send_message(shared, message).await?);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Tasks, that don't write any output for their stall timeout, are killed.
+async fn test_kill() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    add_stalling_task(shared, "echo start && sleep 60", StallAction::Kill).await?;
+    sleep_ms(1500).await;
+
+    let task = wait_for_task_condition(shared, 0, |task| task.is_done()).await?;
+    assert_eq!(task.status, TaskStatus::Done(TaskResult::Killed));
+    assert_eq!(
+        task.failure_reason,
+        Some("Killed, as it didn't write any output for 1s".to_string())
+    );
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Tasks, that don't write any output for their stall timeout, can be paused instead.
+async fn test_pause() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    add_stalling_task(shared, "sleep 60", StallAction::Pause).await?;
+    sleep_ms(1500).await;
+
+    wait_for_task_condition(shared, 0, |task| task.status == TaskStatus::Paused).await?;
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Tasks, that keep writing output, never stall.
+async fn test_output_prevents_stall() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    let command = "for i in $(seq 1 6); do echo $i; sleep 0.5; done";
+    add_stalling_task(shared, command, StallAction::Kill).await?;
+    sleep_ms(3000).await;
+
+    let task = wait_for_task_condition(shared, 0, |task| task.is_done()).await?;
+    assert_eq!(task.status, TaskStatus::Done(TaskResult::Success));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// The stall timeout of a group applies to all of its tasks.
+/// The default action calls the callback with the `stalled` event.
+async fn test_group_notify() -> Result<()> {
+    let (mut settings, tempdir) = daemon_base_setup()?;
+    let callback_path = tempdir.path().join("callback");
+    settings.daemon.callback = Some(format!(
+        "echo '{{{{event}}}} {{{{id}}}}' >> {}",
+        callback_path.to_string_lossy()
+    ));
+    settings.daemon.group_stall_timeouts.insert(
+        PUEUE_DEFAULT_GROUP.to_string(),
+        StallTimeout {
+            seconds: 1,
+            action: StallAction::Notify,
+        },
+    );
+    settings
+        .save(&Some(tempdir.path().join("pueue.yml")))
+        .expect("Couldn't write pueue config to temporary directory");
+    let daemon = daemon_with_settings(settings, tempdir).await?;
+    let shared = &daemon.settings.shared;
+
+    assert_success(add_task(shared, "sleep 60", false).await?);
+    sleep_ms(1000).await;
+
+    // The callback is only called once, while the task keeps running.
+    let mut tries = 0;
+    loop {
+        let output = read_to_string(&callback_path).unwrap_or_default();
+        if output == "stalled 0\n" {
+            break;
+        } else if tries > 40 {
+            bail!("Callback hasn't been called for the stalled task: {output:?}");
+        }
+        tries += 1;
+        sleep_ms(50).await;
+    }
+    sleep_ms(1500).await;
+    assert_eq!(read_to_string(&callback_path)?, "stalled 0\n");
+
+    let task = get_task(shared, 0).await?;
+    assert_eq!(task.status, TaskStatus::Running);
+
+    Ok(())
+}
@@ -0,0 +1,174 @@
+use std::fs::read_to_string;
+
+use anyhow::{bail, Result};
+use pueue_lib::network::message::*;
+use pueue_lib::settings::Shared;
+use pueue_lib::task::*;
+
+use crate::fixtures::*;
+use crate::helper::*;
+
+/// Add a task with the given command and return its token, once it's running.
+/// The token is only known to the task itself, which is why it prints the token first.
+async fn add_running_task(shared: &Shared, command: &str) -> Result<String> {
+    let command = format!("echo \"$PUEUE_TASK_TOKEN\" && {command}");
+    assert_success(add_task(shared, &command, false).await?);
+
+    wait_for_token(shared).await
+}
+
+/// Wait until task 0 is running and printed its token. Then return the token.
+async fn wait_for_token(shared: &Shared) -> Result<String> {
+    wait_for_task_condition(shared, 0, |task| task.is_running()).await?;
+
+    let tries = 20;
+    let mut current_try = 0;
+    while current_try <= tries {
+        let log = get_task_log(shared, 0, None).await?;
+        if let Some(token) = log.strip_suffix('\n') {
+            return Ok(token.to_string());
+        }
+
+        current_try += 1;
+        sleep_ms(50).await;
+    }
+
+    bail!("Task 0 didn't print its token after about 1 second.")
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Running tasks can set their own label and report their progress.
+async fn test_label_and_progress() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    let token = add_running_task(shared, "sleep 60").await?;
+    let mut stream = get_task_stream(shared, &token).await?;
+
+    let message = TaskControlMessage::SetLabel("from task".to_string());
+    assert_success(send_message_with_stream(&mut stream, message).await?);
+    let message = TaskControlMessage::ReportProgress("50%".to_string());
+    assert_success(send_message_with_stream(&mut stream, message).await?);
+
+    let task = get_task(shared, 0).await?;
+    assert_eq!(task.label, Some("from task".to_string()));
+    assert_eq!(task.progress, Some("50%".to_string()));
+
+    // The changes are persisted right away.
+    let state = read_to_string(shared.pueue_directory().join("state.json"))?;
+    assert!(state.contains("from task"));
+    assert!(state.contains("50%"));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Follow-up tasks always depend on the task that added them and inherit its group.
+async fn test_follow_up() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    add_group_with_slots(shared, "other", 1).await?;
+    let command = "echo \"$PUEUE_TASK_TOKEN\" && sleep 60";
+    let mut message = create_add_message(shared, command);
+    message.group = "other".to_string();
+    assert_success(send_message(shared, message).await?);
+    let token = wait_for_token(shared).await?;
+    let mut stream = get_task_stream(shared, &token).await?;
+
+    let message = TaskControlMessage::AddFollowUp(FollowUpMessage {
+        command: "ls".to_string(),
+        label: Some("next".to_string()),
+    });
+    assert_success(send_message_with_stream(&mut stream, message).await?);
+
+    let parent = get_task(shared, 0).await?;
+    let task = get_task(shared, 1).await?;
+    assert_eq!(task.command, "ls");
+    assert_eq!(task.label, Some("next".to_string()));
+    assert_eq!(task.dependencies, vec![0]);
+    assert_eq!(task.group, "other");
+    assert_eq!(task.path, parent.path);
+    assert_eq!(task.status, TaskStatus::Queued);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Tasks that requested a retry are restarted in place, once they finished.
+async fn test_retry() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    let token = add_running_task(shared, "sleep 0.5").await?;
+    let mut stream = get_task_stream(shared, &token).await?;
+    assert_success(send_message_with_stream(&mut stream, TaskControlMessage::Retry).await?);
+
+    wait_for_task_condition(shared, 0, |task| task.attempt == 2).await?;
+    let task = wait_for_task_condition(shared, 0, |task| task.is_done()).await?;
+    assert_eq!(task.status, TaskStatus::Done(TaskResult::Success));
+    assert_eq!(task.attempt, 2);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Connections of tasks may only send task control messages.
+async fn test_restricted_messages() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    let token = add_running_task(shared, "sleep 60").await?;
+    let mut stream = get_task_stream(shared, &token).await?;
+    assert_failure(send_message_with_stream(&mut stream, Message::Status).await?);
+
+    // Clients that know the secret cannot send task control messages either.
+    let message = TaskControlMessage::SetLabel("from client".to_string());
+    assert_failure(send_message(shared, message).await?);
+
+    // Invalid tokens aren't accepted at all.
+    assert!(get_task_stream(shared, "invalid").await.is_err());
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Tokens are only valid, while the task's run is still going on.
+async fn test_token_expires() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    let token = add_running_task(shared, "sleep 0.5").await?;
+    let mut stream = get_task_stream(shared, &token).await?;
+    wait_for_task_condition(shared, 0, |task| task.is_done()).await?;
+
+    let message = TaskControlMessage::SetLabel("too late".to_string());
+    assert_failure(send_message_with_stream(&mut stream, message).await?);
+    assert!(get_task_stream(shared, &token).await.is_err());
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Requested retries are limited by the task's exit code policy.
+async fn test_retry_limit() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+
+    let mut message = create_add_message(shared, "echo \"$PUEUE_TASK_TOKEN\" && sleep 60");
+    message.exit_code_policy = Some(ExitCodePolicy {
+        success: vec![0],
+        temporary: Vec::new(),
+        max_retries: 0,
+    });
+    assert_success(send_message(shared, message).await?);
+    let token = wait_for_token(shared).await?;
+
+    let mut stream = get_task_stream(shared, &token).await?;
+    assert_failure(send_message_with_stream(&mut stream, TaskControlMessage::Retry).await?);
+
+    let task = get_task(shared, 0).await?;
+    assert!(!task.retry);
+
+    Ok(())
+}
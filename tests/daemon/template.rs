@@ -0,0 +1,96 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use pretty_assertions::assert_eq;
+
+use pueue_lib::network::message::*;
+use pueue_lib::settings::Shared;
+use pueue_lib::template::{DependencyPolicy, TaskTemplate};
+
+use crate::fixtures::*;
+use crate::helper::*;
+
+/// Create a template message with the given command and dependency policy.
+fn get_template_message(command: &str, dependencies: DependencyPolicy) -> TemplateMessage {
+    TemplateMessage::Add {
+        name: "backup".to_string(),
+        template: TaskTemplate {
+            command: command.to_string(),
+            group: Some("backups".to_string()),
+            path: None,
+            envs: HashMap::from([("TARGET_HOST".to_string(), "backup-host".to_string())]),
+            label: Some("backup {target}".to_string()),
+            timeout: Some(3600),
+            dependencies,
+            last_task: None,
+        },
+    }
+}
+
+/// Create a run message for the `backup` template.
+fn get_run_message(shared: &Shared, target: &str) -> RunMessage {
+    RunMessage {
+        template: "backup".to_string(),
+        parameters: HashMap::from([("target".to_string(), target.to_string())]),
+        path: shared.pueue_directory(),
+        envs: HashMap::new(),
+        group: None,
+        start_immediately: false,
+        stashed: true,
+    }
+}
+
+/// Tasks created from a template get the template's defaults and rendered parameters.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_run_template() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+    add_group_with_slots(shared, "backups", 1).await?;
+
+    let message = get_template_message(
+        "backup.sh {target} --keep {keep=7}",
+        DependencyPolicy::Independent,
+    );
+    assert_success(send_message(shared, message).await?);
+    assert_success(send_message(shared, get_run_message(shared, "db")).await?);
+
+    let task = get_task(shared, 0).await?;
+    assert_eq!(task.command, "backup.sh db --keep 7");
+    assert_eq!(task.label, Some("backup db".to_string()));
+    assert_eq!(task.group, "backups");
+    assert_eq!(task.timeout, Some(3600));
+    assert_eq!(
+        task.envs.get("TARGET_HOST"),
+        Some(&"backup-host".to_string())
+    );
+    assert!(task.dependencies.is_empty());
+
+    // Unknown parameters are rejected.
+    let mut message = get_run_message(shared, "db");
+    message
+        .parameters
+        .insert("kep".to_string(), "1".to_string());
+    assert_failure(send_message(shared, message).await?);
+
+    Ok(())
+}
+
+/// Tasks of templates with the `AfterPrevious` policy depend on the previous unfinished task.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_run_template_after_previous() -> Result<()> {
+    let daemon = daemon().await?;
+    let shared = &daemon.settings.shared;
+    add_group_with_slots(shared, "backups", 1).await?;
+
+    let message = get_template_message("backup.sh {target}", DependencyPolicy::AfterPrevious);
+    assert_success(send_message(shared, message).await?);
+    assert_success(send_message(shared, get_run_message(shared, "db")).await?);
+    assert_success(send_message(shared, get_run_message(shared, "files")).await?);
+
+    let state = get_state(shared).await?;
+    assert!(state.tasks.get(&0).unwrap().dependencies.is_empty());
+    assert_eq!(state.tasks.get(&1).unwrap().dependencies, vec![0]);
+    assert_eq!(state.templates.get("backup").unwrap().last_task, Some(1));
+
+    Ok(())
+}
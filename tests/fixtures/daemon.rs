@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs::File;
 use std::io::Write;
 use std::path::{Path, PathBuf};
@@ -11,6 +11,7 @@ use tokio::io::{self, AsyncWriteExt};
 
 use pueue_daemon_lib::run;
 use pueue_lib::settings::*;
+use pueue_lib::task::ExitCodePolicy;
 
 use crate::helper::*;
 
@@ -162,8 +163,23 @@ pub fn daemon_base_setup() -> Result<(Settings, TempDir)> {
     let daemon = Daemon {
         pause_group_on_failure: false,
         pause_all_on_failure: false,
+        detach_tasks: false,
+        crash_recovery: CrashRecovery::default(),
+        group_crash_recovery: BTreeMap::new(),
         callback: None,
         callback_log_lines: 15,
+        state_backup_count: 10,
+        state_backup_max_age_hours: None,
+        edit_lock_lease_seconds: 3600,
+        separate_output: false,
+        log_timestamps: false,
+        log_limit: None,
+        group_log_limits: BTreeMap::new(),
+        exit_code_policy: ExitCodePolicy::default(),
+        group_exit_code_policies: BTreeMap::new(),
+        stall_timeout: None,
+        group_stall_timeouts: BTreeMap::new(),
+        compress_logs: false,
         groups: None,
     };
 
@@ -36,6 +36,11 @@ pub async fn assert_worker_envs(
             task_ids: vec![task_id],
             send_logs: true,
             lines: None,
+            range: None,
+            chunk_size: None,
+            stream: None,
+            timestamps: false,
+            since: None,
         },
     )
     .await?;
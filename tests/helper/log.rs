@@ -1,6 +1,7 @@
 use std::io::Read;
 
 use anyhow::{bail, Context, Result};
+use pueue_lib::log::{get_log_path, LogRange, OutputStream};
 use pueue_lib::network::message::*;
 use pueue_lib::settings::*;
 use snap::read::FrameDecoder;
@@ -22,10 +23,26 @@ pub fn decompress_log(bytes: Vec<u8>) -> Result<String> {
 /// Convenience function to get the log of a specific task.
 /// `lines: None` requests all log lines.
 pub async fn get_task_log(shared: &Shared, task_id: usize, lines: Option<usize>) -> Result<String> {
+    get_task_stream_log(shared, task_id, lines, None).await
+}
+
+/// Convenience function to get the log of a single output stream of a specific task.
+/// `stream: None` requests the interleaved output of both streams.
+pub async fn get_task_stream_log(
+    shared: &Shared,
+    task_id: usize,
+    lines: Option<usize>,
+    stream: Option<OutputStream>,
+) -> Result<String> {
     let message = LogRequestMessage {
         task_ids: vec![task_id],
         send_logs: true,
         lines,
+        range: None,
+        chunk_size: None,
+        stream,
+        timestamps: false,
+        since: None,
     };
     let response = send_message(shared, message).await?;
 
@@ -43,3 +60,56 @@ pub async fn get_task_log(shared: &Shared, task_id: usize, lines: Option<usize>)
 
     decompress_log(bytes)
 }
+
+/// Convenience function to get a part of the log of a specific task.
+/// Returns the output and whether it's the task's complete output.
+pub async fn get_task_log_range(
+    shared: &Shared,
+    task_id: usize,
+    range: LogRange,
+) -> Result<(String, bool)> {
+    let message = LogRequestMessage {
+        task_ids: vec![task_id],
+        send_logs: true,
+        lines: None,
+        range: Some(range),
+        chunk_size: None,
+        stream: None,
+        timestamps: false,
+        since: None,
+    };
+    let response = send_message(shared, message).await?;
+
+    let mut logs = match response {
+        Message::LogResponse(logs) => logs,
+        _ => bail!("Didn't get log response: {response:?}"),
+    };
+
+    let log = logs
+        .remove(&task_id)
+        .context("Didn't find log of requested task")?;
+    let bytes = log
+        .output
+        .context("Didn't get log output even though requested.")?;
+
+    Ok((decompress_log(bytes)?, log.output_complete))
+}
+
+/// Wait until the daemon compressed the log of a finished task.
+pub async fn wait_for_compressed_log(shared: &Shared, task_id: usize) -> Result<()> {
+    let log_path = get_log_path(task_id, &shared.pueue_directory());
+    let compressed_path = log_path.with_extension("log.sz");
+
+    let tries = 40;
+    let mut current_try = 0;
+    while current_try <= tries {
+        if compressed_path.exists() && !log_path.exists() {
+            return Ok(());
+        }
+
+        current_try += 1;
+        sleep_ms(50).await;
+    }
+
+    bail!("The log of task {task_id} hasn't been compressed after about 2 seconds.")
+}
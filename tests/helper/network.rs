@@ -15,13 +15,24 @@ where
 {
     let mut stream = get_authenticated_stream(shared).await?;
 
+    send_message_with_stream(&mut stream, message).await
+}
+
+/// Send a message via an existing stream and return the response.
+///
+/// This is necessary for protocols that span several messages on the same connection,
+/// such as editing tasks.
+pub async fn send_message_with_stream<T>(stream: &mut GenericStream, message: T) -> Result<Message>
+where
+    T: Into<Message>,
+{
     // Check if we can receive the response from the daemon
-    internal_send_message(message, &mut stream)
+    internal_send_message(message, stream)
         .await
         .map_err(|err| anyhow!("Failed to send message: {err}"))?;
 
     // Check if we can receive the response from the daemon
-    receive_message(&mut stream)
+    receive_message(stream)
         .await
         .map_err(|err| anyhow!("Failed to receive message: {err}"))
 }
@@ -30,6 +41,19 @@ where
 ///
 /// Pueue creates a new socket stream for each command, which is why we do it the same way.
 pub async fn get_authenticated_stream(shared: &Shared) -> Result<GenericStream> {
+    let secret =
+        read_shared_secret(&shared.shared_secret_path()).context("Couldn't read shared secret.")?;
+
+    get_stream_with_credentials(shared, &secret).await
+}
+
+/// Create a new stream, that authenticated with the token of a running task.
+pub async fn get_task_stream(shared: &Shared, token: &str) -> Result<GenericStream> {
+    get_stream_with_credentials(shared, token.as_bytes()).await
+}
+
+/// Create a new stream, that already finished the handshake with the given credentials.
+async fn get_stream_with_credentials(shared: &Shared, credentials: &[u8]) -> Result<GenericStream> {
     // Connect to daemon and get stream used for communication.
     let mut stream = match get_client_stream(shared).await {
         Ok(stream) => stream,
@@ -41,9 +65,7 @@ pub async fn get_authenticated_stream(shared: &Shared) -> Result<GenericStream>
     // Next we do a handshake with the daemon
     // 1. Client sends the secret to the daemon.
     // 2. If successful, the daemon responds with their version.
-    let secret =
-        read_shared_secret(&shared.shared_secret_path()).context("Couldn't read shared secret.")?;
-    send_bytes(&secret, &mut stream)
+    send_bytes(credentials, &mut stream)
         .await
         .context("Failed to send bytes.")?;
     let version_bytes = receive_bytes(&mut stream)
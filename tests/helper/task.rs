@@ -33,6 +33,14 @@ pub fn create_add_message(shared: &Shared, command: &str) -> AddMessage {
         dependencies: Vec::new(),
         label: None,
         print_task_id: false,
+        batch: None,
+        timeout: None,
+        separate_output: false,
+        timestamps: false,
+        log_limit: None,
+        exit_code_policy: None,
+        output_rules: None,
+        stall_timeout: None,
     }
 }
 
@@ -92,7 +100,7 @@ pub async fn get_task(shared: &Shared, task_id: usize) -> Result<Task> {
     let state = get_state(shared).await?;
     let task = state
         .tasks
-        .get(&0)
+        .get(&task_id)
         .ok_or_else(|| anyhow!("Couldn't find task {task_id}"))?;
 
     Ok(task.clone())
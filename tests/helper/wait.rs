@@ -161,16 +161,14 @@ pub async fn wait_for_group_status(
     group: &str,
     expected_status: GroupStatus,
 ) -> Result<()> {
-    let state = get_state(shared).await?;
-
-    // Give the daemon about 1 sec to shutdown.
+    // Give the daemon about 1 sec to change the group's status.
     let tries = 20;
     let mut current_try = 0;
 
     while current_try < tries {
-        // Process is still alive, wait a little longer
-        if let Some(status) = state.groups.get(group) {
-            if matches!(status, _expected_status) {
+        let state = get_state(shared).await?;
+        if let Some(group) = state.groups.get(group) {
+            if group.status == expected_status {
                 return Ok(());
             }
         }